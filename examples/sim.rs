@@ -1,28 +1,32 @@
 use anyhow::Result;
-use rand::seq::IndexedRandom;
-use rand_distr::{Bernoulli, Beta, Distribution, Exp, Gamma};
-use std::{iter::repeat, sync::LazyLock};
-use trg::gen::{Positive, PrimaryGenerator, SecondaryGenerator, Source, WireEvent, WirePattern};
-use trg::mlu::{LookupTable, TrgSignal};
+use rand_distr::{Bernoulli, Distribution, Uniform};
+use std::collections::{HashSet, VecDeque};
+use std::{fs::File, iter::repeat};
+use trg::gen::{
+    AfterpulseLibrary, EventId, GeneratorStats, PbarGenerator, PoissonGenerator, Positive,
+    SecondaryGenerator, Seed, Source, WireEvent, WirePattern,
+};
+use trg::mlu::TrgSignal;
+use trg::odb::TrgOdbConfig;
+use trg::statistics::{Efficiency, RateEstimate};
 use trg::{Observer, World};
 use uom::si::f64::{Frequency, Time};
-use uom::si::{
-    frequency::hertz,
-    time::{nanosecond, second},
-};
+use uom::si::{frequency::hertz, time::second};
 
+// The only pattern in the example MLU file.
 const MLU_INTERESTING: u16 = 2;
 const MLU_NOT_INTERESTING: u16 = 1;
 
-static COSMIC_AFTERPULSES: LazyLock<Vec<Vec<f64>>> = LazyLock::new(|| {
-    let contents = std::fs::read_to_string("../data/cosmic_afterpulses.json").unwrap();
-    serde_json::from_str(&contents).unwrap()
-});
-
-static MIXING_AFTERPULSES: LazyLock<Vec<Vec<f64>>> = LazyLock::new(|| {
-    let contents = std::fs::read_to_string("../data/mixing_afterpulses.json").unwrap();
-    serde_json::from_str(&contents).unwrap()
-});
+// Patterns that pass the MLU with probability `pass_mlu`.
+fn mlu_patterns(pass_mlu: f64) -> Result<impl Distribution<WirePattern>> {
+    Ok(Bernoulli::new(pass_mlu)?.map(|i| {
+        if i {
+            WirePattern::from_bits(MLU_INTERESTING)
+        } else {
+            WirePattern::from_bits(MLU_NOT_INTERESTING)
+        }
+    }))
+}
 
 #[derive(Default)]
 struct MyObserver {
@@ -31,6 +35,11 @@ struct MyObserver {
     trg_in: u32,
     drift_veto: u32,
     trg_out: u32,
+    // Signal events (primary and afterpulses) not yet in an output trigger.
+    pending_signal: VecDeque<(Time, EventId)>,
+    // Signal events with the primary or any afterpulse in an output trigger.
+    triggered_signal: HashSet<EventId>,
+    generator_stats: Vec<GeneratorStats>,
 }
 
 impl Observer for MyObserver {
@@ -42,6 +51,9 @@ impl Observer for MyObserver {
             Source::PrimaryPbar => self.signal_counter += 1,
             _ => {}
         }
+        if let (Source::PrimaryPbar | Source::SecondaryPbar, Some(id)) = (event.source, event.id) {
+            self.pending_signal.push_back((event.time, id));
+        }
     }
 
     fn on_trg_in(&mut self, _signal: &TrgSignal<Self::Time>) {
@@ -53,8 +65,22 @@ impl Observer for MyObserver {
         self.drift_veto -= 1;
     }
 
-    fn on_trg_out(&mut self, _signal: &TrgSignal<Self::Time>) {
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, _number: u64) {
         self.trg_out += 1;
+        // Events before this prompt window can not be in a later one.
+        while let Some(&(time, id)) = self.pending_signal.front() {
+            if time >= signal.window_stop {
+                break;
+            }
+            if time >= signal.window_start {
+                self.triggered_signal.insert(id);
+            }
+            self.pending_signal.pop_front();
+        }
+    }
+
+    fn on_generator_stats(&mut self, stats: &[GeneratorStats]) {
+        self.generator_stats = stats.to_vec();
     }
 }
 
@@ -65,123 +91,75 @@ fn main() -> Result<()> {
     // These are most likely your free parameters:
     let duration = Time::new::<second>(1000.0);
     let signal_rate = Frequency::new::<hertz>(100.0);
+    // The same seed always gives the same results.
+    let seed = Seed::new(0);
     // ===========================================
 
     // ===========================================
     // Then, these are parameters you estimated either
     // experimentally/theoretically/simulated:
-    let bkg_observed = 434120.0;
-    let bkg_time_window = 1385.0;
-    let bkg_passed_mlu = 21949.0;
-    let bkg_total_mlu = 103763.0;
+    let bkg_observed =
+        RateEstimate::new(434120, Positive::new(Time::new::<second>(1385.0)).unwrap());
+    let bkg_mlu = Efficiency::new(21949, 103763);
 
-    let signal_passed_mlu = 55993.0;
-    let signal_total_mlu = 75520.0;
+    let signal_mlu = Efficiency::new(55993, 75520);
+    // ===========================================
+
+    // ===========================================
+    // Then, this is your trigger configuration. You get it from the ODB
+    let odb = TrgOdbConfig::from_reader(File::open("../data/example_odb.json")?)?;
     // ===========================================
 
     // ===========================================
-    // Then, this is your trigger configuration. You get these from the ODB
-    let prompt_window = Time::new::<nanosecond>(64.0 * 8.0);
-    let wait_gate = Time::new::<nanosecond>(128.0 * 8.0);
-    let drift_veto = Time::new::<nanosecond>(300.0 * 16.0);
-    let dead_time = Time::new::<nanosecond>(211864.0 * 16.0);
+    // And these are the measured afterpulses of each type of event:
+    let cosmic_afterpulses =
+        AfterpulseLibrary::from_reader_with_uom(File::open("../data/cosmic_afterpulses.json")?)?;
+    let mixing_afterpulses =
+        AfterpulseLibrary::from_reader_with_uom(File::open("../data/mixing_afterpulses.json")?)?;
     // ===========================================
 
     // ===========================================
     // Actual simulation code:
-    let pass_mlu = Beta::new(bkg_passed_mlu + 1.0, bkg_total_mlu - bkg_passed_mlu + 1.0)?
-        .sample(&mut rand::rng());
-    let bkg_rate = Frequency::new::<hertz>(
-        Gamma::new(bkg_observed + 1.0, 1.0 / (bkg_time_window + 0.0))?.sample(&mut rand::rng()),
-    );
+    let pass_mlu = bkg_mlu.sample(&mut seed.derive(0).rng());
+    let bkg_rate = bkg_observed.sample(&mut seed.derive(1).rng());
 
-    let bkg_gen = PrimaryGenerator::builder()
+    let bkg_seed = seed.derive(2);
+    let mut afterpulse_rng = bkg_seed.derive(2).rng();
+    let bkg_gen = PoissonGenerator::builder()
         .source(Source::PrimaryCosmic)
         .origin(Time::new::<second>(0.0))
         .duration(Positive::new(duration).unwrap())
-        .inter_arrival_time(
-            Exp::new(bkg_rate.get::<hertz>())?
-                .map(|delta| Positive::new(Time::new::<second>(delta)).unwrap())
-                .sample_iter(rand::rng()),
-        )
-        .wire_pattern(
-            Bernoulli::new(pass_mlu)?
-                .map(|i| {
-                    if i {
-                        WirePattern::from_bits(MLU_INTERESTING)
-                    } else {
-                        WirePattern::from_bits(MLU_NOT_INTERESTING)
-                    }
-                })
-                .sample_iter(rand::rng()),
-        )
-        .afterpulse(|event: &WireEvent<_>| {
+        .rate(Positive::new(bkg_rate).unwrap())
+        .rng(bkg_seed.derive(0).rng())
+        .wire_pattern(mlu_patterns(pass_mlu)?)
+        .build()
+        .afterpulse(move |event: &WireEvent<_>| {
             SecondaryGenerator::builder()
                 .source(Source::SecondaryCosmic)
                 .wire_pattern(repeat(event.wire_pattern))
-                .inter_arrival_time(
-                    COSMIC_AFTERPULSES
-                        .choose(&mut rand::rng())
-                        .unwrap()
-                        .into_iter()
-                        .map(|n| {
-                            Positive::new(Time::new::<nanosecond>((*n as f64) * 16.0)).unwrap()
-                        }),
-                )
-        })
-        .build();
+                .inter_arrival_time(cosmic_afterpulses.choose(&mut afterpulse_rng))
+        });
 
-    let pass_mlu = Beta::new(
-        signal_passed_mlu + 1.0,
-        signal_total_mlu - signal_passed_mlu + 1.0,
-    )?
-    .sample(&mut rand::rng());
+    let pass_mlu = signal_mlu.sample(&mut seed.derive(3).rng());
 
-    let signal_gen = PrimaryGenerator::builder()
-        .source(Source::PrimaryPbar)
+    let signal_gen = PbarGenerator::builder()
         .origin(Time::new::<second>(0.0))
-        .duration(Positive::new(duration).unwrap())
-        .inter_arrival_time(
-            Exp::new(signal_rate.get::<hertz>())?
-                .map(|delta| Positive::new(Time::new::<second>(delta)).unwrap())
-                .sample_iter(rand::rng()),
-        )
+        .window(Positive::new(duration).unwrap())
+        .rate(Positive::new(signal_rate).unwrap())
+        .rng(seed.derive(4).rng())
         .wire_pattern(
-            Bernoulli::new(pass_mlu)?
-                .map(|i| {
-                    if i {
-                        WirePattern::from_bits(MLU_INTERESTING)
-                    } else {
-                        WirePattern::from_bits(MLU_NOT_INTERESTING)
-                    }
-                })
-                .sample_iter(rand::rng()),
+            Uniform::new_inclusive(MLU_NOT_INTERESTING, MLU_INTERESTING)?
+                .map(WirePattern::from_bits),
         )
-        .afterpulse(|event: &WireEvent<_>| {
-            SecondaryGenerator::builder()
-                .source(Source::SecondaryPbar)
-                .wire_pattern(repeat(event.wire_pattern))
-                .inter_arrival_time(
-                    MIXING_AFTERPULSES
-                        .choose(&mut rand::rng())
-                        .unwrap()
-                        .into_iter()
-                        .map(|n| {
-                            Positive::new(Time::new::<nanosecond>((*n as f64) * 16.0)).unwrap()
-                        }),
-                )
-        })
+        .pass_mlu(pass_mlu, odb.lookup_table()?)
+        .afterpulses(mixing_afterpulses)
         .build();
 
-    let observer = World::builder()
+    let builder = World::builder()
         .add_generator(bkg_gen)
-        .add_generator(signal_gen)
-        .prompt_window(Positive::new(prompt_window).unwrap())
-        .wait_gate(Positive::new(wait_gate).unwrap())
-        .lookup_table(LookupTable::from([WirePattern::from_bits(MLU_INTERESTING)]))
-        .drift_veto(Positive::new(drift_veto).unwrap())
-        .scaledown(0)
-        .dead_time(Positive::new(dead_time).unwrap())
+        .add_generator(signal_gen);
+    let observer = odb
+        .apply(builder)?
         .observer(MyObserver::default())
         .build()
         .run();
@@ -191,6 +169,19 @@ fn main() -> Result<()> {
     println!("Input counter: {}", observer.trg_in);
     println!("Drift veto counter: {}", observer.drift_veto);
     println!("Output counter: {}", observer.trg_out);
+    println!(
+        "Signal efficiency: {}",
+        observer.triggered_signal.len() as f64 / f64::from(observer.signal_counter)
+    );
+    for (name, stats) in ["Background", "Signal"]
+        .iter()
+        .zip(&observer.generator_stats)
+    {
+        println!(
+            "{name} generator: {} primary and {} afterpulse events",
+            stats.primary_events, stats.secondary_events
+        );
+    }
 
     Ok(())
 }
@@ -0,0 +1,67 @@
+/// Time types that can be quantized to the ticks of a clock.
+pub trait Quantize {
+    /// Returns the start of the clock tick that contains `self` i.e. the
+    /// largest multiple of `period` that is less than or equal to `self`.
+    fn floor_to(&self, period: &Self) -> Self;
+}
+
+impl Quantize for i32 {
+    fn floor_to(&self, period: &Self) -> Self {
+        self.div_euclid(*period) * period
+    }
+}
+
+impl Quantize for i64 {
+    fn floor_to(&self, period: &Self) -> Self {
+        self.div_euclid(*period) * period
+    }
+}
+
+impl Quantize for f64 {
+    fn floor_to(&self, period: &Self) -> Self {
+        (self / period).floor() * period
+    }
+}
+
+#[cfg(feature = "uom")]
+impl Quantize for uom::si::f64::Time {
+    fn floor_to(&self, period: &Self) -> Self {
+        let mut time = *self;
+        // Value in the base unit (seconds).
+        time.value = self.value.floor_to(&period.value);
+        time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantize_integer() {
+        assert_eq!(0.floor_to(&8), 0);
+        assert_eq!(7.floor_to(&8), 0);
+        assert_eq!(8.floor_to(&8), 8);
+        assert_eq!((-1i64).floor_to(&8), -8);
+        assert_eq!(17i64.floor_to(&8), 16);
+    }
+
+    #[test]
+    fn quantize_float() {
+        assert_eq!(7.9.floor_to(&8.0), 0.0);
+        assert_eq!(8.0.floor_to(&8.0), 8.0);
+        assert_eq!((-0.5).floor_to(&8.0), -8.0);
+        assert_eq!(1.3.floor_to(&0.5), 1.0);
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn quantize_uom() {
+        use uom::si::f64::Time;
+        use uom::si::time::nanosecond;
+
+        let time = Time::new::<nanosecond>(21.0);
+        let period = Time::new::<nanosecond>(8.0);
+        assert!((time.floor_to(&period).get::<nanosecond>() - 16.0).abs() < 1e-9);
+    }
+}
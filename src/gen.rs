@@ -1,11 +1,29 @@
+use crate::mlu::LookupTable;
 use bon::bon;
 pub use num_traits::identities::Zero;
-use std::iter::{zip, Peekable, Zip};
-use std::ops::{Add, BitOr};
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::binary_heap::{BinaryHeap, PeekMut};
+use std::fmt::{self, Write};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Mul, Neg, Not};
 use std::str::FromStr;
 
+/// Replay of recorded [`WireEvent`]s from CSV files.
+#[cfg(feature = "csv")]
+pub mod csv;
+/// Distributions given by measured histograms.
+pub mod empirical;
+/// Recording and replay of [`WireEvent`]s in a binary format.
+pub mod record;
+
 /// The source of a [`WireEvent`].
-#[derive(Clone, Copy, Debug)]
+///
+/// Sources that are not covered by the built-in variants (e.g. calibration
+/// pulses) can be labeled with [`Source::custom`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Source {
     /// The first avalanche from a cosmic event.
     PrimaryCosmic,
@@ -19,6 +37,171 @@ pub enum Source {
     SecondaryPbar,
     /// Noise events.
     Noise,
+    /// User-defined source. The number is an index into the registry of labels
+    /// of [`Source::custom`], so it is only meaningful within a program;
+    /// serialized and recorded sources use the label instead.
+    Custom(u16),
+}
+
+// Labels of custom sources, indexed by the number of `Source::Custom`.
+static CUSTOM_LABELS: std::sync::RwLock<Vec<String>> = std::sync::RwLock::new(Vec::new());
+
+const BUILT_IN_SOURCES: [(&str, Source); 5] = [
+    ("PrimaryCosmic", Source::PrimaryCosmic),
+    ("SecondaryCosmic", Source::SecondaryCosmic),
+    ("PrimaryPbar", Source::PrimaryPbar),
+    ("SecondaryPbar", Source::SecondaryPbar),
+    ("Noise", Source::Noise),
+];
+
+impl Source {
+    /// Returns the [`Source::Custom`] with the given label, registering the
+    /// label (for the whole program) the first time it is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the label is empty, has characters other than ASCII
+    /// alphanumerics, `_`, and `-`, or is the name of a built-in variant. Also
+    /// panics if more than 65536 labels are registered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::Source;
+    /// let laser = Source::custom("laser");
+    /// assert_eq!(laser, Source::custom("laser"));
+    /// assert_eq!(laser.to_string(), "laser");
+    /// assert_eq!("laser".parse::<Source>()?, laser);
+    /// # Ok::<(), trg::gen::ParseSourceError>(())
+    /// ```
+    pub fn custom(label: &str) -> Self {
+        assert!(
+            is_custom_label(label),
+            "invalid custom source label `{label}`"
+        );
+
+        if let Some(source) = Self::find_custom(label) {
+            return source;
+        }
+        let mut labels = CUSTOM_LABELS.write().unwrap();
+        // Another thread could have registered the label in the meantime.
+        let index = match labels.iter().position(|l| l == label) {
+            Some(index) => index,
+            None => {
+                labels.push(label.to_string());
+                labels.len() - 1
+            }
+        };
+        Self::Custom(index.try_into().expect("too many custom source labels"))
+    }
+
+    // Parses the `Display` form of a source, registering the label of a
+    // custom source (e.g. written by another program) if needed.
+    pub(crate) fn parse_or_register(s: &str) -> Option<Self> {
+        s.parse()
+            .ok()
+            .or_else(|| is_custom_label(s).then(|| Self::custom(s)))
+    }
+
+    fn find_custom(label: &str) -> Option<Self> {
+        CUSTOM_LABELS
+            .read()
+            .unwrap()
+            .iter()
+            .position(|l| l == label)
+            .map(|index| Self::Custom(index.try_into().unwrap()))
+    }
+}
+
+fn is_custom_label(label: &str) -> bool {
+    !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && BUILT_IN_SOURCES.iter().all(|(name, _)| *name != label)
+}
+
+impl fmt::Display for Source {
+    /// Formats the source as the name of its variant e.g. `PrimaryCosmic`. A
+    /// [`Source::Custom`] is formatted as its registered label, or as e.g.
+    /// `Custom(3)` if no label has that number.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom(index) => match CUSTOM_LABELS.read().unwrap().get(usize::from(*index)) {
+                Some(label) => f.write_str(label),
+                None => write!(f, "Custom({index})"),
+            },
+            _ => {
+                let (name, _) = BUILT_IN_SOURCES.iter().find(|(_, s)| s == self).unwrap();
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+/// The error type returned when parsing a [`Source`] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSourceError(String);
+
+impl fmt::Display for ParseSourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown source `{}`", self.0)
+    }
+}
+
+impl std::error::Error for ParseSourceError {}
+
+impl FromStr for Source {
+    type Err = ParseSourceError;
+
+    /// Convert a string slice to a [`Source`]. This is the inverse of
+    /// [`Display`](fmt::Display): the name of a variant, the label of a
+    /// registered custom source, or e.g. `Custom(3)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, source)) = BUILT_IN_SOURCES.iter().find(|(name, _)| *name == s) {
+            return Ok(*source);
+        }
+        s.strip_prefix("Custom(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|index| index.parse().ok())
+            .map(Self::Custom)
+            .or_else(|| Self::find_custom(s))
+            .ok_or_else(|| ParseSourceError(s.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Source {
+    /// Serializes the source as its [`Display`](fmt::Display) form e.g.
+    /// `"Noise"`, or the label of a custom source.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Source {
+    /// Deserializes the source from its [`Display`](fmt::Display) form. The
+    /// label of a custom source is registered if needed (see
+    /// [`Source::custom`]).
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Source;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "the name of a source or a custom source label")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Source::parse_or_register(v)
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
 }
 
 /// Anode wires pattern.
@@ -46,691 +229,8238 @@ impl WirePattern {
     pub fn from_bits(bits: u16) -> Self {
         Self(bits)
     }
+    /// Create a new [`WirePattern`] with the given boards high.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any board index is not less than 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_wires([9, 10]);
+    /// assert_eq!(pattern, WirePattern::from_bits(0b0000011000000000));
+    /// ```
+    pub fn from_wires(iter: impl IntoIterator<Item = u8>) -> Self {
+        let mut pattern = Self(0);
+        for idx in iter {
+            pattern.set_wire(idx);
+        }
+
+        pattern
+    }
+    /// Returns the bit pattern. Bit `i` is the state of board `i`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_bits(0b0100000000000001);
+    /// assert_eq!(pattern.bits(), 0b0100000000000001);
+    /// ```
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+    /// Sets board `idx` high.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let mut pattern = WirePattern::from_bits(0);
+    /// pattern.set_wire(15);
+    /// assert_eq!(pattern, WirePattern::from_bits(0b1000000000000000));
+    /// ```
+    pub fn set_wire(&mut self, idx: u8) {
+        self.0 |= board_mask(idx);
+    }
+    /// Sets board `idx` low.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let mut pattern = WirePattern::from_bits(0b11);
+    /// pattern.clear_wire(0);
+    /// assert_eq!(pattern, WirePattern::from_bits(0b10));
+    /// ```
+    pub fn clear_wire(&mut self, idx: u8) {
+        self.0 &= !board_mask(idx);
+    }
+    /// Returns `true` if board `idx` is high.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is not less than 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_bits(0b10);
+    /// assert!(pattern.is_wire_set(1));
+    /// assert!(!pattern.is_wire_set(0));
+    /// ```
+    pub fn is_wire_set(&self, idx: u8) -> bool {
+        self.0 & board_mask(idx) != 0
+    }
+    /// Returns the number of boards that are high.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_bits(0b0110000000000001);
+    /// assert_eq!(pattern.count_ones(), 3);
+    /// ```
+    pub fn count_ones(&self) -> u32 {
+        self.0.count_ones()
+    }
+    /// Returns the pattern rotated by `n` boards around the ring: the state of
+    /// board `i` moves to board `(i + n) % 16` (bit `i` is the board `i`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_bits(0b1000000000000001);
+    /// assert_eq!(pattern.rotate(1), WirePattern::from_bits(0b0000000000000011));
+    /// assert_eq!(pattern.rotate(16), pattern);
+    /// ```
+    pub fn rotate(&self, n: u32) -> Self {
+        Self(self.0.rotate_left(n))
+    }
+    /// Returns the number of boards that are in a different state in the two
+    /// patterns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let a = WirePattern::from_wires([15, 0, 1]);
+    /// let b = WirePattern::from_wires([0, 1, 2]);
+    /// assert_eq!(a.hamming_distance(&b), 2);
+    /// ```
+    pub fn hamming_distance(&self, other: &Self) -> u32 {
+        (*self ^ *other).count_ones()
+    }
+    /// Returns the number of boards that are high in both patterns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let a = WirePattern::from_wires([15, 0, 1]);
+    /// let b = WirePattern::from_wires([0, 1, 2]);
+    /// assert_eq!(a.overlap(&b), 2);
+    /// ```
+    pub fn overlap(&self, other: &Self) -> u32 {
+        (*self & *other).count_ones()
+    }
+    /// Returns `true` if all the boards that are high in `other` are also
+    /// high in `self`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let a = WirePattern::from_wires([15, 0, 1]);
+    /// assert!(a.contains(&WirePattern::from_wires([15, 0])));
+    /// assert!(!a.contains(&WirePattern::from_wires([0, 2])));
+    /// ```
+    pub fn contains(&self, other: &Self) -> bool {
+        *self & *other == *other
+    }
+    /// Returns an iterator over the 16 cyclic rotations of the pattern (see
+    /// [`WirePattern::rotate`]), starting with the pattern itself. Patterns
+    /// with a symmetry are repeated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_wires([0]);
+    /// let rotations = pattern.all_rotations().collect::<Vec<_>>();
+    /// assert_eq!(rotations.len(), 16);
+    /// assert_eq!(rotations[15], WirePattern::from_wires([15]));
+    /// ```
+    pub fn all_rotations(&self) -> impl Iterator<Item = WirePattern> {
+        let pattern = *self;
+        (0..16).map(move |n| pattern.rotate(n))
+    }
+    /// Returns the pattern with also the `n` neighbors on each side of every
+    /// high board set high. The boards are arranged in a ring (see
+    /// [`WirePattern::cluster_count`]), so the neighbors wrap around.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_wires([0, 8]);
+    /// assert_eq!(pattern.dilate(1), WirePattern::from_wires([15, 0, 1, 7, 8, 9]));
+    /// ```
+    pub fn dilate(&self, n: u8) -> Self {
+        let mut dilated = *self;
+        for shift in 1..=u32::from(n.min(8)) {
+            dilated = dilated | self.rotate(shift) | self.rotate(16 - shift);
+        }
+
+        dilated
+    }
+    /// Returns `true` if all the high boards form a single cluster (see
+    /// [`WirePattern::cluster_count`]), including clusters that wrap around
+    /// from board 15 to board 0. A pattern without high boards is not
+    /// contiguous.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// assert!(WirePattern::from_wires([14, 15, 0]).is_contiguous());
+    /// assert!(!WirePattern::from_wires([14, 0]).is_contiguous());
+    /// ```
+    pub fn is_contiguous(&self) -> bool {
+        self.cluster_count() == 1
+    }
+    /// Returns the indices of the boards that are high, in ascending order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_bits(0b1000000000000101);
+    /// assert_eq!(pattern.iter_wires().collect::<Vec<_>>(), [0, 2, 15]);
+    /// ```
+    pub fn iter_wires(&self) -> impl Iterator<Item = u8> {
+        let pattern = *self;
+        (0..16).filter(move |&idx| pattern.is_wire_set(idx))
+    }
+    /// Returns the number of clusters of adjacent boards that are high. The
+    /// boards are arranged in a ring, so the first and the last boards (bits 0
+    /// and 15) are adjacent: a cluster can wrap around from board 15 to board
+    /// 0. If all boards are high, there is a single cluster.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// // The first and last boards form a single cluster.
+    /// let pattern = WirePattern::from_bits(0b1000110000000001);
+    /// assert_eq!(pattern.cluster_count(), 2);
+    /// ```
+    pub fn cluster_count(&self) -> u32 {
+        let n = self.0;
+        let mut count = 0;
+        let mut in_cluster = n & (1 << 15) != 0;
+
+        for i in 0..16 {
+            if n & (1 << i) != 0 {
+                if !in_cluster {
+                    count += 1;
+                    in_cluster = true;
+                }
+            } else {
+                in_cluster = false;
+            }
+        }
+
+        if count == 0 && in_cluster {
+            count += 1;
+        }
+
+        count
+    }
+}
+
+// Bit mask of a board of a `WirePattern`.
+fn board_mask(idx: u8) -> u16 {
+    assert!(idx < 16, "board index {idx} is out of range (0..16)");
+    1 << idx
 }
 
 impl BitOr for WirePattern {
     type Output = Self;
 
+    /// Returns the boards that are high in either pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let a = WirePattern::from_bits(0b1100);
+    /// let b = WirePattern::from_bits(0b1010);
+    /// assert_eq!(a | b, WirePattern::from_bits(0b1110));
+    /// ```
     fn bitor(self, rhs: Self) -> Self::Output {
         Self(self.0 | rhs.0)
     }
 }
 
-impl FromStr for WirePattern {
-    type Err = std::num::ParseIntError;
+impl BitAnd for WirePattern {
+    type Output = Self;
 
-    /// Convert a string slice in base 2 to a [`WirePattern`].
+    /// Returns the boards that are high in both patterns.
     ///
     /// # Example
+    ///
     /// ```
     /// # use trg::gen::WirePattern;
-    /// let pattern = "0100000000000000".parse::<WirePattern>()?;
-    /// let expected = WirePattern::from_bits(0b0100000000000000);
-    ///
-    /// assert_eq!(pattern, expected);
-    /// # Ok::<(), std::num::ParseIntError>(())
+    /// let a = WirePattern::from_bits(0b1100);
+    /// let b = WirePattern::from_bits(0b1010);
+    /// assert_eq!(a & b, WirePattern::from_bits(0b1000));
     /// ```
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let bits = u16::from_str_radix(s, 2)?;
-        Ok(Self(bits))
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
     }
 }
 
-/// A [`WireEvent`] represents an input signal to the trigger system.
-///
-/// The digitized anode wire waveforms go into digital discriminators. This
-/// discriminator outputs ([`WireEvent`]s) are then sent to the trigger system.
-#[derive(Clone, Copy, Debug)]
-pub struct WireEvent<F> {
-    /// The source of the event.
-    pub source: Source,
-    /// Anode wire preamp pattern.
-    pub wire_pattern: WirePattern,
-    /// Time of the event.
-    pub time: F,
+impl BitXor for WirePattern {
+    type Output = Self;
+
+    /// Returns the boards that are high in exactly one of the patterns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let a = WirePattern::from_bits(0b1100);
+    /// let b = WirePattern::from_bits(0b1010);
+    /// assert_eq!(a ^ b, WirePattern::from_bits(0b0110));
+    /// ```
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self(self.0 ^ rhs.0)
+    }
 }
 
-/// A value that is known to be greater than zero.
-#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
-pub struct Positive<F>(F);
+impl Not for WirePattern {
+    type Output = Self;
 
-impl<F> Positive<F>
-where
-    F: Zero + PartialOrd,
-{
-    pub fn new(value: F) -> Option<Self> {
-        if value > F::zero() {
-            Some(Self(value))
-        } else {
-            None
-        }
+    /// Returns the boards that are low in the pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let a = WirePattern::from_bits(0b1111111100000000);
+    /// assert_eq!(!a, WirePattern::from_bits(0b0000000011111111));
+    /// ```
+    fn not(self) -> Self::Output {
+        Self(!self.0)
     }
 }
 
-impl<F> Positive<F> {
-    pub(crate) fn inner(&self) -> &F {
-        &self.0
+impl fmt::Display for WirePattern {
+    /// Formats the pattern as one character per board, starting from board 0:
+    /// `X` if the board is high, and `.` otherwise. This is the notation of
+    /// the MLU files.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let pattern = WirePattern::from_bits(0b1000000000000011);
+    /// assert_eq!(pattern.to_string(), "XX.............X");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for idx in 0..16 {
+            f.write_char(if self.is_wire_set(idx) { 'X' } else { '.' })?;
+        }
+
+        Ok(())
     }
 }
 
-mod sealed {
-    pub trait OrderedIterator: Iterator {}
+/// The error type returned when parsing a [`WirePattern`] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseWirePatternError {
+    input: String,
+    span: std::ops::Range<usize>,
 }
 
-/// Generator of [`WireEvent`]s.
-///
-/// Events are guaranteed to be produced in increasing order of time.
-pub trait EventGenerator: sealed::OrderedIterator<Item = WireEvent<Self::Time>> {
-    type Time;
+impl ParseWirePatternError {
+    fn new(input: &str, position: usize) -> Self {
+        // Point at the whole offending character, or past the end of the
+        // input if it is too short.
+        let len = input[position..].chars().next().map_or(0, char::len_utf8);
+        Self {
+            input: input.to_string(),
+            span: position..position + len,
+        }
+    }
+    /// Returns the byte range of the input where parsing failed. It is empty
+    /// (at the end of the input) if the input is too short.
+    pub fn span(&self) -> std::ops::Range<usize> {
+        self.span.clone()
+    }
 }
 
-impl<T, G> EventGenerator for G
-where
-    G: sealed::OrderedIterator<Item = WireEvent<T>>,
-{
-    type Time = T;
+impl fmt::Display for ParseWirePatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = annotate_snippets::Level::Error
+            .title("invalid wire pattern")
+            .snippet(
+                annotate_snippets::Snippet::source(&self.input)
+                    .annotation(annotate_snippets::Level::Error.span(self.span.clone())),
+            );
+        let renderer = annotate_snippets::Renderer::plain();
+        let rendered = renderer.render(message);
+        rendered.fmt(f)
+    }
 }
 
-/// A trait for dealing with iterators that produce positive values.
-pub trait PositiveIterator: Iterator<Item = Positive<Self::Type>> {
-    type Type;
+impl std::error::Error for ParseWirePatternError {}
+
+// Parse the digits of a pattern in the given radix, with at most `max_digits`
+// digits. `offset` is the position of the digits in `input`.
+fn parse_digits(
+    input: &str,
+    offset: usize,
+    radix: u32,
+    max_digits: usize,
+) -> Result<u16, ParseWirePatternError> {
+    let digits = &input[offset..];
+    for (n, (i, c)) in digits.char_indices().enumerate() {
+        if n >= max_digits || !c.is_digit(radix) {
+            return Err(ParseWirePatternError::new(input, offset + i));
+        }
+    }
+    if digits.is_empty() {
+        return Err(ParseWirePatternError::new(input, input.len()));
+    }
+
+    // All the digits are valid, and there are few enough to fit in a `u16`.
+    Ok(u16::from_str_radix(digits, radix).unwrap())
 }
 
-impl<T, I: Iterator<Item = Positive<T>>> PositiveIterator for I {
-    type Type = T;
+impl FromStr for WirePattern {
+    type Err = ParseWirePatternError;
+
+    /// Convert a string slice to a [`WirePattern`]. The string can be:
+    ///
+    /// - The notation of [`Display`](fmt::Display): 16 characters (`X` or
+    ///   `.`), starting from board 0.
+    /// - Hexadecimal with a `0x` prefix e.g. `0x8001`.
+    /// - Base 2 (up to 16 digits), starting from board 15 e.g.
+    ///   `1000000000000001`.
+    ///
+    /// The error points to the first invalid character.
+    ///
+    /// # Example
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// let expected = WirePattern::from_bits(0b0100000000000000);
+    ///
+    /// assert_eq!("0100000000000000".parse::<WirePattern>()?, expected);
+    /// assert_eq!("..............X.".parse::<WirePattern>()?, expected);
+    /// assert_eq!("0x4000".parse::<WirePattern>()?, expected);
+    /// # Ok::<(), trg::gen::ParseWirePatternError>(())
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x") || s.starts_with("0X") {
+            return parse_digits(s, 2, 16, 4).map(Self);
+        }
+        if !s.is_empty() && s.chars().all(|c| c == '0' || c == '1') {
+            return parse_digits(s, 0, 2, 16).map(Self);
+        }
+
+        let mut pattern = Self(0);
+        let mut boards = s.char_indices();
+        for idx in 0..16 {
+            match boards.next() {
+                Some((_, 'X')) => pattern.set_wire(idx),
+                Some((_, '.')) => {}
+                Some((i, _)) => return Err(ParseWirePatternError::new(s, i)),
+                None => return Err(ParseWirePatternError::new(s, s.len())),
+            }
+        }
+        if let Some((i, _)) = boards.next() {
+            return Err(ParseWirePatternError::new(s, i));
+        }
+
+        Ok(pattern)
+    }
 }
 
-/// A generator of [`WireEvent`]s without afterpulses.
+#[cfg(feature = "serde")]
+impl serde::Serialize for WirePattern {
+    /// Serializes the pattern as a hex string (e.g. `"0x8001"`), the same
+    /// notation used in MLU files.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("0x{:04x}", self.0))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WirePattern {
+    /// Deserializes the pattern from either a hex string (e.g. `"0x8001"`) or
+    /// an integer.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = WirePattern;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a 16-bit integer or a hex string")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                u16::try_from(v)
+                    .map(WirePattern)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                u16::try_from(v)
+                    .map(WirePattern)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.strip_prefix("0x")
+                    .or_else(|| v.strip_prefix("0X"))
+                    .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+                    .map(WirePattern)
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// A distribution of [`WirePattern`]s made of clusters of adjacent boards.
 ///
-/// The generator produces a stream of [`WireEvent`]s all with the same
-/// [`Source`]. A generator stops producing events when either the desired
-/// duration has been reached, or when the inter-arrival time/wire pattern
-/// distributions have been exhausted.
-#[derive(Clone, Debug)]
-pub struct SecondaryGenerator<I, P>
-where
-    I: PositiveIterator,
-{
-    source: Source,
-    current_time: Option<I::Type>,
-    max_time: Option<I::Type>,
-    // (time, wire_pattern)
-    iter: Zip<I, P>,
+/// Each sample draws a number of clusters and the width (number of boards) of
+/// each cluster. The clusters are separated by at least one low board and the
+/// gaps between them are random; the whole pattern is then rotated by a
+/// uniformly random number of boards. The [`WirePattern::cluster_count`] of a
+/// sample is the sampled number of clusters (clamped to at most 8).
+///
+/// # Example
+///
+/// ```
+/// use rand::distr::{Distribution, Uniform};
+/// use trg::gen::{ClusterPatternModel, Positive, SecondaryGenerator, Source};
+///
+/// let model = ClusterPatternModel::builder()
+///     .clusters(Uniform::new_inclusive(1, 2)?)
+///     .width(Uniform::new_inclusive(1, 3)?)
+///     .build();
+/// let generator = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0.0)
+///     .inter_arrival_time(std::iter::repeat(Positive::new(1.0).unwrap()))
+///     .wire_pattern(model.sample_iter(rand::rng()))
+///     .duration(Positive::new(10.0).unwrap())
+///     .build();
+/// assert!(generator.map(|event| event.wire_pattern.cluster_count()).all(|n| n == 1 || n == 2));
+/// # Ok::<(), rand::distr::uniform::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct ClusterPatternModel<C, W> {
+    clusters: C,
+    width: W,
 }
 
 #[bon]
-impl<I, P> SecondaryGenerator<I, P>
-where
-    I: PositiveIterator,
-{
+impl<C, W> ClusterPatternModel<C, W> {
     #[builder]
-    pub fn new<T1, T2>(
-        /// The source of the generated events.
-        source: Source,
-        /// The time at which the generator starts producing events. Note that
-        /// the first event is produced at `origin` + `delta_t`, where `delta_t`
-        /// is the first value produced by `inter_arrival_time`.
-        origin: I::Type,
-        /// Length of time the generator produces events for. All events are
-        /// guaranteed to have a time less than `origin` + `duration`.
-        duration: Option<Positive<I::Type>>,
-        /// The distribution of inter-arrival times between events.
-        inter_arrival_time: T1,
-        /// The distribution of wire patterns.
-        wire_pattern: T2,
-    ) -> Self
-    where
-        T1: IntoIterator<IntoIter = I>,
-        T2: IntoIterator<IntoIter = P>,
-        // Another alternative could be
-        // `I::Type: for<'a> Add<&'a I::Type, Output = I::Type>` instead.
-        // But:
-        // 1. `impl Iterator` requires `I::Type: Clone` anyway.
-        // 2. I want to use `Quantity` types from the `uom` crate, and these
-        // don't implement `Add<&T, Output = T>` yet (maybe soon they will).
-        I::Type: Add<Output = I::Type> + Clone,
-    {
-        Self {
-            source,
-            current_time: Some(origin.clone()),
-            max_time: duration.map(|Positive(t)| t + origin),
-            iter: zip(inter_arrival_time, wire_pattern),
-        }
+    pub fn new(
+        /// The distribution of the number of clusters. Values larger than 8
+        /// (the most clusters that fit in 16 boards) are clamped to 8.
+        clusters: C,
+        /// The distribution of the width of each cluster. Zero is treated as 1,
+        /// and the widest clusters are narrowed until all of them fit.
+        width: W,
+    ) -> Self {
+        Self { clusters, width }
     }
 }
 
-impl<I, P> Iterator for SecondaryGenerator<I, P>
+impl<C, W> Distribution<WirePattern> for ClusterPatternModel<C, W>
 where
-    I: PositiveIterator,
-    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
-    P: Iterator<Item = WirePattern>,
+    C: Distribution<u32>,
+    W: Distribution<u32>,
 {
-    type Item = WireEvent<I::Type>;
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> WirePattern {
+        let clusters = self.clusters.sample(rng).min(8);
+        if clusters == 0 {
+            return WirePattern(0);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match (self.current_time.take(), self.iter.next()) {
-            (None, _) => None,
-            (_, None) => None,
-            (Some(time), Some((Positive(delta_t), wire_pattern))) => {
-                let time = time + delta_t;
-                if let Some(max_time) = &self.max_time {
-                    if time < *max_time {
-                        self.current_time = Some(time.clone());
-                    } else {
-                        return None;
-                    }
-                } else {
-                    self.current_time = Some(time.clone());
-                }
+        // Every cluster is followed by at least one low board. With all the
+        // other clusters 1 board wide, a cluster can be `17 - 2 * clusters`
+        // boards wide.
+        let mut widths = (0..clusters)
+            .map(|_| self.width.sample(rng).clamp(1, 17 - 2 * clusters))
+            .collect::<Vec<_>>();
+        while widths.iter().sum::<u32>() + clusters > 16 {
+            *widths.iter_mut().max().unwrap() -= 1;
+        }
+        let mut gaps = vec![1; widths.len()];
+        for _ in 0..16 - widths.iter().sum::<u32>() - clusters {
+            gaps[rng.random_range(0..widths.len())] += 1;
+        }
 
-                Some(WireEvent {
-                    source: self.source,
-                    wire_pattern,
-                    time,
-                })
+        let mut pattern = WirePattern(0);
+        let mut board = 0;
+        for (width, gap) in widths.into_iter().zip(gaps) {
+            for _ in 0..width {
+                pattern.set_wire(board);
+                board += 1;
             }
+            board += gap;
         }
+
+        pattern.rotate(rng.random_range(0..16))
     }
 }
 
-impl<I, P> sealed::OrderedIterator for SecondaryGenerator<I, P>
-where
-    I: PositiveIterator,
-    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
-    P: Iterator<Item = WirePattern>,
-{
+/// A [`WireEvent`] represents an input signal to the trigger system.
+///
+/// The digitized anode wire waveforms go into digital discriminators. This
+/// discriminator outputs ([`WireEvent`]s) are then sent to the trigger system.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WireEvent<F> {
+    /// The source of the event.
+    pub source: Source,
+    /// Anode wire preamp pattern.
+    pub wire_pattern: WirePattern,
+    /// Time of the event.
+    pub time: F,
+    /// The primary event that caused this event (its own id for a primary
+    /// event), if it was produced by a [`PrimaryGenerator`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub id: Option<EventId>,
+    /// Statistical weight of the event e.g. the ratio of the true to the
+    /// sampled probability when rare events are oversampled. Generators
+    /// produce events with a weight of 1 (set it with
+    /// [`EventGenerator::map_event`]); the afterpulses of a primary event are
+    /// not weighted again, its weight already accounts for them.
+    ///
+    /// The [`TrgSignal`](crate::mlu::TrgSignal) of a prompt window has the
+    /// product of the weights of all the events in the window.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "unit_weight", skip_serializing_if = "is_unit_weight")
+    )]
+    pub weight: f64,
 }
 
-/// A generator of [`WireEvent`]s with afterpulses.
-///
-/// The generator produces a stream of [`WireEvent`]s in increasing order of
-/// time. Each primary event triggers a set of secondary events. The generator
-/// stops when all primary and secondary events have been produced.
-pub struct PrimaryGenerator<I1, P1, B, I2, P2>
-where
-    I1: PositiveIterator,
-    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
-    P1: Iterator<Item = WirePattern>,
-    I2: PositiveIterator<Type = I1::Type>,
-    P2: Iterator<Item = WirePattern>,
-{
-    primary: Peekable<SecondaryGenerator<I1, P1>>,
-    afterpulse: B,
-    secondaries: Vec<Peekable<SecondaryGenerator<I2, P2>>>,
+#[cfg(feature = "serde")]
+pub(crate) fn unit_weight() -> f64 {
+    1.0
 }
 
-// The Derive macro is not smart enough to implement Clone in this case.
-impl<I1: Clone, P1: Clone, B: Clone, I2: Clone, P2: Clone> Clone
-    for PrimaryGenerator<I1, P1, B, I2, P2>
-where
-    I1: PositiveIterator,
-    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
-    P1: Iterator<Item = WirePattern>,
-    I2: PositiveIterator<Type = I1::Type>,
-    P2: Iterator<Item = WirePattern>,
-{
-    fn clone(&self) -> Self {
-        Self {
-            primary: self.primary.clone(),
-            afterpulse: self.afterpulse.clone(),
-            secondaries: self.secondaries.clone(),
-        }
+#[cfg(feature = "serde")]
+pub(crate) fn is_unit_weight(weight: &f64) -> bool {
+    *weight == 1.0
+}
+
+/// Identifier of a primary event, shared by all its afterpulses.
+///
+/// A [`PrimaryGenerator`] numbers its primary events in increasing order,
+/// starting from zero. Ids are only unique within a generator; use the
+/// [`Source`] of the events to tell generators apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventId(u64);
+
+impl EventId {
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+    /// Returns the id as a number.
+    pub fn get(self) -> u64 {
+        self.0
     }
 }
 
-#[bon]
-impl<I1, P1, B, I2, P2> PrimaryGenerator<I1, P1, B, I2, P2>
-where
-    I1: PositiveIterator,
-    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
-    P1: Iterator<Item = WirePattern>,
-    I2: PositiveIterator<Type = I1::Type>,
-    P2: Iterator<Item = WirePattern>,
-{
-    #[builder]
-    pub fn new<T1, T2>(
-        /// The source of the primary events.
+impl<F> WireEvent<F> {
+    /// Create a new [`WireEvent`] from the indices of the physical anode wires
+    /// that are over threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any wire index is not less than 256.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::{SectorMap, Source, WireEvent, WirePattern};
+    /// let event = WireEvent::from_physical_wires(Source::Noise, 0.0, [3, 17], &SectorMap::default());
+    /// assert_eq!(event.wire_pattern, WirePattern::from_wires([0, 1]));
+    /// ```
+    pub fn from_physical_wires(
         source: Source,
-        /// The time at which the generator starts producing events. Note that
-        /// the first event is produced at `origin` + `delta_t`, where `delta_t`
-        /// is the first value produced by `inter_arrival_time`.
-        origin: I1::Type,
-        /// Length of time the generator produces primary events for. Note that
-        /// the generator will keep producing secondary events until all
-        /// secondary generators have also been exhausted (which could be after
-        /// the primary generator has stopped producing events).
-        duration: Option<Positive<I1::Type>>,
-        /// The distribution of inter-arrival times between primary events.
-        inter_arrival_time: T1,
-        /// The distribution of wire patterns for primary events.
-        wire_pattern: T2,
-        /// Secondary generator builder.
-        ///
-        /// The `origin` of the secondary generator is automatically set to the
-        /// time of the primary event that triggered it.
-        afterpulse: B,
-    ) -> Self
-    where
-        T1: IntoIterator<IntoIter = I1>,
-        T2: IntoIterator<IntoIter = P1>,
-    {
-        let primary = SecondaryGenerator::builder()
-            .source(source)
-            .origin(origin)
-            .maybe_duration(duration)
-            .inter_arrival_time(inter_arrival_time)
-            .wire_pattern(wire_pattern)
-            .build()
-            .peekable();
-
+        time: F,
+        wires: impl IntoIterator<Item = u16>,
+        sector_map: &SectorMap,
+    ) -> Self {
         Self {
-            primary,
-            afterpulse,
-            secondaries: Vec::new(),
+            source,
+            wire_pattern: sector_map.compress(wires),
+            time,
+            id: None,
+            weight: 1.0,
         }
     }
 }
 
-use secondary_generator_builder::{IsSet, IsUnset, State};
-
-impl<I1, P1, B, I2, P2, T3, T4, S: State> PrimaryGenerator<I1, P1, B, I2, P2>
-where
-    I1: PositiveIterator,
-    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
-    P1: Iterator<Item = WirePattern>,
-    I2: PositiveIterator<Type = I1::Type>,
-    P2: Iterator<Item = WirePattern>,
-    B: FnMut(&WireEvent<I1::Type>) -> SecondaryGeneratorBuilder<I2, P2, T3, T4, S>,
-    T3: IntoIterator<IntoIter = I2>,
-    T4: IntoIterator<IntoIter = P2>,
-    S::Source: IsSet,
-    S::Origin: IsUnset,
-    S::InterArrivalTime: IsSet,
-    S::WirePattern: IsSet,
-{
-    fn next_primary(&mut self) -> Option<WireEvent<I1::Type>> {
-        if let Some(next_event) = self.primary.next() {
-            let mut generator = (self.afterpulse)(&next_event)
-                .origin(next_event.time.clone())
-                .build()
-                .peekable();
-            // Only keep around secondary generators that have something to
-            // produce.
-            if generator.peek().is_some() {
-                self.secondaries.push(generator);
-            }
+/// Mapping from the 256 physical anode wires to the 16 boards (sectors) of a
+/// [`WirePattern`].
+///
+/// The default mapping is the detector layout: wire `i` is in sector `i / 16`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SectorMap {
+    sectors: [u8; 256],
+}
 
-            Some(next_event)
-        } else {
-            None
+impl SectorMap {
+    /// Create a new [`SectorMap`]. The `i`th element is the sector of wire
+    /// `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any sector is not less than 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::{SectorMap, WirePattern};
+    /// // Interleaved sectors: wire `i` is in sector `i % 16`.
+    /// let map = SectorMap::new(std::array::from_fn(|i| (i % 16) as u8));
+    /// assert_eq!(map.compress([17]), WirePattern::from_wires([1]));
+    /// ```
+    pub fn new(sectors: [u8; 256]) -> Self {
+        if let Some(sector) = sectors.iter().find(|&&sector| sector >= 16) {
+            panic!("sector {sector} is out of range (0..16)");
         }
-    }
 
-    fn next_secondary(&mut self, index: usize) -> Option<WireEvent<I1::Type>> {
-        let next_event = self.secondaries[index].next();
-        if self.secondaries[index].peek().is_none() {
-            let _ = self.secondaries.swap_remove(index);
+        Self { sectors }
+    }
+    /// Returns the sector of a wire.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wire` is not less than 256.
+    pub fn sector(&self, wire: u16) -> u8 {
+        match self.sectors.get(usize::from(wire)) {
+            Some(&sector) => sector,
+            None => panic!("wire index {wire} is out of range (0..256)"),
         }
-
-        next_event
     }
-}
+    /// Returns the pattern seen by the MLU: a sector is high if any of its
+    /// wires is over threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any wire index is not less than 256.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::{SectorMap, WirePattern};
+    /// let map = SectorMap::default();
+    /// assert_eq!(map.compress([0, 15, 16, 255]), WirePattern::from_wires([0, 1, 15]));
+    /// ```
+    pub fn compress(&self, wires: impl IntoIterator<Item = u16>) -> WirePattern {
+        WirePattern::from_wires(wires.into_iter().map(|wire| self.sector(wire)))
+    }
+    /// Returns the wires of a sector in the default mapping.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sector` is not less than 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use trg::gen::SectorMap;
+    /// assert_eq!(SectorMap::sector_wires(1), 16..32);
+    /// ```
+    pub fn sector_wires(sector: u8) -> std::ops::Range<u16> {
+        assert!(sector < 16, "sector {sector} is out of range (0..16)");
+        let start = u16::from(sector) * 16;
 
-impl<I1, P1, B, I2, P2, T3, T4, S: State> Iterator for PrimaryGenerator<I1, P1, B, I2, P2>
-where
-    I1: PositiveIterator,
-    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
-    P1: Iterator<Item = WirePattern>,
-    I2: PositiveIterator<Type = I1::Type>,
-    P2: Iterator<Item = WirePattern>,
-    B: FnMut(&WireEvent<I1::Type>) -> SecondaryGeneratorBuilder<I2, P2, T3, T4, S>,
-    T3: IntoIterator<IntoIter = I2>,
-    T4: IntoIterator<IntoIter = P2>,
-    S::Source: IsSet,
-    S::Origin: IsUnset,
-    S::InterArrivalTime: IsSet,
-    S::WirePattern: IsSet,
-{
-    type Item = WireEvent<I1::Type>;
+        start..start + 16
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some((index, next_secondary)) = self
-            .secondaries
-            .iter_mut()
-            // We only keep around generators that will return `Some`.
-            .map(|g| g.peek().unwrap())
-            .enumerate()
-            // No `NaN` values because of the `PositiveIterator` bound.
-            .min_by(|(_, a), (_, b)| a.time.partial_cmp(&b.time).unwrap())
-        {
-            if let Some(next_primary) = self.primary.peek() {
-                if next_primary.time < next_secondary.time {
-                    self.next_primary()
-                } else {
-                    self.next_secondary(index)
+impl Default for SectorMap {
+    fn default() -> Self {
+        Self::new(std::array::from_fn(|wire| (wire / 16) as u8))
+    }
+}
+
+impl From<[u8; 256]> for SectorMap {
+    fn from(sectors: [u8; 256]) -> Self {
+        Self::new(sectors)
+    }
+}
+
+/// Faulty boards, applied to generated events with
+/// [`EventGenerator::mask_channels`].
+///
+/// # Example
+///
+/// ```
+/// use trg::gen::{ChannelMask, WirePattern};
+///
+/// // Board 7 is dead, and board 3 fires spuriously in 1% of the events.
+/// let mask = ChannelMask::builder()
+///     .disabled(WirePattern::from_bits(1 << 7))
+///     .hot(vec![(3, 0.01)])
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChannelMask {
+    disabled: WirePattern,
+    hot: Vec<(u8, f64)>,
+}
+
+#[bon]
+impl ChannelMask {
+    /// # Panics
+    ///
+    /// Panics if a hot board is not in `0..16`, or if its probability is not
+    /// in `[0, 1]`.
+    #[builder]
+    pub fn new(
+        /// Boards that never fire. They are cleared from every event, even if
+        /// they are also hot. None by default.
+        #[builder(default = WirePattern(0))]
+        disabled: WirePattern,
+        /// Boards that fire spuriously, each with its probability per event.
+        /// None by default.
+        #[builder(default)]
+        hot: Vec<(u8, f64)>,
+    ) -> Self {
+        for &(board, probability) in &hot {
+            assert!(board < 16, "board {board} is out of range");
+            assert!(
+                (0.0..=1.0).contains(&probability),
+                "probability {probability} of board {board} is not in [0, 1]"
+            );
+        }
+
+        Self { disabled, hot }
+    }
+}
+
+/// Cross-talk between neighboring boards, applied to generated events with
+/// [`EventGenerator::cross_talk`].
+///
+/// Each high board of an event independently induces a signal on each of its
+/// neighbors with some probability. Boards 15 and 0 are neighbors. Only the
+/// boards of the original pattern induce cross-talk.
+///
+/// # Example
+///
+/// ```
+/// use trg::gen::CrossTalk;
+///
+/// let cross_talk = CrossTalk::builder().neighbor(0.1).second_neighbor(0.01).build();
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct CrossTalk {
+    neighbor: f64,
+    second_neighbor: f64,
+}
+
+#[bon]
+impl CrossTalk {
+    /// # Panics
+    ///
+    /// Panics if a probability is not in `[0, 1]`.
+    #[builder]
+    pub fn new(
+        /// Probability of inducing a signal on each adjacent board.
+        neighbor: f64,
+        /// Probability of inducing a signal on each board two positions away.
+        /// Zero by default.
+        #[builder(default)]
+        second_neighbor: f64,
+    ) -> Self {
+        for probability in [neighbor, second_neighbor] {
+            assert!(
+                (0.0..=1.0).contains(&probability),
+                "cross-talk probability {probability} is not in [0, 1]"
+            );
+        }
+
+        Self {
+            neighbor,
+            second_neighbor,
+        }
+    }
+
+    fn apply<R: Rng + ?Sized>(&self, pattern: WirePattern, rng: &mut R) -> WirePattern {
+        let mut induced = pattern;
+        for board in pattern.iter_wires() {
+            for (distance, probability) in [(1, self.neighbor), (2, self.second_neighbor)] {
+                for neighbor in [(board + distance) % 16, (board + 16 - distance) % 16] {
+                    if rng.random_bool(probability) {
+                        induced.set_wire(neighbor);
+                    }
                 }
-            } else {
-                self.next_secondary(index)
             }
-        } else {
-            self.next_primary()
         }
+
+        induced
     }
 }
 
-impl<I1, P1, B, I2, P2, T3, T4, S: State> sealed::OrderedIterator
-    for PrimaryGenerator<I1, P1, B, I2, P2>
+/// A value that is known to be greater than zero.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Positive<F>(F);
+
+impl<F> Positive<F>
 where
-    I1: PositiveIterator,
-    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
-    P1: Iterator<Item = WirePattern>,
-    I2: PositiveIterator<Type = I1::Type>,
-    P2: Iterator<Item = WirePattern>,
-    B: FnMut(&WireEvent<I1::Type>) -> SecondaryGeneratorBuilder<I2, P2, T3, T4, S>,
-    T3: IntoIterator<IntoIter = I2>,
-    T4: IntoIterator<IntoIter = P2>,
-    S::Source: IsSet,
-    S::Origin: IsUnset,
-    S::InterArrivalTime: IsSet,
-    S::WirePattern: IsSet,
+    F: Zero + PartialOrd,
 {
+    pub fn new(value: F) -> Option<Self> {
+        if value > F::zero() {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+}
+
+impl<F> Positive<F> {
+    pub(crate) fn inner(&self) -> &F {
+        &self.0
+    }
+}
+
+/// Seed of the random number generators of a simulation.
+///
+/// Generators produce events from arbitrary iterators, so the randomness of a
+/// simulation is entirely under control of the user e.g.
+/// `distribution.sample_iter(seed.rng())`. A single master seed can be split
+/// into independent seeds (one per generator, one per distribution, etc.) with
+/// [`Seed::derive`]; see also
+/// [`WorldBuilder::add_seeded_generator`](crate::WorldBuilder::add_seeded_generator).
+///
+/// The same seed with the same version of this crate (and of its
+/// dependencies) always produces the same stream of events.
+///
+/// # Examples
+///
+/// ```
+/// use rand::distr::{Distribution, Uniform};
+/// use trg::gen::{Positive, SecondaryGenerator, Seed, Source, WirePattern};
+///
+/// let seed = Seed::new(42);
+/// let times = |seed: Seed| {
+///     SecondaryGenerator::builder()
+///         .source(Source::Noise)
+///         .origin(0.0)
+///         .inter_arrival_time(
+///             Uniform::new(1.0, 2.0)
+///                 .unwrap()
+///                 .map(|t| Positive::new(t).unwrap())
+///                 .sample_iter(seed.rng()),
+///         )
+///         .wire_pattern(std::iter::repeat(WirePattern::from_bits(1)))
+///         .build()
+///         .take(10)
+///         .map(|event| event.time)
+///         .collect::<Vec<_>>()
+/// };
+/// assert_eq!(times(seed.derive(0)), times(seed.derive(0)));
+/// assert_ne!(times(seed.derive(0)), times(seed.derive(1)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Seed(u64);
+
+// SplitMix64 output function. This is part of the reproducibility guarantee of
+// `Seed`, so it must never change.
+fn splitmix64(z: u64) -> u64 {
+    let z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Seed {
+    /// Create a new seed.
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    /// Returns the raw value of the seed.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+    /// Derive the `index`-th child seed. Different indices (or parents) give
+    /// statistically independent seeds.
+    pub fn derive(self, index: u64) -> Self {
+        Self(splitmix64(self.0 ^ splitmix64(index)))
+    }
+    /// Returns a random number generator seeded with this seed.
+    pub fn rng(self) -> StdRng {
+        StdRng::seed_from_u64(self.0)
+    }
+}
+
+impl From<u64> for Seed {
+    fn from(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+/// The reason why a generator stopped producing [`WireEvent`]s.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExhaustionReason {
+    /// The requested duration has been reached.
+    Duration,
+    /// The inter-arrival time distribution ran out of values.
+    InterArrivalTime,
+    /// The wire pattern distribution ran out of values. This is usually a
+    /// bug e.g. a finite `Vec` of patterns that is one element short.
+    WirePattern,
+    /// The generator found invalid input (e.g. a malformed row in a file).
+    InvalidInput,
+    /// The requested number of events has been produced.
+    EventCount,
+    /// The offsets of [`EventGenerator::jitter`] ran out of values.
+    Jitter,
+}
+
+pub(crate) mod sealed {
+    pub trait OrderedIterator: Iterator {
+        // Only meaningful after the iterator has returned `None`.
+        fn exhaustion_reason(&self) -> Option<super::ExhaustionReason> {
+            None
+        }
+        // Whether the last event was produced by a generator spawned by this
+        // one (e.g. afterpulses of a primary event).
+        fn spawned_last(&self) -> bool {
+            false
+        }
+    }
+}
+
+// Allows generators to be borrowed by a `World` and inspected afterwards.
+impl<G: sealed::OrderedIterator + ?Sized> sealed::OrderedIterator for &mut G {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        (**self).exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        (**self).spawned_last()
+    }
 }
 
-type InnerGen<T> = Box<dyn EventGenerator<Time = T, Item = WireEvent<T>>>;
+/// Generator of [`WireEvent`]s.
+///
+/// Events are guaranteed to be produced in increasing order of time.
+pub trait EventGenerator: sealed::OrderedIterator<Item = WireEvent<Self::Time>> {
+    type Time;
+
+    /// Limit the generator to its first `n` events.
+    ///
+    /// All events count towards the limit, including the afterpulses of a
+    /// [`PrimaryGenerator`]; afterpulses of the last primary event that come
+    /// after the `n`-th event are not produced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2, 3], WirePattern::from_bits(1));
+    /// assert_eq!(gen.take_events(2).count(), 2);
+    /// ```
+    fn take_events(self, n: usize) -> TakeEvents<Self>
+    where
+        Self: Sized,
+    {
+        TakeEvents {
+            inner: self,
+            remaining: n,
+        }
+    }
+    /// Limit the generator to events with a time less than `end`.
+    ///
+    /// This applies to all events, including the afterpulses of a
+    /// [`PrimaryGenerator`]; unlike its `duration`, which only limits the
+    /// primary events.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2, 3], WirePattern::from_bits(1));
+    /// assert_eq!(gen.until_time(3).count(), 2);
+    /// ```
+    fn until_time(self, end: Self::Time) -> UntilTime<Self, Self::Time>
+    where
+        Self: Sized,
+    {
+        UntilTime {
+            inner: self,
+            end,
+            reached: false,
+        }
+    }
+    /// Shift the time of all events by `delta`.
+    ///
+    /// All events are shifted by the same amount, so the afterpulses of a
+    /// [`PrimaryGenerator`] keep their timing relative to the primary event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2], WirePattern::from_bits(1));
+    /// let times: Vec<_> = gen.offset(100).map(|event| event.time).collect();
+    /// assert_eq!(times, [101, 102]);
+    /// ```
+    fn offset(self, delta: Self::Time) -> Offset<Self, Self::Time>
+    where
+        Self: Sized,
+    {
+        Offset { inner: self, delta }
+    }
+    /// Multiply the rate of events by `factor` i.e. divide the time of all
+    /// events by `factor`.
+    ///
+    /// Times are scaled with respect to zero, and this applies to all events;
+    /// the delay of the afterpulses of a [`PrimaryGenerator`] is scaled too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not a positive finite number.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1.0, 2.0], WirePattern::from_bits(1));
+    /// let times: Vec<_> = gen.scale_rate(2.0).map(|event| event.time).collect();
+    /// assert_eq!(times, [0.5, 1.0]);
+    /// ```
+    fn scale_rate(self, factor: f64) -> ScaleRate<Self>
+    where
+        Self: Sized,
+        Self::Time: Mul<f64, Output = Self::Time>,
+    {
+        assert!(
+            factor.is_finite() && factor > 0.0,
+            "rate factor {factor} is not a positive finite number"
+        );
+        ScaleRate {
+            inner: self,
+            scale: factor.recip(),
+        }
+    }
+    /// Keep only the events for which `predicate` returns `true`.
+    ///
+    /// Named differently from [`Iterator::filter`] so that both traits can be
+    /// in scope; the result is still an [`EventGenerator`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2, 3], WirePattern::from_bits(1));
+    /// let times: Vec<_> = gen
+    ///     .filter_events(|event| event.time != 2)
+    ///     .map(|event| event.time)
+    ///     .collect();
+    /// assert_eq!(times, [1, 3]);
+    /// ```
+    fn filter_events<F>(self, predicate: F) -> FilterEvents<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&WireEvent<Self::Time>) -> bool,
+    {
+        FilterEvents {
+            inner: self,
+            predicate,
+        }
+    }
+    /// Transform each event with `f`, dropping the events for which it returns
+    /// `None`.
+    ///
+    /// The generator does not re-sort events, so `f` must not change their
+    /// times in a way that breaks the time ordering.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2], WirePattern::from_bits(0b11));
+    /// let patterns: Vec<_> = gen
+    ///     .map_event(|mut event| {
+    ///         event.wire_pattern = event.wire_pattern & WirePattern::from_bits(0b10);
+    ///         Some(event)
+    ///     })
+    ///     .map(|event| event.wire_pattern.bits())
+    ///     .collect();
+    /// assert_eq!(patterns, [0b10, 0b10]);
+    /// ```
+    fn map_event<F>(self, f: F) -> MapEvent<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(WireEvent<Self::Time>) -> Option<WireEvent<Self::Time>>,
+    {
+        MapEvent { inner: self, f }
+    }
+    /// Convert the time of each event with `f` e.g. from integer ticks of a
+    /// firmware dump to `uom` quantities (or the other way around), so that
+    /// generators with different time types can be added to the same
+    /// [`World`](crate::World).
+    ///
+    /// All events are converted, including the afterpulses of a
+    /// [`PrimaryGenerator`]. The generator does not re-sort events, so `f`
+    /// must not decrease between consecutive times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// // Ticks of 16 ns.
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1u64, 2, 3], WirePattern::from_bits(1));
+    /// let times: Vec<f64> = gen
+    ///     .map_time(|ticks| ticks as f64 * 16.0)
+    ///     .map(|event| event.time)
+    ///     .collect();
+    /// assert_eq!(times, [16.0, 32.0, 48.0]);
+    /// ```
+    fn map_time<F, U>(self, f: F) -> MapTime<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Time) -> U,
+    {
+        MapTime { inner: self, f }
+    }
+    /// Apply a [`ChannelMask`] to all events, drawing the hot boards of each
+    /// event from `rng`. Events without any high board after masking are
+    /// dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::{rngs::StdRng, SeedableRng};
+    /// use trg::gen::{ChannelMask, EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2], WirePattern::from_bits(0b11));
+    /// let mask = ChannelMask::builder().disabled(WirePattern::from_bits(0b01)).build();
+    /// let patterns: Vec<_> = gen
+    ///     .mask_channels(mask, StdRng::seed_from_u64(0))
+    ///     .map(|event| event.wire_pattern.bits())
+    ///     .collect();
+    /// assert_eq!(patterns, [0b10, 0b10]);
+    /// ```
+    fn mask_channels<R: Rng>(self, mask: ChannelMask, rng: R) -> MaskChannels<Self, R>
+    where
+        Self: Sized,
+    {
+        MaskChannels {
+            inner: self,
+            mask,
+            rng,
+        }
+    }
+    /// Apply [`CrossTalk`] to the pattern of all events, drawing the induced
+    /// boards from `rng`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{CrossTalk, EventGenerator, ScriptedGenerator, Seed, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1], WirePattern::from_bits(1));
+    /// let cross_talk = CrossTalk::builder().neighbor(1.0).build();
+    /// let mut gen = gen.cross_talk(cross_talk, Seed::new(0).rng());
+    /// assert_eq!(gen.next().unwrap().wire_pattern.bits(), 0b1000_0000_0000_0011);
+    /// ```
+    fn cross_talk<R: Rng>(self, cross_talk: CrossTalk, rng: R) -> WithCrossTalk<Self, R>
+    where
+        Self: Sized,
+    {
+        WithCrossTalk {
+            inner: self,
+            cross_talk,
+            rng,
+        }
+    }
+    /// Add the next value of `offsets` to the time of each event e.g. to
+    /// simulate the timing jitter of the discriminators.
+    ///
+    /// Offsets are clamped to `[-window, window]`, and events are re-sorted so
+    /// that the generator stays time ordered; an event is only produced once no
+    /// later event can be jittered before it. Events with the same jittered
+    /// time keep their original order. The generator stops (after all buffered
+    /// events) with [`ExhaustionReason::Jitter`] if `offsets` runs out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, Positive, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [10, 12, 20], WirePattern::from_bits(1));
+    /// let times: Vec<_> = gen
+    ///     .jitter([3, -3, 100], Positive::new(5).unwrap())
+    ///     .map(|event| event.time)
+    ///     .collect();
+    /// assert_eq!(times, [9, 13, 25]);
+    /// ```
+    fn jitter<O>(self, offsets: O, window: Positive<Self::Time>) -> Jitter<Self, O::IntoIter>
+    where
+        Self: Sized,
+        O: IntoIterator<Item = Self::Time>,
+    {
+        Jitter {
+            inner: self,
+            offsets: offsets.into_iter(),
+            window: window.0,
+            buffer: BinaryHeap::new(),
+            latest: None,
+            read: 0,
+            done: false,
+            offsets_exhausted: false,
+            spawned_last: false,
+        }
+    }
+    /// Produce all the events of `other` after this generator is exhausted.
+    /// Events of `other` that are earlier than the last event of this
+    /// generator are dropped.
+    ///
+    /// Named differently from [`Iterator::chain`] so that both traits can be
+    /// in scope. See [`EventGenerator::chain_with`] to start `other` relative
+    /// to the last event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let first = ScriptedGenerator::from_times(Source::Noise, [1, 5], WirePattern::from_bits(1));
+    /// let second = ScriptedGenerator::from_times(Source::Noise, [3, 7], WirePattern::from_bits(2));
+    /// let times: Vec<_> = first.chain_events(second).map(|event| event.time).collect();
+    /// assert_eq!(times, [1, 5, 7]);
+    /// ```
+    fn chain_events<H>(self, other: H) -> Chain<Self, ChainThen<Self::Time, H>, H>
+    where
+        Self: Sized,
+        H: EventGenerator<Time = Self::Time>,
+    {
+        Chain {
+            first: self,
+            then: None,
+            second: Some(other),
+            last: None,
+            first_done: false,
+        }
+    }
+    /// Produce all the events of the generator returned by `then` after this
+    /// generator is exhausted. `then` is called once, with the time of the
+    /// last event (or `None` if this generator produced no events). Events of
+    /// the new generator that are earlier than the last event are dropped.
+    ///
+    /// # Examples
+    ///
+    /// A cool-down followed by a second phase that starts 10 time units after
+    /// the last event of the cool-down:
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let cool_down = ScriptedGenerator::from_times(Source::Noise, [1, 5], WirePattern::from_bits(1));
+    /// let gen = cool_down.chain_with(|last| {
+    ///     let origin = last.unwrap_or(0) + 10;
+    ///     ScriptedGenerator::from_times(Source::Noise, [origin, origin + 1], WirePattern::from_bits(2))
+    /// });
+    /// let times: Vec<_> = gen.map(|event| event.time).collect();
+    /// assert_eq!(times, [1, 5, 15, 16]);
+    /// ```
+    fn chain_with<F, H>(self, then: F) -> Chain<Self, F, H>
+    where
+        Self: Sized,
+        F: FnOnce(Option<Self::Time>) -> H,
+        H: EventGenerator<Time = Self::Time>,
+    {
+        Chain {
+            first: self,
+            then: Some(then),
+            second: None,
+            last: None,
+            first_done: false,
+        }
+    }
+    /// Merge runs of events that are within `window` of the first event of
+    /// the run (i.e. earlier than its time plus `window`) into a single event
+    /// e.g. to simulate the double-pulse resolution of the discriminators.
+    ///
+    /// The merged event has the time of the first event of the run, the OR of
+    /// all the patterns, the product of all the weights, and the source (and
+    /// id) of the event chosen by `policy`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{CoalescePolicy, EventGenerator, Positive, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [0, 2, 4, 5], WirePattern::from_bits(1))
+    ///     .map_event(|mut event| {
+    ///         event.wire_pattern = WirePattern::from_bits(1 << event.time);
+    ///         Some(event)
+    ///     });
+    /// let events: Vec<_> = gen
+    ///     .coalesce(Positive::new(3).unwrap(), CoalescePolicy::First)
+    ///     .map(|event| (event.time, event.wire_pattern.bits()))
+    ///     .collect();
+    /// assert_eq!(events, [(0, 0b101), (4, 0b110000)]);
+    /// ```
+    fn coalesce(self, window: Positive<Self::Time>, policy: CoalescePolicy) -> Coalesce<Self>
+    where
+        Self: Sized,
+    {
+        Coalesce {
+            inner: self,
+            window: window.0,
+            policy,
+            next: None,
+            done: false,
+            spawned_last: false,
+        }
+    }
+    /// Run the generator on a worker thread, which produces up to `depth`
+    /// events ahead of the consumer e.g. so that an expensive background
+    /// generator runs in parallel with the trigger logic.
+    ///
+    /// Events are produced in the same order as by the generator itself.
+    /// Dropping the result stops the worker thread (after the event it is
+    /// currently producing) and waits for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics (when it would produce the next event) if the generator panics
+    /// on the worker thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::{EventGenerator, ScriptedGenerator, Source, WirePattern};
+    ///
+    /// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2, 3], WirePattern::from_bits(1));
+    /// let times: Vec<_> = gen.buffered(2).map(|event| event.time).collect();
+    /// assert_eq!(times, [1, 2, 3]);
+    /// ```
+    fn buffered(self, depth: usize) -> Buffered<Self::Time>
+    where
+        Self: Sized + Send + 'static,
+        Self::Time: Send + 'static,
+    {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(depth);
+        let worker = std::thread::spawn(move || {
+            let mut gen = self;
+            loop {
+                let message = match gen.next() {
+                    Some(event) => Buffer::Event(event, gen.spawned_last()),
+                    None => Buffer::Exhausted(gen.exhaustion_reason()),
+                };
+                let last = matches!(message, Buffer::Exhausted(_));
+                // The consumer was dropped.
+                if sender.send(message).is_err() || last {
+                    break;
+                }
+            }
+        });
+
+        Buffered {
+            receiver: Some(receiver),
+            worker: Some(worker),
+            exhausted: None,
+            spawned_last: false,
+        }
+    }
+}
+
+impl<T, G> EventGenerator for G
+where
+    G: sealed::OrderedIterator<Item = WireEvent<T>>,
+{
+    type Time = T;
+}
+
+/// An [`EventGenerator`] limited to a number of events. See
+/// [`EventGenerator::take_events`].
+#[derive(Clone, Debug)]
+pub struct TakeEvents<G> {
+    inner: G,
+    remaining: usize,
+}
+
+impl<G: EventGenerator> Iterator for TakeEvents<G> {
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let event = self.inner.next()?;
+        self.remaining -= 1;
+
+        Some(event)
+    }
+}
+
+impl<G: EventGenerator> sealed::OrderedIterator for TakeEvents<G> {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        if self.remaining == 0 {
+            Some(ExhaustionReason::EventCount)
+        } else {
+            self.inner.exhaustion_reason()
+        }
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] limited to events before a given time. See
+/// [`EventGenerator::until_time`].
+#[derive(Clone, Debug)]
+pub struct UntilTime<G, T> {
+    inner: G,
+    end: T,
+    reached: bool,
+}
+
+impl<G: EventGenerator<Time = T>, T: PartialOrd> Iterator for UntilTime<G, T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.reached {
+            return None;
+        }
+        let event = self.inner.next()?;
+        if event.time < self.end {
+            Some(event)
+        } else {
+            self.reached = true;
+            None
+        }
+    }
+}
+
+impl<G: EventGenerator<Time = T>, T: PartialOrd> sealed::OrderedIterator for UntilTime<G, T> {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        if self.reached {
+            Some(ExhaustionReason::Duration)
+        } else {
+            self.inner.exhaustion_reason()
+        }
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] with all times shifted. See
+/// [`EventGenerator::offset`].
+#[derive(Clone, Debug)]
+pub struct Offset<G, T> {
+    inner: G,
+    delta: T,
+}
+
+impl<G, T> Iterator for Offset<G, T>
+where
+    G: EventGenerator<Time = T>,
+    T: Add<Output = T> + Clone,
+{
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut event = self.inner.next()?;
+        event.time = event.time + self.delta.clone();
+
+        Some(event)
+    }
+}
+
+impl<G, T> sealed::OrderedIterator for Offset<G, T>
+where
+    G: EventGenerator<Time = T>,
+    T: Add<Output = T> + Clone,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] that drops events matching a predicate. See
+/// [`EventGenerator::filter_events`].
+#[derive(Clone)]
+pub struct FilterEvents<G, F> {
+    inner: G,
+    predicate: F,
+}
+
+impl<G, F> Iterator for FilterEvents<G, F>
+where
+    G: EventGenerator,
+    F: FnMut(&WireEvent<G::Time>) -> bool,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find(&mut self.predicate)
+    }
+}
+
+impl<G, F> sealed::OrderedIterator for FilterEvents<G, F>
+where
+    G: EventGenerator,
+    F: FnMut(&WireEvent<G::Time>) -> bool,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] with all events transformed. See
+/// [`EventGenerator::map_event`].
+#[derive(Clone)]
+pub struct MapEvent<G, F> {
+    inner: G,
+    f: F,
+}
+
+impl<G, F> Iterator for MapEvent<G, F>
+where
+    G: EventGenerator,
+    F: FnMut(WireEvent<G::Time>) -> Option<WireEvent<G::Time>>,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(&mut self.f)
+    }
+}
+
+impl<G, F> sealed::OrderedIterator for MapEvent<G, F>
+where
+    G: EventGenerator,
+    F: FnMut(WireEvent<G::Time>) -> Option<WireEvent<G::Time>>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] with converted times. See
+/// [`EventGenerator::map_time`].
+#[derive(Clone, Debug)]
+pub struct MapTime<G, F> {
+    inner: G,
+    f: F,
+}
+
+impl<G, F, U> Iterator for MapTime<G, F>
+where
+    G: EventGenerator,
+    F: FnMut(G::Time) -> U,
+{
+    type Item = WireEvent<U>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.inner.next()?;
+        Some(WireEvent {
+            source: event.source,
+            wire_pattern: event.wire_pattern,
+            time: (self.f)(event.time),
+            id: event.id,
+            weight: event.weight,
+        })
+    }
+}
+
+impl<G, F, U> sealed::OrderedIterator for MapTime<G, F>
+where
+    G: EventGenerator,
+    F: FnMut(G::Time) -> U,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] with faulty boards. See
+/// [`EventGenerator::mask_channels`].
+#[derive(Clone, Debug)]
+pub struct MaskChannels<G, R> {
+    inner: G,
+    mask: ChannelMask,
+    rng: R,
+}
+
+impl<G: EventGenerator, R: Rng> Iterator for MaskChannels<G, R> {
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut event = self.inner.next()?;
+            for &(board, probability) in &self.mask.hot {
+                if self.rng.random_bool(probability) {
+                    event.wire_pattern.set_wire(board);
+                }
+            }
+            event.wire_pattern.0 &= !self.mask.disabled.0;
+
+            if event.wire_pattern.0 != 0 {
+                return Some(event);
+            }
+        }
+    }
+}
+
+impl<G: EventGenerator, R: Rng> sealed::OrderedIterator for MaskChannels<G, R> {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] with cross-talk between boards. See
+/// [`EventGenerator::cross_talk`].
+#[derive(Clone, Debug)]
+pub struct WithCrossTalk<G, R> {
+    inner: G,
+    cross_talk: CrossTalk,
+    rng: R,
+}
+
+impl<G: EventGenerator, R: Rng> Iterator for WithCrossTalk<G, R> {
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut event = self.inner.next()?;
+        event.wire_pattern = self.cross_talk.apply(event.wire_pattern, &mut self.rng);
+
+        Some(event)
+    }
+}
+
+impl<G: EventGenerator, R: Rng> sealed::OrderedIterator for WithCrossTalk<G, R> {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// An [`EventGenerator`] with jittered times. See [`EventGenerator::jitter`].
+#[derive(Clone)]
+pub struct Jitter<G: EventGenerator, O> {
+    inner: G,
+    offsets: O,
+    window: G::Time,
+    // Jittered events that could still be preceded by a later event, and
+    // whether they were produced by a spawned generator.
+    buffer: BinaryHeap<Head<WireEvent<G::Time>, bool>>,
+    // Original time of the last event read from `inner`.
+    latest: Option<G::Time>,
+    // Number of events read from `inner`; breaks ties in `buffer`.
+    read: usize,
+    // No more events will be added to `buffer`.
+    done: bool,
+    offsets_exhausted: bool,
+    spawned_last: bool,
+}
+
+impl<G, O> Iterator for Jitter<G, O>
+where
+    G: EventGenerator,
+    G::Time: Add<Output = G::Time> + Neg<Output = G::Time> + Clone + PartialOrd,
+    O: Iterator<Item = G::Time>,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(head) = self.buffer.peek() {
+                // Later events have an original time of at least `latest`, so
+                // they can not be jittered to before `latest - window`.
+                let settled = self.done
+                    || self.latest.as_ref().is_some_and(|latest| {
+                        head.event.time.clone() + self.window.clone() <= *latest
+                    });
+                if settled {
+                    return self.buffer.pop().map(|head| {
+                        self.spawned_last = head.source;
+                        head.event
+                    });
+                }
+            } else if self.done {
+                return None;
+            }
+
+            let Some(mut event) = self.inner.next() else {
+                self.done = true;
+                continue;
+            };
+            let Some(mut offset) = self.offsets.next() else {
+                self.done = true;
+                self.offsets_exhausted = true;
+                continue;
+            };
+            if offset > self.window {
+                offset = self.window.clone();
+            } else if offset < -self.window.clone() {
+                offset = -self.window.clone();
+            }
+
+            self.latest = Some(event.time.clone());
+            event.time = event.time + offset;
+            self.buffer.push(Head {
+                id: self.read,
+                tie: 0,
+                event,
+                source: self.inner.spawned_last(),
+            });
+            self.read += 1;
+        }
+    }
+}
+
+impl<G, O> sealed::OrderedIterator for Jitter<G, O>
+where
+    G: EventGenerator,
+    G::Time: Add<Output = G::Time> + Neg<Output = G::Time> + Clone + PartialOrd,
+    O: Iterator<Item = G::Time>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        if self.offsets_exhausted {
+            Some(ExhaustionReason::Jitter)
+        } else {
+            self.inner.exhaustion_reason()
+        }
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.spawned_last
+    }
+}
+
+/// The event that gives its [`Source`] to an event merged by
+/// [`EventGenerator::coalesce`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CoalescePolicy {
+    /// The first event of the run.
+    #[default]
+    First,
+    /// The event whose source comes first in the list; sources that are not
+    /// in the list come after all the others. Ties go to the earliest event.
+    Priority(Vec<Source>),
+}
+
+impl CoalescePolicy {
+    // Lower is preferred.
+    fn rank(&self, source: Source) -> usize {
+        match self {
+            Self::First => 0,
+            Self::Priority(sources) => sources
+                .iter()
+                .position(|s| *s == source)
+                .unwrap_or(sources.len()),
+        }
+    }
+}
+
+/// An [`EventGenerator`] with near-simultaneous events merged. See
+/// [`EventGenerator::coalesce`].
+#[derive(Clone)]
+pub struct Coalesce<G: EventGenerator> {
+    inner: G,
+    window: G::Time,
+    policy: CoalescePolicy,
+    // First event of the next run (already taken from `inner`), and whether it
+    // was produced by a spawned generator.
+    next: Option<(WireEvent<G::Time>, bool)>,
+    // Whether `inner` is exhausted; it is not polled again.
+    done: bool,
+    spawned_last: bool,
+}
+
+impl<G> Iterator for Coalesce<G>
+where
+    G: EventGenerator,
+    G::Time: Add<Output = G::Time> + Clone + PartialOrd,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (first, mut spawned) = match self.next.take() {
+            Some(next) => next,
+            None if self.done => return None,
+            None => match self.inner.next() {
+                Some(event) => (event, self.inner.spawned_last()),
+                None => {
+                    self.done = true;
+                    return None;
+                }
+            },
+        };
+        let end = first.time.clone() + self.window.clone();
+        let mut wire_pattern = first.wire_pattern;
+        let mut weight = first.weight;
+        let mut representative = (first.source, first.id);
+        while !self.done {
+            let Some(event) = self.inner.next() else {
+                self.done = true;
+                break;
+            };
+            if event.time >= end {
+                self.next = Some((event, self.inner.spawned_last()));
+                break;
+            }
+            wire_pattern = wire_pattern | event.wire_pattern;
+            weight *= event.weight;
+            if self.policy.rank(event.source) < self.policy.rank(representative.0) {
+                representative = (event.source, event.id);
+                spawned = self.inner.spawned_last();
+            }
+        }
+        self.spawned_last = spawned;
+
+        Some(WireEvent {
+            source: representative.0,
+            wire_pattern,
+            time: first.time,
+            id: representative.1,
+            weight,
+        })
+    }
+}
+
+impl<G> sealed::OrderedIterator for Coalesce<G>
+where
+    G: EventGenerator,
+    G::Time: Add<Output = G::Time> + Clone + PartialOrd,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.spawned_last
+    }
+}
+
+// Never called; `other` is already known.
+type ChainThen<T, H> = fn(Option<T>) -> H;
+
+// Message from the worker thread of a `Buffered` generator.
+enum Buffer<T> {
+    // An event, and whether it was produced by a spawned generator.
+    Event(WireEvent<T>, bool),
+    Exhausted(Option<ExhaustionReason>),
+}
+
+/// An [`EventGenerator`] that runs on a worker thread. See
+/// [`EventGenerator::buffered`].
+pub struct Buffered<T> {
+    // `None` once the worker is done.
+    receiver: Option<std::sync::mpsc::Receiver<Buffer<T>>>,
+    worker: Option<std::thread::JoinHandle<()>>,
+    exhausted: Option<ExhaustionReason>,
+    spawned_last: bool,
+}
+
+impl<T> Buffered<T> {
+    // Stop the worker (if it is still running) and wait for it. Returns the
+    // panic of the worker, if any.
+    fn join(&mut self) -> Option<Box<dyn std::any::Any + Send>> {
+        // Any blocked or later `send` fails.
+        self.receiver = None;
+        self.worker.take()?.join().err()
+    }
+}
+
+impl<T> Iterator for Buffered<T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.as_ref()?.recv() {
+            Ok(Buffer::Event(event, spawned)) => {
+                self.spawned_last = spawned;
+                Some(event)
+            }
+            Ok(Buffer::Exhausted(reason)) => {
+                self.exhausted = reason;
+                self.join();
+                None
+            }
+            // The worker hung up without a reason i.e. it panicked.
+            Err(_) => {
+                if let Some(panic) = self.join() {
+                    std::panic::resume_unwind(panic);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<T> sealed::OrderedIterator for Buffered<T> {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.spawned_last
+    }
+}
+
+impl<T> Drop for Buffered<T> {
+    fn drop(&mut self) {
+        // A panic of the worker is only reported to the consumer.
+        let _ = self.join();
+    }
+}
+
+/// An [`EventGenerator`] followed by another one. See
+/// [`EventGenerator::chain_events`] and [`EventGenerator::chain_with`].
+#[derive(Clone)]
+pub struct Chain<G: EventGenerator, F, H> {
+    first: G,
+    // Builds `second` once `first` is exhausted.
+    then: Option<F>,
+    second: Option<H>,
+    // Time of the last event of `first`.
+    last: Option<G::Time>,
+    first_done: bool,
+}
+
+impl<G, F, H> Iterator for Chain<G, F, H>
+where
+    G: EventGenerator,
+    G::Time: PartialOrd + Clone,
+    F: FnOnce(Option<G::Time>) -> H,
+    H: EventGenerator<Time = G::Time>,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.first_done {
+            if let Some(event) = self.first.next() {
+                self.last = Some(event.time.clone());
+                return Some(event);
+            }
+            self.first_done = true;
+            if let Some(then) = self.then.take() {
+                self.second = Some(then(self.last.clone()));
+            }
+        }
+
+        let last = &self.last;
+        self.second
+            .as_mut()?
+            .find(|event| last.as_ref().is_none_or(|last| event.time >= *last))
+    }
+}
+
+impl<G, F, H> sealed::OrderedIterator for Chain<G, F, H>
+where
+    G: EventGenerator,
+    G::Time: PartialOrd + Clone,
+    F: FnOnce(Option<G::Time>) -> H,
+    H: EventGenerator<Time = G::Time>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        match &self.second {
+            Some(second) if self.first_done => second.exhaustion_reason(),
+            _ => self.first.exhaustion_reason(),
+        }
+    }
+
+    fn spawned_last(&self) -> bool {
+        match &self.second {
+            Some(second) if self.first_done => second.spawned_last(),
+            _ => self.first.spawned_last(),
+        }
+    }
+}
+
+/// An [`EventGenerator`] with all times scaled. See
+/// [`EventGenerator::scale_rate`].
+#[derive(Clone, Debug)]
+pub struct ScaleRate<G> {
+    inner: G,
+    // Reciprocal of the rate factor.
+    scale: f64,
+}
+
+impl<G> Iterator for ScaleRate<G>
+where
+    G: EventGenerator,
+    G::Time: Mul<f64, Output = G::Time>,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.inner.next()?;
+
+        Some(WireEvent {
+            time: event.time * self.scale,
+            ..event
+        })
+    }
+}
+
+impl<G> sealed::OrderedIterator for ScaleRate<G>
+where
+    G: EventGenerator,
+    G::Time: Mul<f64, Output = G::Time>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// A trait for dealing with iterators that produce positive values.
+pub trait PositiveIterator: Iterator<Item = Positive<Self::Type>> {
+    type Type;
+}
+
+impl<T, I: Iterator<Item = Positive<T>>> PositiveIterator for I {
+    type Type = T;
+}
+
+use secondary_generator_builder::{
+    IsSet, IsUnset, SetEnd, SetInterArrivalTime, SetOrigin, SetRelativeToParent, State,
+};
+
+// Time limit of a generator, given by the `duration` or `end_time` setters of
+// its builder.
+#[derive(Clone, Debug)]
+enum End<T> {
+    Duration(Positive<T>),
+    Time(T),
+}
+
+impl<T: Add<Output = T>> End<T> {
+    // Returns the (exclusive) end time for a generator that starts at `origin`.
+    fn time(self, origin: T) -> T {
+        match self {
+            Self::Duration(Positive(duration)) => origin + duration,
+            Self::Time(time) => time,
+        }
+    }
+}
+
+/// A generator of [`WireEvent`]s without afterpulses.
+///
+/// The generator produces a stream of [`WireEvent`]s all with the same
+/// [`Source`]. A generator stops producing events when either the desired
+/// duration has been reached, or when the inter-arrival time/wire pattern
+/// distributions have been exhausted. See
+/// [`SecondaryGenerator::exhaustion_reason`] to tell these cases apart.
+///
+/// # Examples
+///
+/// ```
+/// use std::iter::repeat;
+/// use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+///
+/// let gen = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0)
+///     .end_time(30)
+///     .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+///     .build();
+/// // The end time is exclusive.
+/// assert_eq!(gen.map(|event| event.time).collect::<Vec<_>>(), [10, 20]);
+/// ```
+///
+/// A `duration` and an `end_time` can not be both set:
+///
+/// ```compile_fail
+/// # use std::iter::repeat;
+/// # use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+/// let gen = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0)
+///     .duration(Positive::new(30).unwrap())
+///     .end_time(30)
+///     .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct SecondaryGenerator<I, P>
+where
+    I: PositiveIterator,
+{
+    source: Source,
+    current_time: Option<I::Type>,
+    max_time: Option<I::Type>,
+    inter_arrival_time: I,
+    wire_pattern: P,
+    remaining_events: Option<usize>,
+    // Set by the `PrimaryGenerator` that spawned this generator.
+    event_id: Option<EventId>,
+    exhausted: Option<ExhaustionReason>,
+}
+
+#[bon]
+impl<I, P> SecondaryGenerator<I, P>
+where
+    I: PositiveIterator,
+{
+    #[builder]
+    pub fn new<T1, T2>(
+        /// The source of the generated events.
+        source: Source,
+        /// Set with `origin`, or by the [`PrimaryGenerator`] that spawns the
+        /// generator (see `relative_to_parent`).
+        #[builder(setters(vis = "", name = origin_internal))]
+        origin: I::Type,
+        /// Only used by the builder, to prevent setting both `origin` and
+        /// `relative_to_parent`.
+        #[builder(default, setters(vis = "", name = relative_to_parent_internal))]
+        relative_to_parent: bool,
+        /// Set with either `duration` or `end_time`.
+        #[builder(setters(vis = ""))]
+        end: Option<End<I::Type>>,
+        /// Maximum number of events, independent of `duration`. Unlimited by
+        /// default.
+        max_events: Option<usize>,
+        /// The distribution of inter-arrival times between events.
+        inter_arrival_time: T1,
+        /// The distribution of wire patterns.
+        wire_pattern: T2,
+    ) -> Self
+    where
+        T1: IntoIterator<IntoIter = I>,
+        T2: IntoIterator<IntoIter = P>,
+        // Another alternative could be
+        // `I::Type: for<'a> Add<&'a I::Type, Output = I::Type>` instead.
+        // But:
+        // 1. `impl Iterator` requires `I::Type: Clone` anyway.
+        // 2. I want to use `Quantity` types from the `uom` crate, and these
+        // don't implement `Add<&T, Output = T>` yet (maybe soon they will).
+        I::Type: Add<Output = I::Type> + Clone,
+    {
+        let _ = relative_to_parent;
+        Self {
+            source,
+            current_time: Some(origin.clone()),
+            max_time: end.map(|end| end.time(origin)),
+            inter_arrival_time: inter_arrival_time.into_iter(),
+            wire_pattern: wire_pattern.into_iter(),
+            remaining_events: max_events,
+            event_id: None,
+            exhausted: None,
+        }
+    }
+}
+
+impl<I, P, T1, T2, S: State> SecondaryGeneratorBuilder<I, P, T1, T2, S>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone,
+    T1: IntoIterator<IntoIter = I>,
+    T2: IntoIterator<IntoIter = P>,
+{
+    /// The (absolute) time at which the generator starts producing events.
+    /// Note that the first event is produced at `origin` + `delta_t`, where
+    /// `delta_t` is the first value produced by `inter_arrival_time`.
+    ///
+    /// Required unless the builder is returned by the `afterpulse` closure of
+    /// a [`PrimaryGenerator`], and mutually exclusive with
+    /// [`relative_to_parent`](Self::relative_to_parent).
+    pub fn origin(self, origin: I::Type) -> SecondaryGeneratorBuilder<I, P, T1, T2, SetOrigin<S>>
+    where
+        S::Origin: IsUnset,
+        S::RelativeToParent: IsUnset,
+    {
+        self.origin_internal(origin)
+    }
+    /// Start the generator at the time of the primary event that spawns it.
+    /// This is the default for a builder returned by the `afterpulse` closure
+    /// of a [`PrimaryGenerator`] (see [`Afterpulses`]), so setting it only
+    /// makes the intent explicit: the generator can then not be built with an
+    /// absolute [`origin`](Self::origin).
+    ///
+    /// A `duration` is relative to the time of the primary event, but an
+    /// `end_time` is still absolute.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::iter::repeat;
+    /// use trg::gen::{PrimaryGenerator, Positive, SecondaryGenerator, Source, WirePattern};
+    ///
+    /// let gen = PrimaryGenerator::builder()
+    ///     .source(Source::PrimaryCosmic)
+    ///     .origin(0)
+    ///     .end_time(250)
+    ///     .inter_arrival_time(repeat(Positive::new(100).unwrap()))
+    ///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+    ///     .afterpulse(|_: &_| {
+    ///         SecondaryGenerator::builder()
+    ///             .source(Source::SecondaryCosmic)
+    ///             .relative_to_parent()
+    ///             .inter_arrival_time([Positive::new(5).unwrap()])
+    ///             .wire_pattern(repeat(WirePattern::from_bits(1)))
+    ///     })
+    ///     .build();
+    /// assert_eq!(gen.map(|event| event.time).collect::<Vec<_>>(), [100, 105, 200, 205]);
+    /// ```
+    ///
+    /// A relative generator can not have an `origin`:
+    ///
+    /// ```compile_fail
+    /// # use std::iter::repeat;
+    /// # use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+    /// let gen = SecondaryGenerator::builder()
+    ///     .source(Source::SecondaryCosmic)
+    ///     .relative_to_parent()
+    ///     .origin(0)
+    ///     .inter_arrival_time([Positive::new(5).unwrap()])
+    ///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+    ///     .build();
+    /// ```
+    ///
+    /// Nor can it be built on its own:
+    ///
+    /// ```compile_fail
+    /// # use std::iter::repeat;
+    /// # use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+    /// let gen = SecondaryGenerator::builder()
+    ///     .source(Source::SecondaryCosmic)
+    ///     .relative_to_parent()
+    ///     .inter_arrival_time([Positive::new(5).unwrap()])
+    ///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+    ///     .build();
+    /// ```
+    pub fn relative_to_parent(
+        self,
+    ) -> SecondaryGeneratorBuilder<I, P, T1, T2, SetRelativeToParent<S>>
+    where
+        S::Origin: IsUnset,
+        S::RelativeToParent: IsUnset,
+    {
+        self.relative_to_parent_internal(true)
+    }
+    /// Length of time the generator produces events for. All events have a
+    /// time strictly less than `origin` + `duration`; an event at exactly
+    /// `origin` + `duration` is not produced. Unlimited by default, and
+    /// mutually exclusive with `end_time`.
+    pub fn duration(
+        self,
+        duration: Positive<I::Type>,
+    ) -> SecondaryGeneratorBuilder<I, P, T1, T2, SetEnd<S>>
+    where
+        S::End: IsUnset,
+    {
+        self.end(End::Duration(duration))
+    }
+    /// Same as [`duration`](Self::duration), but optional.
+    pub fn maybe_duration(
+        self,
+        duration: Option<Positive<I::Type>>,
+    ) -> SecondaryGeneratorBuilder<I, P, T1, T2, SetEnd<S>>
+    where
+        S::End: IsUnset,
+    {
+        self.maybe_end(duration.map(End::Duration))
+    }
+    /// Time at which the generator stops producing events. All events have a
+    /// time strictly less than `end_time`. Unlimited by default, and mutually
+    /// exclusive with `duration`.
+    pub fn end_time(self, end_time: I::Type) -> SecondaryGeneratorBuilder<I, P, T1, T2, SetEnd<S>>
+    where
+        S::End: IsUnset,
+    {
+        self.end(End::Time(end_time))
+    }
+    /// Same as [`end_time`](Self::end_time), but optional.
+    pub fn maybe_end_time(
+        self,
+        end_time: Option<I::Type>,
+    ) -> SecondaryGeneratorBuilder<I, P, T1, T2, SetEnd<S>>
+    where
+        S::End: IsUnset,
+    {
+        self.maybe_end(end_time.map(End::Time))
+    }
+}
+
+impl<D, G, F, T, P, T2, S>
+    SecondaryGeneratorBuilder<SampledTimes<D, G, F>, P, SampledTimes<D, G, F>, T2, S>
+where
+    D: Distribution<f64>,
+    G: Rng,
+    F: FnMut(f64) -> T,
+    T: Zero + PartialOrd + Add<Output = T> + Clone,
+    T2: IntoIterator<IntoIter = P>,
+    S: State,
+{
+    /// Set `inter_arrival_time` to samples of `distribution` drawn from `rng`,
+    /// and converted to times with `to_time` (see [`SampledTimes`]).
+    #[allow(clippy::type_complexity)]
+    pub fn inter_arrival_distribution(
+        self,
+        distribution: D,
+        rng: G,
+        to_time: F,
+    ) -> SecondaryGeneratorBuilder<
+        SampledTimes<D, G, F>,
+        P,
+        SampledTimes<D, G, F>,
+        T2,
+        SetInterArrivalTime<S>,
+    >
+    where
+        S::InterArrivalTime: IsUnset,
+    {
+        self.inter_arrival_time(SampledTimes::new(distribution, rng, to_time))
+    }
+}
+
+impl<I, P> SecondaryGenerator<I, P>
+where
+    I: PositiveIterator,
+{
+    /// Returns the reason why the generator stopped producing events, or
+    /// `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+impl<I, P> Iterator for SecondaryGenerator<I, P>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+    P: Iterator<Item = WirePattern>,
+{
+    type Item = WireEvent<I::Type>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = self.current_time.take()?;
+        if self.remaining_events == Some(0) {
+            self.exhausted = Some(ExhaustionReason::EventCount);
+            return None;
+        }
+        let Some(Positive(delta_t)) = self.inter_arrival_time.next() else {
+            self.exhausted = Some(ExhaustionReason::InterArrivalTime);
+            return None;
+        };
+        let time = time + delta_t;
+        if self
+            .max_time
+            .as_ref()
+            .is_some_and(|max_time| time >= *max_time)
+        {
+            self.exhausted = Some(ExhaustionReason::Duration);
+            return None;
+        }
+        // Checked last so that a finite pattern distribution that is exactly
+        // long enough for the duration is not reported.
+        let Some(wire_pattern) = self.wire_pattern.next() else {
+            self.exhausted = Some(ExhaustionReason::WirePattern);
+            return None;
+        };
+        self.current_time = Some(time.clone());
+        if let Some(remaining) = &mut self.remaining_events {
+            *remaining -= 1;
+        }
+
+        Some(WireEvent {
+            source: self.source,
+            wire_pattern,
+            time,
+            id: self.event_id,
+            weight: 1.0,
+        })
+    }
+}
+
+impl<I, P> sealed::OrderedIterator for SecondaryGenerator<I, P>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+    P: Iterator<Item = WirePattern>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+/// A generator of [`WireEvent`]s with afterpulses.
+///
+/// The generator produces a stream of [`WireEvent`]s in increasing order of
+/// time. Each primary event triggers a set of secondary events. The generator
+/// stops when all primary and secondary events have been produced.
+pub struct PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+{
+    primary: SecondaryGenerator<I1, P1>,
+    // Same as `Peekable`, but keeps `primary` accessible.
+    peeked_primary: Option<Option<WireEvent<I1::Type>>>,
+    afterpulse: B,
+    // Secondary generators are identified by the order in which they were
+    // spawned.
+    secondaries: Merge<WireEvent<I1::Type>, SecondaryGenerator<I2, P2>>,
+    spawned: usize,
+    // Number of primary events produced so far.
+    primaries: u64,
+    // Whether the last event was secondary.
+    secondary_last: bool,
+}
+
+// The Derive macro is not smart enough to implement Clone in this case.
+impl<I1: Clone, P1: Clone, B: Clone, I2: Clone, P2: Clone> Clone
+    for PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            peeked_primary: self.peeked_primary.clone(),
+            afterpulse: self.afterpulse.clone(),
+            secondaries: self.secondaries.clone(),
+            spawned: self.spawned,
+            primaries: self.primaries,
+            secondary_last: self.secondary_last,
+        }
+    }
+}
+
+#[bon]
+impl<I1, P1, B, I2, P2> PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+{
+    #[builder]
+    pub fn new<T1, T2>(
+        /// The source of the primary events.
+        source: Source,
+        /// The time at which the generator starts producing events. Note that
+        /// the first event is produced at `origin` + `delta_t`, where `delta_t`
+        /// is the first value produced by `inter_arrival_time`.
+        origin: I1::Type,
+        /// Set with either `duration` or `end_time`.
+        #[builder(setters(vis = ""))]
+        end: Option<End<I1::Type>>,
+        /// The distribution of inter-arrival times between primary events.
+        inter_arrival_time: T1,
+        /// The distribution of wire patterns for primary events.
+        wire_pattern: T2,
+        /// Secondary generator builder, or any collection of them (see
+        /// [`Afterpulses`]).
+        ///
+        /// The `origin` of the secondary generator is automatically set to the
+        /// time of the primary event that triggered it.
+        afterpulse: B,
+    ) -> Self
+    where
+        T1: IntoIterator<IntoIter = I1>,
+        T2: IntoIterator<IntoIter = P1>,
+    {
+        let primary = SecondaryGenerator::builder()
+            .source(source)
+            .origin(origin)
+            .maybe_end(end)
+            .inter_arrival_time(inter_arrival_time)
+            .wire_pattern(wire_pattern)
+            .build();
+
+        Self::from_primary(primary, afterpulse)
+    }
+
+    fn from_primary(primary: SecondaryGenerator<I1, P1>, afterpulse: B) -> Self {
+        Self {
+            primary,
+            peeked_primary: None,
+            afterpulse,
+            secondaries: Merge::default(),
+            spawned: 0,
+            primaries: 0,
+            secondary_last: false,
+        }
+    }
+}
+
+impl<I1, P1, B, I2, P2, T1, T2, S> PrimaryGeneratorBuilder<I1, P1, B, I2, P2, T1, T2, S>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+    T1: IntoIterator<IntoIter = I1>,
+    T2: IntoIterator<IntoIter = P1>,
+    S: primary_generator_builder::State,
+{
+    /// Length of time the generator produces primary events for. All primary
+    /// events have a time strictly less than `origin` + `duration`; a primary
+    /// event at exactly `origin` + `duration` is not produced. Secondary
+    /// events are produced until all secondary generators have been exhausted
+    /// (which could be after the end of the primary events). Unlimited by
+    /// default, and mutually exclusive with `end_time`.
+    pub fn duration(
+        self,
+        duration: Positive<I1::Type>,
+    ) -> PrimaryGeneratorBuilder<I1, P1, B, I2, P2, T1, T2, primary_generator_builder::SetEnd<S>>
+    where
+        S::End: primary_generator_builder::IsUnset,
+    {
+        self.end(End::Duration(duration))
+    }
+    /// Same as [`duration`](Self::duration), but optional.
+    pub fn maybe_duration(
+        self,
+        duration: Option<Positive<I1::Type>>,
+    ) -> PrimaryGeneratorBuilder<I1, P1, B, I2, P2, T1, T2, primary_generator_builder::SetEnd<S>>
+    where
+        S::End: primary_generator_builder::IsUnset,
+    {
+        self.maybe_end(duration.map(End::Duration))
+    }
+    /// Time at which the generator stops producing primary events. All
+    /// primary events have a time strictly less than `end_time`; see
+    /// [`duration`](Self::duration) for secondary events. Unlimited by
+    /// default, and mutually exclusive with `duration`.
+    pub fn end_time(
+        self,
+        end_time: I1::Type,
+    ) -> PrimaryGeneratorBuilder<I1, P1, B, I2, P2, T1, T2, primary_generator_builder::SetEnd<S>>
+    where
+        S::End: primary_generator_builder::IsUnset,
+    {
+        self.end(End::Time(end_time))
+    }
+    /// Same as [`end_time`](Self::end_time), but optional.
+    pub fn maybe_end_time(
+        self,
+        end_time: Option<I1::Type>,
+    ) -> PrimaryGeneratorBuilder<I1, P1, B, I2, P2, T1, T2, primary_generator_builder::SetEnd<S>>
+    where
+        S::End: primary_generator_builder::IsUnset,
+    {
+        self.maybe_end(end_time.map(End::Time))
+    }
+}
+
+impl<D, G, F, T, P1, B, I2, P2, T2, S>
+    PrimaryGeneratorBuilder<SampledTimes<D, G, F>, P1, B, I2, P2, SampledTimes<D, G, F>, T2, S>
+where
+    D: Distribution<f64>,
+    G: Rng,
+    F: FnMut(f64) -> T,
+    T: Zero + PartialOrd + Add<Output = T> + Clone,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = T>,
+    P2: Iterator<Item = WirePattern>,
+    T2: IntoIterator<IntoIter = P1>,
+    S: primary_generator_builder::State,
+{
+    /// Set `inter_arrival_time` to samples of `distribution` drawn from `rng`,
+    /// and converted to times with `to_time` (see [`SampledTimes`]).
+    #[allow(clippy::type_complexity)]
+    pub fn inter_arrival_distribution(
+        self,
+        distribution: D,
+        rng: G,
+        to_time: F,
+    ) -> PrimaryGeneratorBuilder<
+        SampledTimes<D, G, F>,
+        P1,
+        B,
+        I2,
+        P2,
+        SampledTimes<D, G, F>,
+        T2,
+        primary_generator_builder::SetInterArrivalTime<S>,
+    >
+    where
+        S::InterArrivalTime: primary_generator_builder::IsUnset,
+    {
+        self.inter_arrival_time(SampledTimes::new(distribution, rng, to_time))
+    }
+}
+
+impl<I1, P1, B, I2, P2> PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+{
+    /// Returns the reason why the generator stopped producing primary events,
+    /// or `None` if it has not stopped yet. Note that secondary events can
+    /// still be pending after the primary events have stopped.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.primary.exhaustion_reason()
+    }
+}
+
+/// Afterpulses of a primary event, as returned by the `afterpulse` closure of a
+/// [`PrimaryGenerator`].
+///
+/// This is implemented for a [`SecondaryGeneratorBuilder`] without an `origin`
+/// (which is set to the time of the primary event, see
+/// [`relative_to_parent`](SecondaryGeneratorBuilder::relative_to_parent)), and for any collection of
+/// them e.g. an `Option` where `None` means that the primary event has no
+/// afterpulses, or an array with afterpulses from independent processes. All
+/// builders in a collection have to produce the same type of generator.
+pub trait Afterpulses {
+    type InterArrivalTime: PositiveIterator;
+    type WirePattern: Iterator<Item = WirePattern>;
+
+    /// Build all the generators of afterpulses starting at `origin`, and pass
+    /// each of them to `spawn`.
+    fn spawn<F>(self, origin: &<Self::InterArrivalTime as PositiveIterator>::Type, spawn: F)
+    where
+        F: FnMut(SecondaryGenerator<Self::InterArrivalTime, Self::WirePattern>);
+}
+
+impl<I, P, T1, T2, S: State> Afterpulses for SecondaryGeneratorBuilder<I, P, T1, T2, S>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone,
+    P: Iterator<Item = WirePattern>,
+    T1: IntoIterator<IntoIter = I>,
+    T2: IntoIterator<IntoIter = P>,
+    S::Source: IsSet,
+    S::Origin: IsUnset,
+    S::InterArrivalTime: IsSet,
+    S::WirePattern: IsSet,
+{
+    type InterArrivalTime = I;
+    type WirePattern = P;
+
+    fn spawn<F>(self, origin: &I::Type, mut spawn: F)
+    where
+        F: FnMut(SecondaryGenerator<I, P>),
+    {
+        spawn(self.origin_internal(origin.clone()).build());
+    }
+}
+
+impl<C> Afterpulses for C
+where
+    C: IntoIterator,
+    C::Item: Afterpulses,
+{
+    type InterArrivalTime = <C::Item as Afterpulses>::InterArrivalTime;
+    type WirePattern = <C::Item as Afterpulses>::WirePattern;
+
+    fn spawn<F>(self, origin: &<Self::InterArrivalTime as PositiveIterator>::Type, mut spawn: F)
+    where
+        F: FnMut(SecondaryGenerator<Self::InterArrivalTime, Self::WirePattern>),
+    {
+        for afterpulses in self {
+            afterpulses.spawn(origin, &mut spawn);
+        }
+    }
+}
+
+impl<I1, P1, B, I2, P2, A> PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+    B: FnMut(&WireEvent<I1::Type>) -> A,
+    A: Afterpulses<InterArrivalTime = I2, WirePattern = P2>,
+{
+    fn next_primary(&mut self) -> Option<WireEvent<I1::Type>> {
+        let next_event = match self.peeked_primary.take() {
+            Some(peeked) => peeked,
+            None => self.primary.next(),
+        };
+        if let Some(mut next_event) = next_event {
+            self.secondary_last = false;
+            let id = EventId(self.primaries);
+            self.primaries += 1;
+            next_event.id = Some(id);
+
+            let (secondaries, spawned) = (&mut self.secondaries, &mut self.spawned);
+            (self.afterpulse)(&next_event).spawn(&next_event.time, |mut generator| {
+                generator.event_id = Some(id);
+                // Only keep around secondary generators that have something to
+                // produce.
+                if let Some(event) = generator.next() {
+                    secondaries.push(*spawned, event, generator);
+                    *spawned += 1;
+                }
+            });
+
+            Some(next_event)
+        } else {
+            None
+        }
+    }
+
+    fn next_secondary(&mut self) -> Option<WireEvent<I1::Type>> {
+        self.secondary_last = true;
+        self.secondaries.pop().map(|(_, event, _)| event)
+    }
+}
+
+impl<I1, P1, B, I2, P2, A> Iterator for PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+    B: FnMut(&WireEvent<I1::Type>) -> A,
+    A: Afterpulses<InterArrivalTime = I2, WirePattern = P2>,
+{
+    type Item = WireEvent<I1::Type>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // No `NaN` values because of the `PositiveIterator` bound.
+        let Some(next_secondary) = self.secondaries.peek() else {
+            return self.next_primary();
+        };
+        let primary_first = self
+            .peeked_primary
+            .get_or_insert_with(|| self.primary.next())
+            .as_ref()
+            .is_some_and(|next_primary| next_primary.time < next_secondary.time);
+
+        if primary_first {
+            self.next_primary()
+        } else {
+            self.next_secondary()
+        }
+    }
+}
+
+impl<I1, P1, B, I2, P2, A> sealed::OrderedIterator for PrimaryGenerator<I1, P1, B, I2, P2>
+where
+    I1: PositiveIterator,
+    I1::Type: Add<Output = I1::Type> + Clone + PartialOrd,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = I1::Type>,
+    P2: Iterator<Item = WirePattern>,
+    B: FnMut(&WireEvent<I1::Type>) -> A,
+    A: Afterpulses<InterArrivalTime = I2, WirePattern = P2>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.primary.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.secondary_last
+    }
+}
+
+/// Rate of events, with times of type `T`.
+pub trait Rate<T> {
+    /// Returns the time in which `count` events are expected at this rate.
+    fn time_for(&self, count: f64) -> T;
+    /// Returns the ratio between this rate and `other`.
+    fn ratio(&self, other: &Self) -> f64;
+}
+
+impl Rate<f64> for f64 {
+    fn time_for(&self, count: f64) -> f64 {
+        count / self
+    }
+
+    fn ratio(&self, other: &Self) -> f64 {
+        self / other
+    }
+}
+
+#[cfg(feature = "uom")]
+impl Rate<uom::si::f64::Time> for uom::si::f64::Frequency {
+    fn time_for(&self, count: f64) -> uom::si::f64::Time {
+        count / *self
+    }
+
+    fn ratio(&self, other: &Self) -> f64 {
+        (*self / *other).get::<uom::si::ratio::ratio>()
+    }
+}
+
+/// Rate of events for times in integer ticks of a clock (e.g. the 16 ns ticks
+/// of a firmware dump).
+///
+/// Inter-arrival times are rounded up to whole ticks, so a Poisson process
+/// has at most one event per tick: the number of ticks between events follows
+/// a geometric distribution with a success probability of `1 - exp(-rate)`
+/// per tick.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::{PoissonTimes, Positive, TickRate};
+///
+/// // 1 MHz with 16 ns ticks.
+/// let rate = TickRate::from_hz(1.0e6, 16.0e-9);
+/// assert!((rate.per_tick() - 0.016).abs() < 1e-12);
+///
+/// let times = PoissonTimes::<i64, _, _>::seeded(Positive::new(rate).unwrap(), 0);
+/// assert!(times.take(100).all(|ticks| ticks >= Positive::new(1).unwrap()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct TickRate(f64);
+
+impl TickRate {
+    /// Create a new rate from the expected number of events per tick.
+    pub fn new(per_tick: f64) -> Self {
+        Self(per_tick)
+    }
+    /// Create a new rate from a rate in Hz and the length of a tick in
+    /// seconds.
+    pub fn from_hz(hz: f64, tick: f64) -> Self {
+        Self(hz * tick)
+    }
+    /// Returns the expected number of events per tick.
+    pub fn per_tick(self) -> f64 {
+        self.0
+    }
+    // Whole number of ticks (at least one) in which `count` events are
+    // expected.
+    fn ticks_for(self, count: f64) -> f64 {
+        (count / self.0).ceil().max(1.0)
+    }
+}
+
+impl Add for TickRate {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl Zero for TickRate {
+    fn zero() -> Self {
+        Self(0.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0.0
+    }
+}
+
+impl Rate<i64> for TickRate {
+    fn time_for(&self, count: f64) -> i64 {
+        // Saturates for absurdly low rates.
+        self.ticks_for(count) as i64
+    }
+
+    fn ratio(&self, other: &Self) -> f64 {
+        self.0 / other.0
+    }
+}
+
+impl Rate<u64> for TickRate {
+    fn time_for(&self, count: f64) -> u64 {
+        self.ticks_for(count) as u64
+    }
+
+    fn ratio(&self, other: &Self) -> f64 {
+        self.0 / other.0
+    }
+}
+
+/// Inter-arrival times of a non-homogeneous Poisson process with a given
+/// `rate` function e.g. for the `inter_arrival_time` of a
+/// [`SecondaryGenerator`] (see [`RateFunctionGenerator`] for primary events
+/// with afterpulses).
+///
+/// Candidate events are drawn from a homogeneous process ([`PoissonTimes`])
+/// at `max_rate`, and each of them is kept with probability `rate / max_rate`
+/// at its time. A non-positive `max_rate` can not be given, see
+/// [`Positive::new`].
+///
+/// Candidates are not thinned at or after the `end` time (if any), so the
+/// iterator keeps returning times even if the rate vanishes forever. Give the
+/// generator the same `end_time` to discard them.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::{InhomogeneousPoissonTimes, Positive, SecondaryGenerator, Seed, Source, WirePattern};
+/// use std::iter::repeat;
+///
+/// // A rate of 10 that drops to zero at time 10.
+/// let times = InhomogeneousPoissonTimes::new(
+///     0.0,
+///     Some(20.0),
+///     |t: &f64| if *t < 10.0 { 10.0 } else { 0.0 },
+///     Positive::new(10.0).unwrap(),
+///     Seed::new(0).rng(),
+/// );
+/// let gen = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0.0)
+///     .end_time(20.0)
+///     .inter_arrival_time(times)
+///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+///     .build();
+/// let times: Vec<_> = gen.map(|event| event.time).collect();
+/// assert!(times.iter().all(|t| *t < 10.0));
+/// // 100 events are expected.
+/// assert!((50..150).contains(&times.len()));
+/// ```
+///
+/// # Panics
+///
+/// The iterator panics if `rate` is negative or larger than `max_rate`.
+#[derive(Clone, Debug)]
+pub struct InhomogeneousPoissonTimes<T, F, R, G> {
+    time: T,
+    // Candidates are not thinned past this time (where the generator stops
+    // anyway) in case the rate vanishes.
+    end: Option<T>,
+    rate: F,
+    candidates: PoissonTimes<T, R, G>,
+}
+
+impl<T, F, R, G> InhomogeneousPoissonTimes<T, F, R, G>
+where
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+{
+    /// Create a new iterator of inter-arrival times starting at `origin`,
+    /// with candidates and their thinning drawn from `rng`.
+    pub fn new(origin: T, end: Option<T>, rate: F, max_rate: Positive<R>, rng: G) -> Self {
+        Self {
+            time: origin,
+            end,
+            rate,
+            candidates: PoissonTimes::new(max_rate, rng),
+        }
+    }
+    /// Returns the upper bound of the rate.
+    pub fn max_rate(&self) -> &R {
+        self.candidates.rate()
+    }
+}
+
+impl<T, F, R, G> Iterator for InhomogeneousPoissonTimes<T, F, R, G>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+{
+    type Item = Positive<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Time since the last accepted event.
+        let mut delta: Option<T> = None;
+        loop {
+            let Positive(dt) = self.candidates.next()?;
+            self.time = self.time.clone() + dt.clone();
+            let total = match delta.take() {
+                Some(delta) => delta + dt,
+                None => dt,
+            };
+
+            if self.end.as_ref().is_some_and(|end| self.time >= *end) {
+                return Some(Positive(total));
+            }
+            let acceptance = (self.rate)(&self.time).ratio(self.candidates.rate());
+            assert!(
+                (0.0..=1.0).contains(&acceptance),
+                "rate is not in [0, max_rate] at some time"
+            );
+            if self.candidates.rng.random::<f64>() < acceptance {
+                return Some(Positive(total));
+            }
+            delta = Some(total);
+        }
+    }
+}
+
+/// A generator of [`WireEvent`]s with a time-varying rate and afterpulses.
+///
+/// Primary events follow a non-homogeneous Poisson process with the given
+/// `rate` function, generated by thinning of a homogeneous process at
+/// `max_rate`. Otherwise, this behaves exactly like a [`PrimaryGenerator`].
+///
+/// # Examples
+///
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use std::iter::{empty, repeat};
+/// use trg::gen::{Positive, RateFunctionGenerator, SecondaryGenerator, Source, WirePattern};
+///
+/// // Exponentially decaying rate with a time constant of 10.
+/// let gen = RateFunctionGenerator::builder()
+///     .source(Source::PrimaryPbar)
+///     .origin(0.0)
+///     .duration(Positive::new(100.0).unwrap())
+///     .rate(|t: &f64| 5.0 * (-t / 10.0).exp())
+///     .max_rate(Positive::new(5.0).unwrap())
+///     .rng(StdRng::seed_from_u64(0))
+///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+///     .afterpulse(|_: &_| {
+///         SecondaryGenerator::builder()
+///             .source(Source::SecondaryPbar)
+///             .inter_arrival_time(empty())
+///             .wire_pattern(empty())
+///     })
+///     .build();
+/// // 50 events are expected.
+/// assert!((20..80).contains(&gen.count()));
+/// ```
+///
+/// # Panics
+///
+/// The generator panics if `rate` is negative or larger than `max_rate`.
+/// Without a `duration`, it never returns if `rate` is zero forever.
+pub struct RateFunctionGenerator<T, F, R, G, P1, B, I2, P2>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = T>,
+    P2: Iterator<Item = WirePattern>,
+{
+    inner: PrimaryGenerator<InhomogeneousPoissonTimes<T, F, R, G>, P1, B, I2, P2>,
+}
+
+#[bon]
+impl<T, F, R, G, P1, B, I2, P2> RateFunctionGenerator<T, F, R, G, P1, B, I2, P2>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = T>,
+    P2: Iterator<Item = WirePattern>,
+{
+    #[builder]
+    pub fn new<T2>(
+        /// The source of the primary events.
+        source: Source,
+        /// The time at which the generator starts producing events.
+        origin: T,
+        /// Length of time the generator produces primary events for. Same as
+        /// [`PrimaryGenerator`].
+        duration: Option<Positive<T>>,
+        /// Rate of primary events as a function of time.
+        rate: F,
+        /// Upper bound of `rate`. The closer to the actual maximum, the fewer
+        /// candidate events are rejected.
+        max_rate: Positive<R>,
+        /// Random number generator used for the thinning.
+        rng: G,
+        /// The distribution of wire patterns for primary events.
+        wire_pattern: T2,
+        /// Secondary generator builder. Same as [`PrimaryGenerator`].
+        afterpulse: B,
+    ) -> Self
+    where
+        T2: IntoIterator<IntoIter = P1>,
+    {
+        let end = duration
+            .as_ref()
+            .map(|duration| origin.clone() + duration.inner().clone());
+        let inter_arrival_time =
+            InhomogeneousPoissonTimes::new(origin.clone(), end, rate, max_rate, rng);
+
+        Self {
+            inner: PrimaryGenerator::builder()
+                .source(source)
+                .origin(origin)
+                .maybe_duration(duration)
+                .inter_arrival_time(inter_arrival_time)
+                .wire_pattern(wire_pattern)
+                .afterpulse(afterpulse)
+                .build(),
+        }
+    }
+}
+
+impl<T, F, R, G, P1, B, I2, P2> RateFunctionGenerator<T, F, R, G, P1, B, I2, P2>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = T>,
+    P2: Iterator<Item = WirePattern>,
+{
+    /// Returns the reason why the generator stopped producing primary events,
+    /// or `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+}
+
+impl<T, F, R, G, P1, B, I2, P2, A> Iterator for RateFunctionGenerator<T, F, R, G, P1, B, I2, P2>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = T>,
+    P2: Iterator<Item = WirePattern>,
+    B: FnMut(&WireEvent<T>) -> A,
+    A: Afterpulses<InterArrivalTime = I2, WirePattern = P2>,
+{
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<T, F, R, G, P1, B, I2, P2, A> sealed::OrderedIterator
+    for RateFunctionGenerator<T, F, R, G, P1, B, I2, P2>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    F: FnMut(&T) -> R,
+    R: Rate<T>,
+    G: Rng,
+    P1: Iterator<Item = WirePattern>,
+    I2: PositiveIterator<Type = T>,
+    P2: Iterator<Item = WirePattern>,
+    B: FnMut(&WireEvent<T>) -> A,
+    A: Afterpulses<InterArrivalTime = I2, WirePattern = P2>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// Inter-arrival times of a homogeneous Poisson process with a given rate
+/// e.g. for the `inter_arrival_time` of a [`SecondaryGenerator`].
+///
+/// Cloning the iterator (e.g. for parallel replicas) also clones the state of
+/// its random number generator, so the clone produces the same times.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::{PoissonTimes, Positive};
+///
+/// let times = PoissonTimes::<f64, _, _>::seeded(Positive::new(2.0).unwrap(), 0);
+/// assert_eq!(*times.rate(), 2.0);
+/// let first: Vec<_> = times.clone().take(3).collect();
+/// let again: Vec<_> = times.take(3).collect();
+/// assert_eq!(first, again);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PoissonTimes<T, R, G> {
+    rate: R,
+    rng: G,
+    time: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T, R: Rate<T>, G: Rng> PoissonTimes<T, R, G> {
+    /// Create a new iterator of times drawn from `rng`.
+    pub fn new(rate: Positive<R>, rng: G) -> Self {
+        Self {
+            rate: rate.0,
+            rng,
+            time: std::marker::PhantomData,
+        }
+    }
+    /// Returns the rate of the process.
+    pub fn rate(&self) -> &R {
+        &self.rate
+    }
+}
+
+impl<T, R: Rate<T>> PoissonTimes<T, R, StdRng> {
+    /// Create a new iterator of times drawn from the random number generator
+    /// of `seed` (see [`Seed::rng`]). The same seed always gives the same
+    /// times.
+    pub fn seeded(rate: Positive<R>, seed: impl Into<Seed>) -> Self {
+        Self::new(rate, seed.into().rng())
+    }
+}
+
+impl<T, R: Rate<T>, G: Rng> Iterator for PoissonTimes<T, R, G> {
+    type Item = Positive<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let count: f64 = self.rng.sample(rand::distr::Open01);
+        Some(Positive(self.rate.time_for(-count.ln())))
+    }
+}
+
+/// Times sampled from a [`Distribution<f64>`] and converted with a closure
+/// (e.g. `Time::new::<second>`) for the `inter_arrival_time` of a
+/// [`SecondaryGenerator`] or [`PrimaryGenerator`]. Usually created with the
+/// `inter_arrival_distribution` method of their builders.
+///
+/// Times that are not positive (e.g. a sample of zero, or `NaN`) are rejected
+/// and drawn again. To avoid looping forever on a distribution that never
+/// produces positive times, the iterator ends after
+/// [`SampledTimes::DEFAULT_MAX_REJECTIONS`] consecutive rejections (see
+/// [`SampledTimes::with_max_rejections`]); a generator then stops with
+/// [`ExhaustionReason::InterArrivalTime`].
+///
+/// # Examples
+///
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use rand_distr::Exp;
+/// use std::iter::repeat;
+/// use trg::gen::{SecondaryGenerator, Source, WirePattern};
+///
+/// let gen = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0.0)
+///     .max_events(10)
+///     .inter_arrival_distribution(Exp::new(2.0)?, StdRng::seed_from_u64(0), |t| t)
+///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+///     .build();
+/// assert_eq!(gen.count(), 10);
+/// # Ok::<(), rand_distr::ExpError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct SampledTimes<D, G, F> {
+    distribution: D,
+    rng: G,
+    to_time: F,
+    max_rejections: u32,
+}
+
+impl<D, G, F> SampledTimes<D, G, F> {
+    /// The default number of consecutive rejected samples after which the
+    /// iterator ends.
+    pub const DEFAULT_MAX_REJECTIONS: u32 = 1000;
+
+    /// Create a new iterator of times drawn from `rng`.
+    pub fn new(distribution: D, rng: G, to_time: F) -> Self {
+        Self {
+            distribution,
+            rng,
+            to_time,
+            max_rejections: Self::DEFAULT_MAX_REJECTIONS,
+        }
+    }
+    /// Set the number of consecutive rejected samples after which the
+    /// iterator ends. With a bound of 0, the iterator ends without drawing any
+    /// sample.
+    pub fn with_max_rejections(mut self, max_rejections: u32) -> Self {
+        self.max_rejections = max_rejections;
+        self
+    }
+}
+
+impl<D, G, F, T> Iterator for SampledTimes<D, G, F>
+where
+    D: Distribution<f64>,
+    G: Rng,
+    F: FnMut(f64) -> T,
+    T: Zero + PartialOrd,
+{
+    type Item = Positive<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for _ in 0..self.max_rejections {
+            let sample = self.distribution.sample(&mut self.rng);
+            if let Some(time) = Positive::new((self.to_time)(sample)) {
+                return Some(time);
+            }
+        }
+        None
+    }
+}
+
+/// Superposition of several arrival processes e.g. to validate the merged
+/// stream of generators with different rates.
+///
+/// Each process is an iterator of (non-decreasing) arrival times; the result
+/// is a single ordered stream of `(index, time)` pairs, where `index` is the
+/// position of the originating process in `processes`. Arrivals at exactly the
+/// same time are produced in increasing order of `index`, unless
+/// [`Superpose::with_tie_breaking`] says otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::superpose;
+///
+/// let arrivals: Vec<_> = superpose([vec![1, 4, 6], vec![2, 4], vec![]]).collect();
+/// assert_eq!(arrivals, [(0, 1), (1, 2), (0, 4), (1, 4), (0, 6)]);
+/// ```
+pub fn superpose<I>(processes: impl IntoIterator<Item = I>) -> Superpose<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: PartialOrd,
+{
+    Superpose {
+        processes: processes.into_iter().map(IntoIterator::into_iter).collect(),
+        merge: Merge::default(),
+        max_time: None,
+        truncated: false,
+    }
+}
+
+/// Iterator returned by [`superpose`].
+#[derive(Clone)]
+pub struct Superpose<I: Iterator> {
+    // Processes that have not produced their first arrival yet; they are all
+    // moved to `merge` by the first call to `next`.
+    processes: Vec<I>,
+    merge: Merge<Arrival<I::Item>, Arrivals<I>>,
+    // No arrivals are produced at or after this time.
+    max_time: Option<I::Item>,
+    // Whether arrivals were discarded because of `max_time`.
+    truncated: bool,
+}
+
+impl<I: Iterator> Superpose<I> {
+    /// Set how arrivals at exactly the same time are ordered. Same as
+    /// [`WorldBuilder::tie_breaking`](crate::WorldBuilder::tie_breaking).
+    pub fn with_tie_breaking(mut self, tie_breaking: TieBreaking) -> Self {
+        self.merge.set_tie_breaking(tie_breaking);
+        self
+    }
+    /// Stop at `max_time` even if some processes are not exhausted (e.g.
+    /// unlimited processes). Same as
+    /// [`WorldBuilder::max_time`](crate::WorldBuilder::max_time).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::iter::successors;
+    /// use trg::gen::superpose;
+    ///
+    /// let mut arrivals = superpose([successors(Some(0), |t| Some(t + 3))]).with_max_time(10);
+    /// assert_eq!(arrivals.by_ref().map(|(_, t)| t).collect::<Vec<_>>(), [0, 3, 6, 9]);
+    /// assert_eq!(arrivals.truncated(), Some(&10));
+    /// ```
+    pub fn with_max_time(mut self, max_time: I::Item) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+    /// Returns the `max_time` if it stopped the superposition while some
+    /// processes could still produce arrivals.
+    pub fn truncated(&self) -> Option<&I::Item> {
+        self.max_time.as_ref().filter(|_| self.truncated)
+    }
+}
+
+// `Merge` is not `Debug`.
+impl<I: Iterator> std::fmt::Debug for Superpose<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Superpose").finish_non_exhaustive()
+    }
+}
+
+impl<I> Iterator for Superpose<I>
+where
+    I: Iterator,
+    I::Item: PartialOrd,
+{
+    type Item = (usize, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, mut process) in self.processes.drain(..).enumerate() {
+            if let Some(time) = process.next() {
+                self.merge.push(index, Arrival(time), Arrivals(process));
+            }
+        }
+        if let Some(max_time) = &self.max_time {
+            if self.merge.peek()?.0 >= *max_time {
+                // All remaining arrivals are later; drop the (possibly
+                // infinite) processes.
+                self.merge = Merge::default();
+                self.truncated = true;
+                return None;
+            }
+        }
+        let (index, Arrival(time), _) = self.merge.pop()?;
+        Some((index, time))
+    }
+}
+
+// An arrival time of one of the processes of a `Superpose`.
+#[derive(Clone)]
+struct Arrival<T>(T);
+
+impl<T: PartialOrd> Timed for Arrival<T> {
+    type Time = T;
+
+    fn time(&self) -> &T {
+        &self.0
+    }
+}
+
+#[derive(Clone)]
+struct Arrivals<I>(I);
+
+impl<I: Iterator> Iterator for Arrivals<I> {
+    type Item = Arrival<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Arrival)
+    }
+}
+
+type PatternSamples<D> = rand::distr::Iter<D, StdRng, WirePattern>;
+
+/// A generator of [`WireEvent`]s at a constant rate (a homogeneous Poisson
+/// process).
+///
+/// This is a [`SecondaryGenerator`] with [`PoissonTimes`] and wire patterns
+/// sampled from a distribution, all drawn from a single `rng`. Afterpulses can
+/// be added with [`PoissonGenerator::afterpulse`].
+///
+/// # Examples
+///
+/// ```
+/// use rand::distr::{Distribution, Uniform};
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use trg::gen::{PoissonGenerator, Positive, Source, WirePattern};
+///
+/// let gen = PoissonGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0.0)
+///     .duration(Positive::new(100.0).unwrap())
+///     .rate(Positive::new(0.5).unwrap())
+///     .rng(StdRng::seed_from_u64(0))
+///     .wire_pattern(Uniform::new(0, 16)?.map(|i| WirePattern::from_bits(1 << i)))
+///     .build();
+/// // 50 events are expected.
+/// assert!((20..80).contains(&gen.count()));
+/// # Ok::<(), rand::distr::uniform::Error>(())
+/// ```
+pub struct PoissonGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    inner: SecondaryGenerator<PoissonTimes<T, R, G>, PatternSamples<D>>,
+}
+
+#[bon]
+impl<T, R, G, D> PoissonGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    #[builder]
+    pub fn new(
+        /// The source of the generated events.
+        source: Source,
+        /// The time at which the generator starts producing events. All
+        /// events have a time strictly greater than `origin`.
+        origin: T,
+        /// Set with either `duration` or `end_time`.
+        #[builder(setters(vis = ""))]
+        end: Option<End<T>>,
+        /// Maximum number of events, independent of `duration`. Unlimited by
+        /// default.
+        max_events: Option<usize>,
+        /// Rate of events.
+        rate: Positive<R>,
+        /// Random number generator of the inter-arrival times and the wire
+        /// patterns.
+        mut rng: G,
+        /// The distribution of wire patterns.
+        wire_pattern: D,
+    ) -> Self {
+        let patterns = wire_pattern.sample_iter(StdRng::from_rng(&mut rng));
+
+        Self {
+            inner: SecondaryGenerator::builder()
+                .source(source)
+                .origin(origin)
+                .maybe_end(end)
+                .maybe_max_events(max_events)
+                .inter_arrival_time(PoissonTimes::new(rate, rng))
+                .wire_pattern(patterns)
+                .build(),
+        }
+    }
+}
+
+impl<T, R, G, D, S> PoissonGeneratorBuilder<T, R, G, D, S>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+    S: poisson_generator_builder::State,
+{
+    /// Length of time the generator produces events for. All events have a
+    /// time strictly less than `origin` + `duration`. Unlimited by default,
+    /// and mutually exclusive with `end_time`.
+    pub fn duration(
+        self,
+        duration: Positive<T>,
+    ) -> PoissonGeneratorBuilder<T, R, G, D, poisson_generator_builder::SetEnd<S>>
+    where
+        S::End: poisson_generator_builder::IsUnset,
+    {
+        self.end(End::Duration(duration))
+    }
+    /// Same as [`duration`](Self::duration), but optional.
+    pub fn maybe_duration(
+        self,
+        duration: Option<Positive<T>>,
+    ) -> PoissonGeneratorBuilder<T, R, G, D, poisson_generator_builder::SetEnd<S>>
+    where
+        S::End: poisson_generator_builder::IsUnset,
+    {
+        self.maybe_end(duration.map(End::Duration))
+    }
+    /// Time at which the generator stops producing events. All events have a
+    /// time strictly less than `end_time`. Unlimited by default, and mutually
+    /// exclusive with `duration`.
+    pub fn end_time(
+        self,
+        end_time: T,
+    ) -> PoissonGeneratorBuilder<T, R, G, D, poisson_generator_builder::SetEnd<S>>
+    where
+        S::End: poisson_generator_builder::IsUnset,
+    {
+        self.end(End::Time(end_time))
+    }
+    /// Same as [`end_time`](Self::end_time), but optional.
+    pub fn maybe_end_time(
+        self,
+        end_time: Option<T>,
+    ) -> PoissonGeneratorBuilder<T, R, G, D, poisson_generator_builder::SetEnd<S>>
+    where
+        S::End: poisson_generator_builder::IsUnset,
+    {
+        self.maybe_end(end_time.map(End::Time))
+    }
+}
+
+impl<T, R, G, D> PoissonGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    /// Returns the reason why the generator stopped producing events, or
+    /// `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+    /// Turn the events of this generator into the primary events of a
+    /// [`PrimaryGenerator`] with the given `afterpulse` closure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::distr::Distribution;
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    /// use std::iter::repeat;
+    /// use trg::gen::{PoissonGenerator, Positive, SecondaryGenerator, Source, WireEvent, WirePattern};
+    ///
+    /// let gen = PoissonGenerator::builder()
+    ///     .source(Source::PrimaryCosmic)
+    ///     .origin(0.0)
+    ///     .duration(Positive::new(100.0).unwrap())
+    ///     .rate(Positive::new(0.5).unwrap())
+    ///     .rng(StdRng::seed_from_u64(0))
+    ///     .wire_pattern(rand::distr::StandardUniform.map(WirePattern::from_bits))
+    ///     .build()
+    ///     .afterpulse(|event: &WireEvent<_>| {
+    ///         SecondaryGenerator::builder()
+    ///             .source(Source::SecondaryCosmic)
+    ///             .inter_arrival_time([Positive::new(0.1).unwrap()])
+    ///             .wire_pattern(repeat(event.wire_pattern))
+    ///     });
+    /// assert_eq!(gen.count() % 2, 0);
+    /// ```
+    pub fn afterpulse<B, I2, P2, A>(
+        self,
+        afterpulse: B,
+    ) -> PrimaryGenerator<PoissonTimes<T, R, G>, PatternSamples<D>, B, I2, P2>
+    where
+        B: FnMut(&WireEvent<T>) -> A,
+        A: Afterpulses<InterArrivalTime = I2, WirePattern = P2>,
+        I2: PositiveIterator<Type = T>,
+        P2: Iterator<Item = WirePattern>,
+    {
+        PrimaryGenerator::from_primary(self.inner, afterpulse)
+    }
+}
+
+impl<T, R, G, D> Iterator for PoissonGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<T, R, G, D> sealed::OrderedIterator for PoissonGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+}
+
+/// Period (in nanoseconds) of the clock in which measured afterpulse
+/// inter-arrival times are recorded.
+pub const AFTERPULSE_TICK_NS: f64 = 16.0;
+
+/// The error type returned when an [`AfterpulseLibrary`] can not be read.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum AfterpulseError {
+    /// The JSON is not an array of arrays of numbers.
+    Json(serde_json::Error),
+    /// An inter-arrival time is not positive. Trains and afterpulses are
+    /// counted from 0.
+    InterArrivalTime {
+        train: usize,
+        afterpulse: usize,
+        ticks: f64,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for AfterpulseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(error) => write!(f, "invalid afterpulse library: {error}"),
+            Self::InterArrivalTime {
+                train,
+                afterpulse,
+                ticks,
+            } => write!(
+                f,
+                "afterpulse {afterpulse} of train {train} is {ticks} ticks after the previous \
+                 pulse (inter-arrival times have to be positive)"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for AfterpulseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            Self::InterArrivalTime { .. } => None,
+        }
+    }
+}
+
+/// Measured afterpulses of primary events (e.g. from simulated or recorded
+/// events) for a [`PbarGenerator`], or the afterpulse closure of any other
+/// generator.
+///
+/// Each train is the sequence of inter-arrival times of the afterpulses of one
+/// primary event; the first one is relative to the time of the primary event.
+/// Every primary event gets the afterpulses of a uniformly random train.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use rand::distr::Distribution;
+/// use std::iter::repeat;
+/// use trg::gen::{
+///     AfterpulseLibrary, PoissonGenerator, Positive, SecondaryGenerator, Seed, Source,
+///     WireEvent, WirePattern,
+/// };
+///
+/// // Times in ns.
+/// let json = "[[1, 1, 3], [], [2]]";
+/// let library = AfterpulseLibrary::from_reader(json.as_bytes(), |ns| ns)?;
+/// assert_eq!(library.trains()[0][2], Positive::new(48.0).unwrap());
+///
+/// let mut rng = Seed::new(0).rng();
+/// let gen = PoissonGenerator::builder()
+///     .source(Source::PrimaryCosmic)
+///     .origin(0.0)
+///     .duration(Positive::new(1e6).unwrap())
+///     .rate(Positive::new(1e-4).unwrap())
+///     .rng(Seed::new(1).rng())
+///     .wire_pattern(rand::distr::StandardUniform.map(WirePattern::from_bits))
+///     .build()
+///     .afterpulse(move |event: &WireEvent<_>| {
+///         SecondaryGenerator::builder()
+///             .source(Source::SecondaryCosmic)
+///             .inter_arrival_time(library.choose(&mut rng))
+///             .wire_pattern(repeat(event.wire_pattern))
+///     });
+/// assert!(gen.count() > 0);
+/// # }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct AfterpulseLibrary<T> {
+    trains: Vec<Vec<Positive<T>>>,
+}
+
+impl<T> AfterpulseLibrary<T> {
+    /// Create a new library. An empty library produces no afterpulses.
+    pub fn new(trains: Vec<Vec<Positive<T>>>) -> Self {
+        Self { trains }
+    }
+    /// Returns all the trains of afterpulses.
+    pub fn trains(&self) -> &[Vec<Positive<T>>] {
+        &self.trains
+    }
+    /// Returns the inter-arrival times of a uniformly random train, or no
+    /// times if the library is empty.
+    pub fn choose<G: Rng + ?Sized>(&self, rng: &mut G) -> std::vec::IntoIter<Positive<T>>
+    where
+        T: Clone,
+    {
+        use rand::seq::IndexedRandom;
+
+        self.trains
+            .choose(rng)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Zero + PartialOrd> AfterpulseLibrary<T> {
+    /// Read the library from JSON: an array of trains, each an array of
+    /// inter-arrival times in ticks of the [`AFTERPULSE_TICK_NS`] clock e.g.
+    /// `[[1, 1, 7], [], [2]]`. Times in nanoseconds are converted with
+    /// `to_time`.
+    pub fn from_reader<Rd: std::io::Read>(
+        reader: Rd,
+        mut to_time: impl FnMut(f64) -> T,
+    ) -> Result<Self, AfterpulseError> {
+        let trains: Vec<Vec<f64>> =
+            serde_json::from_reader(reader).map_err(AfterpulseError::Json)?;
+        let trains = trains
+            .into_iter()
+            .enumerate()
+            .map(|(train, ticks)| {
+                ticks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(afterpulse, ticks)| {
+                        Positive::new(to_time(ticks * AFTERPULSE_TICK_NS)).ok_or(
+                            AfterpulseError::InterArrivalTime {
+                                train,
+                                afterpulse,
+                                ticks,
+                            },
+                        )
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { trains })
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "uom"))]
+impl AfterpulseLibrary<uom::si::f64::Time> {
+    /// Read the library from JSON (see [`AfterpulseLibrary::from_reader`])
+    /// with times as [`uom`] quantities.
+    pub fn from_reader_with_uom<Rd: std::io::Read>(reader: Rd) -> Result<Self, AfterpulseError> {
+        Self::from_reader(reader, uom::si::f64::Time::new::<uom::si::time::nanosecond>)
+    }
+}
+
+// Afterpulses of a primary event of a `PbarGenerator`.
+struct PbarAfterpulses<T> {
+    train: std::vec::IntoIter<Positive<T>>,
+    wire_pattern: WirePattern,
+}
+
+impl<T: Add<Output = T> + Clone> Afterpulses for PbarAfterpulses<T> {
+    type InterArrivalTime = std::vec::IntoIter<Positive<T>>;
+    type WirePattern = std::iter::Repeat<WirePattern>;
+
+    fn spawn<F>(self, origin: &T, mut spawn: F)
+    where
+        F: FnMut(SecondaryGenerator<Self::InterArrivalTime, Self::WirePattern>),
+    {
+        spawn(
+            SecondaryGenerator::builder()
+                .source(Source::SecondaryPbar)
+                .origin(origin.clone())
+                .inter_arrival_time(self.train)
+                .wire_pattern(std::iter::repeat(self.wire_pattern))
+                .build(),
+        );
+    }
+}
+
+type PbarAfterpulseFn<T> = Box<dyn FnMut(&WireEvent<T>) -> Option<PbarAfterpulses<T>>>;
+
+type PbarPrimaryGenerator<T, R, G, D> = PrimaryGenerator<
+    PoissonTimes<T, R, G>,
+    PatternSamples<PbarPatterns<D>>,
+    PbarAfterpulseFn<T>,
+    std::vec::IntoIter<Positive<T>>,
+    std::iter::Repeat<WirePattern>,
+>;
+
+// Patterns drawn from the model until the MLU decision matches.
+const MAX_PATTERN_DRAWS: usize = 100_000;
+
+// Wire patterns of a `PbarGenerator`.
+#[derive(Clone, Debug)]
+struct PbarPatterns<D> {
+    model: D,
+    // Lookup table, and whether a pattern has to pass it.
+    mlu: Option<(LookupTable, rand::distr::Bernoulli)>,
+}
+
+impl<D: Distribution<WirePattern>> Distribution<WirePattern> for PbarPatterns<D> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> WirePattern {
+        let Some((table, pass)) = &self.mlu else {
+            return self.model.sample(rng);
+        };
+        let pass = pass.sample(rng);
+        std::iter::repeat_with(|| self.model.sample(rng))
+            .take(MAX_PATTERN_DRAWS)
+            .find(|&pattern| table.contains(pattern) == pass)
+            .unwrap_or_else(|| {
+                panic!(
+                    "the wire pattern model does not produce patterns that {} the MLU",
+                    if pass { "pass" } else { "fail" }
+                )
+            })
+    }
+}
+
+/// A generator of antiproton annihilations during a mixing window.
+///
+/// Primary events ([`Source::PrimaryPbar`]) are a homogeneous Poisson process
+/// (see [`PoissonGenerator`]) with wire patterns drawn from a model (e.g. a
+/// [`ClusterPatternModel`], or a list of observed patterns). With
+/// `pass_mlu(probability, table)`, patterns are drawn from the model until
+/// they pass (with the given probability) or fail the lookup table. Each
+/// primary event gets the afterpulses ([`Source::SecondaryPbar`]) of a random
+/// train from an [`AfterpulseLibrary`], all with the pattern of the primary
+/// event.
+///
+/// # Examples
+///
+/// ```
+/// use rand::distr::{Distribution, Uniform};
+/// use trg::gen::{AfterpulseLibrary, PbarGenerator, Positive, Seed, Source, WirePattern};
+/// use trg::mlu::LookupTable;
+///
+/// let table = LookupTable::from([WirePattern::from_bits(1)]);
+/// let gen = PbarGenerator::builder()
+///     .origin(0.0)
+///     .window(Positive::new(1000.0).unwrap())
+///     .rate(Positive::new(0.1).unwrap())
+///     .rng(Seed::new(0).rng())
+///     .wire_pattern(Uniform::new(0, 16)?.map(|i| WirePattern::from_bits(1 << i)))
+///     .pass_mlu(0.5, table)
+///     .afterpulses(AfterpulseLibrary::new(vec![vec![Positive::new(2.0).unwrap()]]))
+///     .build();
+/// let primaries: Vec<_> = gen
+///     .filter(|event| event.source == Source::PrimaryPbar)
+///     .collect();
+/// // About half of them pass the MLU (instead of 1 in 16).
+/// let passed = primaries
+///     .iter()
+///     .filter(|event| table.contains(event.wire_pattern))
+///     .count();
+/// assert!(passed > primaries.len() / 4);
+/// # Ok::<(), rand::distr::uniform::Error>(())
+/// ```
+///
+/// # Panics
+///
+/// Building the generator panics if the MLU pass probability is not in `[0,
+/// 1]`. Sampling panics if the model (almost) never produces patterns that
+/// pass (or fail) the lookup table.
+pub struct PbarGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    inner: PbarPrimaryGenerator<T, R, G, D>,
+}
+
+#[bon]
+impl<T, R, G, D> PbarGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd + 'static,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    #[builder]
+    pub fn new(
+        /// The start of the mixing window.
+        origin: T,
+        /// Length of the mixing window. Primary events are only produced
+        /// within the window; afterpulses can be produced after it.
+        window: Positive<T>,
+        /// Rate of primary events.
+        rate: Positive<R>,
+        /// Random number generator of all the random choices.
+        mut rng: G,
+        /// The model of wire patterns of primary events.
+        wire_pattern: D,
+        /// Set with `pass_mlu`.
+        #[builder(setters(vis = ""))]
+        mlu: Option<(LookupTable, f64)>,
+        /// Afterpulses of primary events. No afterpulses by default.
+        afterpulses: Option<AfterpulseLibrary<T>>,
+    ) -> Self {
+        let mlu = mlu.map(|(table, probability)| {
+            let pass = rand::distr::Bernoulli::new(probability)
+                .unwrap_or_else(|_| panic!("MLU pass probability {probability} is not in [0, 1]"));
+            (table, pass)
+        });
+        let mut afterpulse_rng = StdRng::from_rng(&mut rng);
+        let primary = PoissonGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(origin)
+            .duration(window)
+            .rate(rate)
+            .rng(rng)
+            .wire_pattern(PbarPatterns {
+                model: wire_pattern,
+                mlu,
+            })
+            .build()
+            .inner;
+
+        let library = afterpulses.unwrap_or_else(|| AfterpulseLibrary::new(Vec::new()));
+        let afterpulse: PbarAfterpulseFn<T> = Box::new(move |event| {
+            (!library.trains.is_empty()).then(|| PbarAfterpulses {
+                train: library.choose(&mut afterpulse_rng),
+                wire_pattern: event.wire_pattern,
+            })
+        });
+
+        Self {
+            inner: PrimaryGenerator::from_primary(primary, afterpulse),
+        }
+    }
+}
+
+impl<T, R, G, D, S> PbarGeneratorBuilder<T, R, G, D, S>
+where
+    T: Add<Output = T> + Clone + PartialOrd + 'static,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+    S: pbar_generator_builder::State,
+{
+    /// Make a fraction `probability` of the primary events pass `table` (i.e.
+    /// the MLU), and the rest fail it. By default, patterns are drawn from the
+    /// model as they are.
+    pub fn pass_mlu(
+        self,
+        probability: f64,
+        table: LookupTable,
+    ) -> PbarGeneratorBuilder<T, R, G, D, pbar_generator_builder::SetMlu<S>>
+    where
+        S::Mlu: pbar_generator_builder::IsUnset,
+    {
+        self.mlu((table, probability))
+    }
+}
+
+impl<T, R, G, D> PbarGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    /// Returns the reason why the generator stopped producing primary events,
+    /// or `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+}
+
+impl<T, R, G, D> Iterator for PbarGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<T, R, G, D> sealed::OrderedIterator for PbarGenerator<T, R, G, D>
+where
+    T: Add<Output = T> + Clone + PartialOrd,
+    R: Rate<T>,
+    G: Rng,
+    D: Distribution<WirePattern>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+/// A generator of [`WireEvent`]s in periodic bursts (e.g. antiproton spills).
+///
+/// Burst `k` (starting at zero) covers the times in `[origin + k * period,
+/// origin + k * period + duration)`, and no events are produced in the gaps
+/// between bursts. Within each burst, events are produced exactly like a
+/// [`SecondaryGenerator`] with its `origin` at the start of the burst; the
+/// inter-arrival time that crosses the end of a burst is discarded.
+///
+/// The generator stops after the requested number of bursts, or when the
+/// inter-arrival time/wire pattern distributions have been exhausted.
+///
+/// # Examples
+///
+/// ```
+/// use std::iter::repeat;
+/// use trg::gen::{BurstGenerator, Positive, Source, WirePattern};
+///
+/// let gen = BurstGenerator::builder()
+///     .source(Source::PrimaryPbar)
+///     .origin(0)
+///     .burst_period(Positive::new(100).unwrap())
+///     .burst_duration(Positive::new(30).unwrap())
+///     .bursts(2)
+///     .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+///     .wire_pattern(repeat(WirePattern::from_bits(1)))
+///     .build();
+/// let times: Vec<_> = gen.map(|event| event.time).collect();
+/// assert_eq!(times, [10, 20, 110, 120]);
+/// ```
+///
+/// # Panics
+///
+/// Building the generator panics if `burst_duration` is longer than
+/// `burst_period`.
+#[derive(Clone, Debug)]
+pub struct BurstGenerator<I, P>
+where
+    I: PositiveIterator,
+{
+    source: Source,
+    burst_start: I::Type,
+    burst_end: I::Type,
+    current_time: I::Type,
+    burst_period: I::Type,
+    burst_duration: I::Type,
+    // Including the current burst.
+    remaining_bursts: Option<usize>,
+    inter_arrival_time: I,
+    wire_pattern: P,
+    exhausted: Option<ExhaustionReason>,
+}
+
+#[bon]
+impl<I, P> BurstGenerator<I, P>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+{
+    #[builder]
+    pub fn new<T1, T2>(
+        /// The source of the generated events.
+        source: Source,
+        /// The start of the first burst.
+        origin: I::Type,
+        /// Time between the start of consecutive bursts.
+        burst_period: Positive<I::Type>,
+        /// Length of each burst.
+        burst_duration: Positive<I::Type>,
+        /// Number of bursts. Unlimited by default.
+        bursts: Option<usize>,
+        /// The distribution of inter-arrival times between events within a
+        /// burst.
+        inter_arrival_time: T1,
+        /// The distribution of wire patterns.
+        wire_pattern: T2,
+    ) -> Self
+    where
+        T1: IntoIterator<IntoIter = I>,
+        T2: IntoIterator<IntoIter = P>,
+    {
+        let (Positive(burst_period), Positive(burst_duration)) = (burst_period, burst_duration);
+        assert!(
+            burst_duration <= burst_period,
+            "burst duration is longer than the burst period"
+        );
+
+        Self {
+            source,
+            burst_start: origin.clone(),
+            burst_end: origin.clone() + burst_duration.clone(),
+            current_time: origin,
+            burst_period,
+            burst_duration,
+            remaining_bursts: bursts,
+            inter_arrival_time: inter_arrival_time.into_iter(),
+            wire_pattern: wire_pattern.into_iter(),
+            exhausted: None,
+        }
+    }
+}
+
+impl<I, P> BurstGenerator<I, P>
+where
+    I: PositiveIterator,
+{
+    /// Returns the reason why the generator stopped producing events, or
+    /// `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+impl<I, P> Iterator for BurstGenerator<I, P>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+    P: Iterator<Item = WirePattern>,
+{
+    type Item = WireEvent<I::Type>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted.is_some() {
+            return None;
+        }
+        loop {
+            if self.remaining_bursts == Some(0) {
+                self.exhausted = Some(ExhaustionReason::Duration);
+                return None;
+            }
+            let Some(Positive(delta_t)) = self.inter_arrival_time.next() else {
+                self.exhausted = Some(ExhaustionReason::InterArrivalTime);
+                return None;
+            };
+            let time = self.current_time.clone() + delta_t;
+            if time < self.burst_end {
+                let Some(wire_pattern) = self.wire_pattern.next() else {
+                    self.exhausted = Some(ExhaustionReason::WirePattern);
+                    return None;
+                };
+                self.current_time = time.clone();
+
+                return Some(WireEvent {
+                    source: self.source,
+                    wire_pattern,
+                    time,
+                    id: None,
+                    weight: 1.0,
+                });
+            }
+            // Both ends of the next burst are computed from its start, so
+            // the length of the bursts does not drift.
+            self.burst_start = self.burst_start.clone() + self.burst_period.clone();
+            self.burst_end = self.burst_start.clone() + self.burst_duration.clone();
+            self.current_time = self.burst_start.clone();
+            if let Some(remaining) = &mut self.remaining_bursts {
+                *remaining -= 1;
+            }
+        }
+    }
+}
+
+impl<I, P> sealed::OrderedIterator for BurstGenerator<I, P>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+    P: Iterator<Item = WirePattern>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+/// A generator of identical [`WireEvent`]s at a fixed frequency (e.g. a
+/// calibration pulser).
+///
+/// Pulse `n` (starting at one) is produced at `origin + n * period`. Each time
+/// is computed directly, instead of accumulating the period, so floating point
+/// errors do not drift over long runs.
+///
+/// The generator stops after the requested number of pulses, or when the
+/// duration has been reached.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::{PulserGenerator, Positive, Source, WirePattern};
+///
+/// let gen = PulserGenerator::builder()
+///     .source(Source::custom("pulser"))
+///     .origin(0.0)
+///     .period(Positive::new(0.5).unwrap())
+///     .count(3)
+///     .wire_pattern(WirePattern::from_bits(u16::MAX))
+///     .build();
+/// let times: Vec<_> = gen.map(|event| event.time).collect();
+/// assert_eq!(times, [0.5, 1.0, 1.5]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PulserGenerator<T> {
+    source: Source,
+    wire_pattern: WirePattern,
+    origin: T,
+    period: T,
+    max_time: Option<T>,
+    max_pulses: Option<usize>,
+    pulses: usize,
+    exhausted: Option<ExhaustionReason>,
+}
+
+#[bon]
+impl<T: Add<Output = T> + Clone> PulserGenerator<T> {
+    #[builder]
+    pub fn new(
+        /// The source of the generated events.
+        source: Source,
+        /// The time of the (not produced) pulse zero.
+        origin: T,
+        /// Time between consecutive pulses.
+        period: Positive<T>,
+        /// Length of time the generator produces events for. All events are
+        /// guaranteed to have a time less than `origin` + `duration`.
+        /// Unlimited by default.
+        duration: Option<Positive<T>>,
+        /// Number of pulses. Unlimited by default.
+        count: Option<usize>,
+        /// The wire pattern of all the pulses.
+        wire_pattern: WirePattern,
+    ) -> Self {
+        Self {
+            source,
+            wire_pattern,
+            max_time: duration.map(|Positive(t)| origin.clone() + t),
+            origin,
+            period: period.0,
+            max_pulses: count,
+            pulses: 0,
+            exhausted: None,
+        }
+    }
+}
+
+impl<T> PulserGenerator<T> {
+    /// Returns the reason why the generator stopped producing events, or
+    /// `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+impl<T> Iterator for PulserGenerator<T>
+where
+    T: Add<Output = T> + Mul<f64, Output = T> + Clone + PartialOrd,
+{
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted.is_some() {
+            return None;
+        }
+        if self.max_pulses == Some(self.pulses) {
+            self.exhausted = Some(ExhaustionReason::EventCount);
+            return None;
+        }
+        let pulse = self.pulses + 1;
+        let time = self.origin.clone() + self.period.clone() * pulse as f64;
+        if self
+            .max_time
+            .as_ref()
+            .is_some_and(|max_time| time >= *max_time)
+        {
+            self.exhausted = Some(ExhaustionReason::Duration);
+            return None;
+        }
+        self.pulses = pulse;
+
+        Some(WireEvent {
+            source: self.source,
+            wire_pattern: self.wire_pattern,
+            time,
+            id: None,
+            weight: 1.0,
+        })
+    }
+}
+
+impl<T> sealed::OrderedIterator for PulserGenerator<T> where
+    T: Add<Output = T> + Mul<f64, Output = T> + Clone + PartialOrd
+{
+}
+
+type PatternIter<'a> = Box<dyn Iterator<Item = WirePattern> + 'a>;
+
+/// A generator of [`WireEvent`]s from a mixture of species (e.g. noise,
+/// cosmic, and antiproton events).
+///
+/// Events are produced with a single stream of inter-arrival times, and each
+/// event is independently assigned to one of the species with probability
+/// proportional to its weight. The [`Source`] and wire pattern of the event are
+/// then those of the species. If the inter-arrival times follow a Poisson
+/// process, each species is itself a Poisson process with a rate proportional
+/// to its weight.
+///
+/// # Examples
+///
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use std::iter::repeat;
+/// use trg::gen::{MixtureGenerator, Positive, Source, WirePattern};
+///
+/// let gen = MixtureGenerator::builder()
+///     .add_species(3.0, Source::Noise, repeat(WirePattern::from_bits(1)))
+///     .add_species(1.0, Source::PrimaryCosmic, repeat(WirePattern::from_bits(3)))
+///     .origin(0)
+///     .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+///     .rng(StdRng::seed_from_u64(0))
+///     .build();
+/// let cosmic = gen.take(100).filter(|e| e.wire_pattern.bits() == 3).count();
+/// // 25 cosmic events are expected.
+/// assert!((10..40).contains(&cosmic));
+/// ```
+///
+/// # Panics
+///
+/// Building the generator panics if there are no species, or if the weights
+/// are negative, not finite, or all zero.
+pub struct MixtureGenerator<'a, I, G>
+where
+    I: PositiveIterator,
+{
+    current_time: Option<I::Type>,
+    max_time: Option<I::Type>,
+    inter_arrival_time: I,
+    species: Vec<(Source, PatternIter<'a>)>,
+    choice: WeightedIndex<f64>,
+    rng: G,
+    exhausted: Option<ExhaustionReason>,
+}
+
+#[bon]
+impl<'a, I, G> MixtureGenerator<'a, I, G>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone,
+{
+    #[builder]
+    pub fn new<T1>(
+        #[builder(field)] species: Vec<(f64, Source, PatternIter<'a>)>,
+        /// The time at which the generator starts producing events. Note that
+        /// the first event is produced at `origin` + `delta_t`, where `delta_t`
+        /// is the first value produced by `inter_arrival_time`.
+        origin: I::Type,
+        /// Length of time the generator produces events for. All events are
+        /// guaranteed to have a time less than `origin` + `duration`.
+        duration: Option<Positive<I::Type>>,
+        /// The distribution of inter-arrival times between events of any
+        /// species.
+        inter_arrival_time: T1,
+        /// Random number generator used to choose the species of each event.
+        rng: G,
+    ) -> Self
+    where
+        T1: IntoIterator<IntoIter = I>,
+    {
+        let choice = WeightedIndex::new(species.iter().map(|(weight, _, _)| *weight))
+            .expect("species weights must be non-negative, finite, and not all zero");
+
+        Self {
+            current_time: Some(origin.clone()),
+            max_time: duration.map(|Positive(t)| t + origin),
+            inter_arrival_time: inter_arrival_time.into_iter(),
+            species: species
+                .into_iter()
+                .map(|(_, source, wire_pattern)| (source, wire_pattern))
+                .collect(),
+            choice,
+            rng,
+            exhausted: None,
+        }
+    }
+}
+
+impl<'a, I, G, T1, S> MixtureGeneratorBuilder<'a, I, G, T1, S>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone,
+    T1: IntoIterator<IntoIter = I>,
+    S: mixture_generator_builder::State,
+{
+    /// Add a species with the given relative `weight`, [`Source`], and
+    /// distribution of wire patterns.
+    pub fn add_species<T>(mut self, weight: f64, source: Source, wire_pattern: T) -> Self
+    where
+        T: IntoIterator<Item = WirePattern>,
+        T::IntoIter: 'a,
+    {
+        self.species
+            .push((weight, source, Box::new(wire_pattern.into_iter())));
+        self
+    }
+}
+
+impl<I, G> MixtureGenerator<'_, I, G>
+where
+    I: PositiveIterator,
+{
+    /// Returns the reason why the generator stopped producing events, or
+    /// `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+impl<I, G> Iterator for MixtureGenerator<'_, I, G>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+    G: Rng,
+{
+    type Item = WireEvent<I::Type>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = self.current_time.take()?;
+        let Some(Positive(delta_t)) = self.inter_arrival_time.next() else {
+            self.exhausted = Some(ExhaustionReason::InterArrivalTime);
+            return None;
+        };
+        let time = time + delta_t;
+        if self
+            .max_time
+            .as_ref()
+            .is_some_and(|max_time| time >= *max_time)
+        {
+            self.exhausted = Some(ExhaustionReason::Duration);
+            return None;
+        }
+        let (source, wire_pattern) = &mut self.species[self.choice.sample(&mut self.rng)];
+        let Some(wire_pattern) = wire_pattern.next() else {
+            self.exhausted = Some(ExhaustionReason::WirePattern);
+            return None;
+        };
+        self.current_time = Some(time.clone());
+
+        Some(WireEvent {
+            source: *source,
+            wire_pattern,
+            time,
+            id: None,
+            weight: 1.0,
+        })
+    }
+}
+
+impl<I, G> sealed::OrderedIterator for MixtureGenerator<'_, I, G>
+where
+    I: PositiveIterator,
+    I::Type: Add<Output = I::Type> + Clone + PartialOrd,
+    G: Rng,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+/// A generator that replays a fixed list of [`WireEvent`]s.
+///
+/// Events are sorted by time on construction (keeping the relative order of
+/// events with the same time), produced one by one, and the generator is
+/// exhausted afterwards. This is mostly useful to write down exact scenarios
+/// e.g. in tests.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::{ScriptedGenerator, Source, WireEvent, WirePattern};
+///
+/// let pattern = WirePattern::from_bits(1);
+/// let events: Vec<_> = ScriptedGenerator::from_times(Source::Noise, [20, 10], pattern)
+///     .map(|event| event.time)
+///     .collect();
+/// assert_eq!(events, [10, 20]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ScriptedGenerator<T> {
+    events: std::vec::IntoIter<WireEvent<T>>,
+}
+
+impl<T: PartialOrd> ScriptedGenerator<T> {
+    /// Create a new generator that produces the given events in increasing
+    /// order of time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if two event times are not comparable (e.g. `NaN`).
+    pub fn new(mut events: Vec<WireEvent<T>>) -> Self {
+        events.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .expect("event times must be comparable")
+        });
+
+        Self {
+            events: events.into_iter(),
+        }
+    }
+    /// Create a new generator that produces one event at each of the given
+    /// times, all with the same source and wire pattern.
+    ///
+    /// # Panics
+    ///
+    /// Same as [`ScriptedGenerator::new`].
+    pub fn from_times<I>(source: Source, times: I, wire_pattern: WirePattern) -> Self
+    where
+        I: IntoIterator<Item = T>,
+    {
+        Self::new(
+            times
+                .into_iter()
+                .map(|time| WireEvent {
+                    source,
+                    wire_pattern,
+                    time,
+                    id: None,
+                    weight: 1.0,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<T> Iterator for ScriptedGenerator<T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+impl<T> sealed::OrderedIterator for ScriptedGenerator<T> {}
+
+// Anything that is merged in order of time by a `Merge`.
+trait Timed {
+    type Time: PartialOrd;
+
+    fn time(&self) -> &Self::Time;
+}
+
+impl<T: PartialOrd> Timed for WireEvent<T> {
+    type Time = T;
+
+    fn time(&self) -> &T {
+        &self.time
+    }
+}
+
+// Next event of one of the sources of a `Merge`.
+#[derive(Clone)]
+struct Head<E, S> {
+    id: usize,
+    // Random key of the event if ties are broken randomly, zero otherwise.
+    tie: u64,
+    event: E,
+    source: S,
+}
+
+// Reversed, so that the `BinaryHeap` is a min-heap. Ties are broken by the
+// random key, then by insertion order.
+impl<E: Timed, S> Ord for Head<E, S> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .event
+            .time()
+            .partial_cmp(self.event.time())
+            .expect("event times must be comparable")
+            .then(other.tie.cmp(&self.tie))
+            .then(other.id.cmp(&self.id))
+    }
+}
+
+impl<E: Timed, S> PartialOrd for Head<E, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<E: Timed, S> PartialEq for Head<E, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<E: Timed, S> Eq for Head<E, S> {}
+
+/// How events with exactly the same time (common with integer times, or
+/// quantized to a clock) are ordered when the events of several generators
+/// are merged. The order matters e.g. when a prompt window opens at that time.
+///
+/// Events of the same generator are always kept in the order in which the
+/// generator produced them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreaking {
+    /// In the order in which their generators were added.
+    #[default]
+    InsertionOrder,
+    /// In a random order, reproducible from the seed. Useful to study how
+    /// sensitive the results are to the ordering.
+    Random(Seed),
+}
+
+// K-way merge of ordered sources of events. Each source is identified by an
+// `id` chosen by the caller; events with the same time are produced in
+// increasing order of `id`, unless ties are broken randomly.
+#[derive(Clone)]
+struct Merge<E, S> {
+    heap: BinaryHeap<Head<E, S>>,
+    // Sources added since the last call to `pop`. Keeping them aside means
+    // that adding a source does not require `E: Timed`.
+    pending: Vec<Head<E, S>>,
+    // Random keys of events, if ties are broken randomly.
+    ties: Option<StdRng>,
+}
+
+// Deriving `Default` would only work for `E: Default` and `S: Default`.
+impl<E, S> Default for Merge<E, S> {
+    fn default() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            pending: Vec::new(),
+            ties: None,
+        }
+    }
+}
+
+impl<E, S> Merge<E, S> {
+    // `event` is the first event produced by `source`.
+    fn push(&mut self, id: usize, event: E, source: S) {
+        self.pending.push(Head {
+            id,
+            tie: 0,
+            event,
+            source,
+        });
+    }
+    fn set_tie_breaking(&mut self, tie_breaking: TieBreaking) {
+        self.ties = match tie_breaking {
+            TieBreaking::InsertionOrder => None,
+            TieBreaking::Random(seed) => Some(seed.rng()),
+        };
+    }
+}
+
+fn tie_key(ties: &mut Option<StdRng>) -> u64 {
+    ties.as_mut().map_or(0, |rng| rng.random())
+}
+
+impl<E: Timed, S: Iterator<Item = E>> Merge<E, S> {
+    fn flush_pending(&mut self) {
+        let ties = &mut self.ties;
+        self.heap.extend(self.pending.drain(..).map(|mut head| {
+            head.tie = tie_key(ties);
+            head
+        }));
+    }
+    fn peek(&mut self) -> Option<&E> {
+        self.flush_pending();
+        self.heap.peek().map(|head| &head.event)
+    }
+    // Returns the source of the earliest event.
+    fn peek_source(&mut self) -> Option<&S> {
+        self.flush_pending();
+        self.heap.peek().map(|head| &head.source)
+    }
+    // Returns the earliest event, the id of its source, and the source itself
+    // if it has been exhausted (and removed).
+    fn pop(&mut self) -> Option<(usize, E, Option<S>)> {
+        self.flush_pending();
+        let tie = tie_key(&mut self.ties);
+        let mut head = self.heap.peek_mut()?;
+        match head.source.next() {
+            Some(event) => {
+                let id = head.id;
+                head.tie = tie;
+                // The heap is fixed when `head` is dropped.
+                Some((id, std::mem::replace(&mut head.event, event), None))
+            }
+            None => {
+                let Head {
+                    id, event, source, ..
+                } = PeekMut::pop(head);
+                Some((id, event, Some(source)))
+            }
+        }
+    }
+}
+
+/// Number of [`WireEvent`]s produced by a generator added to a
+/// [`World`](crate::World). See
+/// [`Observer::on_generator_stats`](crate::Observer::on_generator_stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GeneratorStats {
+    /// Number of events produced by the generator itself.
+    pub primary_events: u64,
+    /// Number of events produced by secondary generators spawned during the
+    /// run (e.g. afterpulses of a [`PrimaryGenerator`]).
+    pub secondary_events: u64,
+}
+
+impl GeneratorStats {
+    /// Returns the total number of events.
+    pub fn events(&self) -> u64 {
+        self.primary_events + self.secondary_events
+    }
+}
+
+/// A boxed [`EventGenerator`] with times of type `T` e.g. to return generators
+/// of different types from a [`CycleGenerator`] template.
+pub type BoxedGenerator<'a, T> = Box<dyn EventGenerator<Time = T, Item = WireEvent<T>> + 'a>;
+
+// A generator of a `Generator`, together with the origin of its next event
+// (already taken from `gen`).
+struct Member<'a, T> {
+    gen: BoxedGenerator<'a, T>,
+    spawned: bool,
+}
+
+impl<T> Iterator for Member<'_, T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.gen.next();
+        self.spawned = self.gen.spawned_last();
+        event
+    }
+}
+
+pub(super) struct Generator<'a, T> {
+    // Each generator is identified by the order in which it was added, and
+    // kept together with its next event.
+    inner: Merge<WireEvent<T>, Member<'a, T>>,
+    added: usize,
+    last: Option<usize>,
+    // Whether the last event was produced by a spawned generator.
+    spawned_last: bool,
+    // Indexed by generator.
+    stats: Vec<GeneratorStats>,
+    // Generators that stopped producing events (not yet reported).
+    exhausted: Vec<(usize, ExhaustionReason)>,
+    // Master seed of `add_seeded_generator`.
+    seed: Option<Seed>,
+    // No events are produced at or after this time.
+    max_time: Option<T>,
+    // Whether events were discarded because of `max_time`.
+    truncated: bool,
+}
+
+// Deriving `Default` would only work for `T: Default`.
+impl<T> Default for Generator<'_, T> {
+    fn default() -> Self {
+        Self {
+            inner: Merge::default(),
+            added: 0,
+            last: None,
+            spawned_last: false,
+            stats: Vec::new(),
+            exhausted: Vec::new(),
+            seed: None,
+            max_time: None,
+            truncated: false,
+        }
+    }
+}
+
+impl<'a, T> Generator<'a, T> {
+    pub(super) fn add_generator<G>(&mut self, gen: G)
+    where
+        G: EventGenerator<Time = T> + 'a,
+    {
+        let id = self.added;
+        self.added += 1;
+        self.stats.push(GeneratorStats::default());
+
+        let mut gen = Box::new(gen) as BoxedGenerator<'a, T>;
+        // Only keep around useful generators.
+        match gen.next() {
+            Some(event) => {
+                let spawned = gen.spawned_last();
+                self.inner.push(id, event, Member { gen, spawned });
+            }
+            None => self.record_exhausted(id, &gen),
+        }
+    }
+    pub(super) fn set_seed(&mut self, seed: Seed) {
+        self.seed = Some(seed);
+    }
+    pub(super) fn set_tie_breaking(&mut self, tie_breaking: TieBreaking) {
+        self.inner.set_tie_breaking(tie_breaking);
+    }
+    /// Stop producing events (regardless of the state of the generators) at
+    /// `max_time`.
+    pub(super) fn set_max_time(&mut self, max_time: T) {
+        self.max_time = Some(max_time);
+    }
+    /// Returns the `max_time` if it stopped the generator while some
+    /// generators could still produce events.
+    pub(super) fn truncated(&self) -> Option<&T> {
+        self.max_time.as_ref().filter(|_| self.truncated)
+    }
+    /// Add the generator returned by `f`, which is given the seed derived
+    /// from the master seed and the index of the generator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no master seed has been set.
+    pub(super) fn add_seeded_generator<G, F>(&mut self, f: F)
+    where
+        G: EventGenerator<Time = T> + 'a,
+        F: FnOnce(Seed) -> G,
+    {
+        let seed = self
+            .seed
+            .expect("a seed must be set before adding seeded generators");
+        // `usize` to `u64` is lossless on all supported platforms.
+        self.add_generator(f(seed.derive(self.added as u64)));
+    }
+    /// Returns the index (in order of addition) of the generator that
+    /// produced the last event.
+    pub(super) fn last_generator(&self) -> Option<usize> {
+        self.last
+    }
+    /// Returns whether the last event was produced by a generator spawned
+    /// during the run.
+    pub(super) fn spawned_last(&self) -> bool {
+        self.spawned_last
+    }
+    /// Returns the number of events produced so far by each generator (in
+    /// order of addition).
+    pub(super) fn stats(&self) -> &[GeneratorStats] {
+        &self.stats
+    }
+    /// Returns (and forgets) the index and reason of all the generators that
+    /// stopped producing events since the last call.
+    pub(super) fn drain_exhausted(&mut self) -> std::vec::Drain<'_, (usize, ExhaustionReason)> {
+        self.exhausted.drain(..)
+    }
+
+    fn record_exhausted(&mut self, id: usize, gen: &BoxedGenerator<'a, T>) {
+        if let Some(reason) = gen.exhaustion_reason() {
+            self.exhausted.push((id, reason));
+        }
+    }
+}
+
+// Events of all generators are merged in increasing order of time; events
+// with the same time are ordered as set by `set_tie_breaking`. Each event
+// costs `O(log k)` with `k` active generators.
+impl<T: PartialOrd> Iterator for Generator<'_, T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(max_time) = &self.max_time {
+            if self.inner.peek()?.time >= *max_time {
+                // All remaining events are later; drop the (possibly
+                // infinite) generators.
+                self.inner = Merge::default();
+                self.truncated = true;
+                return None;
+            }
+        }
+        let spawned = self.inner.peek_source()?.spawned;
+        let (id, event, exhausted) = self.inner.pop()?;
+        self.last = Some(id);
+        self.spawned_last = spawned;
+        if spawned {
+            self.stats[id].secondary_events += 1;
+        } else {
+            self.stats[id].primary_events += 1;
+        }
+        if let Some(member) = exhausted {
+            self.record_exhausted(id, &member.gen);
+        }
+
+        Some(event)
+    }
+}
+
+/// A generator of [`WireEvent`]s in repeated cycles of data taking (e.g. 240 s
+/// of cosmic background followed by a 1 s mixing window, 100 times).
+///
+/// Cycle `n` (starting at zero) starts at `origin + n * period`, and its
+/// generators are returned by `template(n, start)`. Only the events of a cycle
+/// before the start of the next one are kept, so the generators of a cycle can
+/// be infinite. The generators of a cycle are only created once the previous
+/// cycle is over, so memory usage does not grow with the number of cycles.
+///
+/// The generator stops with [`ExhaustionReason::Duration`] after the last
+/// cycle.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::{BoxedGenerator, CycleGenerator, Positive, ScriptedGenerator, Source, WirePattern};
+///
+/// let gen = CycleGenerator::builder()
+///     .origin(0.0)
+///     .period(Positive::new(10.0).unwrap())
+///     .cycles(2)
+///     .template(|_, start: f64| {
+///         let pattern = WirePattern::from_bits(1);
+///         let gen = ScriptedGenerator::from_times(Source::Noise, [start + 1.0, start + 2.0], pattern);
+///         vec![Box::new(gen) as BoxedGenerator<_>]
+///     })
+///     .build();
+/// let times: Vec<_> = gen.map(|event| event.time).collect();
+/// assert_eq!(times, [1.0, 2.0, 11.0, 12.0]);
+/// ```
+pub struct CycleGenerator<'a, T, F> {
+    template: F,
+    origin: T,
+    period: T,
+    cycles: u32,
+    // Number of cycles started so far.
+    started: u32,
+    // Start and end of the current cycle.
+    bounds: Option<(T, T)>,
+    // Generators of the current cycle, identified by their position in the
+    // template.
+    current: Merge<WireEvent<T>, Member<'a, T>>,
+    spawned_last: bool,
+    exhausted: Option<ExhaustionReason>,
+}
+
+#[bon]
+impl<'a, T, F> CycleGenerator<'a, T, F> {
+    #[builder]
+    pub fn new(
+        /// The start time of the first cycle.
+        origin: T,
+        /// Time between the start of consecutive cycles.
+        period: Positive<T>,
+        /// Number of cycles.
+        cycles: u32,
+        /// Returns the generators of a cycle, given its index and start time.
+        template: F,
+    ) -> Self {
+        Self {
+            template,
+            origin,
+            period: period.0,
+            cycles,
+            started: 0,
+            bounds: None,
+            current: Merge::default(),
+            spawned_last: false,
+            exhausted: None,
+        }
+    }
+}
+
+impl<T, F> CycleGenerator<'_, T, F> {
+    /// Returns the reason why the generator stopped producing events, or
+    /// `None` if it has not stopped yet.
+    pub fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+}
+
+impl<'a, T, F> Iterator for CycleGenerator<'a, T, F>
+where
+    T: Add<Output = T> + Mul<f64, Output = T> + Clone + PartialOrd,
+    F: FnMut(u32, T) -> Vec<BoxedGenerator<'a, T>>,
+{
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.exhausted.is_some() {
+            return None;
+        }
+        loop {
+            let spawned = self
+                .current
+                .peek_source()
+                .is_some_and(|member| member.spawned);
+            if let (Some((_, event, _)), Some((start, end))) = (self.current.pop(), &self.bounds) {
+                if event.time >= *end {
+                    // Events are in order, so the rest of the cycle is also
+                    // past its end.
+                    self.current = Merge::default();
+                } else if event.time >= *start {
+                    self.spawned_last = spawned;
+                    return Some(event);
+                }
+                continue;
+            }
+
+            if self.started == self.cycles {
+                self.exhausted = Some(ExhaustionReason::Duration);
+                return None;
+            }
+            // Computed directly (see `PulserGenerator`) to avoid drifting.
+            let cycle_time = |n: u32| self.origin.clone() + self.period.clone() * f64::from(n);
+            let (start, end) = (cycle_time(self.started), cycle_time(self.started + 1));
+            let generators = (self.template)(self.started, start.clone());
+            for (id, mut gen) in generators.into_iter().enumerate() {
+                if let Some(event) = gen.next() {
+                    let spawned = gen.spawned_last();
+                    self.current.push(id, event, Member { gen, spawned });
+                }
+            }
+            self.bounds = Some((start, end));
+            self.started += 1;
+        }
+    }
+}
+
+impl<'a, T, F> sealed::OrderedIterator for CycleGenerator<'a, T, F>
+where
+    T: Add<Output = T> + Mul<f64, Output = T> + Clone + PartialOrd,
+    F: FnMut(u32, T) -> Vec<BoxedGenerator<'a, T>>,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.exhausted
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.spawned_last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter::{repeat, repeat_n, Repeat};
+
+    #[test]
+    fn positive_new() {
+        assert_eq!(Positive::new(0.0), None);
+        assert_eq!(Positive::new(-1.0), None);
+        assert_eq!(Positive::new(1.0), Some(Positive(1.0)));
+    }
+
+    #[test]
+    fn wire_pattern() {
+        let a = WirePattern::from_bits(0b1000000000000000);
+        let b = "1000000000000000".parse::<WirePattern>().unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(a, WirePattern(32768));
+    }
+
+    #[test]
+    fn wire_pattern_display() {
+        assert_eq!(WirePattern(0).to_string(), "................");
+        assert_eq!(WirePattern(u16::MAX).to_string(), "XXXXXXXXXXXXXXXX");
+        assert_eq!(WirePattern(0x8e61).to_string(), "X....XX..XXX...X");
+        for n in [0, 1, 0x8000, 0x8e61, 0x5555, u16::MAX] {
+            let pattern = WirePattern(n);
+            assert_eq!(pattern.to_string().parse::<WirePattern>(), Ok(pattern));
+            assert_eq!(format!("{:#06x}", n).parse::<WirePattern>(), Ok(pattern));
+        }
+    }
+
+    #[test]
+    fn wire_pattern_from_str() {
+        assert_eq!("0X8E61".parse(), Ok(WirePattern(0x8e61)));
+        assert_eq!("0x1".parse(), Ok(WirePattern(1)));
+        assert_eq!("1".parse(), Ok(WirePattern(1)));
+        assert_eq!("10".parse(), Ok(WirePattern(2)));
+
+        for (input, span) in [
+            ("X....XX..XXx...X", 11..12),
+            ("X....XX..XXX...", 15..15),
+            ("X....XX..XXX...X.", 16..17),
+            ("X....XX..XXX..éX", 14..16),
+            ("", 0..0),
+            ("0x", 2..2),
+            ("0x8g61", 3..4),
+            ("0x8e610", 6..7),
+            ("10000000000000000", 16..17),
+            ("0101X", 0..1),
+        ] {
+            let error = input.parse::<WirePattern>().unwrap_err();
+            assert_eq!(error.span(), span, "{input}");
+        }
+
+        let error = "0x8g61".parse::<WirePattern>().unwrap_err();
+        assert!(error.to_string().contains("invalid wire pattern"));
+    }
+
+    #[test]
+    fn wire_pattern_bitor() {
+        let a = WirePattern::from_bits(0b1000000000000000);
+        let b = WirePattern::from_bits(0b0100000000000010);
+        let c = WirePattern::from_bits(0b0000000000000010);
+
+        let d = a | b;
+        assert_eq!(d, WirePattern(0b1100000000000010));
+
+        let e = b | c;
+        assert_eq!(e, WirePattern(0b0100000000000010));
+    }
+
+    #[test]
+    fn wire_pattern_bit_operators() {
+        let a = WirePattern(0b1100000000000011);
+        let b = WirePattern(0b1010000000000101);
+        assert_eq!(a & b, WirePattern(0b1000000000000001));
+        assert_eq!(a ^ b, WirePattern(0b0110000000000110));
+        assert_eq!(!a, WirePattern(0b0011111111111100));
+        assert_eq!(!!a, a);
+        assert_eq!((a ^ b) | (a & b), a | b);
+        assert_eq!((a & !a).bits(), 0);
+    }
+
+    #[test]
+    fn wire_pattern_wires() {
+        let mut pattern = WirePattern(0);
+        for idx in [0, 7, 15] {
+            assert!(!pattern.is_wire_set(idx));
+            pattern.set_wire(idx);
+            assert!(pattern.is_wire_set(idx));
+        }
+        assert_eq!(pattern.bits(), 0b1000000010000001);
+        pattern.set_wire(7);
+        assert_eq!(pattern.count_ones(), 3);
+
+        pattern.clear_wire(7);
+        pattern.clear_wire(8);
+        assert_eq!(pattern.bits(), 0b1000000000000001);
+        assert!((0..16).all(|idx| pattern.is_wire_set(idx) == (idx == 0 || idx == 15)));
+    }
+
+    #[test]
+    #[should_panic(expected = "board index 16 is out of range (0..16)")]
+    fn wire_pattern_wire_out_of_range() {
+        WirePattern(0).is_wire_set(16);
+    }
+
+    #[test]
+    fn wire_pattern_count_ones() {
+        assert_eq!(WirePattern(0).count_ones(), 0);
+        assert_eq!(WirePattern(0b1000110000000001).count_ones(), 4);
+        assert_eq!(WirePattern(u16::MAX).count_ones(), 16);
+    }
+
+    #[test]
+    fn wire_pattern_cluster_count() {
+        assert_eq!(WirePattern(0).cluster_count(), 0);
+        assert_eq!(WirePattern(0b0000000000000001).cluster_count(), 1);
+        assert_eq!(WirePattern(0b0101010101010101).cluster_count(), 8);
+        // Clusters wrap around.
+        assert_eq!(WirePattern(0b1000110000000001).cluster_count(), 2);
+        assert_eq!(WirePattern(u16::MAX).cluster_count(), 1);
+    }
+
+    #[test]
+    fn wire_pattern_rotate() {
+        let pattern = WirePattern(0b0100000000000110);
+        assert_eq!(pattern.rotate(0), pattern);
+        assert_eq!(pattern.rotate(2), WirePattern(0b0000000000011001));
+        assert_eq!(pattern.rotate(17), pattern.rotate(1));
+        assert_eq!(pattern.rotate(5).rotate(11), pattern);
+        for n in 0..16 {
+            let rotated = pattern.rotate(n);
+            assert_eq!(rotated.count_ones(), pattern.count_ones());
+            assert_eq!(rotated.cluster_count(), pattern.cluster_count());
+        }
+    }
+
+    #[test]
+    fn wire_pattern_iter_wires() {
+        assert_eq!(WirePattern(0).iter_wires().count(), 0);
+        assert!(WirePattern(u16::MAX).iter_wires().eq(0..16));
+        assert_eq!(
+            WirePattern(0b1000110000000001)
+                .iter_wires()
+                .collect::<Vec<_>>(),
+            [0, 10, 11, 15]
+        );
+        for n in [0x8001, 0x1234, 0x5555, 0xfffe] {
+            let pattern = WirePattern(n);
+            assert_eq!(pattern.iter_wires().count() as u32, pattern.count_ones());
+            let mut rebuilt = WirePattern(0);
+            pattern.iter_wires().for_each(|idx| rebuilt.set_wire(idx));
+            assert_eq!(rebuilt, pattern);
+        }
+    }
+
+    #[test]
+    fn wire_pattern_cluster_count_tricky() {
+        for (bits, clusters) in [
+            // Wrap-around clusters of different sizes.
+            (0b1000000000000001, 1),
+            (0b1100000000000001, 1),
+            (0b1000000000000011, 1),
+            (0b1110000000000111, 1),
+            // Touching only one end of the ring.
+            (0b1000000000000000, 1),
+            (0b0000000000000001, 1),
+            (0b1000000000000010, 2),
+            (0b0100000000000001, 2),
+            // A wrap-around cluster plus others.
+            (0b1000000100000001, 2),
+            (0b1001001001001001, 5),
+            (0b1010101010101011, 7),
+            // No boards, or all boards.
+            (0b0000000000000000, 0),
+            (0b1111111111111111, 1),
+            // Almost full rings.
+            (0b0111111111111111, 1),
+            (0b1111111111111110, 1),
+            (0b0111111101111111, 2),
+        ] {
+            assert_eq!(WirePattern(bits).cluster_count(), clusters, "{bits:016b}");
+        }
+    }
+
+    #[test]
+    fn wire_pattern_from_wires() {
+        assert_eq!(WirePattern::from_wires([]), WirePattern(0));
+        assert_eq!(WirePattern::from_wires([15, 0, 0]), WirePattern(0x8001));
+        assert_eq!(WirePattern::from_wires(0..16), WirePattern(u16::MAX));
+        let pattern = WirePattern(0x8e61);
+        assert_eq!(WirePattern::from_wires(pattern.iter_wires()), pattern);
+    }
+
+    #[test]
+    #[should_panic(expected = "board index 16 is out of range (0..16)")]
+    fn wire_pattern_from_wires_out_of_range() {
+        WirePattern::from_wires([16]);
+    }
+
+    #[test]
+    fn wire_pattern_dilate() {
+        let pattern = WirePattern::from_wires([5]);
+        assert_eq!(pattern.dilate(0), pattern);
+        assert_eq!(pattern.dilate(2), WirePattern::from_wires(3..=7));
+        // Wrap around from board 15 to board 0 and back.
+        assert_eq!(
+            WirePattern::from_wires([15]).dilate(1),
+            WirePattern::from_wires([14, 15, 0])
+        );
+        assert_eq!(
+            WirePattern::from_wires([0]).dilate(2),
+            WirePattern::from_wires([14, 15, 0, 1, 2])
+        );
+        assert_eq!(
+            WirePattern::from_wires([0, 15]).dilate(1),
+            WirePattern::from_wires([14, 15, 0, 1])
+        );
+        assert_eq!(pattern.dilate(7).count_ones(), 15);
+        assert_eq!(pattern.dilate(8), WirePattern(u16::MAX));
+        assert_eq!(pattern.dilate(200), WirePattern(u16::MAX));
+        assert_eq!(WirePattern(0).dilate(3), WirePattern(0));
+    }
+
+    #[test]
+    fn wire_pattern_is_contiguous() {
+        assert!(!WirePattern(0).is_contiguous());
+        assert!(WirePattern(u16::MAX).is_contiguous());
+        assert!(WirePattern::from_wires([7]).is_contiguous());
+        assert!(WirePattern::from_wires(3..9).is_contiguous());
+        // Wrap around from board 15 to board 0.
+        assert!(WirePattern::from_wires([15, 0]).is_contiguous());
+        assert!(WirePattern::from_wires([13, 14, 15, 0, 1]).is_contiguous());
+        assert!(!WirePattern::from_wires([14, 0]).is_contiguous());
+        assert!(!WirePattern::from_wires([15, 1]).is_contiguous());
+        assert!(WirePattern::from_wires([14, 0]).dilate(1).is_contiguous());
+    }
+
+    #[test]
+    fn cluster_pattern_model() {
+        use rand::distr::Uniform;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let model = ClusterPatternModel::builder()
+            .clusters(Uniform::new_inclusive(0, 3).unwrap())
+            .width(Uniform::new_inclusive(1, 4).unwrap())
+            .build();
+        let mut histogram = [0; 4];
+        let mut rotations = [0; 16];
+        let samples = 40_000;
+        for pattern in model.sample_iter(StdRng::seed_from_u64(11)).take(samples) {
+            histogram[pattern.cluster_count() as usize] += 1;
+            // Clusters 1 to 4 boards wide.
+            assert!(pattern.count_ones() <= 4 * pattern.cluster_count());
+            assert!(pattern.count_ones() >= pattern.cluster_count());
+            if pattern.cluster_count() == 1 {
+                rotations[pattern.iter_wires().next().unwrap() as usize] += 1;
+            }
+        }
+        // 5 standard deviations of a binomial distribution.
+        let sigma = (samples as f64 * 0.25 * 0.75).sqrt();
+        for count in histogram {
+            assert!((count as f64 - samples as f64 / 4.0).abs() < 5.0 * sigma);
+        }
+        // All the rotations are possible.
+        assert!(rotations.iter().all(|&count| count > 0), "{rotations:?}");
+    }
+
+    #[test]
+    fn cluster_pattern_model_narrows_clusters() {
+        use rand::distr::Uniform;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(11);
+        // 8 clusters only fit as alternating boards.
+        let model = ClusterPatternModel::builder()
+            .clusters(Uniform::new_inclusive(8, 20).unwrap())
+            .width(Uniform::new_inclusive(0, 100).unwrap())
+            .build();
+        for _ in 0..100 {
+            let pattern = model.sample(&mut rng);
+            assert!(pattern == WirePattern(0x5555) || pattern == WirePattern(0xaaaa));
+        }
+
+        let model = ClusterPatternModel::builder()
+            .clusters(Uniform::new_inclusive(3, 3).unwrap())
+            .width(Uniform::new_inclusive(10, 10).unwrap())
+            .build();
+        for _ in 0..100 {
+            let pattern = model.sample(&mut rng);
+            assert_eq!((pattern.cluster_count(), pattern.count_ones()), (3, 13));
+        }
+
+        let model = ClusterPatternModel::builder()
+            .clusters(Uniform::new_inclusive(1, 1).unwrap())
+            .width(Uniform::new_inclusive(16, 16).unwrap())
+            .build();
+        let pattern = model.sample(&mut rng);
+        assert_eq!((pattern.cluster_count(), pattern.count_ones()), (1, 15));
+    }
+
+    #[test]
+    fn wire_pattern_similarity() {
+        let a = WirePattern::from_wires([14, 15, 0]);
+        let b = WirePattern::from_wires([15, 0, 1]);
+        assert_eq!(a.hamming_distance(&b), 2);
+        assert_eq!(a.overlap(&b), 2);
+        assert_eq!(a.hamming_distance(&a), 0);
+        assert_eq!(a.overlap(&a), 3);
+        assert_eq!(a.hamming_distance(&!a), 16);
+        assert_eq!(a.overlap(&!a), 0);
+
+        assert!(a.contains(&WirePattern::from_wires([15, 0])));
+        assert!(a.contains(&WirePattern(0)));
+        assert!(a.contains(&a));
+        assert!(!a.contains(&b));
+        assert!(WirePattern(u16::MAX).contains(&a));
+        assert!(!WirePattern(0).contains(&a));
+    }
+
+    #[test]
+    fn wire_pattern_all_rotations() {
+        let pattern = WirePattern::from_wires([15, 0]);
+        let rotations = pattern.all_rotations().collect::<Vec<_>>();
+        assert_eq!(rotations[0], pattern);
+        assert_eq!(rotations[1], WirePattern::from_wires([0, 1]));
+        assert_eq!(rotations[15], WirePattern::from_wires([14, 15]));
+        assert!(rotations.iter().all(|rotation| rotation.is_contiguous()));
+        let unique = rotations
+            .iter()
+            .map(|p| p.0)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique.len(), 16);
+
+        // Alternating boards only have 2 distinct rotations.
+        let unique = WirePattern(0x5555)
+            .all_rotations()
+            .map(|p| p.0)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique, [0x5555, 0xaaaa].into());
+    }
+
+    #[test]
+    fn sector_map() {
+        let map = SectorMap::default();
+        // Sector boundaries.
+        for sector in 0..16 {
+            let wires = SectorMap::sector_wires(sector);
+            assert_eq!(map.sector(wires.start), sector);
+            assert_eq!(map.sector(wires.end - 1), sector);
+            assert_eq!(map.compress(wires), WirePattern::from_wires([sector]));
+        }
+        assert_eq!(map.compress([15, 16]), WirePattern::from_wires([0, 1]));
+        assert_eq!(map.compress([255, 0]), WirePattern::from_wires([15, 0]));
+        assert_eq!(map.compress([]), WirePattern(0));
+        assert_eq!(map.compress(0..256), WirePattern(u16::MAX));
+
+        let reversed = SectorMap::new(std::array::from_fn(|wire| 15 - (wire / 16) as u8));
+        assert_eq!(
+            reversed.compress([0, 16]),
+            WirePattern::from_wires([15, 14])
+        );
+
+        let event = WireEvent::from_physical_wires(Source::Noise, 1, [31, 32], &map);
+        assert_eq!((event.wire_pattern, event.time), (WirePattern(0b110), 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "wire index 256 is out of range (0..256)")]
+    fn sector_map_wire_out_of_range() {
+        SectorMap::default().compress([0, 256]);
+    }
+
+    #[test]
+    #[should_panic(expected = "sector 16 is out of range (0..16)")]
+    fn sector_map_sector_out_of_range() {
+        SectorMap::new([16; 256]);
+    }
+
+    #[test]
+    #[should_panic(expected = "sector 16 is out of range (0..16)")]
+    fn sector_wires_out_of_range() {
+        SectorMap::sector_wires(16);
+    }
+
+    #[test]
+    fn wire_pattern_cluster_count_wrap_around() {
+        // Single boards at both ends of the ring.
+        assert_eq!(WirePattern(0x8000).cluster_count(), 1);
+        assert_eq!(WirePattern(0x0001).cluster_count(), 1);
+        // Bits 15 and 0 are adjacent.
+        assert_eq!(WirePattern(0x8001).cluster_count(), 1);
+        assert_eq!(WirePattern(0xc003).cluster_count(), 1);
+        assert_eq!(WirePattern(0x8002).cluster_count(), 2);
+        assert_eq!(WirePattern(0x4001).cluster_count(), 2);
+        // A single gap anywhere in the ring leaves a single cluster.
+        for gap in 0..16 {
+            assert_eq!(WirePattern(!(1 << gap)).cluster_count(), 1, "{gap}");
+        }
+        // Two gaps split the ring in two, unless they are adjacent.
+        assert_eq!(WirePattern(!0x8001).cluster_count(), 1);
+        assert_eq!(WirePattern(!0x0101).cluster_count(), 2);
+        assert_eq!(WirePattern(!0x4001).cluster_count(), 2);
+    }
+
+    #[test]
+    fn wire_pattern_cluster_count_exhaustive() {
+        for n in 0..=u16::MAX {
+            // A cluster starts at every high board whose previous (ring)
+            // neighbor is low.
+            let starts = (n & !n.rotate_left(1)).count_ones();
+            let expected = if n == u16::MAX { 1 } else { starts };
+            assert_eq!(WirePattern(n).cluster_count(), expected, "{n:#06x}");
+        }
+    }
+
+    #[test]
+    fn secondary_generator_source() {
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+            .wire_pattern(vec![WirePattern::from_bits(0)])
+            .build();
+
+        for event in gen {
+            assert!(matches!(event.source, Source::Noise));
+        }
+    }
+
+    #[test]
+    fn secondary_generator_origin() {
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(-10.0)
+            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+            .wire_pattern(vec![WirePattern::from_bits(0)])
+            .build();
+
+        assert_eq!(gen.next().unwrap().time, -9.0);
+    }
+
+    #[test]
+    fn secondary_generator_duration() {
+        let events = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .duration(Positive::new(10.0).unwrap())
+            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(0)))
+            .build()
+            .collect::<Vec<_>>();
+
+        assert_eq!(events.last().unwrap().time, 9.0);
+    }
+
+    #[test]
+    fn secondary_generator_inter_arrival_time() {
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_time(vec![
+                Positive::new(1.0).unwrap(),
+                Positive::new(2.0).unwrap(),
+            ])
+            .wire_pattern(repeat(WirePattern::from_bits(0)))
+            .build();
+
+        assert_eq!(gen.next().unwrap().time, 1.0);
+        assert_eq!(gen.next().unwrap().time, 3.0);
+        assert!(gen.next().is_none());
+    }
+
+    #[test]
+    fn secondary_generator_wire_pattern() {
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
+            .wire_pattern(vec![
+                WirePattern::from_bits(0),
+                WirePattern::from_bits(u16::MAX),
+            ])
+            .build();
+
+        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(0));
+        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(u16::MAX));
+        assert!(gen.next().is_none());
+    }
+
+    #[test]
+    fn secondary_generator_exhaustion_reason() {
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(3).unwrap())
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .wire_pattern(repeat_n(WirePattern::from_bits(0), 2))
+            .build();
+        assert_eq!(gen.exhaustion_reason(), None);
+        assert_eq!(gen.by_ref().count(), 2);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::Duration));
+
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat_n(Positive::new(1).unwrap(), 2))
+            .wire_pattern(repeat(WirePattern::from_bits(0)))
+            .build();
+        assert_eq!(gen.by_ref().count(), 2);
+        assert_eq!(
+            gen.exhaustion_reason(),
+            Some(ExhaustionReason::InterArrivalTime)
+        );
+
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .wire_pattern(repeat_n(WirePattern::from_bits(0), 2))
+            .build();
+        assert_eq!(gen.by_ref().count(), 2);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::WirePattern));
+    }
+
+    #[test]
+    fn primary_generator_source() {
+        let mut gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+            .wire_pattern(vec![WirePattern::from_bits(0)])
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+                    .wire_pattern(vec![WirePattern::from_bits(0)])
+            })
+            .build();
+
+        assert!(matches!(gen.next().unwrap().source, Source::PrimaryPbar));
+        assert!(matches!(gen.next().unwrap().source, Source::SecondaryPbar));
+    }
+
+    #[test]
+    fn primary_generator_origin() {
+        let mut gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+            .wire_pattern(vec![WirePattern::from_bits(0)])
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+                    .wire_pattern(vec![WirePattern::from_bits(0)])
+            })
+            .build();
+
+        assert_eq!(gen.next().unwrap().time, 1.0);
+    }
+
+    #[test]
+    fn secondary_generator_absolute_origin() {
+        let times = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(100)
+            .duration(Positive::new(20).unwrap())
+            .inter_arrival_time(repeat(Positive::new(7).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build()
+            .map(|event| event.time)
+            .collect::<Vec<_>>();
+
+        assert_eq!(times, [107, 114]);
+    }
+
+    #[test]
+    fn primary_generator_relative_afterpulses() {
+        // Afterpulses start at the primary event, and their duration is
+        // relative to it.
+        let expected = [1100, 1107, 1114, 1150, 1157, 1164];
+
+        let explicit = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(1000)
+            .inter_arrival_time([100, 50].map(|t| Positive::new(t).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .relative_to_parent()
+                    .duration(Positive::new(20).unwrap())
+                    .inter_arrival_time(repeat(Positive::new(7).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build()
+            .map(|event| event.time)
+            .collect::<Vec<_>>();
+        assert_eq!(explicit, expected);
+
+        // Same as the default.
+        let default = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(1000)
+            .inter_arrival_time([100, 50].map(|t| Positive::new(t).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .duration(Positive::new(20).unwrap())
+                    .inter_arrival_time(repeat(Positive::new(7).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build()
+            .map(|event| event.time)
+            .collect::<Vec<_>>();
+        assert_eq!(default, expected);
+    }
+
+    #[test]
+    fn primary_generator_duration() {
+        let events = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .duration(Positive::new(10.0).unwrap())
+            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(0)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
+                    .wire_pattern(vec![WirePattern::from_bits(0)])
+            })
+            .build()
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            events
+                .iter()
+                .rfind(|e| matches!(e.source, Source::PrimaryPbar))
+                .unwrap()
+                .time,
+            9.0
+        );
+        assert_eq!(events.last().unwrap().time, 10.0);
+    }
+
+    #[test]
+    fn primary_generator_inter_arrival_time() {
+        let mut gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .inter_arrival_time(vec![
+                Positive::new(1.0).unwrap(),
+                Positive::new(2.0).unwrap(),
+            ])
+            .wire_pattern(repeat(WirePattern::from_bits(0)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(vec![Positive::new(0.1).unwrap()])
+                    .wire_pattern(vec![WirePattern::from_bits(0)])
+            })
+            .build();
+
+        assert_eq!(gen.next().unwrap().time, 1.0);
+        assert_eq!(gen.next().unwrap().time, 1.1);
+        assert_eq!(gen.next().unwrap().time, 3.0);
+        assert_eq!(gen.next().unwrap().time, 3.1);
+        assert!(gen.next().is_none());
+    }
+
+    #[test]
+    fn primary_generator_wire_pattern() {
+        let mut gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .inter_arrival_time(repeat(Positive::new(2.0).unwrap()))
+            .wire_pattern(vec![WirePattern::from_bits(0), WirePattern::from_bits(0)])
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
+                    .wire_pattern(vec![WirePattern::from_bits(u16::MAX)])
+            })
+            .build();
+
+        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(0));
+        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(u16::MAX));
+        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(0));
+        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(u16::MAX));
+        assert!(gen.next().is_none());
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::WirePattern));
+    }
+
+    #[test]
+    fn primary_generator_afterpulse() {
+        let mut count = 0;
+
+        let mut gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .inter_arrival_time(repeat_n(Positive::new(1.0).unwrap(), 3))
+            .wire_pattern(repeat(WirePattern::from_bits(0)))
+            .afterpulse(|_: &_| {
+                let n = count;
+                let delta_t = 1.0 / (n + 1) as f64;
+                count += 1;
+
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(repeat_n(Positive::new(delta_t).unwrap(), n))
+                    .wire_pattern(repeat(WirePattern::from_bits(0)))
+            })
+            .build();
+
+        assert!((gen.next().unwrap().time - 1.0).abs() < 1e-6);
+        assert!((gen.next().unwrap().time - 2.0).abs() < 1e-6);
+        assert!((gen.next().unwrap().time - 2.5).abs() < 1e-6);
+        assert!((gen.next().unwrap().time - 3.0).abs() < 1e-6);
+        assert!((gen.next().unwrap().time - 10.0 / 3.0).abs() < 1e-6);
+        assert!((gen.next().unwrap().time - 11.0 / 3.0).abs() < 1e-6);
+        assert!(gen.next().is_none());
+    }
+
+    fn noise() -> SecondaryGenerator<Repeat<Positive<i32>>, Repeat<WirePattern>> {
+        SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build()
+    }
+
+    #[test]
+    fn filter_events() {
+        let mut gen = noise()
+            .take_events(6)
+            .filter_events(|event| event.time % 20 != 0);
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [10, 30, 50]);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+    }
+
+    #[test]
+    fn map_event() {
+        let mut patterns = [0b11, 0b01, 0b10, 0b01].into_iter();
+        let gen = noise()
+            .take_events(4)
+            .map_event(|mut event| {
+                event.wire_pattern = WirePattern::from_bits(patterns.next().unwrap());
+                Some(event)
+            })
+            .map_event(|mut event| {
+                // Dead board 0.
+                event.wire_pattern = event.wire_pattern & !WirePattern::from_bits(1);
+                (event.wire_pattern.bits() != 0).then_some(event)
+            });
+        let events: Vec<_> = gen
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        assert_eq!(events, [(10, 0b10), (30, 0b10)]);
+    }
+
+    #[test]
+    fn map_time() {
+        use uom::si::f64::Time;
+        use uom::si::time::nanosecond;
+
+        let ticks = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(0u64)
+            .end_time(25)
+            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .inter_arrival_time([Positive::new(3).unwrap()])
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build();
+        let mut gen = ticks.map_time(|t| Time::new::<nanosecond>(t as f64 * 16.0));
+        let events: Vec<_> = gen
+            .by_ref()
+            .map(|event| {
+                (
+                    event.time.get::<nanosecond>().round(),
+                    event.source,
+                    event.id,
+                )
+            })
+            .collect();
+        // Afterpulses are converted too.
+        assert_eq!(
+            events,
+            [
+                (160.0, Source::PrimaryCosmic, Some(EventId(0))),
+                (208.0, Source::SecondaryCosmic, Some(EventId(0))),
+                (320.0, Source::PrimaryCosmic, Some(EventId(1))),
+                (368.0, Source::SecondaryCosmic, Some(EventId(1))),
+            ]
+        );
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::Duration)
+        );
+
+        // And back to ticks.
+        let times: Vec<_> = ScriptedGenerator::from_times(
+            Source::Noise,
+            [160.0, 208.0].map(Time::new::<nanosecond>),
+            WirePattern::from_bits(1),
+        )
+        .map_time(|t| (t.get::<nanosecond>() / 16.0).round() as u64)
+        .map(|event| event.time)
+        .collect();
+        assert_eq!(times, [10, 13]);
+    }
+
+    #[test]
+    fn mask_channels() {
+        let mask = ChannelMask::builder()
+            .disabled(WirePattern::from_bits(0b0110))
+            .hot(vec![(2, 1.0), (3, 0.25)])
+            .build();
+        let mut gen = noise()
+            .take_events(100_000)
+            .mask_channels(mask, StdRng::seed_from_u64(0));
+        let mut hot = 0;
+        let mut count = 0;
+        for event in gen.by_ref() {
+            let bits = event.wire_pattern.bits();
+            // Board 2 is both hot and disabled.
+            assert!(bits == 0b0001 || bits == 0b1001);
+            hot += usize::from(bits == 0b1001);
+            count += 1;
+        }
+        assert_eq!(count, 100_000);
+        // 5 sigma.
+        let expected = 25_000.0;
+        let sigma = (100_000.0f64 * 0.25 * 0.75).sqrt();
+        assert!((hot as f64 - expected).abs() < 5.0 * sigma);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+
+        // Events without high boards are dropped.
+        let mask = ChannelMask::builder()
+            .disabled(WirePattern::from_bits(1))
+            .build();
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .wire_pattern([1, 3, 1, 2].map(WirePattern::from_bits))
+            .build()
+            .mask_channels(mask, StdRng::seed_from_u64(0));
+        let events: Vec<_> = gen
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        assert_eq!(events, [(2, 2), (4, 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "probability 1.5 of board 3 is not in [0, 1]")]
+    fn channel_mask_invalid_probability() {
+        ChannelMask::builder().hot(vec![(3, 1.5)]).build();
+    }
+
+    #[test]
+    #[should_panic(expected = "board 16 is out of range")]
+    fn channel_mask_invalid_board() {
+        ChannelMask::builder().hot(vec![(16, 0.5)]).build();
+    }
+
+    #[test]
+    fn cross_talk_fraction() {
+        let cross_talk = CrossTalk::builder().neighbor(0.1).build();
+        let n = 100_000;
+        let mut counts = [0; 4];
+        let gen = noise()
+            .take_events(n)
+            .cross_talk(cross_talk, StdRng::seed_from_u64(0));
+        for event in gen {
+            let pattern = event.wire_pattern;
+            assert!(pattern.is_wire_set(0) && pattern.is_contiguous());
+            counts[pattern.count_ones() as usize] += 1;
+        }
+        // One neighbor fires with probability 2p(1 - p), and both with p^2.
+        for (count, p) in [(counts[2], 0.18), (counts[3], 0.01)] {
+            let expected = n as f64 * p;
+            let sigma = (n as f64 * p * (1.0 - p)).sqrt();
+            assert!((count as f64 - expected).abs() < 5.0 * sigma);
+        }
+        assert_eq!(counts.iter().sum::<usize>(), n);
+    }
+
+    #[test]
+    fn cross_talk_wrap_around() {
+        let patterns = |bits: u16, cross_talk: CrossTalk| {
+            ScriptedGenerator::from_times(Source::Noise, [0], WirePattern::from_bits(bits))
+                .cross_talk(cross_talk, StdRng::seed_from_u64(0))
+                .map(|event| event.wire_pattern.bits())
+                .next()
+                .unwrap()
+        };
+        let neighbor = CrossTalk::builder().neighbor(1.0).build();
+        assert_eq!(patterns(1 << 0, neighbor), (1 << 15) | 0b11);
+        assert_eq!(patterns(1 << 15, neighbor), (0b11 << 14) | 1);
+        let second = CrossTalk::builder()
+            .neighbor(0.0)
+            .second_neighbor(1.0)
+            .build();
+        assert_eq!(patterns(1 << 0, second), (1 << 14) | 0b101);
+        assert_eq!(patterns(1 << 15, second), (1 << 13) | (1 << 15) | 0b10);
+        // Induced boards do not induce cross-talk themselves.
+        assert_eq!(patterns(0b1001, neighbor), 0b1_1111 | (1 << 15));
+    }
+
+    #[test]
+    #[should_panic(expected = "cross-talk probability -0.1 is not in [0, 1]")]
+    fn cross_talk_invalid_probability() {
+        CrossTalk::builder()
+            .neighbor(0.5)
+            .second_neighbor(-0.1)
+            .build();
+    }
+
+    #[test]
+    fn jitter_swaps_close_events() {
+        let gen = ScriptedGenerator::new(
+            [(100, 1), (102, 2), (103, 3), (200, 4)]
+                .map(|(time, bits)| WireEvent {
+                    source: Source::Noise,
+                    wire_pattern: WirePattern::from_bits(bits),
+                    time,
+                    id: None,
+                    weight: 1.0,
+                })
+                .to_vec(),
+        );
+        let mut gen = gen.jitter([2, -2, -1, 0], Positive::new(3).unwrap());
+        let events: Vec<_> = gen
+            .by_ref()
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        // The first two events swap; ties keep the original order.
+        assert_eq!(events, [(100, 2), (102, 1), (102, 3), (200, 4)]);
+        assert_eq!(sealed::OrderedIterator::exhaustion_reason(&gen), None);
+    }
+
+    #[test]
+    fn jitter_clamps_offsets() {
+        let mut gen = noise()
+            .take_events(4)
+            .jitter([-100, 100, 4, -4], Positive::new(5).unwrap());
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [5, 25, 34, 36]);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+
+        // Offsets run out; buffered events are still produced.
+        let mut gen = noise().jitter([1, -1], Positive::new(5).unwrap());
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [11, 19]);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::Jitter)
+        );
+    }
+
+    #[test]
+    fn jitter_keeps_spawned_last() {
+        // Primary events at 10 and 20, each with an afterpulse 1 later.
+        let gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0)
+            .inter_arrival_time(repeat_n(Positive::new(10).unwrap(), 2))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .max_events(1)
+                    .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build();
+        let mut gen = gen.jitter([2, -2, 0, 0], Positive::new(3).unwrap());
+        let mut events = Vec::new();
+        while let Some(event) = gen.next() {
+            events.push((event.time, sealed::OrderedIterator::spawned_last(&gen)));
+        }
+        assert_eq!(events, [(9, true), (12, false), (20, false), (21, true)]);
+    }
+
+    #[test]
+    fn chain_events_drops_overlap() {
+        let first =
+            ScriptedGenerator::from_times(Source::Noise, [10, 20], WirePattern::from_bits(1));
+        let mut gen = first.chain_events(noise().take_events(4));
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [10, 20, 20, 30, 40]);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+    }
+
+    #[test]
+    fn chain_with_origin_after_last() {
+        let mut called = 0;
+        let gen = noise().take_events(3).chain_with(|last| {
+            called += 1;
+            assert_eq!(last, Some(30));
+            SecondaryGenerator::builder()
+                .source(Source::PrimaryPbar)
+                .origin(last.unwrap() + 100)
+                .max_events(2)
+                .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(2)))
+                .build()
+        });
+        let events: Vec<_> = gen.map(|event| (event.time, event.source)).collect();
+        assert_eq!(
+            events,
+            [
+                (10, Source::Noise),
+                (20, Source::Noise),
+                (30, Source::Noise),
+                (131, Source::PrimaryPbar),
+                (132, Source::PrimaryPbar),
+            ]
+        );
+        assert_eq!(called, 1);
+    }
+
+    #[test]
+    fn chain_with_empty_first() {
+        let first = ScriptedGenerator::from_times(Source::Noise, [], WirePattern::from_bits(1));
+        let mut gen = first.chain_with(|last| {
+            assert_eq!(last, None);
+            noise().take_events(2)
+        });
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [10, 20]);
+        assert!(gen.next().is_none());
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+    }
+
+    // Events with a single board that encodes the time (e.g. `[0, 3]` gives
+    // the patterns `0b1` and `0b1000`).
+    fn time_patterns(source: Source, times: &[i32]) -> Vec<WireEvent<i32>> {
+        times
+            .iter()
+            .map(|&time| WireEvent {
+                source,
+                wire_pattern: WirePattern::from_bits(1 << time),
+                time,
+                id: None,
+                weight: 1.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn coalesce_runs() {
+        let raw = time_patterns(Source::Noise, &[0, 1, 2, 3, 7, 8, 12]);
+        let gen = ScriptedGenerator::new(raw.clone());
+        let coalesced: Vec<_> = gen
+            .coalesce(Positive::new(3).unwrap(), CoalescePolicy::First)
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        // Runs are measured from their first event, not the previous one.
+        assert_eq!(
+            coalesced,
+            [(0, 0b111), (3, 0b1000), (7, 0b1_1000_0000), (12, 1 << 12)]
+        );
+        // The OR of the patterns is preserved.
+        let or = |patterns: &mut dyn Iterator<Item = u16>| patterns.fold(0, |a, b| a | b);
+        assert_eq!(
+            or(&mut coalesced.iter().map(|(_, bits)| *bits)),
+            or(&mut raw.iter().map(|event| event.wire_pattern.bits()))
+        );
+
+        // Smaller than the spacing of the events.
+        let gen = ScriptedGenerator::new(raw.clone())
+            .coalesce(Positive::new(1).unwrap(), CoalescePolicy::First);
+        assert_eq!(
+            gen.map(|event| event.wire_pattern).collect::<Vec<_>>(),
+            raw.iter()
+                .map(|event| event.wire_pattern)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn coalesce_does_not_poll_exhausted_inner() {
+        // Panics if it is polled again after it returned `None`.
+        struct Unfused(std::vec::IntoIter<WireEvent<i32>>, bool);
+        impl Iterator for Unfused {
+            type Item = WireEvent<i32>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                assert!(!self.1, "polled after it returned `None`");
+                let event = self.0.next();
+                self.1 = event.is_none();
+                event
+            }
+        }
+        impl sealed::OrderedIterator for Unfused {}
+
+        // Times of the events, and the number of runs.
+        for (times, runs) in [(&[][..], 0), (&[0, 1], 1), (&[0, 5], 2)] {
+            let raw = time_patterns(Source::Noise, times);
+            let mut gen = Unfused(raw.into_iter(), false)
+                .coalesce(Positive::new(3).unwrap(), CoalescePolicy::First);
+            assert_eq!(gen.by_ref().count(), runs);
+            assert!(gen.next().is_none());
+            assert!(gen.next().is_none());
+        }
+    }
+
+    #[test]
+    fn coalesce_source_policy() {
+        let mut raw = time_patterns(Source::Noise, &[0]);
+        raw.extend(time_patterns(Source::SecondaryPbar, &[1]));
+        raw.extend(time_patterns(Source::PrimaryPbar, &[2]));
+        raw.extend(time_patterns(Source::Noise, &[5]));
+        raw[2].id = Some(EventId::new(3));
+        raw[1].weight = 4.0;
+        raw[2].weight = 0.5;
+        let weights: Vec<_> = ScriptedGenerator::new(raw.clone())
+            .coalesce(Positive::new(5).unwrap(), CoalescePolicy::First)
+            .map(|event| event.weight)
+            .collect();
+        assert_eq!(weights, [2.0, 1.0]);
+        let sources = |policy| {
+            ScriptedGenerator::new(raw.clone())
+                .coalesce(Positive::new(5).unwrap(), policy)
+                .map(|event| (event.source, event.id))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sources(CoalescePolicy::First),
+            [(Source::Noise, None), (Source::Noise, None)]
+        );
+        assert_eq!(
+            sources(CoalescePolicy::Priority(vec![
+                Source::PrimaryPbar,
+                Source::SecondaryPbar
+            ])),
+            [
+                (Source::PrimaryPbar, Some(EventId::new(3))),
+                (Source::Noise, None)
+            ]
+        );
+        // Unlisted sources come last.
+        assert_eq!(
+            sources(CoalescePolicy::Priority(vec![Source::SecondaryPbar])),
+            [(Source::SecondaryPbar, None), (Source::Noise, None)]
+        );
+    }
+
+    #[test]
+    fn coalesce_spawned_last() {
+        // Primary events at 10 and 20, each with an afterpulse 1 later.
+        let gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0)
+            .inter_arrival_time(repeat_n(Positive::new(10).unwrap(), 2))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .max_events(1)
+                    .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build();
+        let policy = CoalescePolicy::Priority(vec![Source::SecondaryPbar]);
+        let mut gen = gen.coalesce(Positive::new(2).unwrap(), policy);
+        let mut events = Vec::new();
+        while let Some(event) = gen.next() {
+            events.push((
+                event.time,
+                event.wire_pattern.bits(),
+                sealed::OrderedIterator::spawned_last(&gen),
+            ));
+        }
+        assert_eq!(events, [(10, 0b11, true), (20, 0b11, true)]);
+    }
+
+    #[test]
+    fn buffered_same_events() {
+        let gen = || {
+            PoissonGenerator::builder()
+                .source(Source::PrimaryPbar)
+                .origin(0.0)
+                .duration(Positive::new(1000.0).unwrap())
+                .rate(Positive::new(1.0).unwrap())
+                .rng(Seed::new(0).rng())
+                .wire_pattern(rand::distr::StandardUniform.map(WirePattern::from_bits))
+                .build()
+                .afterpulse(|event: &WireEvent<_>| {
+                    SecondaryGenerator::builder()
+                        .source(Source::SecondaryPbar)
+                        .inter_arrival_time(repeat_n(Positive::new(0.5).unwrap(), 2))
+                        .wire_pattern(repeat(event.wire_pattern))
+                })
+        };
+        // Events (with `spawned_last`) and the exhaustion reason.
+        type Run = (
+            Vec<(f64, u16, Option<EventId>, bool)>,
+            Option<ExhaustionReason>,
+        );
+        fn events<G: EventGenerator<Time = f64>>(mut gen: G) -> Run {
+            let mut events = Vec::new();
+            while let Some(event) = gen.next() {
+                events.push((
+                    event.time,
+                    event.wire_pattern.bits(),
+                    event.id,
+                    gen.spawned_last(),
+                ));
+            }
+            (events, gen.exhaustion_reason())
+        }
+
+        let (expected, reason) = events(gen());
+        assert!(expected.len() > 2000);
+        assert_eq!(reason, Some(ExhaustionReason::Duration));
+        for depth in [0, 1, 64] {
+            assert_eq!(events(gen().buffered(depth)), (expected.clone(), reason));
+        }
+    }
+
+    #[test]
+    fn buffered_drop_stops_worker() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let produced = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&produced);
+        // Never exhausted.
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build()
+            .map_event(move |event| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                Some(event)
+            });
+        let mut gen = gen.buffered(4);
+        assert_eq!(gen.by_ref().take(3).count(), 3);
+        drop(gen);
+
+        // The worker stops at most a full buffer (and the event it was
+        // sending) ahead.
+        let produced = produced.load(Ordering::Relaxed);
+        assert!((3..=8).contains(&produced), "{produced} events produced");
+    }
+
+    #[test]
+    #[should_panic(expected = "worker failed")]
+    fn buffered_propagates_panic() {
+        let gen =
+            ScriptedGenerator::from_times(Source::Noise, [1, 2, 3], WirePattern::from_bits(1))
+                .map_event(|event| {
+                    assert!(event.time < 3, "worker failed");
+                    Some(event)
+                });
+        gen.buffered(1).for_each(drop);
+    }
+
+    #[test]
+    fn jitter_gaussian() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let offsets = rand_distr::Normal::new(0.0, 2.0)
+            .unwrap()
+            .sample_iter(&mut rng);
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build()
+            .take_events(10_000)
+            .jitter(offsets, Positive::new(10.0).unwrap());
+
+        let times: Vec<f64> = gen.map(|event| event.time).collect();
+        assert_eq!(times.len(), 10_000);
+        assert!(times.is_sorted());
+        // Events are only reordered locally.
+        let swapped = times
+            .iter()
+            .enumerate()
+            .filter(|(i, t)| (**t - (*i + 1) as f64).abs() > 20.0)
+            .count();
+        assert_eq!(swapped, 0);
+    }
+
+    #[test]
+    fn take_events() {
+        let mut gen = noise().take_events(3);
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [10, 20, 30]);
+        assert!(gen.next().is_none());
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+
+        // The inner generator stops first.
+        let mut gen = ScriptedGenerator::from_times(Source::Noise, [1], WirePattern::from_bits(1))
+            .take_events(3);
+        assert_eq!(gen.by_ref().count(), 1);
+        assert_eq!(sealed::OrderedIterator::exhaustion_reason(&gen), None);
+
+        assert_eq!(noise().take_events(0).count(), 0);
+    }
+
+    #[test]
+    fn until_time() {
+        let mut gen = noise().until_time(40);
+        assert_eq!(sealed::OrderedIterator::exhaustion_reason(&gen), None);
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [10, 20, 30]);
+        assert!(gen.next().is_none());
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::Duration)
+        );
+
+        // The inner generator stops first.
+        let mut gen = noise().take_events(2).until_time(100);
+        assert_eq!(gen.by_ref().count(), 2);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::EventCount)
+        );
+    }
+
+    #[test]
+    fn offset() {
+        let times = |gen: &mut dyn Iterator<Item = WireEvent<i32>>| {
+            gen.map(|event| event.time).collect::<Vec<_>>()
+        };
+        assert_eq!(times(&mut noise().offset(-5).take_events(2)), [5, 15]);
+        // Offset before and after the limit.
+        assert_eq!(
+            times(&mut noise().offset(100).until_time(135)),
+            [110, 120, 130]
+        );
+        assert_eq!(
+            times(&mut noise().until_time(35).offset(100)),
+            [110, 120, 130]
+        );
+        let mut gen = noise().until_time(20).offset(100);
+        gen.by_ref().for_each(drop);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::Duration)
+        );
+    }
+
+    #[test]
+    fn scale_rate() {
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let times: Vec<_> = gen
+            .scale_rate(4.0)
+            .offset(10.0)
+            .until_time(11.0)
+            .map(|event| event.time)
+            .collect();
+        assert_eq!(times, [10.25, 10.5, 10.75]);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate factor 0 is not a positive finite number")]
+    fn scale_rate_zero() {
+        let _ = ScriptedGenerator::from_times(Source::Noise, [1.0], WirePattern::from_bits(1))
+            .scale_rate(0.0);
+    }
+
+    #[test]
+    fn adapters_with_afterpulses() {
+        let gen = || {
+            PrimaryGenerator::builder()
+                .source(Source::PrimaryCosmic)
+                .origin(0)
+                .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .afterpulse(|_: &_| {
+                    SecondaryGenerator::builder()
+                        .source(Source::SecondaryCosmic)
+                        .inter_arrival_time(repeat_n(Positive::new(4).unwrap(), 2))
+                        .wire_pattern(repeat(WirePattern::from_bits(2)))
+                })
+                .build()
+        };
+        // Primaries at 10, 20, ...; afterpulses 4 and 8 later.
+        let times = |gen: &mut dyn Iterator<Item = WireEvent<i32>>| {
+            gen.map(|event| event.time).collect::<Vec<_>>()
+        };
+        assert_eq!(times(&mut gen().take_events(4)), [10, 14, 18, 20]);
+        assert_eq!(times(&mut gen().until_time(25)), [10, 14, 18, 20, 24]);
+        // Afterpulses keep their delay with respect to the primary event.
+        assert_eq!(
+            times(&mut gen().offset(1000).until_time(1025)),
+            [1010, 1014, 1018, 1020, 1024]
+        );
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn inhomogeneous_poisson_times() {
+        use uom::si::f64::{Frequency, Time};
+        use uom::si::{frequency::hertz, time::second};
+
+        // Ramps down from 200 Hz to zero at 10 s: 1000 events are expected.
+        let rate = |t: &Time| {
+            let t = t.get::<second>();
+            Frequency::new::<hertz>(if t < 10.0 {
+                200.0 * (1.0 - t / 10.0)
+            } else {
+                0.0
+            })
+        };
+        let times = InhomogeneousPoissonTimes::new(
+            Time::new::<second>(0.0),
+            Some(Time::new::<second>(20.0)),
+            rate,
+            Positive::new(Frequency::new::<hertz>(200.0)).unwrap(),
+            Seed::new(0).rng(),
+        );
+        assert_eq!(times.max_rate().get::<hertz>(), 200.0);
+        let times: Vec<_> = SecondaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(Time::new::<second>(0.0))
+            .end_time(Time::new::<second>(20.0))
+            .inter_arrival_time(times)
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build()
+            .map(|event| event.time.get::<second>())
+            .collect();
+
+        assert!(times.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(times.iter().all(|t| *t < 10.0));
+        let count = times.len() as f64;
+        assert!(
+            (count - 1000.0).abs() < 5.0 * 1000f64.sqrt(),
+            "{count} events, 1000 expected"
+        );
+    }
+
+    #[test]
+    fn rate_function_generator() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let rate = |t: &f64| match *t {
+            t if t < 100.0 => 10.0,
+            t if t < 200.0 => 2.0,
+            _ => 20.0,
+        };
+        let gen = RateFunctionGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .duration(Positive::new(300.0).unwrap())
+            .rate(rate)
+            .max_rate(Positive::new(20.0).unwrap())
+            .rng(StdRng::seed_from_u64(3))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(repeat_n(Positive::new(0.5).unwrap(), 1))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build();
+
+        let mut counts = [0.0; 3];
+        let mut afterpulses = 0;
+        let mut last = 0.0;
+        for event in gen {
+            assert!(event.time >= last);
+            last = event.time;
+            if event.wire_pattern.bits() == 2 {
+                afterpulses += 1;
+            } else {
+                counts[(event.time / 100.0) as usize] += 1.0;
+            }
+        }
+        for (count, expected) in counts.into_iter().zip([1000.0, 200.0, 2000.0]) {
+            let sigma = f64::sqrt(expected);
+            assert!(
+                (count - expected).abs() < 5.0 * sigma,
+                "{count} events, {expected} expected"
+            );
+        }
+        assert_eq!(afterpulses, counts.iter().sum::<f64>() as usize);
+    }
+
+    #[test]
+    fn secondary_generator_inter_arrival_distribution() {
+        // Zero one third of the time.
+        let distribution = || rand::distr::Uniform::new(0, 3).unwrap().map(f64::from);
+        let samples: Vec<_> = distribution()
+            .sample_iter(StdRng::seed_from_u64(0))
+            .take(100)
+            .collect();
+        assert!(samples.contains(&0.0));
+
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_distribution(distribution(), StdRng::seed_from_u64(0), |t| t)
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let mut last = 0.0;
+        let deltas = gen.map(|event| {
+            let delta = event.time - last;
+            last = event.time;
+            delta
+        });
+        let expected = samples.into_iter().filter(|t| *t > 0.0);
+        for (delta, expected) in deltas.zip(expected) {
+            assert_eq!(delta, expected);
+        }
+    }
+
+    #[test]
+    fn sampled_times_gamma_mean() {
+        let distribution = rand_distr::Gamma::new(2.0, 1.5).unwrap();
+        let n = 100_000;
+        let times: Vec<_> = SampledTimes::new(distribution, StdRng::seed_from_u64(0), |t| t)
+            .take(n)
+            .collect();
+        assert_eq!(times.len(), n);
+        let mean = times.iter().map(|t| *t.inner()).sum::<f64>() / n as f64;
+        // Mean 3 and standard deviation sqrt(4.5); 5 sigma of the sample mean.
+        let tolerance = 5.0 * 4.5f64.sqrt() / (n as f64).sqrt();
+        assert!((mean - 3.0).abs() < tolerance, "{mean}");
+    }
+
+    #[test]
+    fn sampled_times_max_rejections() {
+        let zero = || rand::distr::Uniform::new(0.0, 1.0).unwrap().map(|_| 0.0);
+        // Number of samples drawn by an iterator that rejects all of them.
+        let draws = |max_rejections: Option<u32>| {
+            let draws = std::cell::Cell::new(0);
+            let mut times = SampledTimes::new(zero(), StdRng::seed_from_u64(0), |t| {
+                draws.set(draws.get() + 1);
+                t
+            });
+            if let Some(max_rejections) = max_rejections {
+                times = times.with_max_rejections(max_rejections);
+            }
+            assert!(times.next().is_none());
+            draws.get()
+        };
+        assert_eq!(
+            draws(None),
+            SampledTimes::<(), (), ()>::DEFAULT_MAX_REJECTIONS
+        );
+        assert_eq!(draws(Some(5)), 5);
+        assert_eq!(draws(Some(0)), 0);
+
+        // Only every third sample is positive.
+        let mut count = 0;
+        let every_third = move |_: f64| {
+            count += 1;
+            if count % 3 == 0 {
+                1.0
+            } else {
+                -1.0
+            }
+        };
+        let times = SampledTimes::new(zero(), StdRng::seed_from_u64(0), every_third);
+        assert_eq!(times.with_max_rejections(3).take(10).count(), 10);
+        let times = SampledTimes::new(zero(), StdRng::seed_from_u64(0), every_third);
+        assert_eq!(times.with_max_rejections(2).take(10).count(), 0);
+
+        let mut gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .inter_arrival_distribution(zero(), StdRng::seed_from_u64(0), |t| t)
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        assert!(gen.next().is_none());
+        assert_eq!(
+            gen.exhaustion_reason(),
+            Some(ExhaustionReason::InterArrivalTime)
+        );
+    }
+
+    #[test]
+    fn superpose_processes() {
+        // Arrival times of Poisson processes at 1000, 10, and 0.1 Hz.
+        let rates = [1000.0, 10.0, 0.1];
+        let duration = 100.0;
+        let arrival_times = |i: usize| {
+            PoissonTimes::<f64, _, _>::seeded(Positive::new(rates[i]).unwrap(), i as u64)
+                .scan(0.0, |time, dt| {
+                    *time += dt.0;
+                    Some(*time)
+                })
+                .take_while(move |&time| time < duration)
+        };
+        let arrivals: Vec<_> = superpose((0..rates.len()).map(arrival_times)).collect();
+
+        assert!(arrivals.windows(2).all(|w| w[0].1 <= w[1].1));
+        for (i, rate) in rates.into_iter().enumerate() {
+            let times: Vec<_> = arrivals
+                .iter()
+                .filter(|(index, _)| *index == i)
+                .map(|(_, time)| *time)
+                .collect();
+            assert_eq!(times, arrival_times(i).collect::<Vec<_>>());
+            // 5 sigma of the Poisson count.
+            let mean = rate * duration;
+            assert!((times.len() as f64 - mean).abs() < 5.0 * mean.sqrt());
+        }
+    }
+
+    #[test]
+    fn superpose_ties() {
+        let arrivals: Vec<_> = superpose([vec![1, 1, 3], vec![0, 1], vec![1]]).collect();
+        assert_eq!(arrivals, [(1, 0), (0, 1), (0, 1), (1, 1), (2, 1), (0, 3)]);
+        assert_eq!(superpose(Vec::<Vec<i32>>::new()).next(), None);
+    }
+
+    #[test]
+    fn superpose_max_time() {
+        let unlimited = || std::iter::successors(Some(0), |t| Some(t + 4));
+        let mut arrivals = superpose([unlimited(), unlimited()]).with_max_time(6);
+        assert_eq!(arrivals.truncated(), None);
+        assert_eq!(
+            arrivals.by_ref().collect::<Vec<_>>(),
+            [(0, 0), (1, 0), (0, 4), (1, 4)]
+        );
+        assert_eq!(arrivals.truncated(), Some(&6));
+        assert_eq!(arrivals.next(), None);
+
+        // Not truncated if all processes stop earlier.
+        let mut arrivals = superpose([vec![1, 5], vec![2]]).with_max_time(6);
+        assert_eq!(arrivals.by_ref().count(), 3);
+        assert_eq!(arrivals.truncated(), None);
+    }
+
+    #[test]
+    fn superpose_random_ties() {
+        let processes = [vec![1, 1, 2], vec![1], vec![1, 2]];
+        let arrivals = |seed| {
+            superpose(processes.clone())
+                .with_tie_breaking(TieBreaking::Random(Seed::new(seed)))
+                .collect::<Vec<_>>()
+        };
+
+        let orders: Vec<_> = (0..20).map(arrivals).collect();
+        for order in &orders {
+            let times: Vec<_> = order.iter().map(|&(_, t)| t).collect();
+            assert_eq!(times, [1, 1, 1, 1, 2, 2]);
+        }
+        assert!(orders.iter().any(|order| order[0].0 != 0));
+        assert_eq!(arrivals(7), arrivals(7));
+        assert_eq!(
+            superpose(processes)
+                .with_tie_breaking(TieBreaking::InsertionOrder)
+                .collect::<Vec<_>>(),
+            [(0, 1), (0, 1), (1, 1), (2, 1), (0, 2), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn primary_generator_inter_arrival_distribution() {
+        use uom::si::f64::Time;
+        use uom::si::time::{microsecond, second};
+
+        // `NaN` half of the time.
+        let distribution =
+            rand::distr::StandardUniform.map(|u: f64| if u < 0.5 { f64::NAN } else { 1.0 });
+        let gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(Time::new::<second>(0.0))
+            .duration(Positive::new(Time::new::<microsecond>(100.5)).unwrap())
+            .inter_arrival_distribution(
+                distribution,
+                StdRng::seed_from_u64(0),
+                Time::new::<microsecond>,
+            )
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .inter_arrival_time(repeat_n(
+                        Positive::new(Time::new::<second>(1.0)).unwrap(),
+                        0,
+                    ))
+                    .wire_pattern(repeat(WirePattern::from_bits(1)))
+            })
+            .build();
+        assert_eq!(gen.count(), 100);
+    }
+
+    #[test]
+    fn rate_f64() {
+        assert_eq!(2.0.time_for(3.0), 1.5);
+        assert_eq!(2.0.ratio(&8.0), 0.25);
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn rate_uom() {
+        use uom::si::f64::{Frequency, Time};
+        use uom::si::{frequency::kilohertz, time::millisecond};
+
+        let rate = Frequency::new::<kilohertz>(2.0);
+        let time: Time = rate.time_for(3.0);
+        assert!((time.get::<millisecond>() - 1.5).abs() < 1e-12);
+        assert!((rate.ratio(&Frequency::new::<kilohertz>(8.0)) - 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tick_rate() {
+        let rate = TickRate::new(0.25);
+        assert_eq!(Rate::<i64>::time_for(&rate, 1.0), 4);
+        assert_eq!(Rate::<u64>::time_for(&rate, 1.0), 4);
+        // Rounded up to whole ticks, and never zero.
+        assert_eq!(Rate::<i64>::time_for(&rate, 0.3), 2);
+        assert_eq!(Rate::<i64>::time_for(&rate, 1e-12), 1);
+        assert_eq!(Rate::<u64>::time_for(&rate, 1e-12), 1);
+        assert_eq!(Rate::<i64>::ratio(&rate, &TickRate::new(1.0)), 0.25);
+
+        assert_eq!(TickRate::from_hz(1000.0, 0.002), TickRate::new(2.0));
+        assert!(Positive::new(TickRate::new(0.0)).is_none());
+        assert!(Positive::new(TickRate::new(-1.0)).is_none());
+    }
+
+    #[test]
+    fn tick_rate_poisson_times() {
+        // Geometric number of ticks between events.
+        let p = 1.0 - f64::exp(-0.5);
+        let expected = 1.0 / p;
+        let sigma = f64::sqrt(1.0 - p) / p;
+
+        let n = 100_000;
+        let times =
+            PoissonTimes::<i64, _, _>::seeded(Positive::new(TickRate::new(0.5)).unwrap(), 0);
+        let mut sum = 0;
+        let mut ones = 0;
+        for Positive(ticks) in times.take(n) {
+            assert!(ticks >= 1);
+            sum += ticks;
+            ones += i32::from(ticks == 1);
+        }
+        let mean = sum as f64 / n as f64;
+        assert!(
+            (mean - expected).abs() < 5.0 * sigma / (n as f64).sqrt(),
+            "{mean} ticks, {expected} expected"
+        );
+        let ones = f64::from(ones);
+        let n = n as f64;
+        assert!(
+            (ones - n * p).abs() < 5.0 * f64::sqrt(n * p * (1.0 - p)),
+            "{ones} single ticks, {} expected",
+            n * p
+        );
+
+        // Also works with a generator in ticks.
+        let gen = PoissonGenerator::builder()
+            .source(Source::Noise)
+            .origin(0u64)
+            .duration(Positive::new(10_000).unwrap())
+            .rate(Positive::new(TickRate::from_hz(1.0e6, 16.0e-9)).unwrap())
+            .rng(Seed::new(1).rng())
+            .wire_pattern(
+                rand::distr::Uniform::new(0, 16)
+                    .unwrap()
+                    .map(|i| WirePattern::from_bits(1 << i)),
+            )
+            .build();
+        // About 160 events.
+        assert!((100..220).contains(&gen.count()));
+    }
+
+    #[test]
+    fn poisson_times_seeded() {
+        let times = |seed| {
+            PoissonTimes::<f64, _, _>::seeded(Positive::new(0.5).unwrap(), seed)
+                .take(100)
+                .map(|t| t.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(times(1), times(1));
+        assert_ne!(times(1), times(2));
+        assert_eq!(times(1), times(Seed::new(1).0));
+
+        let process = PoissonTimes::<f64, _, _>::seeded(Positive::new(0.5).unwrap(), 3);
+        assert_eq!(*process.rate(), 0.5);
+        // Replicas can be cloned and sent to other threads.
+        let replica = process.clone();
+        let replica = std::thread::spawn(move || replica.take(10).collect::<Vec<_>>())
+            .join()
+            .unwrap();
+        assert_eq!(process.take(10).collect::<Vec<_>>(), replica);
+    }
+
+    #[test]
+    fn poisson_generator() {
+        let mut gen = PoissonGenerator::builder()
+            .source(Source::Noise)
+            .origin(100.0)
+            .duration(Positive::new(10_000.0).unwrap())
+            .rate(Positive::new(2.0).unwrap())
+            .rng(StdRng::seed_from_u64(0))
+            .wire_pattern(
+                rand::distr::Uniform::new(0, 16)
+                    .unwrap()
+                    .map(|i| WirePattern::from_bits(1 << i)),
+            )
+            .build();
+
+        let mut count: f64 = 0.0;
+        let mut boards = [0.0; 16];
+        let mut last = 100.0;
+        for event in gen.by_ref() {
+            assert!(event.time > last && event.time < 10_100.0);
+            last = event.time;
+            count += 1.0;
+            boards[event.wire_pattern.iter_wires().next().unwrap() as usize] += 1.0;
+        }
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::Duration));
+        let expected = 20_000.0;
+        assert!(
+            (count - expected).abs() < 5.0 * f64::sqrt(expected),
+            "{count} events, {expected} expected"
+        );
+        for board in boards {
+            let (p, n) = (1.0 / 16.0, count);
+            let sigma = f64::sqrt(n * p * (1.0 - p));
+            assert!((board - n * p).abs() < 5.0 * sigma);
+        }
+    }
+
+    #[test]
+    fn poisson_generator_end_time() {
+        let gen = |seed| {
+            PoissonGenerator::builder()
+                .source(Source::Noise)
+                .origin(100.0)
+                .end_time(200.0)
+                .rate(Positive::new(1.0).unwrap())
+                .rng(Seed::new(seed).rng())
+                .wire_pattern(rand::distr::StandardUniform.map(WirePattern::from_bits))
+                .build()
+        };
+        for seed in 0..20 {
+            let mut gen = gen(seed);
+            let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+            assert!(times[0] > 100.0);
+            assert!(times.iter().all(|t| *t < 200.0));
+            // 100 events are expected.
+            assert!((50..150).contains(&times.len()));
+            assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::Duration));
+        }
+    }
+
+    #[test]
+    fn poisson_generator_max_events() {
+        let mut gen = PoissonGenerator::builder()
+            .source(Source::Noise)
+            .origin(-50)
+            .duration(Positive::new(1_000_000).unwrap())
+            .max_events(5)
+            .rate(Positive::new(TickRate::new(0.1)).unwrap())
+            .rng(Seed::new(0).rng())
+            .wire_pattern(rand::distr::StandardUniform.map(WirePattern::from_bits))
+            .build();
+        let times: Vec<i64> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times.len(), 5);
+        assert!(times[0] > -50);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::EventCount));
+    }
+
+    #[test]
+    fn poisson_generator_afterpulse() {
+        let gen = PoissonGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(0.0)
+            .duration(Positive::new(1000.0).unwrap())
+            .rate(Positive::new(1.0).unwrap())
+            .rng(StdRng::seed_from_u64(1))
+            .wire_pattern(rand::distr::StandardUniform.map(WirePattern::from_bits))
+            .build()
+            .afterpulse(|event: &WireEvent<_>| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .inter_arrival_time(repeat_n(Positive::new(0.25).unwrap(), 2))
+                    .wire_pattern(repeat(event.wire_pattern))
+            });
+
+        let mut primaries = 0;
+        let mut secondaries = 0;
+        let mut last = 0.0;
+        for event in gen {
+            assert!(event.time >= last);
+            last = event.time;
+            match event.source {
+                Source::PrimaryCosmic => primaries += 1,
+                _ => secondaries += 1,
+            }
+        }
+        assert!((800..1200).contains(&primaries));
+        assert_eq!(secondaries, 2 * primaries);
+    }
+
+    // Single-board patterns at a rate of 1 over a window of 10000.
+    fn single_board_pbars(
+        pass_mlu: Option<(f64, LookupTable)>,
+        afterpulses: Option<AfterpulseLibrary<f64>>,
+    ) -> PbarGenerator<f64, f64, StdRng, impl Distribution<WirePattern>> {
+        let builder = PbarGenerator::builder()
+            .origin(0.0)
+            .window(Positive::new(10000.0).unwrap())
+            .rate(Positive::new(1.0).unwrap())
+            .rng(StdRng::seed_from_u64(0))
+            .wire_pattern(
+                rand::distr::Uniform::new(0, 16)
+                    .unwrap()
+                    .map(|i| WirePattern::from_bits(1 << i)),
+            )
+            .maybe_afterpulses(afterpulses);
+        match pass_mlu {
+            Some((probability, table)) => builder.pass_mlu(probability, table).build(),
+            None => builder.build(),
+        }
+    }
+
+    #[test]
+    fn pbar_generator() {
+        let library = AfterpulseLibrary::new(vec![
+            vec![Positive::new(0.25).unwrap()],
+            vec![Positive::new(0.25).unwrap(), Positive::new(0.5).unwrap()],
+        ]);
+        let gen = single_board_pbars(None, Some(library));
+
+        let mut primaries: f64 = 0.0;
+        let mut secondaries = 0.0;
+        let mut last = None;
+        let mut patterns = std::collections::HashMap::new();
+        for event in gen {
+            assert!(last.is_none_or(|last| event.time >= last));
+            last = Some(event.time);
+            let id = event.id.unwrap();
+            match event.source {
+                Source::PrimaryPbar => {
+                    assert!((0.0..10000.0).contains(&event.time));
+                    primaries += 1.0;
+                    patterns.insert(id, event.wire_pattern);
+                }
+                Source::SecondaryPbar => {
+                    assert_eq!(event.wire_pattern, patterns[&id]);
+                    secondaries += 1.0;
+                }
+                _ => unreachable!(),
+            }
+        }
+        let expected = 10000.0;
+        assert!(
+            (primaries - expected).abs() < 5.0 * f64::sqrt(expected),
+            "{primaries} primary events, {expected} expected"
+        );
+        // One or two afterpulses with equal probability.
+        let (n, p) = (primaries, 0.5);
+        let sigma = f64::sqrt(n * p * (1.0 - p));
+        assert!((secondaries - 1.5 * n).abs() < 5.0 * sigma);
+    }
+
+    #[test]
+    fn pbar_generator_pass_mlu() {
+        let table = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+        for p in [0.0, 0.3, 1.0] {
+            let gen = single_board_pbars(Some((p, table)), None);
+
+            let mut n = 0.0;
+            let mut passed = 0.0;
+            for event in gen {
+                n += 1.0;
+                if table.contains(event.wire_pattern) {
+                    passed += 1.0;
+                }
+            }
+            let sigma = f64::sqrt(n * p * (1.0 - p));
+            assert!(
+                (passed - n * p).abs() <= 5.0 * sigma,
+                "{passed} out of {n} passed, {p} expected"
+            );
+        }
+    }
+
+    // Inter-arrival times in ticks, as in the `data` directory.
+    #[cfg(feature = "serde")]
+    const AFTERPULSES_JSON: &str = "[[1, 1, 1, 7], [], [2, 11], [1]]";
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn afterpulse_library_from_reader() {
+        let library = AfterpulseLibrary::from_reader(AFTERPULSES_JSON.as_bytes(), |ns| ns).unwrap();
+        let trains: Vec<Vec<f64>> = library
+            .trains()
+            .iter()
+            .map(|train| train.iter().map(|t| t.0).collect())
+            .collect();
+        assert_eq!(
+            trains,
+            [
+                vec![16.0, 16.0, 16.0, 112.0],
+                vec![],
+                vec![32.0, 176.0],
+                vec![16.0]
+            ]
+        );
+
+        let library =
+            AfterpulseLibrary::from_reader(AFTERPULSES_JSON.as_bytes(), |ns| ns / 1000.0).unwrap();
+        assert_eq!(library.trains()[3], [Positive(0.016)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn afterpulse_library_errors() {
+        use std::error::Error;
+
+        let error =
+            |json: &str| AfterpulseLibrary::from_reader(json.as_bytes(), |ns| ns).unwrap_err();
+
+        let e = error("[[1, 2], [3, 0]]");
+        assert!(matches!(
+            e,
+            AfterpulseError::InterArrivalTime {
+                train: 1,
+                afterpulse: 1,
+                ..
+            }
+        ));
+        assert_eq!(
+            e.to_string(),
+            "afterpulse 1 of train 1 is 0 ticks after the previous pulse \
+             (inter-arrival times have to be positive)"
+        );
+        assert!(matches!(
+            error("[[-1]]"),
+            AfterpulseError::InterArrivalTime { train: 0, .. }
+        ));
+
+        let e = error("[[1, 2], 3]");
+        assert!(matches!(e, AfterpulseError::Json(_)));
+        assert!(e.to_string().starts_with("invalid afterpulse library: "));
+        assert!(e.source().is_some());
+        assert!(matches!(error("[[1, \"2\"]]"), AfterpulseError::Json(_)));
+        assert!(matches!(error("[[1, 2]"), AfterpulseError::Json(_)));
+    }
+
+    #[test]
+    fn afterpulse_library_choose() {
+        let one = Positive::new(1.0).unwrap();
+        let library = AfterpulseLibrary::new(vec![vec![one], vec![one, one, one]]);
+        let mut rng = Seed::new(0).rng();
+        let n = 10000.0;
+        let mut long = 0.0;
+        for _ in 0..n as usize {
+            match library.choose(&mut rng).count() {
+                1 => {}
+                3 => long += 1.0,
+                _ => unreachable!(),
+            }
+        }
+        let sigma = f64::sqrt(n * 0.5 * 0.5);
+        assert!((long - n * 0.5).abs() < 5.0 * sigma);
+
+        let empty = AfterpulseLibrary::<f64>::new(Vec::new());
+        assert_eq!(empty.choose(&mut rng).count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "MLU pass probability 1.5 is not in [0, 1]")]
+    fn pbar_generator_invalid_probability() {
+        single_board_pbars(Some((1.5, LookupTable::new())), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not produce patterns that pass the MLU")]
+    fn pbar_generator_never_passes() {
+        let table = LookupTable::from([WirePattern::from_bits(3)]);
+        let gen = single_board_pbars(Some((1.0, table)), None);
+        gen.take_events(1).for_each(drop);
+    }
+
+    #[test]
+    #[should_panic(expected = "rate is not in [0, max_rate] at some time")]
+    fn rate_function_generator_max_rate() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let gen = RateFunctionGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .rate(|_: &f64| 2.0)
+            .max_rate(Positive::new(1.0).unwrap())
+            .rng(StdRng::seed_from_u64(0))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .inter_arrival_time(repeat_n(Positive::new(0.5).unwrap(), 0))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build();
+        gen.take_events(1).for_each(drop);
+    }
+
+    #[test]
+    fn pulser_generator() {
+        let origin = 1.0;
+        let period = 0.1;
+        let mut gen = PulserGenerator::builder()
+            .source(Source::Noise)
+            .origin(origin)
+            .period(Positive::new(period).unwrap())
+            .wire_pattern(WirePattern::from_bits(u16::MAX))
+            .build();
+        let pulse = gen.by_ref().nth(999).unwrap();
+        assert_eq!(pulse.time, origin + 1000.0 * period);
+        assert_eq!(pulse.wire_pattern.bits(), u16::MAX);
+        // Accumulating the period drifts.
+        let accumulated = (0..1000).fold(origin, |t, _| t + period);
+        assert_ne!(accumulated, origin + 1000.0 * period);
+        assert_eq!(gen.exhaustion_reason(), None);
+
+        let mut gen = PulserGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .period(Positive::new(1.0).unwrap())
+            .duration(Positive::new(3.0).unwrap())
+            .count(5)
+            .wire_pattern(WirePattern::from_bits(1))
+            .build();
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [1.0, 2.0]);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::Duration));
+
+        let mut gen = PulserGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .period(Positive::new(1.0).unwrap())
+            .count(2)
+            .wire_pattern(WirePattern::from_bits(1))
+            .build();
+        assert_eq!(gen.by_ref().count(), 2);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::EventCount));
+    }
+
+    #[test]
+    fn pulser_generator_uom() {
+        use uom::si::f64::{Frequency, Time};
+        use uom::si::{frequency::hertz, time::second};
+
+        let origin = Time::new::<second>(1.0);
+        let period = Frequency::new::<hertz>(10.0).recip();
+        let mut gen = PulserGenerator::builder()
+            .source(Source::Noise)
+            .origin(origin)
+            .period(Positive::new(period).unwrap())
+            .wire_pattern(WirePattern::from_bits(u16::MAX))
+            .build();
+        let pulse = gen.nth(999).unwrap();
+        assert_eq!(pulse.time, origin + period * 1000.0);
+    }
+
+    #[test]
+    fn cycle_generator() {
+        let calls = std::cell::Cell::new(0);
+        let mut gen = CycleGenerator::builder()
+            .origin(100.0)
+            .period(Positive::new(10.0).unwrap())
+            .cycles(2)
+            .template(|cycle, start| {
+                assert_eq!(cycle, calls.get());
+                calls.set(calls.get() + 1);
+                // Infinite background, and one event in the middle.
+                let background = SecondaryGenerator::builder()
+                    .source(Source::PrimaryCosmic)
+                    .origin(start)
+                    .inter_arrival_time(repeat(Positive::new(4.0).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(1)))
+                    .build();
+                let mixing = ScriptedGenerator::from_times(
+                    Source::PrimaryPbar,
+                    [start + 5.0],
+                    WirePattern::from_bits(2),
+                );
+                vec![Box::new(background) as BoxedGenerator<_>, Box::new(mixing)]
+            })
+            .build();
+
+        let first: Vec<_> = gen.by_ref().take(3).map(|event| event.time).collect();
+        assert_eq!(first, [104.0, 105.0, 108.0]);
+        assert_eq!(calls.get(), 1);
+        let second: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        let shifted: Vec<_> = first.iter().map(|time| time + 10.0).collect();
+        assert_eq!(second, shifted);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::Duration));
+    }
+
+    #[test]
+    fn cycle_generator_outside_cycle() {
+        let gen = |cycles| {
+            CycleGenerator::builder()
+                .origin(0.0)
+                .period(Positive::new(10.0).unwrap())
+                .cycles(cycles)
+                .template(|cycle, start| {
+                    // Nothing in the second cycle.
+                    let times = if cycle == 1 {
+                        Vec::new()
+                    } else {
+                        vec![start - 1.0, start + 1.0, start + 10.0]
+                    };
+                    vec![Box::new(ScriptedGenerator::from_times(
+                        Source::Noise,
+                        times,
+                        WirePattern::from_bits(1),
+                    )) as BoxedGenerator<_>]
+                })
+                .build()
+        };
+        assert!(gen(0).next().is_none());
+        let times: Vec<_> = gen(3).map(|event| event.time).collect();
+        assert_eq!(times, [1.0, 21.0]);
+    }
+
+    #[test]
+    fn burst_generator() {
+        let gen = |bursts, delta_t| {
+            BurstGenerator::builder()
+                .source(Source::PrimaryPbar)
+                .origin(-50)
+                .burst_period(Positive::new(100).unwrap())
+                .burst_duration(Positive::new(30).unwrap())
+                .maybe_bursts(bursts)
+                .inter_arrival_time(repeat(Positive::new(delta_t).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .build()
+        };
+        let times = |gen: BurstGenerator<_, _>| gen.map(|event| event.time).collect::<Vec<_>>();
+
+        assert_eq!(
+            times(gen(Some(3), 7)),
+            [-43, -36, -29, -22, 57, 64, 71, 78, 157, 164, 171, 178]
+        );
+        // The end of a burst is exclusive.
+        assert_eq!(times(gen(Some(2), 10)), [-40, -30, 60, 70]);
+        // Inter-arrival times longer than a burst.
+        assert!(times(gen(Some(5), 30)).is_empty());
+        assert!(times(gen(Some(0), 1)).is_empty());
+        assert_eq!(gen(None, 10).take(100).count(), 100);
+
+        let mut finite = gen(Some(2), 10);
+        finite.by_ref().for_each(drop);
+        assert_eq!(finite.exhaustion_reason(), Some(ExhaustionReason::Duration));
+    }
+
+    #[test]
+    fn burst_generator_gaps() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use rand_distr::Exp;
+
+        let (period, duration): (f64, f64) = (0.1, 0.03);
+        let gen = BurstGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0.0)
+            .burst_period(Positive::new(period).unwrap())
+            .burst_duration(Positive::new(duration).unwrap())
+            .bursts(1000)
+            .inter_arrival_time(
+                Exp::new(1000.0)
+                    .unwrap()
+                    .map(|t| Positive::new(t).unwrap())
+                    .sample_iter(StdRng::seed_from_u64(5)),
+            )
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+
+        let mut count: f64 = 0.0;
+        let mut last = 0.0;
+        for event in gen {
+            assert!(event.time >= last);
+            last = event.time;
+            let burst = (event.time / period).floor();
+            assert!(burst < 1000.0);
+            assert!(event.time - burst * period < duration + 1e-9);
+            count += 1.0;
+        }
+        // 30 events per burst.
+        let expected = 30_000.0;
+        assert!((count - expected).abs() < 5.0 * f64::sqrt(expected));
+    }
+
+    #[test]
+    #[should_panic(expected = "burst duration is longer than the burst period")]
+    fn burst_generator_overlap() {
+        let _ = BurstGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0)
+            .burst_period(Positive::new(10).unwrap())
+            .burst_duration(Positive::new(11).unwrap())
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+    }
+
+    #[test]
+    fn mixture_generator() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let n = 30_000;
+        let gen = MixtureGenerator::builder()
+            .add_species(6.0, Source::Noise, repeat(WirePattern::from_bits(1)))
+            .add_species(
+                3.0,
+                Source::PrimaryCosmic,
+                repeat(WirePattern::from_bits(2)),
+            )
+            .add_species(1.0, Source::PrimaryPbar, repeat(WirePattern::from_bits(4)))
+            .add_species(
+                0.0,
+                Source::SecondaryPbar,
+                repeat(WirePattern::from_bits(8)),
+            )
+            .origin(0)
+            .duration(Positive::new(n + 1).unwrap())
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .rng(StdRng::seed_from_u64(7))
+            .build();
+
+        let mut counts = [0.0; 4];
+        let mut last = 0;
+        for event in gen {
+            assert_eq!(event.time, last + 1);
+            last = event.time;
+            let species = event.wire_pattern.bits().trailing_zeros() as usize;
+            // The source matches the species.
+            assert_eq!(
+                species,
+                match event.source {
+                    Source::Noise => 0,
+                    Source::PrimaryCosmic => 1,
+                    Source::PrimaryPbar => 2,
+                    _ => 3,
+                }
+            );
+            counts[species] += 1.0;
+        }
+        assert_eq!(last, n);
+        for (count, p) in counts.into_iter().zip([0.6, 0.3, 0.1, 0.0]) {
+            let (expected, sigma) = (p * n as f64, f64::sqrt(n as f64 * p * (1.0 - p)));
+            assert!(
+                (count - expected).abs() <= 5.0 * sigma,
+                "{count} events, {expected} expected"
+            );
+        }
+    }
+
+    #[test]
+    fn mixture_generator_exhausted() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut gen = MixtureGenerator::builder()
+            .add_species(1.0, Source::Noise, [WirePattern::from_bits(1)])
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .rng(StdRng::seed_from_u64(0))
+            .build();
+        assert_eq!(gen.by_ref().count(), 1);
+        assert_eq!(gen.exhaustion_reason(), Some(ExhaustionReason::WirePattern));
+    }
+
+    #[test]
+    #[should_panic(expected = "species weights must be non-negative, finite, and not all zero")]
+    fn mixture_generator_no_species() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let _ = MixtureGenerator::builder()
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+            .rng(StdRng::seed_from_u64(0))
+            .build();
+    }
+
+    #[test]
+    fn secondary_generator_end_boundary() {
+        let times = |end: Option<End<i32>>| {
+            let mut gen = SecondaryGenerator::builder()
+                .source(Source::Noise)
+                .origin(5)
+                .maybe_end(end)
+                .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .build();
+            let times: Vec<_> = gen.by_ref().take(10).map(|event| event.time).collect();
+            (times, gen.exhaustion_reason())
+        };
+
+        let duration = |t| Some(End::Duration(Positive::new(t).unwrap()));
+        let duration_reached = Some(ExhaustionReason::Duration);
+        // An event exactly at the end is not produced.
+        assert_eq!(times(duration(30)), (vec![15, 25], duration_reached));
+        assert_eq!(times(duration(31)), (vec![15, 25, 35], duration_reached));
+        assert_eq!(times(Some(End::Time(35))), (vec![15, 25], duration_reached));
+        assert_eq!(
+            times(Some(End::Time(36))),
+            (vec![15, 25, 35], duration_reached)
+        );
+        // An end time before the first event (or the origin).
+        assert_eq!(times(Some(End::Time(15))), (vec![], duration_reached));
+        assert_eq!(times(Some(End::Time(0))), (vec![], duration_reached));
+        assert_eq!(times(None).0.len(), 10);
+    }
+
+    #[test]
+    fn primary_generator_end_boundary() {
+        let times = |end_time| {
+            PrimaryGenerator::builder()
+                .source(Source::PrimaryCosmic)
+                .origin(0)
+                .end_time(end_time)
+                .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .afterpulse(|_: &_| {
+                    SecondaryGenerator::builder()
+                        .source(Source::SecondaryCosmic)
+                        .max_events(1)
+                        .inter_arrival_time(repeat(Positive::new(5).unwrap()))
+                        .wire_pattern(repeat(WirePattern::from_bits(2)))
+                })
+                .build()
+                .map(|event| event.time)
+                .collect::<Vec<_>>()
+        };
+        // Afterpulses of the last primary event can be after the end time.
+        assert_eq!(times(30), [10, 15, 20, 25]);
+        assert_eq!(times(31), [10, 15, 20, 25, 30, 35]);
+
+        let with_duration: Vec<_> = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(100)
+            .duration(Positive::new(30).unwrap())
+            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .inter_arrival_time(repeat_n(Positive::new(1).unwrap(), 0))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build()
+            .map(|event| event.time)
+            .collect();
+        assert_eq!(with_duration, [110, 120]);
+    }
+
+    #[test]
+    fn secondary_generator_max_events() {
+        let gen = |max_events, duration| {
+            SecondaryGenerator::builder()
+                .source(Source::Noise)
+                .origin(0)
+                .maybe_duration(duration)
+                .max_events(max_events)
+                .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .build()
+        };
+
+        let mut limited = gen(3, None);
+        let times: Vec<_> = limited.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [10, 20, 30]);
+        assert_eq!(
+            limited.exhaustion_reason(),
+            Some(ExhaustionReason::EventCount)
+        );
+
+        let mut empty = gen(0, None);
+        assert!(empty.next().is_none());
+        assert_eq!(
+            empty.exhaustion_reason(),
+            Some(ExhaustionReason::EventCount)
+        );
+
+        let mut short = gen(3, Some(Positive::new(25).unwrap()));
+        assert_eq!(short.by_ref().count(), 2);
+        assert_eq!(short.exhaustion_reason(), Some(ExhaustionReason::Duration));
+    }
+
+    #[test]
+    fn primary_generator_afterpulse_multiplicity() {
+        let mut multiplicities = [0, 2, 1, 0, 3].into_iter();
+        let gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(0)
+            .inter_arrival_time(repeat_n(Positive::new(100).unwrap(), 5))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                let n = multiplicities.next().unwrap();
+                (n > 0).then(|| {
+                    SecondaryGenerator::builder()
+                        .source(Source::SecondaryCosmic)
+                        .max_events(n)
+                        .inter_arrival_time(repeat(Positive::new(1).unwrap()))
+                        .wire_pattern(repeat(WirePattern::from_bits(2)))
+                })
+            })
+            .build();
+
+        let times: Vec<_> = gen.map(|event| event.time).collect();
+        assert_eq!(
+            times,
+            [100, 200, 201, 202, 300, 301, 400, 500, 501, 502, 503]
+        );
+    }
+
+    #[test]
+    fn primary_generator_event_ids() {
+        let gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(0)
+            .inter_arrival_time(repeat_n(Positive::new(10).unwrap(), 3))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|event: &WireEvent<_>| {
+                // The id is already set when afterpulses are spawned.
+                let n = event.id.unwrap().get() as usize + 1;
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryCosmic)
+                    .max_events(n)
+                    .inter_arrival_time(repeat(Positive::new(7).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(2)))
+            })
+            .build();
 
-pub(super) struct Generator<T> {
-    inner: Vec<Peekable<InnerGen<T>>>,
-}
+        let events: Vec<_> = gen
+            .map(|event| (event.time, event.id.unwrap().get()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (10, 0),
+                (17, 0),
+                (20, 1),
+                (27, 1),
+                (30, 2),
+                (34, 1),
+                (37, 2),
+                (44, 2),
+                (51, 2)
+            ]
+        );
+        assert!(noise().take(3).all(|event| event.id.is_none()));
+    }
 
-// Deriving `Default` would only work for `T: Default`.
-impl<T> Default for Generator<T> {
-    fn default() -> Self {
-        Self { inner: Vec::new() }
+    #[test]
+    fn primary_generator_multiple_afterpulses() {
+        let gen = PrimaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(0)
+            .inter_arrival_time(repeat_n(Positive::new(10).unwrap(), 2))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .afterpulse(|_: &_| {
+                [(3, 2), (4, 4)].map(|(spacing, bits)| {
+                    SecondaryGenerator::builder()
+                        .source(Source::SecondaryCosmic)
+                        .max_events(3)
+                        .inter_arrival_time(repeat(Positive::new(spacing).unwrap()))
+                        .wire_pattern(repeat(WirePattern::from_bits(bits)))
+                })
+            })
+            .build();
+
+        let events: Vec<_> = gen
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        // Afterpulses of the first primary overlap with the second primary and
+        // its own afterpulses.
+        assert_eq!(
+            events,
+            [
+                (10, 1),
+                (13, 2),
+                (14, 4),
+                (16, 2),
+                (18, 4),
+                (19, 2),
+                (20, 1),
+                (22, 4),
+                (23, 2),
+                (24, 4),
+                (26, 2),
+                (28, 4),
+                (29, 2),
+                (32, 4),
+            ]
+        );
     }
-}
 
-impl<T> Generator<T> {
-    pub(super) fn add_generator<G>(&mut self, gen: G)
-    where
-        G: EventGenerator<Time = T> + 'static,
-    {
-        let mut peekable = (Box::new(gen) as InnerGen<T>).peekable();
-        // Only keep around useful generators.
-        if peekable.peek().is_some() {
-            self.inner.push(peekable);
+    #[test]
+    fn source_round_trip() {
+        for (name, source) in BUILT_IN_SOURCES {
+            assert_eq!(source.to_string(), name);
+            assert_eq!(name.parse::<Source>().unwrap(), source);
         }
-    }
-}
 
-impl<T: PartialOrd> Iterator for Generator<T> {
-    type Item = WireEvent<T>;
+        let a = Source::custom("round-trip_a");
+        let b = Source::custom("round-trip_b");
+        assert_ne!(a, b);
+        assert_eq!(Source::custom("round-trip_a"), a);
+        for source in [a, b] {
+            assert_eq!(source.to_string().parse::<Source>().unwrap(), source);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let (index, _) = self
-            .inner
-            .iter_mut()
-            // Safe to unwrap because we only keep useful generators.
-            .map(|g| g.peek().unwrap())
-            .enumerate()
-            .min_by(|(_, a), (_, b)| a.time.partial_cmp(&b.time).unwrap())?;
+        let unlabeled = Source::Custom(u16::MAX);
+        assert_eq!(unlabeled.to_string(), "Custom(65535)");
+        assert_eq!("Custom(65535)".parse::<Source>().unwrap(), unlabeled);
 
-        let next_event = self.inner[index].next();
-        if self.inner[index].peek().is_none() {
-            let _ = self.inner.swap_remove(index);
+        for invalid in ["", "noise", "Custom(65536)", "Custom()", "round-trip_c"] {
+            let error = invalid.parse::<Source>().unwrap_err();
+            assert_eq!(error.to_string(), format!("unknown source `{invalid}`"));
         }
-
-        next_event
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::iter::repeat;
 
     #[test]
-    fn positive_new() {
-        assert_eq!(Positive::new(0.0), None);
-        assert_eq!(Positive::new(-1.0), None);
-        assert_eq!(Positive::new(1.0), Some(Positive(1.0)));
+    #[should_panic(expected = "invalid custom source label `Noise`")]
+    fn source_custom_built_in_label() {
+        Source::custom("Noise");
     }
 
     #[test]
-    fn wire_pattern() {
-        let a = WirePattern::from_bits(0b1000000000000000);
-        let b = "1000000000000000".parse::<WirePattern>().unwrap();
-
-        assert_eq!(a, b);
-        assert_eq!(a, WirePattern(32768));
+    #[should_panic(expected = "invalid custom source label `a,b`")]
+    fn source_custom_invalid_label() {
+        Source::custom("a,b");
     }
 
     #[test]
-    fn wire_pattern_bitor() {
-        let a = WirePattern::from_bits(0b1000000000000000);
-        let b = WirePattern::from_bits(0b0100000000000010);
-        let c = WirePattern::from_bits(0b0000000000000010);
+    fn scripted_generator() {
+        let event = |time, bits| WireEvent {
+            source: Source::Noise,
+            wire_pattern: WirePattern::from_bits(bits),
+            time,
+            id: None,
+            weight: 1.0,
+        };
+        let mut gen = ScriptedGenerator::new(vec![event(3, 1), event(1, 2), event(3, 3)]);
+        assert_eq!(gen.size_hint(), (3, Some(3)));
+        let events: Vec<_> = gen
+            .by_ref()
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        // Stable with respect to events with the same time.
+        assert_eq!(events, [(1, 2), (3, 1), (3, 3)]);
+        assert!(gen.next().is_none());
+        assert_eq!(sealed::OrderedIterator::exhaustion_reason(&gen), None);
 
-        let d = a | b;
-        assert_eq!(d, WirePattern(0b1100000000000010));
+        let pattern = WirePattern::from_bits(5);
+        let mut gen = ScriptedGenerator::from_times(Source::PrimaryPbar, [2.0, 0.5], pattern);
+        let first = gen.next().unwrap();
+        assert!(matches!(first.source, Source::PrimaryPbar));
+        assert_eq!((first.wire_pattern, first.time), (pattern, 0.5));
+        assert_eq!(gen.next().unwrap().time, 2.0);
+        assert!(gen.next().is_none());
 
-        let e = b | c;
-        assert_eq!(e, WirePattern(0b0100000000000010));
+        assert!(ScriptedGenerator::<f64>::new(Vec::new()).next().is_none());
     }
 
     #[test]
-    fn secondary_generator_source() {
-        let gen = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(0.0)
-            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-            .wire_pattern(vec![WirePattern::from_bits(0)])
-            .build();
+    #[should_panic(expected = "event times must be comparable")]
+    fn scripted_generator_nan() {
+        ScriptedGenerator::from_times(Source::Noise, [1.0, f64::NAN], WirePattern::from_bits(1));
+    }
 
-        for event in gen {
-            assert!(matches!(event.source, Source::Noise));
+    // The linear scan over all generators used before the `BinaryHeap` merge.
+    fn linear_merge<T: PartialOrd>(streams: Vec<Vec<WireEvent<T>>>) -> Vec<(usize, WireEvent<T>)> {
+        let mut inner: Vec<_> = streams
+            .into_iter()
+            .enumerate()
+            .filter_map(|(id, stream)| {
+                let mut stream = stream.into_iter();
+                stream.next().map(|event| (id, event, stream))
+            })
+            .collect();
+
+        let mut merged = Vec::new();
+        while let Some((index, _)) = inner
+            .iter()
+            .map(|(_, event, _)| event)
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.time.partial_cmp(&b.time).unwrap())
+        {
+            let (id, next_event, stream) = &mut inner[index];
+            match stream.next() {
+                Some(event) => merged.push((*id, std::mem::replace(next_event, event))),
+                None => {
+                    let (id, next_event, _) = inner.swap_remove(index);
+                    merged.push((id, next_event));
+                }
+            }
         }
+
+        merged
     }
 
-    #[test]
-    fn secondary_generator_origin() {
-        let mut gen = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(-10.0)
-            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-            .wire_pattern(vec![WirePattern::from_bits(0)])
-            .build();
+    fn heap_merge<T: PartialOrd>(streams: Vec<Vec<WireEvent<T>>>) -> Vec<(usize, WireEvent<T>)> {
+        heap_merge_with(streams, TieBreaking::default())
+    }
 
-        assert_eq!(gen.next().unwrap().time, -9.0);
+    fn heap_merge_with<T: PartialOrd>(
+        streams: Vec<Vec<WireEvent<T>>>,
+        tie_breaking: TieBreaking,
+    ) -> Vec<(usize, WireEvent<T>)> {
+        let mut gen = Generator::default();
+        gen.set_tie_breaking(tie_breaking);
+        for stream in streams {
+            gen.add_generator(ScriptedGenerator::new(stream));
+        }
+
+        let mut merged = Vec::new();
+        while let Some(event) = gen.next() {
+            merged.push((gen.last_generator().unwrap(), event));
+        }
+        merged
     }
 
-    #[test]
-    fn secondary_generator_duration() {
-        let events = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(0.0)
-            .duration(Positive::new(10.0).unwrap())
-            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
-            .wire_pattern(repeat(WirePattern::from_bits(0)))
-            .build()
-            .collect::<Vec<_>>();
+    fn summary<T: Copy>(merged: &[(usize, WireEvent<T>)]) -> Vec<(usize, T, u16)> {
+        merged
+            .iter()
+            .map(|(id, event)| (*id, event.time, event.wire_pattern.bits()))
+            .collect()
+    }
 
-        assert_eq!(events.last().unwrap().time, 9.0);
+    proptest::proptest! {
+        #[test]
+        fn generator_merge_order(
+            deltas in proptest::collection::vec(
+                proptest::collection::vec((0i32..4, proptest::num::u16::ANY), 0..20),
+                0..10,
+            )
+        ) {
+            let streams: Vec<Vec<_>> = deltas
+                .into_iter()
+                .map(|stream| {
+                    stream
+                        .into_iter()
+                        .scan(0, |time, (dt, bits)| {
+                            *time += dt;
+                            Some(WireEvent {
+                                source: Source::Noise,
+                                wire_pattern: WirePattern::from_bits(bits),
+                                time: *time,
+                                id: None,
+                                weight: 1.0,
+                            })
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let new = summary(&heap_merge(streams.clone()));
+            let mut old = summary(&linear_merge(streams));
+            // Same order of times; only ties between generators are now broken
+            // by order of addition.
+            proptest::prop_assert_eq!(
+                new.iter().map(|(_, time, _)| *time).collect::<Vec<_>>(),
+                old.iter().map(|(_, time, _)| *time).collect::<Vec<_>>()
+            );
+            old.sort_by_key(|(id, time, _)| (*time, *id));
+            proptest::prop_assert_eq!(new, old);
+        }
     }
 
     #[test]
-    fn secondary_generator_inter_arrival_time() {
-        let mut gen = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(0.0)
-            .inter_arrival_time(vec![
-                Positive::new(1.0).unwrap(),
-                Positive::new(2.0).unwrap(),
-            ])
-            .wire_pattern(repeat(WirePattern::from_bits(0)))
-            .build();
+    fn seed_derive() {
+        let seed = Seed::new(0);
+        // Part of the reproducibility guarantee.
+        assert_eq!(seed.derive(0).get(), splitmix64(splitmix64(0)));
+        assert_eq!(splitmix64(0), 0xe220_a839_7b1d_cdaf);
 
-        assert_eq!(gen.next().unwrap().time, 1.0);
-        assert_eq!(gen.next().unwrap().time, 3.0);
-        assert!(gen.next().is_none());
+        let children: std::collections::HashSet<_> = (0..1000).map(|i| seed.derive(i)).collect();
+        assert_eq!(children.len(), 1000);
+        assert_ne!(seed.derive(1).derive(0), seed.derive(0).derive(1));
+        assert_eq!(Seed::from(5), Seed::new(5));
     }
 
     #[test]
-    fn secondary_generator_wire_pattern() {
-        let mut gen = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(0.0)
-            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
-            .wire_pattern(vec![
-                WirePattern::from_bits(0),
-                WirePattern::from_bits(u16::MAX),
-            ])
-            .build();
-
-        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(0));
-        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(u16::MAX));
-        assert!(gen.next().is_none());
+    fn generator_merge_ties() {
+        let streams = [[1, 1, 2], [0, 1, 2], [1, 3, 3]].map(|times| {
+            ScriptedGenerator::from_times(Source::Noise, times, WirePattern::from_bits(0))
+                .collect::<Vec<_>>()
+        });
+        let merged = heap_merge(streams.to_vec());
+        assert_eq!(
+            merged
+                .iter()
+                .map(|(id, event)| (*id, event.time))
+                .collect::<Vec<_>>(),
+            [
+                (1, 0),
+                (0, 1),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (0, 2),
+                (1, 2),
+                (2, 3),
+                (2, 3)
+            ]
+        );
     }
 
     #[test]
-    fn primary_generator_source() {
-        let mut gen = PrimaryGenerator::builder()
-            .source(Source::PrimaryPbar)
-            .origin(0.0)
-            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-            .wire_pattern(vec![WirePattern::from_bits(0)])
-            .afterpulse(|_: &_| {
-                SecondaryGenerator::builder()
-                    .source(Source::SecondaryPbar)
-                    .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-                    .wire_pattern(vec![WirePattern::from_bits(0)])
+    fn generator_merge_random_ties() {
+        // 3 generators with 20 events each at times 0 to 3. The pattern is the
+        // order of emission within the generator.
+        let streams: Vec<Vec<_>> = (0..3)
+            .map(|_| {
+                (0..20)
+                    .map(|i| WireEvent {
+                        source: Source::Noise,
+                        wire_pattern: WirePattern::from_bits(i),
+                        time: i / 5,
+                        id: None,
+                        weight: 1.0,
+                    })
+                    .collect()
             })
-            .build();
+            .collect();
+        let random = |seed| summary(&heap_merge_with(streams.clone(), TieBreaking::Random(seed)));
 
-        assert!(matches!(gen.next().unwrap().source, Source::PrimaryPbar));
-        assert!(matches!(gen.next().unwrap().source, Source::SecondaryPbar));
+        let merged = random(Seed::new(0));
+        assert_eq!(merged, random(Seed::new(0)));
+        assert_ne!(merged, random(Seed::new(1)));
+        let insertion_order = summary(&heap_merge(streams.clone()));
+        assert_ne!(merged, insertion_order);
+        // Only ties between generators are shuffled.
+        let mut sorted = merged.clone();
+        sorted.sort_by_key(|(id, time, _)| (*time, *id));
+        assert_eq!(sorted, insertion_order);
     }
 
     #[test]
-    fn primary_generator_origin() {
-        let mut gen = PrimaryGenerator::builder()
-            .source(Source::PrimaryPbar)
-            .origin(0.0)
-            .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-            .wire_pattern(vec![WirePattern::from_bits(0)])
-            .afterpulse(|_: &_| {
-                SecondaryGenerator::builder()
-                    .source(Source::SecondaryPbar)
-                    .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-                    .wire_pattern(vec![WirePattern::from_bits(0)])
-            })
-            .build();
+    fn generator_max_time() {
+        let mut gen = Generator::default();
+        gen.set_max_time(25);
+        gen.add_generator(noise());
+        gen.add_generator(ScriptedGenerator::from_times(
+            Source::PrimaryCosmic,
+            [5],
+            WirePattern::from_bits(2),
+        ));
+        assert_eq!(gen.truncated(), None);
 
-        assert_eq!(gen.next().unwrap().time, 1.0);
+        let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+        assert_eq!(times, [5, 10, 20]);
+        assert_eq!(gen.truncated(), Some(&25));
+        assert!(gen.next().is_none());
+
+        // Not truncated if all generators stop earlier.
+        let mut gen = Generator::default();
+        gen.set_max_time(25);
+        gen.add_generator(noise().take_events(2));
+        assert_eq!(gen.by_ref().count(), 2);
+        assert_eq!(gen.truncated(), None);
     }
 
+    // Run with `cargo test --release -- --ignored --nocapture`.
     #[test]
-    fn primary_generator_duration() {
-        let events = PrimaryGenerator::builder()
-            .source(Source::PrimaryPbar)
-            .origin(0.0)
-            .duration(Positive::new(10.0).unwrap())
-            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
-            .wire_pattern(repeat(WirePattern::from_bits(0)))
-            .afterpulse(|_: &_| {
+    #[ignore]
+    fn generator_merge_timing() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+        use rand_distr::Exp;
+        use std::time::Instant;
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let streams: Vec<Vec<_>> = (0..100)
+            .map(|_| {
                 SecondaryGenerator::builder()
-                    .source(Source::SecondaryPbar)
-                    .inter_arrival_time(vec![Positive::new(1.0).unwrap()])
-                    .wire_pattern(vec![WirePattern::from_bits(0)])
+                    .source(Source::Noise)
+                    .origin(0.0)
+                    .duration(Positive::new(1e4).unwrap())
+                    .inter_arrival_time(
+                        Exp::new(0.1)
+                            .unwrap()
+                            .sample_iter(&mut rng)
+                            .map(|t| Positive::new(t).unwrap())
+                            .take(2000)
+                            .collect::<Vec<_>>(),
+                    )
+                    .wire_pattern(repeat(WirePattern::from_bits(1)))
+                    .build()
+                    .collect()
             })
-            .build()
-            .collect::<Vec<_>>();
+            .collect();
 
-        assert_eq!(
-            events
-                .iter()
-                .filter(|e| matches!(e.source, Source::PrimaryPbar))
-                .last()
-                .unwrap()
-                .time,
-            9.0
+        let start = Instant::now();
+        let old = linear_merge(streams.clone());
+        let linear = start.elapsed();
+        let start = Instant::now();
+        let new = heap_merge(streams);
+        let heap = start.elapsed();
+
+        println!(
+            "{} events: linear scan {linear:?}, binary heap {heap:?}",
+            new.len()
         );
-        assert_eq!(events.last().unwrap().time, 10.0);
+        assert_eq!(summary(&new), summary(&old));
+        assert!(heap < linear);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn primary_generator_inter_arrival_time() {
-        let mut gen = PrimaryGenerator::builder()
-            .source(Source::PrimaryPbar)
-            .origin(0.0)
-            .inter_arrival_time(vec![
-                Positive::new(1.0).unwrap(),
-                Positive::new(2.0).unwrap(),
-            ])
-            .wire_pattern(repeat(WirePattern::from_bits(0)))
-            .afterpulse(|_: &_| {
-                SecondaryGenerator::builder()
-                    .source(Source::SecondaryPbar)
-                    .inter_arrival_time(vec![Positive::new(0.1).unwrap()])
-                    .wire_pattern(vec![WirePattern::from_bits(0)])
-            })
-            .build();
+    fn serde_wire_event() {
+        let pattern = WirePattern::from_bits(0x8001);
+        assert_eq!(serde_json::to_string(&pattern).unwrap(), r#""0x8001""#);
+        for json in [r#""0x8001""#, r#""0X8001""#, "32769"] {
+            assert_eq!(serde_json::from_str::<WirePattern>(json).unwrap(), pattern);
+        }
+        for json in ["65536", "-1", r#""8001""#, r#""0x10000""#] {
+            assert!(serde_json::from_str::<WirePattern>(json).is_err());
+        }
 
-        assert_eq!(gen.next().unwrap().time, 1.0);
-        assert_eq!(gen.next().unwrap().time, 1.1);
-        assert_eq!(gen.next().unwrap().time, 3.0);
-        assert_eq!(gen.next().unwrap().time, 3.1);
-        assert!(gen.next().is_none());
-    }
+        let event = WireEvent {
+            source: Source::SecondaryPbar,
+            wire_pattern: pattern,
+            time: 1.5,
+            id: None,
+            weight: 1.0,
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            r#"{"source":"SecondaryPbar","wire_pattern":"0x8001","time":1.5}"#
+        );
+        let event: WireEvent<f64> = serde_json::from_str(&json).unwrap();
+        assert!(matches!(event.source, Source::SecondaryPbar));
+        assert_eq!((event.wire_pattern, event.time), (pattern, 1.5));
+        assert_eq!(event.id, None);
 
-    #[test]
-    fn primary_generator_wire_pattern() {
-        let mut gen = PrimaryGenerator::builder()
-            .source(Source::PrimaryPbar)
-            .origin(0.0)
-            .inter_arrival_time(repeat(Positive::new(2.0).unwrap()))
-            .wire_pattern(vec![WirePattern::from_bits(0), WirePattern::from_bits(0)])
-            .afterpulse(|_: &_| {
-                SecondaryGenerator::builder()
-                    .source(Source::SecondaryPbar)
-                    .inter_arrival_time(repeat(Positive::new(1.0).unwrap()))
-                    .wire_pattern(vec![WirePattern::from_bits(u16::MAX)])
-            })
-            .build();
+        assert_eq!(event.weight, 1.0);
 
-        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(0));
-        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(u16::MAX));
-        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(0));
-        assert_eq!(gen.next().unwrap().wire_pattern, WirePattern(u16::MAX));
-        assert!(gen.next().is_none());
+        let event = WireEvent {
+            id: Some(EventId::new(7)),
+            weight: 2.5,
+            ..event
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.ends_with(r#""id":7,"weight":2.5}"#));
+        let event: WireEvent<f64> = serde_json::from_str(&json).unwrap();
+        assert_eq!(event.id, Some(EventId::new(7)));
+        assert_eq!(event.weight, 2.5);
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn primary_generator_afterpulse() {
-        let mut count = 0;
-
-        let mut gen = PrimaryGenerator::builder()
-            .source(Source::PrimaryPbar)
-            .origin(0.0)
-            .inter_arrival_time(repeat(Positive::new(1.0).unwrap()).take(3))
-            .wire_pattern(repeat(WirePattern::from_bits(0)))
-            .afterpulse(|_: &_| {
-                let n = count;
-                let delta_t = 1.0 / (n + 1) as f64;
-                count += 1;
+    fn serde_custom_source() {
+        let laser = Source::custom("serde-laser");
+        assert_eq!(serde_json::to_string(&laser).unwrap(), r#""serde-laser""#);
+        assert_eq!(
+            serde_json::from_str::<Source>(r#""serde-laser""#).unwrap(),
+            laser
+        );
+        // A label from e.g. another program is registered.
+        let source = serde_json::from_str::<Source>(r#""serde-unregistered""#).unwrap();
+        assert_eq!(source, Source::custom("serde-unregistered"));
 
-                SecondaryGenerator::builder()
-                    .source(Source::SecondaryPbar)
-                    .inter_arrival_time(repeat(Positive::new(delta_t).unwrap()).take(n))
-                    .wire_pattern(repeat(WirePattern::from_bits(0)))
-            })
-            .build();
+        let unlabeled = Source::Custom(u16::MAX);
+        let json = serde_json::to_string(&unlabeled).unwrap();
+        assert_eq!(json, r#""Custom(65535)""#);
+        assert_eq!(serde_json::from_str::<Source>(&json).unwrap(), unlabeled);
 
-        assert!((gen.next().unwrap().time - 1.0).abs() < 1e-6);
-        assert!((gen.next().unwrap().time - 2.0).abs() < 1e-6);
-        assert!((gen.next().unwrap().time - 2.5).abs() < 1e-6);
-        assert!((gen.next().unwrap().time - 3.0).abs() < 1e-6);
-        assert!((gen.next().unwrap().time - 10.0 / 3.0).abs() < 1e-6);
-        assert!((gen.next().unwrap().time - 11.0 / 3.0).abs() < 1e-6);
-        assert!(gen.next().is_none());
+        for json in [r#""""#, r#""a b""#, r#"{"Custom":0}"#, "0"] {
+            assert!(serde_json::from_str::<Source>(json).is_err());
+        }
     }
 }
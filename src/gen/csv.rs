@@ -0,0 +1,298 @@
+use crate::gen::{sealed, ExhaustionReason, Source, WireEvent, WirePattern};
+use std::fmt;
+use std::io::{BufRead, BufReader, Lines, Read};
+
+/// Optional first row of a CSV file.
+pub const HEADER: &str = "time_ns,pattern_hex,source";
+
+/// The kind of error found in a CSV row.
+#[derive(Debug)]
+pub enum CsvErrorKind {
+    /// The row could not be read.
+    Io(std::io::Error),
+    /// The row does not have exactly 3 fields.
+    FieldCount(usize),
+    /// The time is not a finite number of nanoseconds.
+    Time(String),
+    /// The wire pattern is not a (`0x` prefixed or bare) hex number.
+    WirePattern(String),
+    /// The source is not a valid [`Source`] (see its `FromStr` implementation).
+    Source(String),
+    /// The time is earlier than the time of the previous row.
+    Unordered,
+}
+
+/// The error type returned when a [`CsvEventGenerator`] finds an invalid row.
+#[derive(Debug)]
+pub struct CsvError {
+    row: usize,
+    kind: CsvErrorKind,
+}
+
+impl CsvError {
+    /// Returns the (1-based) row number of the invalid row. The header, if
+    /// present, is row 1.
+    pub fn row(&self) -> usize {
+        self.row
+    }
+    /// Returns the kind of error.
+    pub fn kind(&self) -> &CsvErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: ", self.row)?;
+        match &self.kind {
+            CsvErrorKind::Io(_) => write!(f, "failed to read the row"),
+            CsvErrorKind::FieldCount(n) => write!(f, "expected 3 fields, found {n}"),
+            CsvErrorKind::Time(s) => write!(f, "invalid time `{s}`"),
+            CsvErrorKind::WirePattern(s) => write!(f, "invalid wire pattern `{s}`"),
+            CsvErrorKind::Source(s) => write!(f, "invalid source `{s}`"),
+            CsvErrorKind::Unordered => write!(f, "time is earlier than the previous row"),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            CsvErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Returns the time in nanoseconds, the wire pattern, and the source of a row.
+fn parse_row(line: &str) -> Result<(f64, WirePattern, Source), CsvErrorKind> {
+    let fields: Vec<_> = line.split(',').map(str::trim).collect();
+    let [time, pattern, source] = fields[..] else {
+        return Err(CsvErrorKind::FieldCount(fields.len()));
+    };
+
+    let time = time
+        .parse::<f64>()
+        .ok()
+        .filter(|t| t.is_finite())
+        .ok_or_else(|| CsvErrorKind::Time(time.to_string()))?;
+    let digits = pattern
+        .strip_prefix("0x")
+        .or_else(|| pattern.strip_prefix("0X"))
+        .unwrap_or(pattern);
+    let pattern = u16::from_str_radix(digits, 16)
+        .ok()
+        // `from_str_radix` accepts a leading `+`.
+        .filter(|_| !digits.starts_with('+'))
+        .map(WirePattern::from_bits)
+        .ok_or_else(|| CsvErrorKind::WirePattern(pattern.to_string()))?;
+    let source = source
+        .parse()
+        .map_err(|_| CsvErrorKind::Source(source.to_string()))?;
+
+    Ok((time, pattern, source))
+}
+
+/// A generator that replays [`WireEvent`]s recorded in a CSV file.
+///
+/// Each row has the form `time_ns,pattern_hex,source` e.g.
+/// `1250.5,0x0003,Noise`. The first row can optionally be the [`HEADER`], and
+/// blank lines are ignored. Rows are read lazily, and times have to be in
+/// non-decreasing order. Sources are written as their
+/// [`Display`](std::fmt::Display) form; labels of custom sources have to be
+/// registered with [`Source::custom`] before they are read.
+///
+/// The generator stops at the first invalid row, with an
+/// [`ExhaustionReason::InvalidInput`]. The details are available from
+/// [`CsvEventGenerator::error`]; add a `&mut` reference to the generator to a
+/// [`World`](crate::World) to inspect it after the run.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::csv::CsvEventGenerator;
+///
+/// let csv = "time_ns,pattern_hex,source\n10,0x0001,Noise\n25.5,0x0003,PrimaryCosmic\n";
+/// let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+/// let times: Vec<_> = gen.by_ref().map(|event| event.time).collect();
+/// assert_eq!(times, [10.0, 25.5]);
+/// assert!(gen.error().is_none());
+/// ```
+pub struct CsvEventGenerator<R, F> {
+    lines: Lines<BufReader<R>>,
+    to_time: F,
+    // Number of rows read so far.
+    row: usize,
+    last_ns: Option<f64>,
+    error: Option<CsvError>,
+}
+
+impl<R: Read, F> CsvEventGenerator<R, F> {
+    /// Create a new generator that reads rows from `reader`. Times in
+    /// nanoseconds are converted with `to_time`.
+    pub fn new(reader: R, to_time: F) -> Self {
+        Self {
+            lines: BufReader::new(reader).lines(),
+            to_time,
+            row: 0,
+            last_ns: None,
+            error: None,
+        }
+    }
+}
+
+#[cfg(feature = "uom")]
+impl<R: Read> CsvEventGenerator<R, fn(f64) -> uom::si::f64::Time> {
+    /// Create a new generator that reads rows from `reader` with times as
+    /// [`uom`] quantities.
+    pub fn with_uom(reader: R) -> Self {
+        Self::new(reader, uom::si::f64::Time::new::<uom::si::time::nanosecond>)
+    }
+}
+
+impl<R, F> CsvEventGenerator<R, F> {
+    /// Returns the invalid row that stopped the generator, if any.
+    pub fn error(&self) -> Option<&CsvError> {
+        self.error.as_ref()
+    }
+}
+
+impl<R: Read, F: FnMut(f64) -> T, T> Iterator for CsvEventGenerator<R, F> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        loop {
+            let line = self.lines.next()?;
+            self.row += 1;
+            let result = line.map_err(CsvErrorKind::Io).and_then(|line| {
+                if line.trim().is_empty() || (self.row == 1 && line.trim() == HEADER) {
+                    Ok(None)
+                } else {
+                    parse_row(&line).map(Some)
+                }
+            });
+            let (time, wire_pattern, source) = match result {
+                Ok(Some(row)) => row,
+                Ok(None) => continue,
+                Err(kind) => {
+                    self.error = Some(CsvError {
+                        row: self.row,
+                        kind,
+                    });
+                    return None;
+                }
+            };
+            if self.last_ns.is_some_and(|last| time < last) {
+                self.error = Some(CsvError {
+                    row: self.row,
+                    kind: CsvErrorKind::Unordered,
+                });
+                return None;
+            }
+            self.last_ns = Some(time);
+
+            return Some(WireEvent {
+                source,
+                wire_pattern,
+                time: (self.to_time)(time),
+                id: None,
+                weight: 1.0,
+            });
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(f64) -> T, T> sealed::OrderedIterator for CsvEventGenerator<R, F> {
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.error.as_ref().map(|_| ExhaustionReason::InvalidInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(csv: &str) -> CsvError {
+        let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+        gen.by_ref().for_each(drop);
+        gen.error.unwrap()
+    }
+
+    #[test]
+    fn csv_rows() {
+        let csv = "\n1,3,Noise\n 1 , 0X8000 , SecondaryPbar \n\n2e3,0xffff,PrimaryPbar";
+        let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+        let events: Vec<_> = gen
+            .by_ref()
+            .map(|event| (event.time, event.wire_pattern.bits()))
+            .collect();
+        assert_eq!(events, [(1.0, 3), (1.0, 0x8000), (2000.0, 0xffff)]);
+        assert!(gen.error().is_none());
+        assert_eq!(sealed::OrderedIterator::exhaustion_reason(&gen), None);
+    }
+
+    #[test]
+    fn csv_errors() {
+        let e = error("time_ns,pattern_hex,source\n1,0x1,Noise\n2,0x1");
+        assert_eq!(e.row(), 3);
+        assert!(matches!(e.kind(), CsvErrorKind::FieldCount(2)));
+        assert_eq!(e.to_string(), "row 3: expected 3 fields, found 2");
+
+        let e = error("1,0x1,Noise\ntime_ns,pattern_hex,source");
+        assert_eq!(e.row(), 2);
+        assert!(matches!(e.kind(), CsvErrorKind::Time(t) if t == "time_ns"));
+
+        assert!(matches!(
+            error("NaN,0x1,Noise").kind(),
+            CsvErrorKind::Time(_)
+        ));
+        assert!(matches!(
+            error("1,0x10000,Noise").kind(),
+            CsvErrorKind::WirePattern(_)
+        ));
+        assert!(matches!(
+            error("1,+1,Noise").kind(),
+            CsvErrorKind::WirePattern(_)
+        ));
+        assert!(matches!(
+            error("1,0x1,noise").kind(),
+            CsvErrorKind::Source(s) if s == "noise"
+        ));
+
+        let e = error("2,0x1,Noise\n\n1,0x1,Noise");
+        assert_eq!(e.row(), 3);
+        assert!(matches!(e.kind(), CsvErrorKind::Unordered));
+    }
+
+    #[test]
+    fn csv_custom_source() {
+        let laser = Source::custom("csv-laser");
+        let csv = format!("1,0x1,{laser}\n2,0x1,Custom(65535)\n3,0x1,Noise");
+        let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+        assert_eq!(gen.next().unwrap().source, laser);
+        assert_eq!(gen.next().unwrap().source, Source::Custom(65535));
+        assert_eq!(gen.next().unwrap().source, Source::Noise);
+
+        assert!(matches!(
+            error("1,0x1,csv-unregistered").kind(),
+            CsvErrorKind::Source(s) if s == "csv-unregistered"
+        ));
+    }
+
+    #[test]
+    fn csv_stops_at_error() {
+        let csv = "1,0x1,Noise\n2,0xg,Noise\n3,0x1,Noise";
+        let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+        assert_eq!(gen.next().unwrap().time, 1.0);
+        assert!(gen.next().is_none());
+        assert!(gen.next().is_none());
+        assert_eq!(gen.error().unwrap().row(), 2);
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&gen),
+            Some(ExhaustionReason::InvalidInput)
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::Rng;
+use std::fmt;
+
+/// The error type returned when an [`EmpiricalDistribution`] can not be
+/// created from a histogram.
+#[derive(Debug)]
+pub enum EmpiricalError {
+    /// The number of bin edges is not one more than the (non-zero) number of
+    /// counts.
+    BinCount { edges: usize, counts: usize },
+    /// The bin edges are not finite and strictly increasing.
+    BinEdges,
+    /// All counts are zero, or their sum overflows a `u64`.
+    Counts,
+    /// The JSON is not a valid histogram.
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for EmpiricalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BinCount { edges, counts } => {
+                write!(f, "{edges} bin edges do not match {counts} counts")
+            }
+            Self::BinEdges => write!(f, "bin edges are not finite and strictly increasing"),
+            Self::Counts => write!(f, "counts are all zero or their sum overflows"),
+            #[cfg(feature = "serde")]
+            Self::Json(_) => write!(f, "invalid histogram"),
+        }
+    }
+}
+
+impl std::error::Error for EmpiricalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "serde")]
+            Self::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Serialized form of an `EmpiricalDistribution`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Histogram {
+    bin_edges: Vec<f64>,
+    counts: Vec<u64>,
+}
+
+impl TryFrom<Histogram> for EmpiricalDistribution {
+    type Error = EmpiricalError;
+
+    fn try_from(histogram: Histogram) -> Result<Self, Self::Error> {
+        Self::new(histogram.bin_edges, histogram.counts)
+    }
+}
+
+impl From<EmpiricalDistribution> for Histogram {
+    fn from(distribution: EmpiricalDistribution) -> Self {
+        Self {
+            bin_edges: distribution.bin_edges,
+            counts: distribution.counts,
+        }
+    }
+}
+
+/// A distribution of `f64` values given by a measured histogram (e.g. of
+/// afterpulse delays).
+///
+/// A bin is chosen with probability proportional to its count, and the value
+/// is uniform within the bin: bin `i` covers `[bin_edges[i], bin_edges[i +
+/// 1])`. Plug it into a generator with e.g.
+/// [`SampledTimes`](crate::gen::SampledTimes) or the
+/// `inter_arrival_distribution` method of the generator builders.
+///
+/// With the `serde` feature, the distribution is (de)serialized as the
+/// histogram e.g. `{"bin_edges": [0.0, 1.0, 3.0], "counts": [10, 30]}`.
+///
+/// # Examples
+///
+/// ```
+/// use rand::distr::Distribution;
+/// use trg::gen::empirical::EmpiricalDistribution;
+/// use trg::gen::Seed;
+///
+/// let delays = EmpiricalDistribution::new(vec![0.0, 1.0, 3.0], vec![10, 30])?;
+/// assert_eq!(delays.mean(), 1.625);
+///
+/// let delay = delays.sample(&mut Seed::new(0).rng());
+/// assert!((0.0..3.0).contains(&delay));
+/// # Ok::<(), trg::gen::empirical::EmpiricalError>(())
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Histogram", into = "Histogram")
+)]
+pub struct EmpiricalDistribution {
+    bin_edges: Vec<f64>,
+    counts: Vec<u64>,
+    bins: WeightedIndex<u64>,
+}
+
+impl EmpiricalDistribution {
+    /// Create a new distribution from the edges and counts of a histogram.
+    /// There has to be one more edge than counts.
+    pub fn new(bin_edges: Vec<f64>, counts: Vec<u64>) -> Result<Self, EmpiricalError> {
+        if counts.is_empty() || bin_edges.len() != counts.len() + 1 {
+            return Err(EmpiricalError::BinCount {
+                edges: bin_edges.len(),
+                counts: counts.len(),
+            });
+        }
+        if bin_edges.iter().any(|edge| !edge.is_finite())
+            || bin_edges.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(EmpiricalError::BinEdges);
+        }
+        let bins = WeightedIndex::new(&counts).map_err(|_| EmpiricalError::Counts)?;
+
+        Ok(Self {
+            bin_edges,
+            counts,
+            bins,
+        })
+    }
+    /// Read the histogram from JSON (see [`EmpiricalDistribution`]).
+    #[cfg(feature = "serde")]
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, EmpiricalError> {
+        serde_json::from_reader(reader).map_err(EmpiricalError::Json)
+    }
+    /// Returns the edges of the bins.
+    pub fn bin_edges(&self) -> &[f64] {
+        &self.bin_edges
+    }
+    /// Returns the counts of the bins.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+    /// Returns the mean of the distribution (i.e. of the histogram with its
+    /// counts at the center of the bins).
+    pub fn mean(&self) -> f64 {
+        let total = self.counts.iter().map(|&count| count as f64).sum::<f64>();
+        self.bin_edges
+            .windows(2)
+            .zip(&self.counts)
+            .map(|(pair, &count)| count as f64 * (pair[0] + pair[1]) / 2.0)
+            .sum::<f64>()
+            / total
+    }
+}
+
+impl Distribution<f64> for EmpiricalDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let bin = self.bins.sample(rng);
+        let (low, high) = (self.bin_edges[bin], self.bin_edges[bin + 1]);
+        // Rounding could otherwise give exactly `high`.
+        (low + (high - low) * rng.random::<f64>()).min(high.next_down())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::{Positive, SecondaryGenerator, Seed, Source, WirePattern};
+    use std::iter::repeat;
+
+    fn delays() -> EmpiricalDistribution {
+        EmpiricalDistribution::new(vec![0.0, 1.0, 3.0, 6.0], vec![10, 30, 60]).unwrap()
+    }
+
+    #[test]
+    fn empirical_mean() {
+        let delays = delays();
+        assert_eq!(delays.mean(), 3.35);
+        // Uniform within each bin.
+        let second_moment = [(0.0, 1.0, 0.1), (1.0, 3.0, 0.3), (3.0, 6.0, 0.6)]
+            .iter()
+            .map(|(a, b, p)| p * (a * a + a * b + b * b) / 3.0)
+            .sum::<f64>();
+        let sigma = f64::sqrt(second_moment - delays.mean().powi(2));
+
+        let n = 100_000;
+        let mut rng = Seed::new(0).rng();
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let delay = delays.sample(&mut rng);
+            assert!((0.0..6.0).contains(&delay));
+            sum += delay;
+        }
+        let mean = sum / f64::from(n);
+        let tolerance = 5.0 * sigma / f64::from(n).sqrt();
+        assert!(
+            (mean - delays.mean()).abs() < tolerance,
+            "{mean} sampled, {} expected",
+            delays.mean()
+        );
+    }
+
+    #[test]
+    fn empirical_bins() {
+        let delays = delays();
+        let n = 100_000.0;
+        let mut rng = Seed::new(1).rng();
+        let mut bins = [0.0; 3];
+        for _ in 0..n as usize {
+            let delay = delays.sample(&mut rng);
+            let bin = delays.bin_edges().partition_point(|edge| *edge <= delay) - 1;
+            bins[bin] += 1.0;
+        }
+        for (count, p) in bins.into_iter().zip([0.1, 0.3, 0.6]) {
+            let sigma = f64::sqrt(n * p * (1.0 - p));
+            assert!(
+                (count - n * p).abs() < 5.0 * sigma,
+                "{count} in bin, {p} expected"
+            );
+        }
+    }
+
+    #[test]
+    fn empirical_errors() {
+        let error = |edges: &[f64], counts: &[u64]| {
+            EmpiricalDistribution::new(edges.to_vec(), counts.to_vec()).unwrap_err()
+        };
+        assert!(matches!(
+            error(&[0.0, 1.0], &[1, 1]),
+            EmpiricalError::BinCount {
+                edges: 2,
+                counts: 2
+            }
+        ));
+        assert!(matches!(
+            error(&[0.0], &[]),
+            EmpiricalError::BinCount { .. }
+        ));
+        assert!(matches!(
+            error(&[0.0, 1.0, 1.0], &[1, 1]),
+            EmpiricalError::BinEdges
+        ));
+        assert!(matches!(
+            error(&[0.0, f64::INFINITY], &[1]),
+            EmpiricalError::BinEdges
+        ));
+        assert!(matches!(error(&[0.0, 1.0], &[0]), EmpiricalError::Counts));
+        assert_eq!(
+            error(&[0.0, 1.0, 2.0], &[1]).to_string(),
+            "3 bin edges do not match 1 counts"
+        );
+    }
+
+    #[test]
+    fn empirical_inter_arrival_time() {
+        // Bins that start at zero, so some samples could be rejected.
+        let delays = EmpiricalDistribution::new(vec![0.0, 2.0, 4.0], vec![1, 1]).unwrap();
+        let gen = SecondaryGenerator::builder()
+            .source(Source::SecondaryCosmic)
+            .origin(0.0)
+            .duration(Positive::new(4000.0).unwrap())
+            .inter_arrival_distribution(delays, Seed::new(2).rng(), |t| t)
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+
+        // Mean of 2.
+        let count = gen.count() as f64;
+        let expected = 2000.0;
+        assert!(
+            (count - expected).abs() < 5.0 * f64::sqrt(expected),
+            "{count} events, {expected} expected"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn empirical_json() {
+        use std::error::Error;
+
+        let json = r#"{"bin_edges": [0.0, 1.0, 3.0, 6.0], "counts": [10, 30, 60]}"#;
+        let delays = EmpiricalDistribution::from_reader(json.as_bytes()).unwrap();
+        assert_eq!(delays.bin_edges(), [0.0, 1.0, 3.0, 6.0]);
+        assert_eq!(delays.counts(), [10, 30, 60]);
+
+        let json = serde_json::to_string(&delays).unwrap();
+        assert_eq!(
+            json,
+            r#"{"bin_edges":[0.0,1.0,3.0,6.0],"counts":[10,30,60]}"#
+        );
+
+        let json = r#"{"bin_edges": [0.0, 1.0], "counts": [10, 30]}"#;
+        let error = EmpiricalDistribution::from_reader(json.as_bytes()).unwrap_err();
+        assert!(matches!(error, EmpiricalError::Json(_)));
+        assert!(error.source().unwrap().to_string().contains("bin edges"));
+    }
+}
@@ -0,0 +1,791 @@
+use crate::gen::{
+    sealed, EventGenerator, EventId, ExhaustionReason, Source, WireEvent, WirePattern,
+    BUILT_IN_SOURCES,
+};
+use crate::observers::TimeEncode;
+use std::fmt;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+
+/// First bytes of a recording.
+pub const MAGIC: [u8; 4] = *b"TRGW";
+/// Version of the format written by a [`RecordingGenerator`].
+pub const VERSION: u8 = 2;
+
+// Source tag of a `Source::Custom`. Built-in sources are tagged with their
+// index in `BUILT_IN_SOURCES`.
+const CUSTOM_TAG: u8 = BUILT_IN_SOURCES.len() as u8;
+// Bytes of a record before the label of a custom source and the time: source
+// tag and label length, wire pattern, id flag and id, and weight.
+const FIXED_LEN: usize = 1 + 2 + 2 + 1 + 8 + 8;
+
+/// Binary encoding of the time of a [`WireEvent`]: its [`TimeEncode`], which
+/// has to be decodable. The encoding is canonical, so e.g. a time of `-0.0`
+/// is replayed as `0.0`.
+pub trait TimeCodec: TimeEncode + Sized {
+    /// Decodes a time from exactly the bytes appended by
+    /// [`TimeEncode::encode`]. Returns `None` if they are not a valid
+    /// encoding.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl TimeCodec for i64 {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(i64::from_le_bytes)
+    }
+}
+
+impl TimeCodec for f64 {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(f64::from_le_bytes)
+    }
+}
+
+#[cfg(feature = "uom")]
+impl TimeCodec for uom::si::f64::Time {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        f64::decode(bytes).map(Self::new::<uom::si::time::second>)
+    }
+}
+
+/// A generator that writes every event of another generator to a recording,
+/// which can be read back with a [`FileReplayGenerator`].
+///
+/// A recording starts with the [`MAGIC`] bytes and the [`VERSION`], followed
+/// by one record per event. Each record is prefixed by its length in bytes
+/// (as a little-endian `u32`), and has the source, wire pattern, id, weight,
+/// and the time (encoded with its [`TimeCodec`]) of the event. Custom sources
+/// are recorded by label, and registered (see [`Source::custom`]) when they
+/// are replayed.
+///
+/// Events are yielded unchanged even if writing fails. The first error is
+/// available from [`RecordingGenerator::error`] and returned by
+/// [`RecordingGenerator::finish`]; add a `&mut` reference to the generator to
+/// a [`World`](crate::World) to finish it after the run.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::record::{FileReplayGenerator, RecordingGenerator};
+/// use trg::gen::{ScriptedGenerator, Source, WirePattern};
+///
+/// let gen = ScriptedGenerator::from_times(Source::Noise, [1, 2, 3], WirePattern::from_bits(1));
+/// let mut recorder = RecordingGenerator::new(gen, Vec::new());
+/// assert_eq!(recorder.by_ref().count(), 3);
+/// let bytes = recorder.finish()?;
+///
+/// let replay = FileReplayGenerator::new(bytes.as_slice());
+/// let times: Vec<i64> = replay.map(|event| event.time).collect();
+/// assert_eq!(times, [1, 2, 3]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct RecordingGenerator<G, W: Write> {
+    inner: G,
+    writer: BufWriter<W>,
+    // Reused for the encoding of each record.
+    record: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl<G, W: Write> RecordingGenerator<G, W> {
+    /// Create a new generator that records the events of `inner` to `writer`.
+    /// Writes are buffered, so `writer` does not need to be.
+    pub fn new(inner: G, writer: W) -> Self {
+        let mut writer = BufWriter::new(writer);
+        let error = writer
+            .write_all(&MAGIC)
+            .and_then(|()| writer.write_all(&[VERSION]))
+            .err();
+        Self {
+            inner,
+            writer,
+            record: Vec::new(),
+            error,
+        }
+    }
+    /// Returns the first error found while writing the recording, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+    /// Flush the recording and return the writer, or the first error found
+    /// while writing.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+    }
+}
+
+impl<G, W> Iterator for RecordingGenerator<G, W>
+where
+    G: EventGenerator,
+    G::Time: TimeCodec,
+    W: Write,
+{
+    type Item = WireEvent<G::Time>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.inner.next()?;
+        if self.error.is_none() {
+            self.record.clear();
+            encode_event(&event, &mut self.record);
+            let len = u32::try_from(self.record.len()).expect("record too long");
+            self.error = self
+                .writer
+                .write_all(&len.to_le_bytes())
+                .and_then(|()| self.writer.write_all(&self.record))
+                .err();
+        }
+        Some(event)
+    }
+}
+
+impl<G, W> sealed::OrderedIterator for RecordingGenerator<G, W>
+where
+    G: EventGenerator,
+    G::Time: TimeCodec,
+    W: Write,
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.inner.exhaustion_reason()
+    }
+
+    fn spawned_last(&self) -> bool {
+        self.inner.spawned_last()
+    }
+}
+
+fn encode_event<T: TimeCodec>(event: &WireEvent<T>, bytes: &mut Vec<u8>) {
+    let (tag, label) = match event.source {
+        // The number of a custom source is only meaningful in this program.
+        source @ Source::Custom(_) => (CUSTOM_TAG, source.to_string()),
+        source => {
+            let tag = BUILT_IN_SOURCES
+                .iter()
+                .position(|(_, s)| *s == source)
+                .unwrap();
+            (tag as u8, String::new())
+        }
+    };
+    let label_len = u16::try_from(label.len()).expect("custom source label too long");
+    bytes.push(tag);
+    bytes.extend_from_slice(&label_len.to_le_bytes());
+    bytes.extend_from_slice(&event.wire_pattern.bits().to_le_bytes());
+    bytes.push(event.id.is_some().into());
+    bytes.extend_from_slice(&event.id.map_or(0, EventId::get).to_le_bytes());
+    bytes.extend_from_slice(&event.weight.to_le_bytes());
+    bytes.extend_from_slice(label.as_bytes());
+    event.time.encode(bytes);
+}
+
+/// The kind of error found in a recording.
+#[derive(Debug)]
+pub enum ReplayErrorKind {
+    /// The recording could not be read.
+    Io(io::Error),
+    /// The recording does not start with the [`MAGIC`] bytes and a supported
+    /// [`VERSION`].
+    Header,
+    /// The recording ends in the middle of a header or record.
+    Truncated { expected: usize, found: usize },
+    /// The record is too short to have all fields.
+    Length(usize),
+    /// The source tag is not valid.
+    Source(u8),
+    /// The label of a custom source is not valid (see [`Source::custom`]), or
+    /// a built-in source has a label.
+    Label(String),
+    /// The id flag is neither 0 nor 1.
+    Id(u8),
+    /// The time is not a valid encoding (see [`TimeCodec::decode`]).
+    Time,
+    /// The time is earlier than the time of the previous record.
+    Unordered,
+}
+
+/// The error type returned when a [`FileReplayGenerator`] finds an invalid
+/// recording.
+#[derive(Debug)]
+pub struct ReplayError {
+    offset: u64,
+    kind: ReplayErrorKind,
+}
+
+impl ReplayError {
+    /// Returns the offset in bytes of the invalid header (zero) or record
+    /// (including its length prefix).
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+    /// Returns the kind of error.
+    pub fn kind(&self) -> &ReplayErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "byte {}: ", self.offset)?;
+        match &self.kind {
+            ReplayErrorKind::Io(_) => write!(f, "failed to read the recording"),
+            ReplayErrorKind::Header => write!(f, "not a recording of a supported version"),
+            ReplayErrorKind::Truncated { expected, found } => {
+                write!(f, "truncated, expected {expected} bytes, found {found}")
+            }
+            ReplayErrorKind::Length(len) => {
+                write!(f, "record of {len} bytes is too short")
+            }
+            ReplayErrorKind::Source(tag) => write!(f, "invalid source tag {tag}"),
+            ReplayErrorKind::Label(label) => write!(f, "invalid source label `{label}`"),
+            ReplayErrorKind::Id(flag) => write!(f, "invalid id flag {flag}"),
+            ReplayErrorKind::Time => write!(f, "invalid time"),
+            ReplayErrorKind::Unordered => write!(f, "time is earlier than the previous record"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ReplayErrorKind::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Fills `buf` unless the reader ends first. Returns the number of bytes read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(read)
+}
+
+fn decode_event<T: TimeCodec>(record: &[u8]) -> Result<WireEvent<T>, ReplayErrorKind> {
+    if record.len() < FIXED_LEN {
+        return Err(ReplayErrorKind::Length(record.len()));
+    }
+    let (fixed, rest) = record.split_at(FIXED_LEN);
+    let u16_at = |i: usize| u16::from_le_bytes([fixed[i], fixed[i + 1]]);
+    let u64_at = |i: usize| u64::from_le_bytes(fixed[i..i + 8].try_into().unwrap());
+
+    let label_len = usize::from(u16_at(1));
+    if rest.len() < label_len {
+        return Err(ReplayErrorKind::Length(record.len()));
+    }
+    let (label, time) = rest.split_at(label_len);
+    let invalid_label = || ReplayErrorKind::Label(String::from_utf8_lossy(label).into_owned());
+    let source = match fixed[0] {
+        CUSTOM_TAG => std::str::from_utf8(label)
+            .ok()
+            .and_then(Source::parse_or_register)
+            .filter(|source| matches!(source, Source::Custom(_)))
+            .ok_or_else(invalid_label)?,
+        tag => match BUILT_IN_SOURCES.get(usize::from(tag)) {
+            Some(_) if label_len != 0 => return Err(invalid_label()),
+            Some((_, source)) => *source,
+            None => return Err(ReplayErrorKind::Source(tag)),
+        },
+    };
+    let id = match fixed[5] {
+        0 => None,
+        1 => Some(EventId::new(u64_at(6))),
+        flag => return Err(ReplayErrorKind::Id(flag)),
+    };
+
+    Ok(WireEvent {
+        source,
+        wire_pattern: WirePattern::from_bits(u16_at(3)),
+        time: T::decode(time).ok_or(ReplayErrorKind::Time)?,
+        id,
+        weight: f64::from_bits(u64_at(14)),
+    })
+}
+
+/// A generator that replays the [`WireEvent`]s of a recording made with a
+/// [`RecordingGenerator`].
+///
+/// Records are read lazily, and times have to be in non-decreasing order.
+/// The generator stops at the first invalid (e.g. truncated) header or
+/// record, with an [`ExhaustionReason::InvalidInput`]. The details are
+/// available from [`FileReplayGenerator::error`]; all events before the
+/// invalid record are replayed.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::record::{FileReplayGenerator, RecordingGenerator, ReplayErrorKind};
+/// use trg::gen::{ScriptedGenerator, Source, WirePattern};
+///
+/// let gen = ScriptedGenerator::from_times(Source::Noise, [1.0, 2.0], WirePattern::from_bits(1));
+/// let mut recorder = RecordingGenerator::new(gen, Vec::new());
+/// recorder.by_ref().for_each(drop);
+/// let mut bytes = recorder.finish()?;
+/// bytes.pop();
+///
+/// let mut replay = FileReplayGenerator::<_, f64>::new(bytes.as_slice());
+/// assert_eq!(replay.by_ref().count(), 1);
+/// let error = replay.error().unwrap();
+/// assert!(matches!(error.kind(), ReplayErrorKind::Truncated { .. }));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct FileReplayGenerator<R, T> {
+    reader: BufReader<R>,
+    // Number of bytes read so far.
+    offset: u64,
+    last_time: Option<T>,
+    error: Option<ReplayError>,
+    // Reused for the bytes of each record.
+    record: Vec<u8>,
+    _time: PhantomData<fn() -> T>,
+}
+
+impl<R: Read, T> FileReplayGenerator<R, T> {
+    /// Create a new generator that reads a recording from `reader`. Reads are
+    /// buffered, so `reader` does not need to be.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            offset: 0,
+            last_time: None,
+            error: None,
+            record: Vec::new(),
+            _time: PhantomData,
+        }
+    }
+}
+
+impl<R, T> FileReplayGenerator<R, T> {
+    /// Returns the invalid header or record that stopped the generator, if
+    /// any.
+    pub fn error(&self) -> Option<&ReplayError> {
+        self.error.as_ref()
+    }
+}
+
+impl<R: Read, T: TimeCodec + PartialOrd + Clone> FileReplayGenerator<R, T> {
+    // Returns `None` at the (clean) end of the recording.
+    fn read_event(&mut self) -> Result<Option<WireEvent<T>>, ReplayErrorKind> {
+        if self.offset == 0 {
+            let mut header = [0; MAGIC.len() + 1];
+            let found = read_full(&mut self.reader, &mut header).map_err(ReplayErrorKind::Io)?;
+            if header[..found] != [MAGIC.as_slice(), &[VERSION]].concat()[..found] {
+                return Err(ReplayErrorKind::Header);
+            }
+            if found < header.len() {
+                return Err(ReplayErrorKind::Truncated {
+                    expected: header.len(),
+                    found,
+                });
+            }
+            self.offset = header.len() as u64;
+        }
+
+        let mut len = [0; 4];
+        let found = read_full(&mut self.reader, &mut len).map_err(ReplayErrorKind::Io)?;
+        match found {
+            0 => return Ok(None),
+            4 => {}
+            found => {
+                return Err(ReplayErrorKind::Truncated {
+                    expected: len.len(),
+                    found,
+                })
+            }
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        // The length is untrusted; only grow the buffer as bytes are read.
+        self.record.clear();
+        let found = (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut self.record)
+            .map_err(ReplayErrorKind::Io)?;
+        if found < len {
+            return Err(ReplayErrorKind::Truncated {
+                expected: 4 + len,
+                found: 4 + found,
+            });
+        }
+
+        let event = decode_event::<T>(&self.record)?;
+        if self
+            .last_time
+            .as_ref()
+            .is_some_and(|last| event.time < *last)
+        {
+            return Err(ReplayErrorKind::Unordered);
+        }
+        self.last_time = Some(event.time.clone());
+        self.offset += 4 + len as u64;
+        Ok(Some(event))
+    }
+}
+
+impl<R: Read, T: TimeCodec + PartialOrd + Clone> Iterator for FileReplayGenerator<R, T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        match self.read_event() {
+            Ok(event) => event,
+            Err(kind) => {
+                self.error = Some(ReplayError {
+                    offset: self.offset,
+                    kind,
+                });
+                None
+            }
+        }
+    }
+}
+
+impl<R: Read, T: TimeCodec + PartialOrd + Clone> sealed::OrderedIterator
+    for FileReplayGenerator<R, T>
+{
+    fn exhaustion_reason(&self) -> Option<ExhaustionReason> {
+        self.error.as_ref().map(|_| ExhaustionReason::InvalidInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::ScriptedGenerator;
+
+    fn events() -> Vec<WireEvent<f64>> {
+        let event = |source, bits, time, id: Option<u64>, weight| WireEvent {
+            source,
+            wire_pattern: WirePattern::from_bits(bits),
+            time,
+            id: id.map(EventId::new),
+            weight,
+        };
+        vec![
+            event(Source::PrimaryCosmic, 0x0003, -1.5, Some(0), 1.0),
+            event(Source::SecondaryCosmic, 0x8000, 0.0, Some(0), 1.0),
+            event(Source::Noise, 0xffff, 0.0, None, 0.25),
+            event(Source::custom("record-laser"), 0x0010, 1e9, None, 1.0),
+            event(
+                Source::Custom(65535),
+                0x0001,
+                1e9 + 0.5,
+                Some(u64::MAX),
+                2.0,
+            ),
+        ]
+    }
+
+    type Fields<T> = (Source, u16, T, Option<EventId>, f64);
+
+    fn fields<T: Copy>(events: &[WireEvent<T>]) -> Vec<Fields<T>> {
+        events
+            .iter()
+            .map(|e| (e.source, e.wire_pattern.bits(), e.time, e.id, e.weight))
+            .collect()
+    }
+
+    fn record<T: TimeCodec + PartialOrd>(events: Vec<WireEvent<T>>) -> Vec<u8> {
+        let mut recorder = RecordingGenerator::new(ScriptedGenerator::new(events), Vec::new());
+        recorder.by_ref().for_each(drop);
+        recorder.finish().unwrap()
+    }
+
+    fn replay_error(bytes: &[u8]) -> (usize, ReplayError) {
+        let mut replay = FileReplayGenerator::<_, f64>::new(bytes);
+        let count = replay.by_ref().count();
+        assert!(replay.next().is_none());
+        assert_eq!(
+            sealed::OrderedIterator::exhaustion_reason(&replay),
+            Some(ExhaustionReason::InvalidInput)
+        );
+        (count, replay.error.unwrap())
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let bytes = record(events());
+        assert_eq!(bytes[..5], *b"TRGW\x02");
+        // Custom sources are recorded with their labels.
+        let labels = "record-laser".len() + "Custom(65535)".len();
+        assert_eq!(bytes.len(), 5 + 5 * (4 + FIXED_LEN + 8) + labels);
+
+        let mut replay = FileReplayGenerator::new(bytes.as_slice());
+        let replayed: Vec<_> = replay.by_ref().collect();
+        assert_eq!(fields(&replayed), fields(&events()));
+        assert!(replay.error().is_none());
+        assert_eq!(sealed::OrderedIterator::exhaustion_reason(&replay), None);
+
+        // An empty recording.
+        let bytes = record(Vec::<WireEvent<i64>>::new());
+        assert_eq!(bytes, b"TRGW\x02");
+        let mut replay = FileReplayGenerator::<_, i64>::new(bytes.as_slice());
+        assert!(replay.next().is_none());
+        assert!(replay.error().is_none());
+    }
+
+    #[test]
+    fn record_round_trip_i64() {
+        let gen = ScriptedGenerator::from_times(
+            Source::Noise,
+            [i64::MIN, -1, 0, i64::MAX],
+            WirePattern::from_bits(1),
+        );
+        let events: Vec<_> = gen.clone().collect();
+        let mut recorder = RecordingGenerator::new(gen, Vec::new());
+        // Events are yielded unchanged.
+        let recorded: Vec<_> = recorder.by_ref().collect();
+        assert_eq!(fields(&recorded), fields(&events));
+        let bytes = recorder.finish().unwrap();
+
+        let replayed: Vec<_> = FileReplayGenerator::new(bytes.as_slice()).collect();
+        assert_eq!(fields(&replayed), fields(&events));
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn record_round_trip_uom() {
+        use uom::si::f64::Time;
+        use uom::si::time::{microsecond, second};
+
+        let events: Vec<_> = events()
+            .into_iter()
+            .map(|event| WireEvent {
+                source: event.source,
+                wire_pattern: event.wire_pattern,
+                time: Time::new::<microsecond>(event.time),
+                id: event.id,
+                weight: event.weight,
+            })
+            .collect();
+        let bytes = record(events.clone());
+        // Times are recorded in seconds.
+        let seconds: Vec<_> = FileReplayGenerator::<_, f64>::new(bytes.as_slice())
+            .map(|event| event.time)
+            .collect();
+        let expected: Vec<_> = events
+            .iter()
+            .map(|event| event.time.get::<second>())
+            .collect();
+        assert_eq!(seconds, expected);
+
+        let replayed: Vec<_> = FileReplayGenerator::<_, Time>::new(bytes.as_slice()).collect();
+        assert_eq!(fields(&replayed), fields(&events));
+    }
+
+    // Offsets of the records of a recording, and of its end.
+    fn boundaries(bytes: &[u8]) -> Vec<usize> {
+        let mut boundaries = vec![5];
+        let mut offset = 5;
+        while offset < bytes.len() {
+            let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+            offset += 4 + len as usize;
+            boundaries.push(offset);
+        }
+        boundaries
+    }
+
+    #[test]
+    fn record_truncated() {
+        let bytes = record(events());
+        let boundaries = boundaries(&bytes);
+        assert_eq!(boundaries.len(), 6);
+        // Every cut that is not at a record boundary.
+        for cut in 0..bytes.len() {
+            if cut < 5 {
+                let (count, error) = replay_error(&bytes[..cut]);
+                assert_eq!(count, 0);
+                assert_eq!(error.offset(), 0);
+                assert!(matches!(
+                    error.kind(),
+                    ReplayErrorKind::Truncated { expected: 5, found } if *found == cut
+                ));
+                continue;
+            }
+            let count = boundaries.iter().filter(|&&b| b <= cut).count() - 1;
+            let offset = boundaries[count];
+            if cut == offset {
+                let mut replay = FileReplayGenerator::<_, f64>::new(&bytes[..cut]);
+                assert_eq!(replay.by_ref().count(), count);
+                assert!(replay.error().is_none());
+                continue;
+            }
+            let (replayed, error) = replay_error(&bytes[..cut]);
+            assert_eq!(replayed, count);
+            assert_eq!(error.offset(), offset as u64);
+            let expected = if cut - offset < 4 {
+                4
+            } else {
+                boundaries[count + 1] - offset
+            };
+            assert!(
+                matches!(
+                    error.kind(),
+                    ReplayErrorKind::Truncated { expected: e, found } if *e == expected && *found == cut - offset
+                ),
+                "{error} at cut {cut}"
+            );
+        }
+
+        // The last record has the label `Custom(65535)`.
+        let (_, error) = replay_error(&bytes[..bytes.len() - 3]);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "byte {}: truncated, expected 47 bytes, found 44",
+                5 + 3 * 34 + 46
+            )
+        );
+    }
+
+    #[test]
+    fn record_invalid() {
+        let bytes = record(events());
+        let header = 5;
+        let record_len = 4 + FIXED_LEN + 8;
+
+        let (count, error) = replay_error(b"TRGX\x02");
+        assert_eq!(count, 0);
+        assert!(matches!(error.kind(), ReplayErrorKind::Header));
+        assert_eq!(
+            error.to_string(),
+            "byte 0: not a recording of a supported version"
+        );
+        let (_, error) = replay_error(b"TRGW\x01");
+        assert!(matches!(error.kind(), ReplayErrorKind::Header));
+        // Not a truncated header if the bytes do not match.
+        let (_, error) = replay_error(b"time_ns");
+        assert!(matches!(error.kind(), ReplayErrorKind::Header));
+
+        // Source tag of the second record.
+        let mut invalid = bytes.clone();
+        invalid[header + record_len + 4] = CUSTOM_TAG + 1;
+        let (count, error) = replay_error(&invalid);
+        assert_eq!(count, 1);
+        assert_eq!(error.offset(), (header + record_len) as u64);
+        assert!(matches!(error.kind(), ReplayErrorKind::Source(6)));
+
+        let mut invalid = bytes.clone();
+        invalid[header + 4 + 5] = 2;
+        assert!(matches!(
+            replay_error(&invalid).1.kind(),
+            ReplayErrorKind::Id(2)
+        ));
+
+        // A record without the time.
+        let mut invalid = bytes[..header + record_len].to_vec();
+        invalid[header..header + 4].copy_from_slice(&(FIXED_LEN as u32 - 1).to_le_bytes());
+        invalid.truncate(header + 4 + FIXED_LEN - 1);
+        assert!(matches!(
+            replay_error(&invalid).1.kind(),
+            ReplayErrorKind::Length(len) if *len == FIXED_LEN - 1
+        ));
+        // A record with an `i32` time.
+        let mut invalid = bytes[..header + record_len - 4].to_vec();
+        invalid[header..header + 4].copy_from_slice(&(FIXED_LEN as u32 + 4).to_le_bytes());
+        assert!(matches!(
+            replay_error(&invalid).1.kind(),
+            ReplayErrorKind::Time
+        ));
+
+        // The last record followed by the first one.
+        let mut invalid = bytes.clone();
+        invalid.extend_from_slice(&bytes[header..header + record_len]);
+        let (count, error) = replay_error(&invalid);
+        assert_eq!(count, 5);
+        assert!(matches!(error.kind(), ReplayErrorKind::Unordered));
+    }
+
+    #[test]
+    fn record_custom_labels() {
+        let gen = ScriptedGenerator::from_times(
+            Source::custom("record-x"),
+            [1.0],
+            WirePattern::from_bits(1),
+        );
+        let bytes = record(gen.collect());
+        let label = 5 + 4 + FIXED_LEN;
+        assert_eq!(&bytes[label..label + 8], b"record-x");
+
+        // A label that was never registered (e.g. recorded by another
+        // program) is registered when replayed.
+        let mut other = bytes.clone();
+        other[label..label + 8].copy_from_slice(b"record-y");
+        let replayed: Vec<_> = FileReplayGenerator::<_, f64>::new(other.as_slice()).collect();
+        assert_eq!(replayed[0].source.to_string(), "record-y");
+        assert_eq!(replayed[0].source, Source::custom("record-y"));
+
+        for invalid in [
+            b"record y",
+            b"Noise\0\0\0",
+            b"\xff\xfe\xfd\xfc\xfb\xfa\xf9\xf8",
+        ] {
+            let mut bytes = bytes.clone();
+            bytes[label..label + 8].copy_from_slice(invalid);
+            assert!(matches!(
+                replay_error(&bytes).1.kind(),
+                ReplayErrorKind::Label(_)
+            ));
+        }
+        // A built-in source with a label.
+        let mut invalid = bytes.clone();
+        invalid[5 + 4] = 0;
+        let error = replay_error(&invalid).1;
+        assert!(matches!(error.kind(), ReplayErrorKind::Label(_)));
+        assert_eq!(error.to_string(), "byte 5: invalid source label `record-x`");
+        // A label longer than the record.
+        let mut invalid = bytes.clone();
+        invalid[5 + 4 + 1..5 + 4 + 3].copy_from_slice(&100u16.to_le_bytes());
+        assert!(matches!(
+            replay_error(&invalid).1.kind(),
+            ReplayErrorKind::Length(_)
+        ));
+    }
+
+    #[derive(Debug)]
+    struct FailingWriter(usize);
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 < buf.len() {
+                return Err(io::Error::other("disk full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_write_error() {
+        // More events than fit in the buffer of the writer.
+        let gen = ScriptedGenerator::from_times(Source::Noise, 0..1000, WirePattern::from_bits(1));
+        let expected: Vec<_> = gen.clone().collect();
+        let mut recorder = RecordingGenerator::new(gen, FailingWriter(100));
+        // Events are yielded even if the recording fails.
+        let recorded: Vec<_> = recorder.by_ref().collect();
+        assert_eq!(fields(&recorded), fields(&expected));
+        assert_eq!(recorder.error().unwrap().to_string(), "disk full");
+        assert_eq!(recorder.finish().unwrap_err().to_string(), "disk full");
+
+        // Errors when flushing are also returned.
+        let mut recorder =
+            RecordingGenerator::new(ScriptedGenerator::new(events()), FailingWriter(100));
+        recorder.by_ref().for_each(drop);
+        assert!(recorder.error().is_none());
+        assert_eq!(recorder.finish().unwrap_err().to_string(), "disk full");
+    }
+}
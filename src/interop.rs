@@ -0,0 +1,304 @@
+use bon::bon;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::ops::Sub;
+
+/// A trigger as recorded by the DAQ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrgEvent<T> {
+    /// Value of the trigger counter.
+    pub number: u32,
+    /// Timestamp of the trigger.
+    pub time: T,
+}
+
+/// A pathology that can be injected into a stream of [`TrgEvent`]s.
+#[derive(Clone, Copy, Debug)]
+pub enum Pathology<T> {
+    /// Each trigger is recorded twice with the given probability.
+    DuplicateTrigger { probability: f64 },
+    /// Each trigger is lost with the given probability.
+    DropTrigger { probability: f64 },
+    /// The timestamp counter is reset to zero at the given time (e.g. after a
+    /// firmware reset). All subsequent timestamps are relative to `at_time`.
+    TimestampReset { at_time: T },
+    /// With the given probability, the trigger counter jumps forward by a
+    /// random amount in `1..=max_skip` before a trigger.
+    CounterSkip { probability: f64, max_skip: u32 },
+}
+
+/// Record of a single injected pathology.
+///
+/// All indices refer to the position of the affected trigger in the original
+/// (uncorrupted) stream.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Injection {
+    /// The trigger at `index` was duplicated.
+    DuplicateTrigger { index: usize },
+    /// The trigger at `index` was dropped.
+    DropTrigger { index: usize },
+    /// The timestamp counter was reset right before the trigger at `index`.
+    TimestampReset { index: usize },
+    /// The trigger counter skipped `skip` values right before the trigger at
+    /// `index`.
+    CounterSkip { index: usize, skip: u32 },
+}
+
+/// Corrupts a stream of [`TrgEvent`]s with configurable pathologies.
+///
+/// This is useful to produce labelled data to test the robustness of offline
+/// reconstruction code. The injection is reproducible for a given `seed`.
+#[derive(Clone, Debug)]
+pub struct PathologyInjector<T> {
+    seed: u64,
+    pathologies: Vec<Pathology<T>>,
+}
+
+#[bon]
+impl<T> PathologyInjector<T> {
+    #[builder]
+    pub fn new(
+        #[builder(field)] pathologies: Vec<Pathology<T>>,
+        /// Seed of the random number generator.
+        seed: u64,
+    ) -> Self {
+        Self { seed, pathologies }
+    }
+}
+
+impl<T, S: pathology_injector_builder::State> PathologyInjectorBuilder<T, S> {
+    /// Add a pathology to the [`PathologyInjector`]. Pathologies are applied
+    /// to each trigger in the order in which they were added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the probability of the pathology is not in `[0, 1]`.
+    pub fn pathology(mut self, pathology: Pathology<T>) -> Self {
+        if let Pathology::DuplicateTrigger { probability }
+        | Pathology::DropTrigger { probability }
+        | Pathology::CounterSkip { probability, .. } = pathology
+        {
+            assert!(
+                (0.0..=1.0).contains(&probability),
+                "pathology probability {probability} is not in [0, 1]"
+            );
+        }
+        self.pathologies.push(pathology);
+        self
+    }
+}
+
+impl<T> PathologyInjector<T>
+where
+    T: Sub<Output = T> + PartialOrd + Clone,
+{
+    /// Returns the corrupted stream together with a truth log of all the
+    /// injected pathologies.
+    pub fn inject(&self, events: &[TrgEvent<T>]) -> (Vec<TrgEvent<T>>, Vec<Injection>) {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut corrupted = Vec::with_capacity(events.len());
+        let mut log = Vec::new();
+        // Accumulated counter skips.
+        let mut offset = 0u32;
+        let mut reset_done = vec![false; self.pathologies.len()];
+
+        'events: for (index, event) in events.iter().enumerate() {
+            let mut event = event.clone();
+            let mut duplicate = false;
+
+            for (pathology, reset_done) in self.pathologies.iter().zip(&mut reset_done) {
+                match pathology {
+                    Pathology::DuplicateTrigger { probability } => {
+                        if rng.random_bool(*probability) {
+                            duplicate = true;
+                            log.push(Injection::DuplicateTrigger { index });
+                        }
+                    }
+                    Pathology::DropTrigger { probability } => {
+                        if rng.random_bool(*probability) {
+                            log.push(Injection::DropTrigger { index });
+                            continue 'events;
+                        }
+                    }
+                    Pathology::TimestampReset { at_time } => {
+                        if event.time >= *at_time {
+                            if !*reset_done {
+                                *reset_done = true;
+                                log.push(Injection::TimestampReset { index });
+                            }
+                            event.time = event.time - at_time.clone();
+                        }
+                    }
+                    Pathology::CounterSkip {
+                        probability,
+                        max_skip,
+                    } => {
+                        if *max_skip > 0 && rng.random_bool(*probability) {
+                            let skip = rng.random_range(1..=*max_skip);
+                            offset = offset.wrapping_add(skip);
+                            log.push(Injection::CounterSkip { index, skip });
+                        }
+                    }
+                }
+            }
+
+            event.number = event.number.wrapping_add(offset);
+            if duplicate {
+                corrupted.push(event.clone());
+            }
+            corrupted.push(event);
+        }
+
+        (corrupted, log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream() -> Vec<TrgEvent<i32>> {
+        (0..100)
+            .map(|n| TrgEvent {
+                number: n,
+                time: 10 * i32::try_from(n).unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pathology_injector_empty() {
+        let original = stream();
+        let (corrupted, log) = PathologyInjector::builder()
+            .seed(0)
+            .build()
+            .inject(&original);
+
+        assert_eq!(corrupted, original);
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "pathology probability 1.5 is not in [0, 1]")]
+    fn pathology_injector_invalid_probability() {
+        let _ = PathologyInjector::<i32>::builder()
+            .pathology(Pathology::DropTrigger { probability: 1.5 });
+    }
+
+    #[test]
+    fn pathology_injector_duplicate_trigger() {
+        let original = stream();
+        let (corrupted, log) = PathologyInjector::builder()
+            .seed(0)
+            .pathology(Pathology::DuplicateTrigger { probability: 0.3 })
+            .build()
+            .inject(&original);
+
+        let duplicated = log
+            .iter()
+            .map(|i| match i {
+                Injection::DuplicateTrigger { index } => *index,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert!(!duplicated.is_empty());
+
+        let expected = original
+            .iter()
+            .enumerate()
+            .flat_map(|(i, e)| {
+                let n = if duplicated.contains(&i) { 2 } else { 1 };
+                std::iter::repeat_n(*e, n)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(corrupted, expected);
+    }
+
+    #[test]
+    fn pathology_injector_drop_trigger() {
+        let original = stream();
+        let (corrupted, log) = PathologyInjector::builder()
+            .seed(1)
+            .pathology(Pathology::DropTrigger { probability: 0.3 })
+            .build()
+            .inject(&original);
+
+        let dropped = log
+            .iter()
+            .map(|i| match i {
+                Injection::DropTrigger { index } => *index,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert!(!dropped.is_empty());
+
+        let expected = original
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !dropped.contains(i))
+            .map(|(_, e)| *e)
+            .collect::<Vec<_>>();
+        assert_eq!(corrupted, expected);
+    }
+
+    #[test]
+    fn pathology_injector_timestamp_reset() {
+        let original = stream();
+        let (corrupted, log) = PathologyInjector::builder()
+            .seed(2)
+            .pathology(Pathology::TimestampReset { at_time: 455 })
+            .build()
+            .inject(&original);
+
+        assert_eq!(log, vec![Injection::TimestampReset { index: 46 }]);
+        for (i, (c, o)) in corrupted.iter().zip(&original).enumerate() {
+            assert_eq!(c.number, o.number);
+            if i < 46 {
+                assert_eq!(c.time, o.time);
+            } else {
+                assert_eq!(c.time, o.time - 455);
+            }
+        }
+    }
+
+    #[test]
+    fn pathology_injector_counter_skip() {
+        let original = stream();
+        let (corrupted, log) = PathologyInjector::builder()
+            .seed(3)
+            .pathology(Pathology::CounterSkip {
+                probability: 0.2,
+                max_skip: 5,
+            })
+            .build()
+            .inject(&original);
+        assert!(!log.is_empty());
+
+        let mut offset = 0;
+        let mut log = log.into_iter().peekable();
+        for (i, (c, o)) in corrupted.iter().zip(&original).enumerate() {
+            if let Some(Injection::CounterSkip { index, skip }) = log.peek() {
+                if *index == i {
+                    assert!((1..=5).contains(skip));
+                    offset += skip;
+                    let _ = log.next();
+                }
+            }
+            assert_eq!(c.time, o.time);
+            assert_eq!(c.number, o.number + offset);
+        }
+        assert!(log.next().is_none());
+    }
+
+    #[test]
+    fn pathology_injector_seed() {
+        let original = stream();
+        let injector = PathologyInjector::builder()
+            .seed(4)
+            .pathology(Pathology::DropTrigger { probability: 0.5 })
+            .pathology(Pathology::DuplicateTrigger { probability: 0.5 })
+            .build();
+
+        assert_eq!(injector.inject(&original), injector.inject(&original));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::observers::ScalerCounts;
+use std::fmt;
+
+/// Timing summary of a run. All values are in the same (arbitrary) units.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunStats {
+    /// Total length of the run.
+    pub span: f64,
+    /// Time during which the TRG box was able to send triggers to the DAQ.
+    pub live_time: f64,
+    /// Time during which the TRG box was busy.
+    pub dead_time: f64,
+}
+
+/// An invariant of the trigger system that does not hold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvariantViolation {
+    /// More triggers were sent to the DAQ than signals went into the TRG box.
+    TrgOutExceedsTrgIn { snapshot: usize },
+    /// The signals into the TRG box are not equal to the sum of all the
+    /// signals suppressed at each stage plus the triggers sent to the DAQ.
+    StageSumMismatch { snapshot: usize },
+    /// A counter decreased between two consecutive snapshots.
+    NonMonotoneCounter {
+        counter: &'static str,
+        snapshot: usize,
+    },
+    /// The live time is negative.
+    NegativeLiveTime,
+    /// The dead time is negative.
+    NegativeDeadTime,
+    /// The live time plus the dead time exceed the span of the run.
+    ExceedsSpan,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TrgOutExceedsTrgIn { snapshot } => {
+                write!(f, "TRG out exceeds TRG in (snapshot #{snapshot})")
+            }
+            Self::StageSumMismatch { snapshot } => write!(
+                f,
+                "TRG in is not equal to the sum of all stage counters (snapshot #{snapshot})"
+            ),
+            Self::NonMonotoneCounter { counter, snapshot } => {
+                write!(f, "counter `{counter}` decreased (snapshot #{snapshot})")
+            }
+            Self::NegativeLiveTime => write!(f, "negative live time"),
+            Self::NegativeDeadTime => write!(f, "negative dead time"),
+            Self::ExceedsSpan => write!(f, "live time plus dead time exceed the run span"),
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+// Allow for floating point rounding in the timing checks.
+const TOLERANCE: f64 = 1e-9;
+
+/// Verify the invariants of the trigger system given a sequence of scaler
+/// snapshots (in increasing order of time; the last one being the final
+/// value) and the timing summary of the run:
+///
+/// - TRG out never exceeds TRG in.
+/// - TRG in equals drift veto + scaledown + dead time + TRG out.
+/// - Counters never decrease.
+/// - Live and dead times are non-negative, and they add up to at most the
+///   span of the run.
+pub fn check(scalers: &[ScalerCounts], run_stats: &RunStats) -> Result<(), InvariantViolation> {
+    for (snapshot, counts) in scalers.iter().enumerate() {
+        if counts.trg_out > counts.trg_in {
+            return Err(InvariantViolation::TrgOutExceedsTrgIn { snapshot });
+        }
+        if counts.drift_veto + counts.scaledown + counts.dead_time + counts.trg_out != counts.trg_in
+        {
+            return Err(InvariantViolation::StageSumMismatch { snapshot });
+        }
+    }
+
+    for (snapshot, pair) in scalers.windows(2).enumerate() {
+        let (prev, next) = (pair[0], pair[1]);
+        for (counter, prev, next) in [
+            ("wire_events", prev.wire_events, next.wire_events),
+            ("trg_in", prev.trg_in, next.trg_in),
+            ("drift_veto", prev.drift_veto, next.drift_veto),
+            ("scaledown", prev.scaledown, next.scaledown),
+            ("dead_time", prev.dead_time, next.dead_time),
+            ("trg_out", prev.trg_out, next.trg_out),
+        ] {
+            if next < prev {
+                return Err(InvariantViolation::NonMonotoneCounter {
+                    counter,
+                    snapshot: snapshot + 1,
+                });
+            }
+        }
+    }
+
+    if run_stats.live_time < 0.0 {
+        return Err(InvariantViolation::NegativeLiveTime);
+    }
+    if run_stats.dead_time < 0.0 {
+        return Err(InvariantViolation::NegativeDeadTime);
+    }
+    if run_stats.live_time + run_stats.dead_time > run_stats.span * (1.0 + TOLERANCE) + TOLERANCE {
+        return Err(InvariantViolation::ExceedsSpan);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(trg_in: u64, trg_out: u64) -> ScalerCounts {
+        ScalerCounts {
+            wire_events: 10,
+            trg_in,
+            drift_veto: trg_in - trg_out,
+            scaledown: 0,
+            dead_time: 0,
+            trg_out,
+        }
+    }
+
+    #[test]
+    fn check_ok() {
+        let run_stats = RunStats {
+            span: 10.0,
+            live_time: 7.0,
+            dead_time: 3.0,
+        };
+        assert_eq!(check(&[counts(1, 0), counts(3, 2)], &run_stats), Ok(()));
+    }
+
+    #[test]
+    fn check_scalers() {
+        let run_stats = RunStats::default();
+
+        let mut bad = counts(1, 1);
+        bad.trg_out = 2;
+        assert_eq!(
+            check(&[bad], &run_stats),
+            Err(InvariantViolation::TrgOutExceedsTrgIn { snapshot: 0 })
+        );
+
+        let mut bad = counts(2, 1);
+        bad.scaledown = 1;
+        assert_eq!(
+            check(&[counts(1, 1), bad], &run_stats),
+            Err(InvariantViolation::StageSumMismatch { snapshot: 1 })
+        );
+
+        assert_eq!(
+            check(&[counts(3, 2), counts(3, 1)], &run_stats),
+            Err(InvariantViolation::NonMonotoneCounter {
+                counter: "trg_out",
+                snapshot: 1
+            })
+        );
+    }
+
+    #[test]
+    fn check_run_stats() {
+        let run_stats = |live_time, dead_time| RunStats {
+            span: 10.0,
+            live_time,
+            dead_time,
+        };
+
+        assert_eq!(
+            check(&[], &run_stats(-1.0, 3.0)),
+            Err(InvariantViolation::NegativeLiveTime)
+        );
+        assert_eq!(
+            check(&[], &run_stats(1.0, -3.0)),
+            Err(InvariantViolation::NegativeDeadTime)
+        );
+        assert_eq!(
+            check(&[], &run_stats(8.0, 3.0)),
+            Err(InvariantViolation::ExceedsSpan)
+        );
+    }
+}
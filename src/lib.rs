@@ -1,12 +1,37 @@
-use crate::gen::{EventGenerator, Generator, Positive, WireEvent};
-use crate::mlu::{LookupTable, Mlu, TrgSignal};
+use crate::clock::Quantize;
+use crate::gen::{
+    sealed, EventGenerator, ExhaustionReason, Generator, GeneratorStats, Positive, Seed,
+    TieBreaking, WireEvent, WirePattern, Zero,
+};
+use crate::mlu::{
+    BoundaryPolicy, LookupTable, Mlu, MluDecision, MluStats, TrgSignal, TriggerCondition,
+    WireMapping,
+};
+use crate::validate::{OrderingChecker, OrderingViolation};
 use bon::bon;
+use std::collections::VecDeque;
+use std::fmt::Debug;
 use std::ops::Add;
 
+/// Clock quantization of times.
+pub mod clock;
 /// Utilities to generate input data for the trigger system.
 pub mod gen;
+/// Utilities to test offline analysis code with simulated triggers.
+pub mod interop;
+/// Invariants of the trigger system.
+pub mod invariants;
 /// Memory Lookup Unit.
 pub mod mlu;
+/// Ready-made [`Observer`]s.
+pub mod observers;
+/// Trigger settings from the MIDAS ODB.
+#[cfg(feature = "odb")]
+pub mod odb;
+/// Estimates of efficiencies and rates, with their uncertainties.
+pub mod statistics;
+/// Consistency checks for simulation inputs.
+pub mod validate;
 
 /// A trait that defines the interface for an observer of the trigger system.
 ///
@@ -18,6 +43,14 @@ pub trait Observer {
 
     /// Called when a new [`WireEvent`] is generated.
     fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {}
+    /// Called when the channel dead time clears some boards (`masked`) from
+    /// the pattern of a [`WireEvent`] (`event` is the original event). The
+    /// filtered event is then reported to [`Observer::on_wire_event`], unless
+    /// no boards are left, in which case the event is dropped.
+    fn on_wire_event_masked(&mut self, event: &WireEvent<Self::Time>, masked: WirePattern) {}
+    /// Called when a MLU signal is suppressed by the veto table. Note that
+    /// these signals never go into the TRG box.
+    fn on_mlu_veto(&mut self, signal: &TrgSignal<Self::Time>) {}
     /// Called when a signal goes into the TRG box (i.e. output of the MLU).
     fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {}
     /// Called when a TRG signal is suppressed by the drift veto.
@@ -26,187 +59,2219 @@ pub trait Observer {
     fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {}
     /// Called when a TRG signal is suppressed by the dead time.
     fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {}
-    /// Called when a trigger signal is sent to the DAQ.
-    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>) {}
+    /// Called when a trigger signal is sent to the DAQ. `number` is the
+    /// zero-based count of triggers sent to the DAQ before this one.
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, number: u64) {}
+    /// Called when a generator stops producing [`WireEvent`]s (right after
+    /// its last event). Generators are numbered in the order they were added.
+    fn on_generator_exhausted(&mut self, generator: usize, reason: ExhaustionReason) {}
+    /// Called when a new phase of a [`World::run_phases`] starts. Phases are
+    /// numbered in the order they were given.
+    fn on_phase_start(&mut self, index: usize, time: &Self::Time) {}
+    /// Called when the run is stopped at its `max_time` (see
+    /// [`WorldBuilder::max_time`]) while some generators could still produce
+    /// events. `time` is the `max_time`.
+    fn on_truncated(&mut self, time: &Self::Time) {}
+    /// Called once at the end of the run (right before
+    /// [`Observer::on_mlu_stats`]) with the number of events produced by each
+    /// generator, in the order they were added. The generators of all
+    /// [`Phase`]s count as one generator, added last.
+    fn on_generator_stats(&mut self, stats: &[GeneratorStats]) {}
+    /// Called once at the end of the run (after everything else) with the
+    /// final counters of the MLU.
+    fn on_mlu_stats(&mut self, stats: &MluStats) {}
 }
 
-pub struct World<T, O> {
-    generator: Generator<T>,
+// The `usize` is the number of wire events since the previous TRG output.
+type DeadTimeFn<'a, T> = Box<dyn FnMut(&TrgSignal<T>, usize) -> Positive<T> + 'a>;
+// Maps a time to the start of its clock tick.
+type QuantizeFn<'a, T> = Box<dyn Fn(&T) -> T + 'a>;
+
+// Optional clocks of the MLU and TRG stages.
+struct Clocks<'a, T> {
+    mlu: Option<QuantizeFn<'a, T>>,
+    trg: Option<QuantizeFn<'a, T>>,
+}
+
+// Discriminators can't fire again on the same channel during their dead time.
+struct ChannelDeadTime<T> {
+    dead_time: Positive<T>,
+    busy_until: [Option<T>; 16],
+}
+
+impl<T> ChannelDeadTime<T> {
+    fn new(dead_time: Positive<T>) -> Self {
+        Self {
+            dead_time,
+            busy_until: std::array::from_fn(|_| None),
+        }
+    }
+}
+
+impl<T: Add<Output = T> + PartialOrd + Clone> ChannelDeadTime<T> {
+    // Returns the boards of the event that are suppressed.
+    fn mask(&mut self, event: &WireEvent<T>) -> WirePattern {
+        let mut masked = 0;
+        for (i, busy_until) in self.busy_until.iter_mut().enumerate() {
+            let bit = 1 << i;
+            if event.wire_pattern.0 & bit == 0 {
+                continue;
+            }
+            if busy_until.as_ref().is_some_and(|t| event.time < *t) {
+                masked |= bit;
+            } else {
+                *busy_until = Some(event.time.clone() + self.dead_time.inner().clone());
+            }
+        }
+
+        WirePattern(masked)
+    }
+}
+
+/// A trigger pipeline: the MLU followed by the TRG box.
+///
+/// Multiple pipelines can be simulated side by side on the same input with a
+/// [`MultiWorld`].
+pub struct Trigger<'a, T, O> {
     mlu: Mlu<T>,
     drift_veto: Positive<T>,
     scaledown: u32,
-    dead_time: Positive<T>,
+    dead_time: DeadTimeFn<'a, T>,
     observer: O,
+    clocks: Clocks<'a, T>,
+    channels: Option<ChannelDeadTime<T>>,
+    // Wire events are not sent to the MLU while the TRG box is busy.
+    mlu_respects_busy: bool,
+    // Scheduled lookup table changes, in increasing order of time.
+    table_switches: VecDeque<(T, TriggerCondition)>,
     // Inner state of the TRG box
     veto_until: Option<T>,
     busy_until: Option<T>,
     counter: u32,
+    events_since_trg_out: usize,
+    trg_out_count: u64,
+    // Exhausted generators to report after `prev_event`.
+    exhausted: Vec<(usize, ExhaustionReason)>,
     // Each wire event "flushes" the TRG box. Meaning that the "current" event
     // is ahead of the "current" TRG signal.
     // This allows us to keep the observer "time-aware" i.e. it can assume that
     // everything happens in the correct order.
-    prev_event: Option<WireEvent<T>>,
+    // The event is kept together with the boards masked by the channel dead
+    // time.
+    prev_event: Option<(WireEvent<T>, WirePattern)>,
+    // MLU decisions (in increasing order of time) that are not processed
+    // until all earlier wire events have been reported. Only needed with an
+    // MLU latency, otherwise decisions never wait.
+    pending: VecDeque<MluDecision<T>>,
 }
 
 #[bon]
-impl<T, O> World<T, O> {
+impl<'a, T, O> Trigger<'a, T, O> {
     #[builder]
     pub fn new(
-        #[builder(field)] generator: Generator<T>,
         prompt_window: Positive<T>,
         wait_gate: Positive<T>,
-        lookup_table: LookupTable,
+        #[builder(setters(vis = "", name = condition_internal))] condition: TriggerCondition,
+        /// Wire patterns that suppress the MLU decision even if they satisfy
+        /// the trigger condition.
+        #[builder(with = |table: LookupTable| Box::new(table))]
+        veto_table: Option<Box<LookupTable>>,
+        /// Grouping of the boards into the pattern seen by the MLU. The
+        /// identity mapping is used by default.
+        wire_mapping: Option<WireMapping>,
+        /// Inclusivity of the end of the prompt window and the wait gate. Both
+        /// are exclusive by default.
+        #[builder(default)]
+        boundary_policy: BoundaryPolicy,
+        /// Delay between the end of a prompt window and the TRG signal out of
+        /// the MLU. The drift veto and dead time of the TRG box use the delayed
+        /// time. No delay by default.
+        mlu_latency: Option<Positive<T>>,
         drift_veto: Positive<T>,
         scaledown: u32,
-        dead_time: Positive<T>,
+        #[builder(setters(vis = "", name = dead_time_internal))] dead_time: DeadTimeFn<'a, T>,
         observer: O,
+        /// Lookup tables to install during the run, in increasing order of
+        /// time. Each table is used for the decision of all prompt windows
+        /// that close at or after its switch time.
+        #[builder(default)]
+        table_switches: Vec<(T, LookupTable)>,
+        /// Clock of the MLU (see `mlu_clock_period`). Times are not quantized
+        /// by default.
+        #[builder(setters(vis = "", name = mlu_clock_internal))]
+        mlu_clock: Option<QuantizeFn<'a, T>>,
+        /// Clock of the TRG (see `trg_clock_period`). Times are not quantized
+        /// by default.
+        #[builder(setters(vis = "", name = trg_clock_internal))]
+        trg_clock: Option<QuantizeFn<'a, T>>,
+        /// Per-channel dead time of the discriminators. A board is cleared
+        /// from the pattern of a wire event if it already fired within this
+        /// time, and wire events without any boards left are dropped before
+        /// the MLU. Disabled by default.
+        channel_dead_time: Option<Positive<T>>,
+        /// Ignore (in the MLU) all wire events before the end of the dead time
+        /// of the TRG box, so that no prompt windows are opened while it is
+        /// busy. The events are still reported to the observer. Disabled by
+        /// default.
+        #[builder(default)]
+        mlu_respects_busy: bool,
     ) -> Self {
-        let mlu = Mlu::new(prompt_window, wait_gate, lookup_table);
-
+        let mut mlu =
+            Mlu::new(prompt_window, wait_gate, condition).with_boundary_policy(boundary_policy);
+        if let Some(veto_table) = veto_table {
+            mlu = mlu.with_veto_table(veto_table);
+        }
+        if let Some(wire_mapping) = wire_mapping {
+            mlu = mlu.with_wire_mapping(wire_mapping);
+        }
+        if let Some(mlu_latency) = mlu_latency {
+            mlu = mlu.with_latency(mlu_latency);
+        }
         Self {
-            generator,
             mlu,
             drift_veto,
             scaledown,
             dead_time,
             observer,
+            clocks: Clocks {
+                mlu: mlu_clock,
+                trg: trg_clock,
+            },
+            channels: channel_dead_time.map(ChannelDeadTime::new),
+            mlu_respects_busy,
+            table_switches: table_switches
+                .into_iter()
+                .map(|(time, table)| (time, TriggerCondition::from(table)))
+                .collect(),
             veto_until: None,
             busy_until: None,
             counter: 0,
+            events_since_trg_out: 0,
+            trg_out_count: 0,
+            exhausted: Vec::new(),
             prev_event: None,
+            pending: VecDeque::new(),
         }
     }
 }
 
-impl<T, O, S: world_builder::State> WorldBuilder<T, O, S> {
-    /// Add an event generator to the [`World`].
-    pub fn add_generator<G>(mut self, gen: G) -> Self
+impl<'a, T, O, S> TriggerBuilder<'a, T, O, S>
+where
+    S: trigger_builder::State,
+    S::Condition: trigger_builder::IsUnset,
+{
+    /// Use a [`LookupTable`] as the trigger condition of the MLU.
+    pub fn lookup_table(
+        self,
+        table: LookupTable,
+    ) -> TriggerBuilder<'a, T, O, trigger_builder::SetCondition<S>> {
+        self.condition_internal(table.into())
+    }
+    /// Set the trigger condition of the MLU e.g. a minimum multiplicity
+    /// instead of a [`LookupTable`].
+    pub fn trigger_condition(
+        self,
+        condition: TriggerCondition,
+    ) -> TriggerBuilder<'a, T, O, trigger_builder::SetCondition<S>> {
+        self.condition_internal(condition)
+    }
+}
+
+impl<'a, T, O, S> TriggerBuilder<'a, T, O, S>
+where
+    T: Quantize + 'a,
+    S: trigger_builder::State,
+{
+    /// Quantize the time of wire events to the ticks of the MLU clock (e.g.
+    /// 8 ns) before they go into the MLU.
+    pub fn mlu_clock_period(
+        self,
+        period: Positive<T>,
+    ) -> TriggerBuilder<'a, T, O, trigger_builder::SetMluClock<S>>
     where
-        G: EventGenerator<Time = T> + 'static,
+        S::MluClock: trigger_builder::IsUnset,
     {
-        self.generator.add_generator(gen);
-        self
+        self.mlu_clock_internal(Box::new(move |time| time.floor_to(period.inner())))
+    }
+    /// Quantize the time of TRG signals to the ticks of the TRG clock (e.g.
+    /// 16 ns) before they go through the drift veto and dead time. Observers
+    /// still see the original time of the signals.
+    pub fn trg_clock_period(
+        self,
+        period: Positive<T>,
+    ) -> TriggerBuilder<'a, T, O, trigger_builder::SetTrgClock<S>>
+    where
+        S::TrgClock: trigger_builder::IsUnset,
+    {
+        self.trg_clock_internal(Box::new(move |time| time.floor_to(period.inner())))
+    }
+}
+
+impl<'a, T, O, S> TriggerBuilder<'a, T, O, S>
+where
+    T: 'a,
+    S: trigger_builder::State,
+    S::DeadTime: trigger_builder::IsUnset,
+{
+    /// Set a constant dead time of the TRG box after each trigger sent to the
+    /// DAQ.
+    pub fn dead_time(
+        self,
+        dead_time: Positive<T>,
+    ) -> TriggerBuilder<'a, T, O, trigger_builder::SetDeadTime<S>>
+    where
+        T: Clone,
+    {
+        self.dead_time_internal(Box::new(move |_, _| dead_time.clone()))
+    }
+    /// Set a dead time that depends on the size of the event read out by the
+    /// DAQ. The closure is called for each trigger sent to the DAQ, together
+    /// with the number of wire events since the previous trigger output.
+    pub fn dead_time_fn<F>(self, f: F) -> TriggerBuilder<'a, T, O, trigger_builder::SetDeadTime<S>>
+    where
+        F: FnMut(&TrgSignal<T>, usize) -> Positive<T> + 'a,
+    {
+        self.dead_time_internal(Box::new(f))
     }
 }
 
-impl<T, O> World<T, O>
+impl<T, O> Trigger<'_, T, O>
 where
     T: Add<Output = T> + PartialOrd + Clone,
     O: Observer<Time = T>,
 {
-    /// Run a simulation of the trigger system until all generators are
-    /// exhausted. Note that if any of the provided generators are infinite,
-    /// this method will run forever.
-    pub fn run(mut self) -> O {
-        for event in self.generator {
-            // Needed for time-aware observers
-            if let Some(e) = self.prev_event {
-                self.observer.on_wire_event(&e);
-            }
-            self.prev_event = Some(event.clone());
+    fn process(&mut self, event: &WireEvent<T>) {
+        let masked = match &mut self.channels {
+            Some(channels) => channels.mask(event),
+            None => WirePattern(0),
+        };
+        if masked.0 != 0 && masked == event.wire_pattern {
+            // The event never reaches the MLU.
+            self.advance_to(&event.time);
+            self.observer.on_wire_event_masked(event, masked);
+            return;
+        }
+        let event = WireEvent {
+            wire_pattern: WirePattern(event.wire_pattern.0 & !masked.0),
+            ..event.clone()
+        };
 
-            let Some(trg_signal) = self.mlu.process(&event) else {
-                continue;
+        // Needed for time-aware observers
+        self.flush_prev_event();
+        self.prev_event = Some((event.clone(), masked));
+
+        self.switch_tables(Some(&event.time));
+        let time = event.time.clone();
+        let event = match &self.clocks.mlu {
+            Some(quantize) => WireEvent {
+                time: quantize(&event.time),
+                ..event
+            },
+            None => event,
+        };
+        let mut reaches_mlu = true;
+        if self.mlu_respects_busy {
+            // A prompt window closed before this event can make the TRG box
+            // busy, so it has to be decided first.
+            if let Some(decision) = self.mlu.close_window_before(&event.time) {
+                self.schedule_decision(decision);
+            }
+            self.release_decisions(Some(&time));
+            // Same clock and boundary as the dead time in `process_signal`.
+            let time = match &self.clocks.trg {
+                Some(quantize) => quantize(&time),
+                None => time.clone(),
             };
-            self.observer.on_trg_in(&trg_signal);
+            reaches_mlu = self
+                .busy_until
+                .as_ref()
+                .is_none_or(|busy_until| time > *busy_until);
+        }
+        if reaches_mlu {
+            if let Some(decision) = self.mlu.step(&event) {
+                self.schedule_decision(decision);
+            }
+        }
+        self.release_decisions(Some(&time));
+        // Counted only now because a signal at the same time is processed
+        // before this event.
+        self.events_since_trg_out += 1;
+    }
 
-            if let Some(veto_until) = &self.veto_until {
-                if trg_signal.time <= *veto_until {
-                    self.observer.on_trg_drift_veto(&trg_signal);
-                    continue;
-                }
+    // Apply all the table switches scheduled at or before `now` (all of them
+    // if `None`).
+    fn switch_tables(&mut self, now: Option<&T>) {
+        while self
+            .table_switches
+            .front()
+            .is_some_and(|(time, _)| now.is_none_or(|now| time <= now))
+        {
+            let (time, table) = self.table_switches.pop_front().unwrap();
+            // A prompt window that closed before the switch is decided with
+            // the previous table.
+            if let Some(decision) = self.mlu.close_window_before(&time) {
+                self.schedule_decision(decision);
             }
-            self.veto_until = Some(trg_signal.time.clone() + self.drift_veto.inner().clone());
+            self.mlu.set_condition(table);
+        }
+    }
 
-            if self.counter != self.scaledown {
-                self.observer.on_trg_scaledown(&trg_signal);
-                self.counter += 1;
-                continue;
+    // Report everything that happened before `time`, without processing any
+    // new wire event.
+    fn advance_to(&mut self, time: &T) {
+        self.flush_prev_event();
+        self.switch_tables(Some(time));
+        if let Some(decision) = self.mlu.close_window_before(time) {
+            self.schedule_decision(decision);
+        }
+        self.release_decisions(Some(time));
+    }
+
+    fn phase_start(&mut self, index: usize, time: T) {
+        // Everything that happened before the start of the phase has to be
+        // reported first.
+        self.advance_to(&time);
+        self.observer.on_phase_start(index, &time);
+    }
+
+    fn truncated(&mut self, time: T) {
+        self.advance_to(&time);
+        self.observer.on_truncated(&time);
+    }
+
+    fn schedule_decision(&mut self, decision: MluDecision<T>) {
+        self.pending.push_back(decision);
+    }
+
+    // Process all pending decisions at or before `now` (all of them if
+    // `None`). A signal is processed before any wire event at the same time.
+    fn release_decisions(&mut self, now: Option<&T>) {
+        while self
+            .pending
+            .front()
+            .is_some_and(|decision| now.is_none_or(|now| decision.time() <= now))
+        {
+            let decision = self.pending.pop_front().unwrap();
+            self.process_decision(decision);
+        }
+    }
+
+    fn process_decision(&mut self, decision: MluDecision<T>) {
+        match decision {
+            MluDecision::Accept(trg_signal) => self.process_signal(trg_signal),
+            MluDecision::Veto(trg_signal) => self.observer.on_mlu_veto(&trg_signal),
+        }
+    }
+
+    fn process_signal(&mut self, trg_signal: TrgSignal<T>) {
+        self.observer.on_trg_in(&trg_signal);
+        // Observers always see the original time (to keep them time-aware),
+        // but the TRG box only sees its clock ticks.
+        let time = match &self.clocks.trg {
+            Some(quantize) => quantize(&trg_signal.time),
+            None => trg_signal.time.clone(),
+        };
+
+        if let Some(veto_until) = &self.veto_until {
+            if time <= *veto_until {
+                self.observer.on_trg_drift_veto(&trg_signal);
+                return;
             }
-            self.counter = 0;
+        }
+        self.veto_until = Some(time.clone() + self.drift_veto.inner().clone());
 
-            if let Some(busy_until) = &self.busy_until {
-                if trg_signal.time <= *busy_until {
-                    self.observer.on_trg_dead_time(&trg_signal);
-                    continue;
-                }
+        if self.counter != self.scaledown {
+            self.observer.on_trg_scaledown(&trg_signal);
+            self.counter += 1;
+            return;
+        }
+        self.counter = 0;
+
+        if let Some(busy_until) = &self.busy_until {
+            if time <= *busy_until {
+                self.observer.on_trg_dead_time(&trg_signal);
+                return;
             }
-            self.observer.on_trg_out(&trg_signal);
-            self.busy_until = Some(trg_signal.time + self.dead_time.inner().clone());
         }
-        // Needed for time-aware observers
-        if let Some(e) = self.prev_event {
+        self.observer.on_trg_out(&trg_signal, self.trg_out_count);
+        self.trg_out_count += 1;
+        let dead_time = (self.dead_time)(&trg_signal, self.events_since_trg_out);
+        self.events_since_trg_out = 0;
+        self.busy_until = Some(time + dead_time.inner().clone());
+    }
+
+    fn flush_prev_event(&mut self) {
+        if let Some((e, masked)) = self.prev_event.take() {
+            if masked.0 != 0 {
+                let original = WireEvent {
+                    wire_pattern: e.wire_pattern | masked,
+                    ..e.clone()
+                };
+                self.observer.on_wire_event_masked(&original, masked);
+            }
             self.observer.on_wire_event(&e);
         }
+        self.flush_exhausted();
+    }
+
+    fn generator_exhausted(&mut self, generator: usize, reason: ExhaustionReason) {
+        self.exhausted.push((generator, reason));
+        // Nothing to wait for e.g. a generator that never produced an event.
+        if self.prev_event.is_none() {
+            self.flush_exhausted();
+        }
+    }
+
+    fn flush_exhausted(&mut self) {
+        for (generator, reason) in self.exhausted.drain(..) {
+            self.observer.on_generator_exhausted(generator, reason);
+        }
+    }
+
+    fn finish(mut self, generator_stats: &[GeneratorStats]) -> O {
+        // Needed for time-aware observers
+        self.flush_prev_event();
+        // A prompt window that is still open when the events run out is
+        // decided as if a later event had closed it.
+        self.switch_tables(None);
+        if let Some(decision) = self.mlu.close_window() {
+            self.schedule_decision(decision);
+        }
+        self.release_decisions(None);
+        self.observer.on_generator_stats(generator_stats);
+        self.observer.on_mlu_stats(&self.mlu.stats());
 
         self.observer
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use gen::*;
-    use std::iter::repeat;
+// The only place where an out-of-order wire event is reported.
+#[cold]
+#[track_caller]
+fn out_of_order(violation: String, generator: Option<usize>, prev_generator: Option<usize>) -> ! {
+    let attribution = |g: Option<usize>| match g {
+        Some(g) => format!("generator #{g}"),
+        None => String::from("unknown generator"),
+    };
+    panic!(
+        "{violation} (event from {}, previous event from {})",
+        attribution(generator),
+        attribution(prev_generator)
+    );
+}
 
-    #[derive(Default)]
-    struct TestObserver {
-        events: Vec<WireEvent<i32>>,
-        trg_in: Vec<TrgSignal<i32>>,
-        drift_veto: Vec<TrgSignal<i32>>,
-        scaledown: Vec<TrgSignal<i32>>,
-        dead_time: Vec<TrgSignal<i32>>,
-        trg_out: Vec<TrgSignal<i32>>,
+// The ordering check of an `EventStream`.
+struct OrderingCheck<T> {
+    checker: OrderingChecker<T>,
+    // Generator that produced the previous event.
+    prev_generator: Option<usize>,
+    // Formats a violation with both times. Only the construction of the
+    // check needs `T: Debug`, not the run.
+    describe: fn(&OrderingViolation<T>) -> String,
+}
+
+// All the wire events that go into the trigger system.
+struct EventStream<'a, T> {
+    generator: Generator<'a, T>,
+    // `None` if the ordering check is disabled.
+    ordering: Option<OrderingCheck<T>>,
+}
+
+impl<'a, T: Debug> EventStream<'a, T> {
+    fn new(generator: Generator<'a, T>, check_ordering: bool) -> Self {
+        Self {
+            generator,
+            ordering: check_ordering.then(|| OrderingCheck {
+                checker: OrderingChecker::default(),
+                prev_generator: None,
+                describe: |violation| violation.to_string(),
+            }),
+        }
     }
+}
 
-    impl Observer for TestObserver {
-        type Time = i32;
+impl<T: PartialOrd + Clone> Iterator for EventStream<'_, T> {
+    type Item = WireEvent<T>;
 
-        fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
-            self.events.push(*event);
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.generator.next()?;
+        if let Some(ordering) = &mut self.ordering {
+            let generator = self.generator.last_generator();
+            if let Err(violation) = ordering.checker.check(&event.time) {
+                out_of_order(
+                    (ordering.describe)(&violation),
+                    generator,
+                    ordering.prev_generator,
+                );
+            }
+            ordering.prev_generator = generator;
         }
 
-        fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
-            self.trg_in.push(*signal);
+        Some(event)
+    }
+}
+
+impl<T> EventStream<'_, T> {
+    fn drain_exhausted(&mut self) -> impl Iterator<Item = (usize, ExhaustionReason)> + '_ {
+        self.generator.drain_exhausted()
+    }
+}
+
+pub struct World<'a, T, O> {
+    events: EventStream<'a, T>,
+    trigger: Trigger<'a, T, O>,
+}
+
+impl<'a, T, O> World<'a, T, O> {
+    /// Create a builder of a [`World`] with a single [`Trigger`] pipeline.
+    pub fn builder() -> WorldBuilder<'a, T, O> {
+        WorldBuilder {
+            generator: Generator::default(),
+            check_ordering: cfg!(debug_assertions),
+            trigger: Trigger::builder(),
         }
+    }
+}
 
-        fn on_trg_drift_veto(&mut self, signal: &TrgSignal<Self::Time>) {
-            self.drift_veto.push(*signal);
+// Type states of a `WorldBuilder`, the same as those of the builder of its
+// `Trigger`.
+use trigger_builder as world_builder;
+
+/// Builder of a [`World`]. The options of the [`Trigger`] are forwarded to a
+/// [`TriggerBuilder`], and documented there.
+pub struct WorldBuilder<'a, T, O, S: world_builder::State = world_builder::Empty> {
+    generator: Generator<'a, T>,
+    check_ordering: bool,
+    trigger: TriggerBuilder<'a, T, O, S>,
+}
+
+impl<'a, T, O, S: world_builder::State> WorldBuilder<'a, T, O, S> {
+    /// Add an event generator to the [`World`].
+    ///
+    /// The generator can borrow data (e.g. an afterpulse library) as long as
+    /// it outlives the [`World`].
+    pub fn add_generator<G>(mut self, gen: G) -> Self
+    where
+        G: EventGenerator<Time = T> + 'a,
+    {
+        self.generator.add_generator(gen);
+        self
+    }
+    /// Set the master [`Seed`] of [`WorldBuilder::add_seeded_generator`].
+    pub fn seed(mut self, seed: impl Into<Seed>) -> Self {
+        self.generator.set_seed(seed.into());
+        self
+    }
+    /// Set how events of different generators with exactly the same time are
+    /// ordered. By default, they are produced in the order in which their
+    /// generators were added, so a run is deterministic.
+    pub fn tie_breaking(mut self, tie_breaking: TieBreaking) -> Self {
+        self.generator.set_tie_breaking(tie_breaking);
+        self
+    }
+    /// Stop the run at `max_time` even if some generators are not exhausted
+    /// (e.g. a generator without a `duration`), as a safety net against runs
+    /// that never end. Events at or after `max_time` are not produced, and the
+    /// observer is notified with [`Observer::on_truncated`]. Unlimited by
+    /// default.
+    pub fn max_time(mut self, max_time: T) -> Self {
+        self.generator.set_max_time(max_time);
+        self
+    }
+    /// Add the event generator returned by `f` to the [`World`].
+    ///
+    /// `f` is given the master seed derived (see [`Seed::derive`]) with the
+    /// index of the generator in order of addition. A whole simulation is then
+    /// reproducible from its master seed, as long as the generators are added
+    /// in the same order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`WorldBuilder::seed`] has not been called before.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::distr::Uniform;
+    /// use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+    /// use trg::mlu::LookupTable;
+    /// use trg::observers::Scalers;
+    /// use trg::World;
+    ///
+    /// let run = |seed| {
+    ///     World::builder()
+    ///         .seed(seed)
+    ///         .add_seeded_generator(|seed| {
+    ///             SecondaryGenerator::builder()
+    ///                 .source(Source::Noise)
+    ///                 .origin(0.0)
+    ///                 .duration(Positive::new(1000.0).unwrap())
+    ///                 .inter_arrival_distribution(
+    ///                     Uniform::new(1.0, 20.0).unwrap(),
+    ///                     seed.rng(),
+    ///                     |t| t,
+    ///                 )
+    ///                 .wire_pattern(std::iter::repeat(WirePattern::from_bits(1)))
+    ///                 .build()
+    ///         })
+    ///         .prompt_window(Positive::new(5.0).unwrap())
+    ///         .wait_gate(Positive::new(5.0).unwrap())
+    ///         .lookup_table(LookupTable::min_wires(1))
+    ///         .drift_veto(Positive::new(1.0).unwrap())
+    ///         .scaledown(0)
+    ///         .dead_time(Positive::new(1.0).unwrap())
+    ///         .observer(Scalers::default())
+    ///         .build()
+    ///         .run()
+    ///         .counts()
+    /// };
+    /// assert_eq!(run(7), run(7));
+    /// ```
+    pub fn add_seeded_generator<G, F>(mut self, f: F) -> Self
+    where
+        G: EventGenerator<Time = T> + 'a,
+        F: FnOnce(Seed) -> G,
+    {
+        self.generator.add_seeded_generator(f);
+        self
+    }
+    /// Verify that wire events reach the MLU in increasing order of time.
+    /// Enabled by default in debug builds only.
+    pub fn check_ordering(mut self, check_ordering: bool) -> Self {
+        self.check_ordering = check_ordering;
+        self
+    }
+    /// Finish building the [`World`]. Times have to be `Debug` for the
+    /// message of the ordering check (see [`World::run`]).
+    pub fn build(self) -> World<'a, T, O>
+    where
+        T: Debug,
+        S: world_builder::IsComplete,
+    {
+        World {
+            events: EventStream::new(self.generator, self.check_ordering),
+            trigger: self.trigger.build(),
         }
+    }
 
-        fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {
-            self.scaledown.push(*signal);
+    fn map_trigger<S2: world_builder::State>(
+        self,
+        f: impl FnOnce(TriggerBuilder<'a, T, O, S>) -> TriggerBuilder<'a, T, O, S2>,
+    ) -> WorldBuilder<'a, T, O, S2> {
+        WorldBuilder {
+            generator: self.generator,
+            check_ordering: self.check_ordering,
+            trigger: f(self.trigger),
         }
+    }
+}
 
-        fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {
-            self.dead_time.push(*signal);
+// Setters of `TriggerBuilder` that only need their member to be unset.
+macro_rules! forward_setters {
+    ($($setter:ident($value:ty) -> $set:ident<$member:ident>;)*) => {
+        impl<'a, T, O, S: world_builder::State> WorldBuilder<'a, T, O, S> {
+            $(
+                #[doc = concat!("Same as [`TriggerBuilder::", stringify!($setter), "`].")]
+                pub fn $setter(
+                    self,
+                    value: $value,
+                ) -> WorldBuilder<'a, T, O, world_builder::$set<S>>
+                where
+                    S::$member: world_builder::IsUnset,
+                {
+                    self.map_trigger(|trigger| trigger.$setter(value))
+                }
+            )*
         }
+    };
+}
+forward_setters! {
+    prompt_window(Positive<T>) -> SetPromptWindow<PromptWindow>;
+    wait_gate(Positive<T>) -> SetWaitGate<WaitGate>;
+    veto_table(LookupTable) -> SetVetoTable<VetoTable>;
+    maybe_veto_table(Option<LookupTable>) -> SetVetoTable<VetoTable>;
+    wire_mapping(WireMapping) -> SetWireMapping<WireMapping>;
+    maybe_wire_mapping(Option<WireMapping>) -> SetWireMapping<WireMapping>;
+    boundary_policy(BoundaryPolicy) -> SetBoundaryPolicy<BoundaryPolicy>;
+    maybe_boundary_policy(Option<BoundaryPolicy>) -> SetBoundaryPolicy<BoundaryPolicy>;
+    mlu_latency(Positive<T>) -> SetMluLatency<MluLatency>;
+    maybe_mlu_latency(Option<Positive<T>>) -> SetMluLatency<MluLatency>;
+    drift_veto(Positive<T>) -> SetDriftVeto<DriftVeto>;
+    scaledown(u32) -> SetScaledown<Scaledown>;
+    observer(O) -> SetObserver<Observer>;
+    table_switches(Vec<(T, LookupTable)>) -> SetTableSwitches<TableSwitches>;
+    maybe_table_switches(Option<Vec<(T, LookupTable)>>) -> SetTableSwitches<TableSwitches>;
+    channel_dead_time(Positive<T>) -> SetChannelDeadTime<ChannelDeadTime>;
+    maybe_channel_dead_time(Option<Positive<T>>) -> SetChannelDeadTime<ChannelDeadTime>;
+    mlu_respects_busy(bool) -> SetMluRespectsBusy<MluRespectsBusy>;
+    maybe_mlu_respects_busy(Option<bool>) -> SetMluRespectsBusy<MluRespectsBusy>;
+}
 
-        fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>) {
-            self.trg_out.push(*signal);
+impl<'a, T, O, S> WorldBuilder<'a, T, O, S>
+where
+    S: world_builder::State,
+    S::Condition: world_builder::IsUnset,
+{
+    /// Same as [`TriggerBuilder::lookup_table`].
+    pub fn lookup_table(
+        self,
+        table: LookupTable,
+    ) -> WorldBuilder<'a, T, O, world_builder::SetCondition<S>> {
+        self.map_trigger(|trigger| trigger.lookup_table(table))
+    }
+    /// Same as [`TriggerBuilder::trigger_condition`].
+    pub fn trigger_condition(
+        self,
+        condition: TriggerCondition,
+    ) -> WorldBuilder<'a, T, O, world_builder::SetCondition<S>> {
+        self.map_trigger(|trigger| trigger.trigger_condition(condition))
+    }
+}
+
+impl<'a, T, O, S> WorldBuilder<'a, T, O, S>
+where
+    T: Quantize + 'a,
+    S: world_builder::State,
+{
+    /// Same as [`TriggerBuilder::mlu_clock_period`].
+    pub fn mlu_clock_period(
+        self,
+        period: Positive<T>,
+    ) -> WorldBuilder<'a, T, O, world_builder::SetMluClock<S>>
+    where
+        S::MluClock: world_builder::IsUnset,
+    {
+        self.map_trigger(|trigger| trigger.mlu_clock_period(period))
+    }
+    /// Same as [`TriggerBuilder::trg_clock_period`].
+    pub fn trg_clock_period(
+        self,
+        period: Positive<T>,
+    ) -> WorldBuilder<'a, T, O, world_builder::SetTrgClock<S>>
+    where
+        S::TrgClock: world_builder::IsUnset,
+    {
+        self.map_trigger(|trigger| trigger.trg_clock_period(period))
+    }
+}
+
+impl<'a, T, O, S> WorldBuilder<'a, T, O, S>
+where
+    T: 'a,
+    S: world_builder::State,
+    S::DeadTime: world_builder::IsUnset,
+{
+    /// Same as [`TriggerBuilder::dead_time`].
+    pub fn dead_time(
+        self,
+        dead_time: Positive<T>,
+    ) -> WorldBuilder<'a, T, O, world_builder::SetDeadTime<S>>
+    where
+        T: Clone,
+    {
+        self.map_trigger(|trigger| trigger.dead_time(dead_time))
+    }
+    /// Same as [`TriggerBuilder::dead_time_fn`].
+    pub fn dead_time_fn<F>(self, f: F) -> WorldBuilder<'a, T, O, world_builder::SetDeadTime<S>>
+    where
+        F: FnMut(&TrgSignal<T>, usize) -> Positive<T> + 'a,
+    {
+        self.map_trigger(|trigger| trigger.dead_time_fn(f))
+    }
+}
+
+impl<'a, T, O> World<'a, T, O>
+where
+    T: Add<Output = T> + PartialOrd + Clone,
+    O: Observer<Time = T>,
+{
+    /// Run a simulation of the trigger system until all generators are
+    /// exhausted. Note that if any of the provided generators are infinite,
+    /// this method will run forever unless a `max_time` is set (see
+    /// [`WorldBuilder::max_time`]).
+    ///
+    /// A prompt window that is still open after the last wire event is
+    /// decided at the end of the run (see [`Mlu::flush`]).
+    ///
+    /// # Panics
+    ///
+    /// If the ordering check is enabled (see `check_ordering` in the builder),
+    /// panics when a wire event is earlier than the previous one. The message
+    /// includes both times and the generators (numbered in the order they
+    /// were added) that produced the events.
+    pub fn run(self) -> O {
+        self.run_with_phases(Vec::new())
+    }
+    /// Run a simulation of the trigger system through a sequence of
+    /// back-to-back [`Phase`]s (e.g. cosmic background followed by a mixing
+    /// window), on top of the generators added to the [`World`].
+    ///
+    /// The first phase starts at time zero, and each phase starts when the
+    /// previous one ends. The state of the TRG box (e.g. dead time, drift
+    /// veto, scaledown counter) carries over from one phase to the next.
+    ///
+    /// # Panics
+    ///
+    /// Same as [`World::run`].
+    pub fn run_phases<I>(mut self, phases: I) -> O
+    where
+        I: IntoIterator<Item = Phase<'a, T>>,
+        T: Zero + 'a,
+    {
+        let mut start = T::zero();
+        let mut starts = Vec::new();
+        let mut events = VecDeque::new();
+        for phase in phases {
+            let end = start.clone() + phase.duration.inner().clone();
+            starts.push(start.clone());
+            events.push_back((start, end.clone(), phase.generator));
+            start = end;
         }
+        self.events.generator.add_generator(PhaseEvents(events));
+
+        self.run_with_phases(starts)
     }
 
-    #[test]
-    fn world_generator() {
-        let noise1 = SecondaryGenerator::builder()
+    fn run_with_phases(mut self, phase_starts: Vec<T>) -> O {
+        let mut phase_starts = phase_starts.into_iter().enumerate().peekable();
+        loop {
+            for (generator, reason) in self.events.drain_exhausted() {
+                self.trigger.generator_exhausted(generator, reason);
+            }
+            let Some(event) = self.events.next() else {
+                break;
+            };
+            while let Some((index, start)) = phase_starts.next_if(|(_, start)| *start <= event.time)
+            {
+                self.trigger.phase_start(index, start);
+            }
+            self.trigger.process(&event);
+        }
+        // Phases after the end of a truncated run never start.
+        let truncated = self.events.generator.truncated().cloned();
+        for (index, start) in phase_starts {
+            if truncated.as_ref().is_some_and(|time| start >= *time) {
+                break;
+            }
+            self.trigger.phase_start(index, start);
+        }
+        if let Some(time) = truncated {
+            self.trigger.truncated(time);
+        }
+
+        self.trigger.finish(self.events.generator.stats())
+    }
+}
+
+/// A period of time with its own set of generators. See
+/// [`World::run_phases`].
+pub struct Phase<'a, T> {
+    generator: Generator<'a, T>,
+    duration: Positive<T>,
+}
+
+#[bon]
+impl<'a, T> Phase<'a, T> {
+    #[builder]
+    pub fn new(
+        #[builder(field)] generator: Generator<'a, T>,
+        /// Length of the phase. Events produced after the end of the phase
+        /// are discarded.
+        duration: Positive<T>,
+    ) -> Self {
+        Self {
+            generator,
+            duration,
+        }
+    }
+}
+
+impl<'a, T, S: phase_builder::State> PhaseBuilder<'a, T, S> {
+    /// Add an event generator to the [`Phase`]. Times (e.g. the `origin` of
+    /// the generator) are relative to the start of the phase.
+    pub fn add_generator<G>(mut self, gen: G) -> Self
+    where
+        G: EventGenerator<Time = T> + 'a,
+    {
+        self.generator.add_generator(gen);
+        self
+    }
+    /// Set the master [`Seed`] of [`PhaseBuilder::add_seeded_generator`]. Use
+    /// a different seed for each phase (e.g. [`Seed::derive`] with the index
+    /// of the phase); otherwise the generators of all phases are correlated.
+    pub fn seed(mut self, seed: impl Into<Seed>) -> Self {
+        self.generator.set_seed(seed.into());
+        self
+    }
+    /// Set how events of the generators of the [`Phase`] with exactly the
+    /// same time are ordered. Same as [`WorldBuilder::tie_breaking`]; events
+    /// of a phase with the same time as events of the generators of the
+    /// [`World`] follow the [`World`] setting.
+    pub fn tie_breaking(mut self, tie_breaking: TieBreaking) -> Self {
+        self.generator.set_tie_breaking(tie_breaking);
+        self
+    }
+    /// Add the event generator returned by `f` to the [`Phase`]. Same as
+    /// [`WorldBuilder::add_seeded_generator`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`PhaseBuilder::seed`] has not been called before.
+    pub fn add_seeded_generator<G, F>(mut self, f: F) -> Self
+    where
+        G: EventGenerator<Time = T> + 'a,
+        F: FnOnce(Seed) -> G,
+    {
+        self.generator.add_seeded_generator(f);
+        self
+    }
+}
+
+// The events of all phases, shifted to the start of their phase:
+// (start, end, generator)
+struct PhaseEvents<'a, T>(VecDeque<(T, T, Generator<'a, T>)>);
+
+impl<T: Add<Output = T> + PartialOrd + Clone> Iterator for PhaseEvents<'_, T> {
+    type Item = WireEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (start, end, generator) = self.0.front_mut()?;
+            if let Some(mut event) = generator.next() {
+                event.time = start.clone() + event.time;
+                if event.time < *end {
+                    return Some(event);
+                }
+            }
+            // Events are in order, so the rest of the phase is also past its
+            // end.
+            let _ = self.0.pop_front();
+        }
+    }
+}
+
+impl<T: Add<Output = T> + PartialOrd + Clone> sealed::OrderedIterator for PhaseEvents<'_, T> {
+    fn spawned_last(&self) -> bool {
+        self.0
+            .front()
+            .is_some_and(|(_, _, generator)| generator.spawned_last())
+    }
+}
+
+/// Multiple independent [`Trigger`] pipelines fed with the same wire events.
+///
+/// This is equivalent to running one [`World`] per pipeline on identical
+/// input, but the events are generated only once and streamed to all
+/// pipelines.
+pub struct MultiWorld<'a, T, O> {
+    events: EventStream<'a, T>,
+    triggers: Vec<Trigger<'a, T, O>>,
+}
+
+#[bon]
+impl<'a, T: Debug, O> MultiWorld<'a, T, O> {
+    #[builder]
+    pub fn new(
+        #[builder(field)] generator: Generator<'a, T>,
+        #[builder(field)] triggers: Vec<Trigger<'a, T, O>>,
+        /// Verify that wire events reach the MLUs in increasing order of time.
+        /// Enabled by default in debug builds only.
+        #[builder(default = cfg!(debug_assertions))]
+        check_ordering: bool,
+    ) -> Self {
+        Self {
+            events: EventStream::new(generator, check_ordering),
+            triggers,
+        }
+    }
+}
+
+impl<'a, T: Debug, O, S: multi_world_builder::State> MultiWorldBuilder<'a, T, O, S> {
+    /// Add an event generator to the [`MultiWorld`].
+    pub fn add_generator<G>(mut self, gen: G) -> Self
+    where
+        G: EventGenerator<Time = T> + 'a,
+    {
+        self.generator.add_generator(gen);
+        self
+    }
+    /// Set the master [`Seed`] of [`MultiWorldBuilder::add_seeded_generator`].
+    pub fn seed(mut self, seed: impl Into<Seed>) -> Self {
+        self.generator.set_seed(seed.into());
+        self
+    }
+    /// Set how events of different generators with exactly the same time are
+    /// ordered. Same as [`WorldBuilder::tie_breaking`].
+    pub fn tie_breaking(mut self, tie_breaking: TieBreaking) -> Self {
+        self.generator.set_tie_breaking(tie_breaking);
+        self
+    }
+    /// Stop the run at `max_time` even if some generators are not exhausted.
+    /// Same as [`WorldBuilder::max_time`].
+    pub fn max_time(mut self, max_time: T) -> Self {
+        self.generator.set_max_time(max_time);
+        self
+    }
+    /// Add the event generator returned by `f` to the [`MultiWorld`]. Same as
+    /// [`WorldBuilder::add_seeded_generator`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MultiWorldBuilder::seed`] has not been called before.
+    pub fn add_seeded_generator<G, F>(mut self, f: F) -> Self
+    where
+        G: EventGenerator<Time = T> + 'a,
+        F: FnOnce(Seed) -> G,
+    {
+        self.generator.add_seeded_generator(f);
+        self
+    }
+    /// Add a trigger pipeline to the [`MultiWorld`].
+    pub fn add_trigger(mut self, trigger: Trigger<'a, T, O>) -> Self {
+        self.triggers.push(trigger);
+        self
+    }
+}
+
+impl<T, O> MultiWorld<'_, T, O>
+where
+    T: Add<Output = T> + PartialOrd + Clone,
+    O: Observer<Time = T>,
+{
+    /// Run a simulation of all trigger pipelines until all generators are
+    /// exhausted. Returns the observers in the order in which the pipelines
+    /// were added.
+    ///
+    /// # Panics
+    ///
+    /// Same as [`World::run`].
+    pub fn run(mut self) -> Vec<O> {
+        loop {
+            for (generator, reason) in self.events.drain_exhausted() {
+                for trigger in &mut self.triggers {
+                    trigger.generator_exhausted(generator, reason);
+                }
+            }
+            let Some(event) = self.events.next() else {
+                break;
+            };
+            for trigger in &mut self.triggers {
+                trigger.process(&event);
+            }
+        }
+        if let Some(time) = self.events.generator.truncated() {
+            for trigger in &mut self.triggers {
+                trigger.truncated(time.clone());
+            }
+        }
+
+        let stats = self.events.generator.stats();
+        self.triggers
+            .into_iter()
+            .map(|trigger| trigger.finish(stats))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gen::*;
+    use mlu::{Boundary, TriggerMenu};
+    use std::iter::{repeat, zip};
+
+    #[derive(Default)]
+    struct TestObserver {
+        events: Vec<WireEvent<i32>>,
+        mlu_veto: Vec<TrgSignal<i32>>,
+        // (wire events seen so far, original event, masked boards)
+        masked: Vec<(usize, WireEvent<i32>, WirePattern)>,
+        trg_in: Vec<TrgSignal<i32>>,
+        drift_veto: Vec<TrgSignal<i32>>,
+        scaledown: Vec<TrgSignal<i32>>,
+        dead_time: Vec<TrgSignal<i32>>,
+        trg_out: Vec<TrgSignal<i32>>,
+        // (wire events seen so far, generator, reason)
+        exhausted: Vec<(usize, usize, ExhaustionReason)>,
+        // (wire events seen so far, phase, start time)
+        phases: Vec<(usize, usize, i32)>,
+        // (wire events seen so far, max time)
+        truncated: Vec<(usize, i32)>,
+        generator_stats: Vec<GeneratorStats>,
+        mlu_stats: Option<MluStats>,
+    }
+
+    impl Observer for TestObserver {
+        type Time = i32;
+
+        fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+            self.events.push(*event);
+        }
+
+        fn on_wire_event_masked(&mut self, event: &WireEvent<Self::Time>, masked: WirePattern) {
+            self.masked.push((self.events.len(), *event, masked));
+        }
+
+        fn on_mlu_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+            self.mlu_veto.push(*signal);
+        }
+
+        fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
+            self.trg_in.push(*signal);
+        }
+
+        fn on_trg_drift_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+            self.drift_veto.push(*signal);
+        }
+
+        fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {
+            self.scaledown.push(*signal);
+        }
+
+        fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {
+            self.dead_time.push(*signal);
+        }
+
+        fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, number: u64) {
+            assert_eq!(number, u64::try_from(self.trg_out.len()).unwrap());
+            self.trg_out.push(*signal);
+        }
+
+        fn on_generator_exhausted(&mut self, generator: usize, reason: ExhaustionReason) {
+            self.exhausted.push((self.events.len(), generator, reason));
+        }
+
+        fn on_phase_start(&mut self, index: usize, time: &Self::Time) {
+            self.phases.push((self.events.len(), index, *time));
+        }
+
+        fn on_truncated(&mut self, time: &Self::Time) {
+            self.truncated.push((self.events.len(), *time));
+        }
+
+        fn on_generator_stats(&mut self, stats: &[GeneratorStats]) {
+            self.generator_stats = stats.to_vec();
+        }
+
+        fn on_mlu_stats(&mut self, stats: &MluStats) {
+            self.mlu_stats = Some(*stats);
+        }
+    }
+
+    fn seeded_trg_out(seed: u64) -> Vec<i32> {
+        use rand::distr::{Distribution, Uniform};
+        use rand::seq::IndexedRandom;
+        use rand_distr::Exp;
+
+        let cosmic = |seed: Seed| {
+            let mut afterpulse_rng = seed.derive(2).rng();
+            PrimaryGenerator::builder()
+                .source(Source::PrimaryCosmic)
+                .origin(0)
+                .duration(Positive::new(10_000).unwrap())
+                .inter_arrival_time(
+                    Exp::new(0.01)
+                        .unwrap()
+                        .map(|t: f64| Positive::new(t.ceil() as i32 + 1).unwrap())
+                        .sample_iter(seed.derive(0).rng()),
+                )
+                .wire_pattern(
+                    Uniform::new_inclusive(0, u16::MAX)
+                        .unwrap()
+                        .map(WirePattern::from_bits)
+                        .sample_iter(seed.derive(1).rng()),
+                )
+                .afterpulse(move |event: &WireEvent<i32>| {
+                    SecondaryGenerator::builder()
+                        .source(Source::SecondaryCosmic)
+                        .inter_arrival_time(
+                            [[3, 10], [5, 7]]
+                                .choose(&mut afterpulse_rng)
+                                .unwrap()
+                                .map(|t| Positive::new(t).unwrap()),
+                        )
+                        .wire_pattern(repeat(event.wire_pattern))
+                })
+                .build()
+        };
+        let noise = |seed: Seed| {
+            SecondaryGenerator::builder()
+                .source(Source::Noise)
+                .origin(0)
+                .duration(Positive::new(10_000).unwrap())
+                .inter_arrival_time(
+                    Exp::new(0.05)
+                        .unwrap()
+                        .map(|t: f64| Positive::new(t.ceil() as i32 + 1).unwrap())
+                        .sample_iter(seed.rng()),
+                )
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .build()
+        };
+
+        let observer = World::builder()
+            .seed(seed)
+            .add_seeded_generator(cosmic)
+            .add_seeded_generator(noise)
+            .prompt_window(Positive::new(5).unwrap())
+            .wait_gate(Positive::new(5).unwrap())
+            .lookup_table(LookupTable::min_wires(3))
+            .drift_veto(Positive::new(20).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(50).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        observer.trg_out.into_iter().map(|s| s.time).collect()
+    }
+
+    #[test]
+    fn world_seeded_generators() {
+        let trg_out = seeded_trg_out(1);
+        assert!(trg_out.len() > 10);
+        assert_eq!(trg_out, seeded_trg_out(1));
+        assert_ne!(trg_out, seeded_trg_out(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "a seed must be set before adding seeded generators")]
+    fn world_seeded_generator_without_seed() {
+        let _ = World::<i32, TestObserver>::builder().add_seeded_generator(|_| {
+            ScriptedGenerator::from_times(Source::Noise, [], WirePattern::from_bits(1))
+        });
+    }
+
+    #[test]
+    fn world_generator() {
+        let noise1 = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(3).unwrap())
+            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let noise2 = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(1)
+            .duration(Positive::new(3).unwrap())
+            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+
+        let observer = World::builder()
+            .add_generator(noise1)
+            .add_generator(noise2)
+            .prompt_window(Positive::new(100).unwrap())
+            .wait_gate(Positive::new(100).unwrap())
+            .lookup_table(LookupTable::default())
+            .drift_veto(Positive::new(100).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(100).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        assert_eq!(
+            observer
+                .events
+                .into_iter()
+                .map(|e| e.time)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn world_max_time() {
+        let run = |max_time| {
+            // No duration.
+            let noise = SecondaryGenerator::builder()
+                .source(Source::Noise)
+                .origin(0)
+                .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(1)))
+                .build();
+            let cosmic = ScriptedGenerator::from_times(
+                Source::PrimaryCosmic,
+                [5, 15],
+                WirePattern::from_bits(2),
+            );
+            World::builder()
+                .add_generator(noise)
+                .add_generator(cosmic)
+                .max_time(max_time)
+                .prompt_window(Positive::new(2).unwrap())
+                .wait_gate(Positive::new(2).unwrap())
+                .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+                .drift_veto(Positive::new(1).unwrap())
+                .scaledown(0)
+                .dead_time(Positive::new(1).unwrap())
+                .observer(TestObserver::default())
+                .build()
+                .run()
+        };
+
+        let observer = run(50);
+        let times: Vec<_> = observer.events.iter().map(|event| event.time).collect();
+        assert_eq!(times, [5, 10, 15, 20, 30, 40]);
+        assert_eq!(observer.truncated, [(6, 50)]);
+        assert_eq!(observer.trg_out.len(), 4);
+        // The infinite generator is dropped, not exhausted.
+        assert!(observer.exhausted.is_empty());
+        assert_eq!(observer.generator_stats[0].primary_events, 4);
+
+        // The end time is exclusive.
+        let observer = run(40);
+        assert_eq!(observer.events.last().unwrap().time, 30);
+        assert_eq!(observer.truncated, [(5, 40)]);
+    }
+
+    #[test]
+    fn world_max_time_not_reached() {
+        let observer = World::builder()
+            .add_generator(ScriptedGenerator::from_times(
+                Source::Noise,
+                [1, 2],
+                WirePattern::from_bits(1),
+            ))
+            .max_time(10)
+            .prompt_window(Positive::new(2).unwrap())
+            .wait_gate(Positive::new(2).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run_phases([Phase::builder()
+                .add_generator(ScriptedGenerator::from_times(
+                    Source::Noise,
+                    [5],
+                    WirePattern::from_bits(1),
+                ))
+                .duration(Positive::new(20).unwrap())
+                .build()]);
+
+        assert_eq!(observer.events.len(), 3);
+        assert!(observer.truncated.is_empty());
+    }
+
+    #[test]
+    fn world_max_time_phases() {
+        let phase = |time| {
+            Phase::builder()
+                .add_generator(
+                    SecondaryGenerator::builder()
+                        .source(Source::Noise)
+                        .origin(0)
+                        .inter_arrival_time(repeat(Positive::new(time).unwrap()))
+                        .wire_pattern(repeat(WirePattern::from_bits(1)))
+                        .build(),
+                )
+                .duration(Positive::new(100).unwrap())
+                .build()
+        };
+        let observer = World::builder()
+            .max_time(150)
+            .prompt_window(Positive::new(2).unwrap())
+            .wait_gate(Positive::new(2).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run_phases([phase(30), phase(40), phase(50)]);
+
+        let times: Vec<_> = observer.events.iter().map(|event| event.time).collect();
+        assert_eq!(times, [30, 60, 90, 140]);
+        // The third phase never starts.
+        assert_eq!(observer.phases, [(0, 0, 0), (3, 1, 100)]);
+        assert_eq!(observer.truncated, [(4, 150)]);
+    }
+
+    #[test]
+    fn multi_world_max_time() {
+        let trigger = || {
+            Trigger::builder()
+                .prompt_window(Positive::new(2).unwrap())
+                .wait_gate(Positive::new(2).unwrap())
+                .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+                .drift_veto(Positive::new(1).unwrap())
+                .scaledown(0)
+                .dead_time(Positive::new(1).unwrap())
+                .observer(TestObserver::default())
+                .build()
+        };
+        let observers = MultiWorld::builder()
+            .add_generator(
+                SecondaryGenerator::builder()
+                    .source(Source::Noise)
+                    .origin(0)
+                    .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(1)))
+                    .build(),
+            )
+            .max_time(35)
+            .add_trigger(trigger())
+            .add_trigger(trigger())
+            .build()
+            .run();
+
+        for observer in observers {
+            assert_eq!(observer.events.len(), 3);
+            assert_eq!(observer.truncated, [(3, 35)]);
+        }
+    }
+
+    #[test]
+    fn world_tie_breaking() {
+        let run = |tie_breaking| {
+            let cosmic = ScriptedGenerator::from_times(
+                Source::PrimaryCosmic,
+                [0, 0, 5, 20],
+                WirePattern::from_bits(1),
+            );
+            let noise = ScriptedGenerator::from_times(
+                Source::Noise,
+                [0, 5, 5, 20],
+                WirePattern::from_bits(2),
+            );
+            World::builder()
+                .add_generator(cosmic)
+                .add_generator(noise)
+                .tie_breaking(tie_breaking)
+                .prompt_window(Positive::new(10).unwrap())
+                .wait_gate(Positive::new(10).unwrap())
+                .lookup_table(LookupTable::from([WirePattern::from_bits(3)]))
+                .drift_veto(Positive::new(1).unwrap())
+                .scaledown(0)
+                .dead_time(Positive::new(1).unwrap())
+                .observer(TestObserver::default())
+                .build()
+                .run()
+        };
+        let sources = |observer: &TestObserver| {
+            observer
+                .events
+                .iter()
+                .map(|event| event.source)
+                .collect::<Vec<_>>()
+        };
+        let trg_in = |observer: &TestObserver| {
+            observer
+                .trg_in
+                .iter()
+                .map(|signal| signal.time)
+                .collect::<Vec<_>>()
+        };
+
+        let observer = run(TieBreaking::InsertionOrder);
+        assert_eq!(
+            sources(&observer),
+            [
+                Source::PrimaryCosmic,
+                Source::PrimaryCosmic,
+                Source::Noise,
+                Source::PrimaryCosmic,
+                Source::Noise,
+                Source::Noise,
+                Source::PrimaryCosmic,
+                Source::Noise
+            ]
+        );
+        assert_eq!(trg_in(&observer), [10, 30]);
+        let again = run(TieBreaking::InsertionOrder);
+        assert_eq!(sources(&again), sources(&observer));
+        assert_eq!(trg_in(&again), trg_in(&observer));
+
+        let random = run(TieBreaking::Random(Seed::new(3)));
+        assert_eq!(
+            sources(&random),
+            sources(&run(TieBreaking::Random(Seed::new(3))))
+        );
+        // The decisions do not depend on the order of simultaneous events.
+        assert_eq!(trg_in(&random), trg_in(&observer));
+    }
+
+    #[test]
+    fn world_mapped_time() {
+        use uom::si::f64::Time;
+        use uom::si::time::nanosecond;
+
+        #[derive(Default)]
+        struct TimeObserver {
+            events: Vec<(Source, f64)>,
+            trg_out: Vec<f64>,
+        }
+
+        impl Observer for TimeObserver {
+            type Time = Time;
+
+            fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+                self.events
+                    .push((event.source, event.time.get::<nanosecond>().round()));
+            }
+
+            fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, _number: u64) {
+                self.trg_out.push(signal.time.get::<nanosecond>().round());
+            }
+        }
+
+        let ns = Time::new::<nanosecond>;
+        // Integer ticks of 16 ns from a firmware dump.
+        let ticks = ScriptedGenerator::from_times(
+            Source::Noise,
+            [10u64, 100],
+            WirePattern::from_bits(0b01),
+        );
+        let cosmics = ScriptedGenerator::from_times(
+            Source::PrimaryCosmic,
+            [ns(170.0), ns(1000.0)],
+            WirePattern::from_bits(0b10),
+        );
+
+        let observer = World::builder()
+            .add_generator(ticks.map_time(|t| ns(t as f64 * 16.0)))
+            .add_generator(cosmics)
+            .prompt_window(Positive::new(ns(20.0)).unwrap())
+            .wait_gate(Positive::new(ns(100.0)).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(0b11)]))
+            .drift_veto(Positive::new(ns(1.0)).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(ns(1.0)).unwrap())
+            .observer(TimeObserver::default())
+            .build()
+            .run();
+
+        assert_eq!(
+            observer.events,
+            [
+                (Source::Noise, 160.0),
+                (Source::PrimaryCosmic, 170.0),
+                (Source::PrimaryCosmic, 1000.0),
+                (Source::Noise, 1600.0),
+            ]
+        );
+        // Only the first noise event has a cosmic within its prompt window.
+        assert_eq!(observer.trg_out.len(), 1);
+    }
+
+    #[test]
+    fn world_coalesced_events() {
+        fn run(gen: impl EventGenerator<Time = i32>) -> TestObserver {
+            World::builder()
+                .add_generator(gen)
+                .prompt_window(Positive::new(2).unwrap())
+                .wait_gate(Positive::new(10).unwrap())
+                .lookup_table(LookupTable::from([WirePattern::from_bits(0b11)]))
+                .drift_veto(Positive::new(1).unwrap())
+                .scaledown(0)
+                .dead_time(Positive::new(1).unwrap())
+                .observer(TestObserver::default())
+                .build()
+                .run()
+        }
+        // The second half of each pair is too late for the prompt window
+        // opened by the first one, unless they are merged.
+        let raw = || {
+            ScriptedGenerator::new(
+                [0, 2, 20, 22]
+                    .map(|time| WireEvent {
+                        source: Source::Noise,
+                        wire_pattern: WirePattern::from_bits(if time % 20 == 0 {
+                            0b01
+                        } else {
+                            0b10
+                        }),
+                        time,
+                        id: None,
+                        weight: 1.0,
+                    })
+                    .to_vec(),
+            )
+        };
+
+        let observer = run(raw());
+        assert_eq!(observer.events.len(), 4);
+        assert!(observer.trg_in.is_empty());
+
+        let observer = run(raw().coalesce(Positive::new(3).unwrap(), CoalescePolicy::First));
+        assert_eq!(
+            observer
+                .events
+                .iter()
+                .map(|event| (event.time, event.wire_pattern.bits()))
+                .collect::<Vec<_>>(),
+            [(0, 0b11), (20, 0b11)]
+        );
+        assert_eq!(
+            observer
+                .trg_in
+                .iter()
+                .map(|signal| signal.time)
+                .collect::<Vec<_>>(),
+            [2, 22]
+        );
+    }
+
+    #[test]
+    fn world_borrowed_generator() {
+        // Owned by the caller, and only borrowed by the generator.
+        let inter_arrival_times: Vec<f64> = vec![2.0, 3.0, 0.5];
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .duration(Positive::new(5.25).unwrap())
+            .inter_arrival_time(
+                inter_arrival_times
+                    .as_slice()
+                    .iter()
+                    .map(|&t| Positive::new(t).unwrap()),
+            )
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+
+        let counts = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(100.0).unwrap())
+            .wait_gate(Positive::new(100.0).unwrap())
+            .lookup_table(LookupTable::default())
+            .drift_veto(Positive::new(100.0).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(100.0).unwrap())
+            .observer(observers::Scalers::default())
+            .build()
+            .run()
+            .counts();
+
+        // Events at 2 and 5; the one at 5.5 is after the duration.
+        assert_eq!(counts.wire_events, 2);
+        // Still usable by the caller after the run.
+        assert_eq!(inter_arrival_times.len(), 3);
+    }
+
+    #[test]
+    fn world_generator_exhausted() {
+        let short_pattern = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
+            .wire_pattern(vec![WirePattern::from_bits(1); 2])
+            .build();
+        let finite_duration = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(1)
+            .duration(Positive::new(4).unwrap())
+            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let empty = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(Vec::new())
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+
+        let observer = World::builder()
+            .add_generator(short_pattern)
+            .add_generator(finite_duration)
+            .add_generator(empty)
+            .prompt_window(Positive::new(100).unwrap())
+            .wait_gate(Positive::new(100).unwrap())
+            .lookup_table(LookupTable::default())
+            .drift_veto(Positive::new(100).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(100).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        assert_eq!(
+            observer.exhausted,
+            vec![
+                (0, 2, ExhaustionReason::InterArrivalTime),
+                (2, 1, ExhaustionReason::Duration),
+                (3, 0, ExhaustionReason::WirePattern),
+            ]
+        );
+    }
+
+    #[test]
+    fn world_run_phases() {
+        let cosmic = SecondaryGenerator::builder()
+            .source(Source::PrimaryCosmic)
+            .origin(0)
+            .inter_arrival_time(vec![Positive::new(8).unwrap()])
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let mixing = SecondaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let phases = vec![
+            Phase::builder()
+                .add_generator(cosmic)
+                .duration(Positive::new(10).unwrap())
+                .build(),
+            Phase::builder()
+                .add_generator(mixing)
+                .duration(Positive::new(5).unwrap())
+                .build(),
+            Phase::builder().duration(Positive::new(5).unwrap()).build(),
+        ];
+
+        let observer = World::builder()
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(7).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run_phases(phases);
+
+        assert_eq!(
+            observer
+                .events
+                .into_iter()
+                .map(|e| e.time)
+                .collect::<Vec<_>>(),
+            vec![8, 13]
+        );
+        assert_eq!(observer.phases, vec![(0, 0, 0), (1, 1, 10), (2, 2, 15)]);
+        assert_eq!(
+            observer
+                .trg_out
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![9]
+        );
+        // The dead time after the last trigger of the first phase suppresses
+        // the first trigger of the second phase.
+        assert_eq!(
+            observer
+                .dead_time
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![14]
+        );
+    }
+
+    #[test]
+    fn world_prompt_window() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(-100)
+            .duration(Positive::new(10).unwrap())
+            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(3).unwrap())
+            .wait_gate(Positive::new(100).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(100).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(100).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+        assert_eq!(
+            observer
+                .trg_in
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![-95]
+        );
+    }
+
+    #[test]
+    fn world_wait_gate() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(45).unwrap())
+            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(9).unwrap())
+            .wait_gate(Positive::new(2).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(100).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(100).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+        assert_eq!(
+            observer
+                .trg_in
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![19, 39]
+        );
+        // The events at 20 and 40 are in the wait gate.
+        assert_eq!(
+            observer.mlu_stats,
+            Some(MluStats {
+                windows_opened: 2,
+                decisions: 2,
+                accepted: 2,
+                wait_gate_extensions: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn world_trigger_condition() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+            .wire_pattern(vec![
+                WirePattern::from_bits(0b01),
+                WirePattern::from_bits(0b11),
+                WirePattern::from_bits(0b10),
+                WirePattern::from_bits(0b11),
+            ])
+            .build();
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .trigger_condition(TriggerCondition::MinWires(2))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+        assert_eq!(
+            observer
+                .trg_in
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![7, 13]
+        );
+    }
+
+    #[test]
+    fn world_passthrough() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+            .wire_pattern(vec![WirePattern::from_bits(1); 4])
+            .build();
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(100).unwrap())
+            .trigger_condition(TriggerCondition::Passthrough)
+            .drift_veto(Positive::new(4).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        let times = |signals: &[TrgSignal<i32>]| signals.iter().map(|s| s.time).collect::<Vec<_>>();
+        assert_eq!(times(&observer.trg_in), vec![4, 7, 10, 13]);
+        assert_eq!(times(&observer.drift_veto), vec![7, 13]);
+        assert_eq!(times(&observer.trg_out), vec![4, 10]);
+    }
+
+    #[test]
+    fn world_veto_table() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+            .wire_pattern(vec![
+                WirePattern::from_bits(1),
+                WirePattern::from_bits(2),
+                WirePattern::from_bits(1),
+                WirePattern::from_bits(2),
+            ])
+            .build();
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([
+                WirePattern::from_bits(1),
+                WirePattern::from_bits(2),
+            ]))
+            .veto_table(LookupTable::from([WirePattern::from_bits(2)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+        assert_eq!(
+            observer
+                .mlu_veto
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![7, 13]
+        );
+        assert_eq!(
+            observer
+                .trg_in
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![4, 10]
+        );
+    }
+
+    #[test]
+    fn world_trigger_menu() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+            .wire_pattern(vec![
+                WirePattern::from_bits(1),
+                WirePattern::from_bits(2),
+                WirePattern::from_bits(3),
+            ])
+            .build();
+        let mut menu = TriggerMenu::new();
+        menu.push("one", LookupTable::from([WirePattern::from_bits(1)]));
+        menu.push(
+            "not two",
+            LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(3)]),
+        );
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .trigger_condition(menu.into())
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+        assert_eq!(
+            observer
+                .trg_out
+                .into_iter()
+                .map(|s| (s.time, s.matched))
+                .collect::<Vec<_>>(),
+            vec![(4, 0b11), (10, 0b10)]
+        );
+    }
+
+    #[test]
+    fn world_boundary_policy() {
+        let trg_in = |prompt_window| {
+            let noise = SecondaryGenerator::builder()
+                .source(Source::Noise)
+                .origin(0)
+                .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+                .wire_pattern(vec![WirePattern::from_bits(1), WirePattern::from_bits(2)])
+                .build();
+            World::builder()
+                .add_generator(noise)
+                .prompt_window(Positive::new(10).unwrap())
+                .wait_gate(Positive::new(1).unwrap())
+                .lookup_table(LookupTable::from([WirePattern::from_bits(3)]))
+                .boundary_policy(BoundaryPolicy {
+                    prompt_window,
+                    ..Default::default()
+                })
+                .drift_veto(Positive::new(1).unwrap())
+                .scaledown(0)
+                .dead_time(Positive::new(1).unwrap())
+                .observer(TestObserver::default())
+                .build()
+                .run()
+                .trg_in
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>()
+        };
+        // The second event is exactly at the end of the prompt window.
+        assert!(trg_in(Boundary::Exclusive).is_empty());
+        assert_eq!(trg_in(Boundary::Inclusive), vec![20]);
+    }
+
+    fn mlu_clock_trg_in(time: i32) -> Vec<i32> {
+        let single =
+            ScriptedGenerator::from_times(Source::Noise, [time], WirePattern::from_bits(1));
+        let observer = World::builder()
+            .add_generator(single)
+            .prompt_window(Positive::new(8).unwrap())
+            .wait_gate(Positive::new(8).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .mlu_clock_period(Positive::new(8).unwrap())
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        observer.trg_in.into_iter().map(|s| s.time).collect()
+    }
+
+    #[test]
+    fn world_mlu_clock() {
+        // Both events are in the [8, 16) tick.
+        assert_eq!(mlu_clock_trg_in(9), vec![16]);
+        assert_eq!(mlu_clock_trg_in(15), vec![16]);
+        assert_eq!(mlu_clock_trg_in(16), vec![24]);
+    }
+
+    fn trg_clock_trg_out(second: i32) -> Vec<i32> {
+        let noise =
+            ScriptedGenerator::from_times(Source::Noise, [0, second], WirePattern::from_bits(1));
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .trg_clock_period(Positive::new(4).unwrap())
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(10).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        observer.trg_out.into_iter().map(|s| s.time).collect()
+    }
+
+    #[test]
+    fn world_trg_clock() {
+        // The first TRG signal (at 1) is in the [0, 4) tick, so the TRG box is
+        // busy until 10. Signals at 9 and 11 are both in the [8, 12) tick.
+        assert_eq!(trg_clock_trg_out(8), vec![1]);
+        assert_eq!(trg_clock_trg_out(10), vec![1]);
+        assert_eq!(trg_clock_trg_out(12), vec![1, 13]);
+    }
+
+    fn mlu_latency_trg_out(latency: Option<Positive<i32>>) -> (Vec<i32>, Vec<i32>) {
+        let noise =
+            ScriptedGenerator::from_times(Source::Noise, [6, 25], WirePattern::from_bits(1));
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(10).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .maybe_mlu_latency(latency)
+            .trg_clock_period(Positive::new(8).unwrap())
+            .drift_veto(Positive::new(16).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        let times = |signals: Vec<TrgSignal<i32>>| signals.into_iter().map(|s| s.time).collect();
+        (times(observer.trg_out), times(observer.drift_veto))
+    }
+
+    #[test]
+    fn world_mlu_latency() {
+        // Prompt windows end at 16 and 35, which are in the [16, 24) and
+        // [32, 40) ticks of the TRG clock (i.e. within the drift veto).
+        assert_eq!(mlu_latency_trg_out(None), (vec![16], vec![35]));
+        // The delayed signals at 21 and 40 are in the [16, 24) and [40, 48)
+        // ticks.
+        assert_eq!(
+            mlu_latency_trg_out(Some(Positive::new(5).unwrap())),
+            (vec![21, 40], vec![])
+        );
+    }
+
+    // A TRG clock period of 1 does not change the (integer) times.
+    fn mlu_respects_busy_trg_in(
+        mlu_respects_busy: bool,
+        dead_time: i32,
+        trg_clock: i32,
+    ) -> (Vec<i32>, Vec<i32>) {
+        let noise = ScriptedGenerator::from_times(
+            Source::Noise,
+            (1..=10).map(|i| 3 * i),
+            WirePattern::from_bits(1),
+        );
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .trg_clock_period(Positive::new(trg_clock).unwrap())
+            .mlu_respects_busy(mlu_respects_busy)
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(dead_time).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+        // Events during busy are still reported.
+        assert_eq!(observer.events.len(), 10);
+
+        let times = |signals: Vec<TrgSignal<i32>>| signals.into_iter().map(|s| s.time).collect();
+        (times(observer.trg_in), times(observer.trg_out))
+    }
+
+    #[test]
+    fn world_mlu_respects_busy() {
+        assert_eq!(
+            mlu_respects_busy_trg_in(false, 10, 1),
+            (vec![4, 7, 10, 13, 16, 19, 22, 25, 28, 31], vec![4, 16, 28])
+        );
+        // No prompt windows are opened by the events at 6, 9, 12, 18, 21, 24,
+        // and 30.
+        assert_eq!(
+            mlu_respects_busy_trg_in(true, 10, 1),
+            (vec![4, 16, 28], vec![4, 16, 28])
+        );
+        // The TRG box is busy until 15 (inclusive), so the event at 15 does
+        // not reach the MLU.
+        assert_eq!(
+            mlu_respects_busy_trg_in(true, 11, 1),
+            (vec![4, 19], vec![4, 19])
+        );
+        // With the TRG clock, the events at 15 and 27 are in the [12, 16) and
+        // [24, 28) ticks, i.e. while the TRG box is busy until 14 and 26.
+        assert_eq!(
+            mlu_respects_busy_trg_in(true, 10, 4),
+            (vec![4, 19, 31], vec![4, 19, 31])
+        );
+    }
+
+    #[test]
+    fn world_filter_events() {
+        // Dead board 7: events that only touch it are dropped.
+        let dead = WirePattern::from_bits(1 << 7);
+        let noise = SecondaryGenerator::builder()
             .source(Source::Noise)
             .origin(0)
-            .duration(Positive::new(3).unwrap())
-            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
-            .wire_pattern(repeat(WirePattern::from_bits(1)))
-            .build();
-        let noise2 = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(1)
-            .duration(Positive::new(3).unwrap())
-            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
-            .wire_pattern(repeat(WirePattern::from_bits(1)))
-            .build();
-
+            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+            .wire_pattern([1 << 7, 0b11, 1 << 7, (1 << 7) | 1].map(WirePattern::from_bits))
+            .build()
+            .filter_events(|event| event.wire_pattern != dead);
         let observer = World::builder()
-            .add_generator(noise1)
-            .add_generator(noise2)
+            .add_generator(noise)
             .prompt_window(Positive::new(100).unwrap())
             .wait_gate(Positive::new(100).unwrap())
             .lookup_table(LookupTable::default())
@@ -220,70 +2285,198 @@ mod tests {
         assert_eq!(
             observer
                 .events
-                .into_iter()
-                .map(|e| e.time)
+                .iter()
+                .map(|e| (e.time, e.wire_pattern.bits()))
                 .collect::<Vec<_>>(),
-            vec![2, 3]
+            vec![(20, 0b11), (40, (1 << 7) | 1)]
         );
+        assert_eq!(observer.exhausted, [(2, 0, ExhaustionReason::WirePattern)]);
     }
 
     #[test]
-    fn world_prompt_window() {
-        let noise = SecondaryGenerator::builder()
-            .source(Source::Noise)
-            .origin(-100)
-            .duration(Positive::new(10).unwrap())
-            .inter_arrival_time(repeat(Positive::new(2).unwrap()))
-            .wire_pattern(repeat(WirePattern::from_bits(1)))
+    fn world_mask_channels() {
+        let trg_out = |mask: ChannelMask| {
+            let gen = SecondaryGenerator::builder()
+                .source(Source::Noise)
+                .origin(0)
+                .max_events(5)
+                .inter_arrival_time(repeat(Positive::new(1000).unwrap()))
+                .wire_pattern(repeat(WirePattern::from_bits(0b11)))
+                .build()
+                .mask_channels(mask, Seed::new(0).rng());
+            World::builder()
+                .add_generator(gen)
+                .prompt_window(Positive::new(100).unwrap())
+                .wait_gate(Positive::new(100).unwrap())
+                .lookup_table(LookupTable::min_wires(2))
+                .drift_veto(Positive::new(100).unwrap())
+                .scaledown(0)
+                .dead_time(Positive::new(100).unwrap())
+                .observer(TestObserver::default())
+                .build()
+                .run()
+                .trg_out
+                .len()
+        };
+
+        assert_eq!(trg_out(ChannelMask::builder().build()), 5);
+        // A required board is dead.
+        let dead = ChannelMask::builder()
+            .disabled(WirePattern::from_bits(0b10))
+            .build();
+        assert_eq!(trg_out(dead), 0);
+        // A hot board makes up for it.
+        let hot = ChannelMask::builder()
+            .disabled(WirePattern::from_bits(0b10))
+            .hot(vec![(5, 1.0)])
             .build();
+        assert_eq!(trg_out(hot), 5);
+    }
+
+    #[test]
+    fn world_event_ids() {
+        let pbar = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0)
+            .inter_arrival_time([100, 200].map(|t| Positive::new(t).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(0b01)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .max_events(1)
+                    .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(0b10)))
+            })
+            .build();
+        let noise =
+            ScriptedGenerator::from_times(Source::Noise, [200], WirePattern::from_bits(0b11));
         let observer = World::builder()
+            .add_generator(pbar)
             .add_generator(noise)
-            .prompt_window(Positive::new(3).unwrap())
-            .wait_gate(Positive::new(100).unwrap())
-            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
-            .drift_veto(Positive::new(100).unwrap())
+            .prompt_window(Positive::new(10).unwrap())
+            .wait_gate(Positive::new(10).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(0b11)]))
+            .drift_veto(Positive::new(10).unwrap())
             .scaledown(0)
-            .dead_time(Positive::new(100).unwrap())
+            .dead_time(Positive::new(10).unwrap())
             .observer(TestObserver::default())
             .build()
             .run();
+
+        // The events in the prompt window of each output trigger.
+        let causes: Vec<Vec<_>> = observer
+            .trg_out
+            .iter()
+            .map(|signal| {
+                observer
+                    .events
+                    .iter()
+                    .filter(|e| (signal.window_start..signal.window_stop).contains(&e.time))
+                    .map(|e| e.id.map(EventId::get))
+                    .collect()
+            })
+            .collect();
         assert_eq!(
-            observer
-                .trg_in
-                .into_iter()
-                .map(|s| s.time)
-                .collect::<Vec<_>>(),
-            vec![-95]
+            causes,
+            [vec![Some(0), Some(0)], vec![None], vec![Some(1), Some(1)]]
         );
     }
 
     #[test]
-    fn world_wait_gate() {
-        let noise = SecondaryGenerator::builder()
-            .source(Source::Noise)
+    fn world_generator_stats() {
+        // Primary events at 100 and 300, each with afterpulses 3 and 6 later.
+        let pbar = PrimaryGenerator::builder()
+            .source(Source::PrimaryPbar)
             .origin(0)
-            .duration(Positive::new(45).unwrap())
-            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
-            .wire_pattern(repeat(WirePattern::from_bits(1)))
-            .build();
+            .inter_arrival_time([100, 200].map(|t| Positive::new(t).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(0b01)))
+            .afterpulse(|_: &_| {
+                SecondaryGenerator::builder()
+                    .source(Source::SecondaryPbar)
+                    .max_events(2)
+                    .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+                    .wire_pattern(repeat(WirePattern::from_bits(0b10)))
+            })
+            .build()
+            .take_events(5);
+        let noise =
+            ScriptedGenerator::from_times(Source::Noise, [50, 200], WirePattern::from_bits(0b11));
+        let empty = ScriptedGenerator::from_times(Source::Noise, [], WirePattern::from_bits(0b11));
         let observer = World::builder()
+            .add_generator(pbar)
+            .add_generator(empty)
             .add_generator(noise)
-            .prompt_window(Positive::new(9).unwrap())
-            .wait_gate(Positive::new(2).unwrap())
-            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .prompt_window(Positive::new(10).unwrap())
+            .wait_gate(Positive::new(10).unwrap())
+            .lookup_table(LookupTable::min_wires(1))
+            .drift_veto(Positive::new(10).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(10).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        let stats = |primary_events, secondary_events| GeneratorStats {
+            primary_events,
+            secondary_events,
+        };
+        assert_eq!(
+            observer.generator_stats,
+            [stats(2, 3), stats(0, 0), stats(2, 0)]
+        );
+        assert_eq!(observer.generator_stats[0].events(), 5);
+        assert_eq!(observer.events.len(), 7);
+    }
+
+    #[test]
+    fn world_channel_dead_time() {
+        let afterpulses = ScriptedGenerator::new(
+            [(0, 0b011), (2, 0b001), (3, 0b010), (6, 0b011), (8, 0b110)]
+                .map(|(time, bits)| WireEvent {
+                    source: Source::Noise,
+                    wire_pattern: WirePattern::from_bits(bits),
+                    time,
+                    id: None,
+                    weight: 1.0,
+                })
+                .to_vec(),
+        );
+        let observer = World::builder()
+            .add_generator(afterpulses)
+            .prompt_window(Positive::new(100).unwrap())
+            .wait_gate(Positive::new(100).unwrap())
+            .lookup_table(LookupTable::default())
+            .channel_dead_time(Positive::new(5).unwrap())
             .drift_veto(Positive::new(100).unwrap())
             .scaledown(0)
             .dead_time(Positive::new(100).unwrap())
             .observer(TestObserver::default())
             .build()
             .run();
+
         assert_eq!(
             observer
-                .trg_in
-                .into_iter()
-                .map(|s| s.time)
+                .events
+                .iter()
+                .map(|e| (e.time, e.wire_pattern))
                 .collect::<Vec<_>>(),
-            vec![19, 39]
+            vec![
+                (0, WirePattern(0b011)),
+                (6, WirePattern(0b011)),
+                (8, WirePattern(0b100)),
+            ]
+        );
+        assert_eq!(
+            observer
+                .masked
+                .iter()
+                .map(|(seen, e, masked)| (*seen, e.time, e.wire_pattern, *masked))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, 2, WirePattern(0b001), WirePattern(0b001)),
+                (1, 3, WirePattern(0b010), WirePattern(0b010)),
+                (2, 8, WirePattern(0b110), WirePattern(0b010)),
+            ]
         );
     }
 
@@ -437,25 +2630,289 @@ mod tests {
                 .into_iter()
                 .map(|s| s.time)
                 .collect::<Vec<_>>(),
-            vec![5, 13]
+            vec![5, 13, 21]
+        );
+    }
+
+    #[test]
+    fn world_open_window_at_end() {
+        let single = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(vec![Positive::new(4).unwrap()])
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let observer = World::builder()
+            .add_generator(single)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(7).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        assert_eq!(
+            observer
+                .trg_in
+                .into_iter()
+                .map(|s| s.time)
+                .collect::<Vec<_>>(),
+            vec![5]
+        );
+        assert_eq!(observer.trg_out.len(), 1);
+    }
+
+    fn busy_windows(noise_inter_arrival_time: i32) -> Vec<i32> {
+        let signal = SecondaryGenerator::builder()
+            .source(Source::PrimaryPbar)
+            .origin(0)
+            .duration(Positive::new(350).unwrap())
+            .inter_arrival_time(repeat(Positive::new(100).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(350).unwrap())
+            .inter_arrival_time(repeat(Positive::new(noise_inter_arrival_time).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(2)))
+            .build();
+
+        let mut busy_windows = Vec::new();
+        let _ = World::builder()
+            .add_generator(signal)
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time_fn(|_, n| {
+                let busy = 1 + i32::try_from(n).unwrap();
+                busy_windows.push(busy);
+                Positive::new(busy).unwrap()
+            })
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        busy_windows
+    }
+
+    #[test]
+    fn world_dead_time_fn() {
+        // The signals at 100, 200, and 300 trigger at 101, 201, and 301,
+        // each after 14 noise events (every 7) or 2 (every 45) since the
+        // previous one.
+        assert_eq!(busy_windows(7), vec![16, 16, 16]);
+        assert_eq!(busy_windows(45), vec![4, 4, 4]);
+        // A wire event at the time of a signal is counted for the next one.
+        assert_eq!(busy_windows(101), vec![2, 3, 3]);
+    }
+
+    fn table_switch_trg_in(initial: LookupTable, switch: (i32, LookupTable)) -> Vec<i32> {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(35).unwrap())
+            .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let observer = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(3).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(initial)
+            .table_switches(vec![switch])
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run();
+
+        observer.trg_in.into_iter().map(|s| s.time).collect()
+    }
+
+    #[test]
+    fn world_table_switches() {
+        let table = || LookupTable::from([WirePattern::from_bits(1)]);
+        // Prompt windows are [10, 13), [20, 23), and [30, 33).
+        assert_eq!(
+            table_switch_trg_in(LookupTable::new(), (12, table())),
+            vec![13, 23, 33]
+        );
+        // The window that closes at 13 is decided with the old table.
+        assert_eq!(
+            table_switch_trg_in(table(), (14, LookupTable::new())),
+            vec![13]
+        );
+        // A table is already in effect at its switch time.
+        assert_eq!(
+            table_switch_trg_in(table(), (13, LookupTable::new())),
+            Vec::<i32>::new()
+        );
+        // Switches after the last event still apply to the last window.
+        assert_eq!(
+            table_switch_trg_in(table(), (33, LookupTable::new())),
+            vec![13, 23]
+        );
+        assert_eq!(
+            table_switch_trg_in(table(), (40, LookupTable::new())),
+            vec![13, 23, 33]
+        );
+    }
+
+    fn scaledown_noise() -> impl EventGenerator<Time = i32> {
+        SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(25).unwrap())
+            .inter_arrival_time(repeat(Positive::new(4).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build()
+    }
+
+    fn scaledown_trigger(scaledown: u32) -> Trigger<'static, i32, TestObserver> {
+        Trigger::builder()
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(scaledown)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+    }
+
+    fn scaledown_world(scaledown: u32) -> TestObserver {
+        World::builder()
+            .add_generator(scaledown_noise())
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(scaledown)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .build()
+            .run()
+    }
+
+    #[test]
+    fn multi_world() {
+        let observers = MultiWorld::builder()
+            .add_generator(scaledown_noise())
+            .add_trigger(scaledown_trigger(0))
+            .add_trigger(scaledown_trigger(1))
+            .build()
+            .run();
+        assert_eq!(observers.len(), 2);
+
+        let times =
+            |signals: Vec<TrgSignal<i32>>| signals.into_iter().map(|s| s.time).collect::<Vec<_>>();
+        for (observer, scaledown) in zip(observers, [0, 1]) {
+            let expected = scaledown_world(scaledown);
+
+            assert_eq!(observer.events.len(), expected.events.len());
+            assert_eq!(times(observer.scaledown), times(expected.scaledown));
+            assert_eq!(times(observer.trg_out), times(expected.trg_out));
+        }
+    }
+
+    // Bypasses the ordering guarantee of `EventGenerator`.
+    struct Unordered(std::vec::IntoIter<WireEvent<i32>>);
+
+    impl Iterator for Unordered {
+        type Item = WireEvent<i32>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next()
+        }
+    }
+
+    impl sealed::OrderedIterator for Unordered {}
+
+    fn unordered_world(check_ordering: bool) -> World<'static, i32, TestObserver> {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(10).unwrap())
+            .inter_arrival_time(repeat(Positive::new(4).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        let unordered = Unordered(
+            [5, 3]
+                .map(|time| WireEvent {
+                    source: Source::Noise,
+                    wire_pattern: WirePattern::from_bits(1),
+                    time,
+                    id: None,
+                    weight: 1.0,
+                })
+                .to_vec()
+                .into_iter(),
+        );
+
+        World::builder()
+            .add_generator(noise)
+            .add_generator(unordered)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::default())
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(TestObserver::default())
+            .check_ordering(check_ordering)
+            .build()
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "wire event #2 at time 3 is earlier than the previous event at time 5 \
+(event from generator #1, previous event from generator #1)"
+    )]
+    fn world_check_ordering() {
+        let _ = unordered_world(true).run();
+    }
+
+    #[test]
+    fn world_no_check_ordering() {
+        let observer = unordered_world(false).run();
+        assert_eq!(
+            observer
+                .events
+                .into_iter()
+                .map(|e| e.time)
+                .collect::<Vec<_>>(),
+            vec![4, 5, 3, 8]
         );
     }
 
     #[derive(Default)]
     struct TimeAwareObserver {
         last_event: Option<WireEvent<i32>>,
+        last_trg_in: Option<i32>,
     }
 
     impl Observer for TimeAwareObserver {
         type Time = i32;
 
         fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+            if let Some(last_trg_in) = self.last_trg_in {
+                assert!(event.time >= last_trg_in);
+            }
             self.last_event = Some(*event);
         }
 
         fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
             let last_event_time = self.last_event.unwrap().time;
             assert!(signal.time > last_event_time);
+            self.last_trg_in = Some(signal.time);
         }
     }
 
@@ -480,4 +2937,28 @@ mod tests {
             .build()
             .run();
     }
+
+    #[test]
+    fn world_time_correctness_mlu_latency() {
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .duration(Positive::new(30).unwrap())
+            .inter_arrival_time(repeat(Positive::new(3).unwrap()))
+            .wire_pattern(repeat(WirePattern::from_bits(1)))
+            .build();
+        // Each signal is only out of the MLU a few wire events later.
+        let _ = World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(1).unwrap())
+            .wait_gate(Positive::new(1).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .mlu_latency(Positive::new(10).unwrap())
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(2).unwrap())
+            .observer(TimeAwareObserver::default())
+            .build()
+            .run();
+    }
 }
@@ -1,20 +1,57 @@
 use crate::gen::{Positive, WireEvent, WirePattern};
+use crate::observers::{fnv1a, FNV_OFFSET_BASIS};
+pub use crate::statistics::Efficiency;
+use rand::Rng;
 use std::fmt;
-use std::ops::Add;
-use winnow::ascii::{hex_uint, newline};
-use winnow::combinator::{delimited, opt, separated, terminated};
-use winnow::error::ContextError;
+use std::io::{Read, Write};
+use std::ops::{Add, BitAnd, BitOr, BitXor, Index, Not, Sub};
+use winnow::ascii::{dec_uint, hex_uint, space0, space1};
+use winnow::combinator::{alt, delimited, preceded};
 use winnow::Parser;
 
+/// Naive MLU implementation used to cross-check [`Mlu`].
+pub mod reference;
+
 const TABLE_SIZE: usize = 2usize.pow(16);
+// One bit per wire pattern.
+const WORDS: usize = TABLE_SIZE / 64;
+
+/// Number of bytes of a [`LookupTable`] in its binary format (see
+/// [`LookupTable::to_bytes`]).
+pub const TABLE_BYTES: usize = TABLE_SIZE / 8;
+// Header of a binary lookup table file: "MLU" followed by a format version.
+const BINARY_MAGIC: [u8; 4] = *b"MLU\x01";
+
+// Word index and bit mask of a wire pattern in the packed table.
+fn word_and_mask(wire_pattern: WirePattern) -> (usize, u64) {
+    let index = usize::from(wire_pattern.0);
+    (index / 64, 1 << (index % 64))
+}
+
+// All the subsets of `mask`, in ascending numeric order.
+fn subsets(mask: u16) -> impl Iterator<Item = u16> {
+    std::iter::successors(Some(0u16), move |&sub| {
+        (sub != mask).then(|| sub.wrapping_sub(mask) & mask)
+    })
+}
+
+// All the supersets of `mask`, in ascending numeric order.
+fn supersets(mask: u16) -> impl Iterator<Item = u16> {
+    std::iter::successors(Some(mask), move |&sup| {
+        (sup != u16::MAX).then(|| (sup + 1) | mask)
+    })
+}
 
 /// Set of [`WirePattern`]s.
 ///
 /// The [`LookupTable`] determines the set of wire patterns of interest that
 /// produce a TRG signal out of the MLU.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// At 8 KiB, a table is `Copy` but too large to be moved around cheaply, so
+// types that store one keep it boxed and its operators take references.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct LookupTable {
-    inner: [bool; TABLE_SIZE],
+    // Bit `n % 64` of word `n / 64` is set if pattern `n` is in the table.
+    inner: [u64; WORDS],
 }
 
 impl LookupTable {
@@ -27,9 +64,96 @@ impl LookupTable {
     /// let table = LookupTable::new();
     /// ```
     pub fn new() -> Self {
-        Self {
-            inner: [false; TABLE_SIZE],
-        }
+        Self { inner: [0; WORDS] }
+    }
+    /// Creates a lookup table with all the wire patterns that satisfy a
+    /// predicate. The predicate is evaluated once for every pattern, in
+    /// ascending numeric order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// // At least 2 wires in at least 2 distinct clusters.
+    /// let table = LookupTable::from_predicate(|pattern| {
+    ///     pattern.count_ones() >= 2 && pattern.cluster_count() >= 2
+    /// });
+    /// ```
+    pub fn from_predicate(mut f: impl FnMut(WirePattern) -> bool) -> Self {
+        (0..=u16::MAX)
+            .map(WirePattern)
+            .filter(|&pattern| f(pattern))
+            .collect()
+    }
+    /// Creates a lookup table with all the wire patterns that have at least
+    /// `n` boards high (see [`WirePattern::count_ones`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// assert_eq!(LookupTable::min_wires(16).len(), 1);
+    /// ```
+    pub fn min_wires(n: u32) -> Self {
+        Self::from_predicate(|pattern| pattern.count_ones() >= n)
+    }
+    /// Creates a lookup table with all the wire patterns that have at least
+    /// `n` clusters of adjacent boards high (see
+    /// [`WirePattern::cluster_count`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// assert_eq!(LookupTable::min_clusters(8).len(), 2);
+    /// ```
+    pub fn min_clusters(n: u32) -> Self {
+        Self::from_predicate(|pattern| pattern.cluster_count() >= n)
+    }
+    /// Creates a random lookup table. Each wire pattern is independently in
+    /// the table with probability `density`.
+    ///
+    /// The patterns are drawn in ascending numeric order, so the table is
+    /// deterministic given a seeded `rng`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `density` is not in `[0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::rngs::StdRng;
+    /// use rand::SeedableRng;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut rng = StdRng::seed_from_u64(0);
+    /// let table = LookupTable::random(0.25, &mut rng);
+    /// assert!((table.density() - 0.25).abs() < 0.01);
+    /// ```
+    pub fn random<R: Rng + ?Sized>(density: f64, rng: &mut R) -> Self {
+        Self::random_with_min_bits(density, 0, rng)
+    }
+    /// Creates a random lookup table with only wire patterns that have at
+    /// least `min_bits` boards high. Each of these patterns is independently
+    /// in the table with probability `density`.
+    ///
+    /// The patterns are drawn in ascending numeric order, so the table is
+    /// deterministic given a seeded `rng`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `density` is not in `[0, 1]`.
+    pub fn random_with_min_bits<R: Rng + ?Sized>(density: f64, min_bits: u32, rng: &mut R) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&density),
+            "density {density} is not in [0, 1]"
+        );
+
+        Self::from_predicate(|pattern| pattern.count_ones() >= min_bits && rng.random_bool(density))
     }
     /// Adds a wire pattern to the lookup table. Returns whether the pattern was
     /// newly inserted.
@@ -46,9 +170,9 @@ impl LookupTable {
     /// assert_eq!(table.insert(WirePattern::from_bits(0)), false);
     /// ```
     pub fn insert(&mut self, wire_pattern: WirePattern) -> bool {
-        let index = usize::from(wire_pattern.0);
-        let was_inserted = !self.inner[index];
-        self.inner[index] = true;
+        let (word, mask) = word_and_mask(wire_pattern);
+        let was_inserted = self.inner[word] & mask == 0;
+        self.inner[word] |= mask;
 
         was_inserted
     }
@@ -65,8 +189,8 @@ impl LookupTable {
     /// assert_eq!(table.contains(WirePattern::from_bits(1)), false);
     /// ```
     pub fn contains(&self, wire_pattern: WirePattern) -> bool {
-        let index = usize::from(wire_pattern.0);
-        self.inner[index]
+        let (word, mask) = word_and_mask(wire_pattern);
+        self.inner[word] & mask != 0
     }
     /// Removes a wire pattern from the lookup table. Returns whether the
     /// pattern was present in the table.
@@ -84,39 +208,64 @@ impl LookupTable {
     /// assert_eq!(table.remove(WirePattern::from_bits(0)), false);
     /// ```
     pub fn remove(&mut self, wire_pattern: WirePattern) -> bool {
-        let index = usize::from(wire_pattern.0);
-        let was_present = self.inner[index];
-        self.inner[index] = false;
+        let (word, mask) = word_and_mask(wire_pattern);
+        let was_present = self.inner[word] & mask != 0;
+        self.inner[word] &= !mask;
 
         was_present
     }
-}
+    /// Flips the membership of a wire pattern: inserts it if it is not in the
+    /// lookup table, and removes it otherwise. Returns whether the pattern is
+    /// in the table after the call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut table = LookupTable::new();
+    ///
+    /// assert_eq!(table.toggle(WirePattern::from_bits(0)), true);
+    /// assert_eq!(table.toggle(WirePattern::from_bits(0)), false);
+    /// assert!(table.is_empty());
+    /// ```
+    pub fn toggle(&mut self, wire_pattern: WirePattern) -> bool {
+        let (word, mask) = word_and_mask(wire_pattern);
+        self.inner[word] ^= mask;
 
-impl Default for LookupTable {
-    /// Creates a new empty lookup table.
-    fn default() -> Self {
-        Self::new()
+        self.inner[word] & mask != 0
     }
-}
-
-impl FromIterator<WirePattern> for LookupTable {
-    fn from_iter<T>(iter: T) -> Self
-    where
-        T: IntoIterator<Item = WirePattern>,
-    {
-        let mut lookup_table = Self::new();
-        for wire_pattern in iter {
-            lookup_table.insert(wire_pattern);
+    /// Retains only the wire patterns specified by the predicate. The
+    /// predicate is called once for every pattern in the table, in ascending
+    /// numeric order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut table = LookupTable::from([WirePattern::from_bits(0b11), WirePattern::from_bits(0b101)]);
+    ///
+    /// table.retain(|pattern| pattern.cluster_count() >= 2);
+    /// assert_eq!(table, LookupTable::from([WirePattern::from_bits(0b101)]));
+    /// ```
+    pub fn retain(&mut self, mut f: impl FnMut(WirePattern) -> bool) {
+        for (n, word) in self.inner.iter_mut().enumerate() {
+            let mut bits = *word;
+            while bits != 0 {
+                let bit = bits.trailing_zeros();
+                bits &= bits - 1;
+                // `n * 64 + bit` is at most `u16::MAX`.
+                if !f(WirePattern((n * 64) as u16 + bit as u16)) {
+                    *word &= !(1 << bit);
+                }
+            }
         }
-
-        lookup_table
     }
-}
-
-impl<const N: usize> From<[WirePattern; N]> for LookupTable {
-    /// Converts a `[WirePattern; N]` into a `LookupTable`.
-    ///
-    /// If the array contains any equal values, all but one will be dropped.
+    /// Adds all the cyclic rotations (see [`WirePattern::rotate`]) of every
+    /// wire pattern in the lookup table.
     ///
     /// # Examples
     ///
@@ -124,371 +273,3188 @@ impl<const N: usize> From<[WirePattern; N]> for LookupTable {
     /// # use trg::gen::WirePattern;
     /// use trg::mlu::LookupTable;
     ///
-    /// let table1 = LookupTable::from([WirePattern::from_bits(0)]);
-    /// let table2: LookupTable = [WirePattern::from_bits(0)].into();
-    /// assert_eq!(table1, table2);
+    /// let mut table = LookupTable::from([WirePattern::from_bits(0b11)]);
+    ///
+    /// table.close_under_rotation();
+    /// assert_eq!(table.len(), 16);
+    /// assert!(table.contains(WirePattern::from_bits(0b1000000000000001)));
     /// ```
-    fn from(arr: [WirePattern; N]) -> Self {
-        Self::from_iter(arr)
-    }
-}
-
-fn bit_pattern_string(n: u16) -> String {
-    format!("{:016b}", n.reverse_bits())
-        .replace("0", ".")
-        .replace("1", "X")
-}
-
-fn bits_string(n: u16) -> String {
-    format!("{} bits", n.count_ones())
-}
-
-fn clusters_string(n: u16) -> String {
-    let mut count = 0;
-    let mut in_cluster = n & (1 << 15) != 0;
-
-    for i in 0..16 {
-        if n & (1 << i) != 0 {
-            if !in_cluster {
-                count += 1;
-                in_cluster = true;
-            }
-        } else {
-            in_cluster = false;
+    pub fn close_under_rotation(&mut self) {
+        let patterns = self.iter().collect::<Vec<_>>();
+        for pattern in patterns {
+            self.extend(pattern.all_rotations());
         }
     }
-
-    if count == 0 && in_cluster {
-        count += 1;
+    /// Returns `true` if every cyclic rotation (see [`WirePattern::rotate`])
+    /// of every wire pattern in the lookup table is also in the table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut table = LookupTable::from([WirePattern::from_bits(0b11)]);
+    /// assert!(!table.is_rotation_invariant());
+    ///
+    /// table.close_under_rotation();
+    /// assert!(table.is_rotation_invariant());
+    /// ```
+    pub fn is_rotation_invariant(&self) -> bool {
+        // Invariance under a single step implies invariance under all of them.
+        self.iter().all(|pattern| self.contains(pattern.rotate(1)))
     }
-
-    format!("{count} clusters")
-}
-
-impl fmt::Display for LookupTable {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let text = self
-            .inner
+    /// Returns the number of wire patterns in the lookup table.
+    ///
+    /// This scans the whole table i.e. it takes the same (constant) time
+    /// regardless of the number of patterns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(0), WirePattern::from_bits(1)]);
+    /// assert_eq!(table.len(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner
             .iter()
-            .enumerate()
-            .filter(|(_, &is_present)| is_present)
-            .map(|(n, _)| {
-                format!(
-                    "0x{n:04x} 1 {}, {}, {}",
-                    bit_pattern_string(u16::try_from(n).unwrap()),
-                    bits_string(u16::try_from(n).unwrap()),
-                    clusters_string(u16::try_from(n).unwrap())
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        write!(f, "{text}")
+            .map(|word| word.count_ones() as usize)
+            .sum()
     }
-}
-
-// There is no point in adding any `context` to these errors unless I change
-// this to a "tokenize, then parse" approach (allowing for better semantic
-// errors with spans). But given the use case, I don't think it's worth it. A
-// simple "this line is wrong" is enough.
-fn parse_line(input: &mut &str) -> winnow::Result<u16> {
-    let n: u16 = delimited("0x", hex_uint, " 1 ").parse_next(input)?;
-
-    let _ = (
-        bit_pattern_string(n).as_str(),
-        ", ",
-        bits_string(n).as_str(),
-        ", ",
-        clusters_string(n).as_str(),
-    )
-        .parse_next(input)?;
-
-    Ok(n)
-}
-
-/// The error type returned when parsing a [`LookupTable`] fails.
-#[derive(Debug)]
-pub struct ParseError {
-    input: String,
-    span: std::ops::Range<usize>,
-}
-
-impl ParseError {
-    fn from_parse(error: winnow::error::ParseError<&str, ContextError>) -> Self {
-        let input = error.input().to_string();
-        let span = error.char_span();
-        Self { input, span }
+    /// Returns `true` if the lookup table contains no wire patterns. This
+    /// stops at the first pattern found, but it scans the whole table if it is
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut table = LookupTable::new();
+    /// assert!(table.is_empty());
+    ///
+    /// table.insert(WirePattern::from_bits(0));
+    /// assert!(!table.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.iter().all(|&word| word == 0)
     }
-}
-
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let message = annotate_snippets::Level::Error
-            .title("invalid line starting here")
-            .snippet(
-                annotate_snippets::Snippet::source(&self.input)
-                    .fold(true)
-                    .annotation(annotate_snippets::Level::Error.span(self.span.clone())),
-            );
-        let renderer = annotate_snippets::Renderer::plain();
-        let rendered = renderer.render(message);
-        rendered.fmt(f)
+    /// Removes all wire patterns from the lookup table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut table = LookupTable::from([WirePattern::from_bits(0)]);
+    /// table.clear();
+    /// assert!(table.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.inner = [0; WORDS];
     }
-}
-
-impl std::error::Error for ParseError {}
-
-impl std::str::FromStr for LookupTable {
-    type Err = ParseError;
-
-    /// Parse a [`LookupTable`] from a string. The string should have the same
-    /// format as processed by the real detector.
+    /// Returns the fraction of all possible wire patterns (65536) that are in
+    /// the lookup table. Takes the same time as [`LookupTable::len`].
     ///
     /// # Examples
     ///
-    /// ```no_run
-    /// # use trg::mlu::LookupTable;
-    /// # use std::str::FromStr;
-    /// let string = std::fs::read_to_string("mlu_file.txt")?;
-    /// let table = LookupTable::from_str(&string)?;
-    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    fn from_str(input: &str) -> Result<Self, Self::Err> {
-        let mut inner = [false; TABLE_SIZE];
-
-        let () = terminated(
-            separated(
-                0..,
-                parse_line.map(|n| {
-                    let index = usize::from(n);
-                    inner[index] = true;
-                }),
-                newline,
-            ),
-            opt(newline),
-        )
-        .parse(input)
-        .map_err(ParseError::from_parse)?;
-
-        Ok(Self { inner })
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = (0..16384).map(WirePattern::from_bits).collect::<LookupTable>();
+    /// assert_eq!(table.density(), 0.25);
+    /// ```
+    pub fn density(&self) -> f64 {
+        // Both values are exactly representable.
+        self.len() as f64 / TABLE_SIZE as f64
     }
-}
-
-#[derive(Clone, Copy, Debug)]
-pub struct TrgSignal<T> {
-    pub time: T,
-}
-
-#[derive(Clone, Copy, Debug)]
-enum MluState<T> {
-    Idle,
-    // Accumulating wire patterns during the prompt window.
-    Accumulate {
-        // Time when accumulation will stop and a TRG decision will be made.
-        stop_time: T,
-        cumulative: WirePattern,
-    },
-    Wait {
-        stop_time: T,
-    },
-}
-
-#[derive(Clone, Copy, Debug)]
-pub(super) struct Mlu<T> {
-    state: MluState<T>,
-    prompt_window: Positive<T>,
-    wait_gate: Positive<T>,
-    table: LookupTable,
-}
-
-impl<T> Mlu<T> {
-    pub(super) fn new(
-        prompt_window: Positive<T>,
-        wait_gate: Positive<T>,
-        table: LookupTable,
-    ) -> Self {
-        Self {
-            state: MluState::Idle,
-            prompt_window,
-            wait_gate,
-            table,
+    // Combine two tables word by word.
+    fn zip_with(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        let mut table = Self::new();
+        for (n, word) in table.inner.iter_mut().enumerate() {
+            *word = f(self.inner[n], other.inner[n]);
         }
-    }
-}
 
-impl<T> Mlu<T>
-where
-    T: Add<Output = T> + PartialOrd + Clone,
-{
-    pub(super) fn process(&mut self, event: &WireEvent<T>) -> Option<TrgSignal<T>> {
-        match std::mem::replace(&mut self.state, MluState::Idle) {
-            MluState::Accumulate {
-                stop_time,
-                cumulative,
-            } => {
-                if event.time < stop_time {
+        table
+    }
+    /// Returns a new lookup table with the wire patterns that are in `self`,
+    /// `other`, or both. Same as `&a | &b`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let a = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// let b = LookupTable::from([WirePattern::from_bits(2), WirePattern::from_bits(3)]);
+    /// assert_eq!(a.union(&b).len(), 3);
+    /// ```
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a | b)
+    }
+    /// Returns a new lookup table with the wire patterns that are in both
+    /// `self` and `other`. Same as `&a & &b`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let a = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// let b = LookupTable::from([WirePattern::from_bits(2), WirePattern::from_bits(3)]);
+    /// assert_eq!(a.intersection(&b), LookupTable::from([WirePattern::from_bits(2)]));
+    /// ```
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & b)
+    }
+    /// Returns a new lookup table with the wire patterns that are in `self`
+    /// but not in `other`. Same as `&a - &b`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let a = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// let b = LookupTable::from([WirePattern::from_bits(2), WirePattern::from_bits(3)]);
+    /// assert_eq!(a.difference(&b), LookupTable::from([WirePattern::from_bits(1)]));
+    /// ```
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & !b)
+    }
+    /// Returns a new lookup table with the wire patterns that are in either
+    /// `self` or `other`, but not in both. Same as `&a ^ &b`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let a = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// let b = LookupTable::from([WirePattern::from_bits(2), WirePattern::from_bits(3)]);
+    /// assert_eq!(
+    ///     a.symmetric_difference(&b),
+    ///     LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(3)])
+    /// );
+    /// ```
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a ^ b)
+    }
+    /// Returns a new lookup table with all the wire patterns that are not in
+    /// `self`. Same as `!&a`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(0)]);
+    /// assert_eq!(table.complement().len(), 65535);
+    /// ```
+    pub fn complement(&self) -> Self {
+        self.zip_with(self, |a, _| !a)
+    }
+    /// Returns `true` if all the wire patterns in `self` are also in `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let a = LookupTable::from([WirePattern::from_bits(1)]);
+    /// let b = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// assert!(a.is_subset(&b));
+    /// assert!(!b.is_subset(&a));
+    /// ```
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.difference(other).is_empty()
+    }
+    /// Returns `true` if all the wire patterns in `other` are also in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let a = LookupTable::from([WirePattern::from_bits(1)]);
+    /// let b = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// assert!(b.is_superset(&a));
+    /// assert!(!a.is_superset(&b));
+    /// ```
+    pub fn is_superset(&self, other: &Self) -> bool {
+        other.is_subset(self)
+    }
+    /// Returns summary statistics of the wire patterns in the lookup table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let stats = LookupTable::min_wires(15).stats();
+    /// assert_eq!(stats.count, 17);
+    /// assert_eq!(stats.bits_histogram[15], 16);
+    /// assert_eq!(stats.clusters_histogram[1], 17);
+    /// ```
+    pub fn stats(&self) -> TableStats {
+        let mut stats = TableStats {
+            count: 0,
+            bits_histogram: [0; 17],
+            clusters_histogram: [0; 9],
+            fingerprint: self.fingerprint(),
+        };
+        for pattern in self {
+            stats.count += 1;
+            stats.bits_histogram[pattern.count_ones() as usize] += 1;
+            stats.clusters_histogram[pattern.cluster_count() as usize] += 1;
+        }
+
+        stats
+    }
+    /// Returns the fraction of `patterns` that are in the lookup table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::min_wires(2);
+    /// let patterns = [0b1, 0b11, 0b111, 0b1000].map(WirePattern::from_bits);
+    /// let efficiency = table.efficiency(patterns);
+    /// assert_eq!((efficiency.passed(), efficiency.total()), (2, 4));
+    /// ```
+    pub fn efficiency<I: IntoIterator<Item = WirePattern>>(&self, patterns: I) -> Efficiency {
+        let (passed, total) = patterns
+            .into_iter()
+            .fold((0, 0), |(passed, total), pattern| {
+                (passed + u64::from(self.contains(pattern)), total + 1)
+            });
+        Efficiency::new(passed, total)
+    }
+    /// Returns `true` if the lookup table contains a subset of `pattern`
+    /// (including `pattern` itself) i.e. a pattern with only boards that are
+    /// high in `pattern`.
+    ///
+    /// This takes at most `2^n` lookups, where `n` is the number of boards
+    /// high in `pattern`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(0b11)]);
+    /// assert!(table.contains_subset_of(WirePattern::from_bits(0b111)));
+    /// assert!(!table.contains_subset_of(WirePattern::from_bits(0b101)));
+    /// ```
+    pub fn contains_subset_of(&self, pattern: WirePattern) -> bool {
+        self.subsets_of(pattern).next().is_some()
+    }
+    /// Returns `true` if the lookup table contains a superset of `pattern`
+    /// (including `pattern` itself) i.e. a pattern with at least all the
+    /// boards that are high in `pattern`.
+    ///
+    /// This takes at most `2^(16 - n)` lookups, where `n` is the number of
+    /// boards high in `pattern`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(0b111)]);
+    /// assert!(table.contains_superset_of(WirePattern::from_bits(0b11)));
+    /// assert!(!table.contains_superset_of(WirePattern::from_bits(0b1001)));
+    /// ```
+    pub fn contains_superset_of(&self, pattern: WirePattern) -> bool {
+        self.supersets_of(pattern).next().is_some()
+    }
+    /// Returns an iterator over the wire patterns in the lookup table that are
+    /// subsets of `pattern` (see [`LookupTable::contains_subset_of`]), in
+    /// ascending numeric order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([0b1, 0b11, 0b100].map(WirePattern::from_bits));
+    /// let subsets = table.subsets_of(WirePattern::from_bits(0b11)).collect::<Vec<_>>();
+    /// assert_eq!(subsets, [WirePattern::from_bits(0b1), WirePattern::from_bits(0b11)]);
+    /// ```
+    pub fn subsets_of(&self, pattern: WirePattern) -> impl Iterator<Item = WirePattern> + '_ {
+        subsets(pattern.0)
+            .map(WirePattern)
+            .filter(|&sub| self.contains(sub))
+    }
+    /// Returns an iterator over the wire patterns in the lookup table that are
+    /// supersets of `pattern` (see [`LookupTable::contains_superset_of`]), in
+    /// ascending numeric order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([0b1, 0b11, 0b100].map(WirePattern::from_bits));
+    /// let supersets = table.supersets_of(WirePattern::from_bits(0b1)).collect::<Vec<_>>();
+    /// assert_eq!(supersets, [WirePattern::from_bits(0b1), WirePattern::from_bits(0b11)]);
+    /// ```
+    pub fn supersets_of(&self, pattern: WirePattern) -> impl Iterator<Item = WirePattern> + '_ {
+        supersets(pattern.0)
+            .map(WirePattern)
+            .filter(|&sup| self.contains(sup))
+    }
+    /// Returns the wire patterns that have to be added to and removed from
+    /// `self` to get `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let old = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(2)]);
+    /// let new = LookupTable::from([WirePattern::from_bits(2), WirePattern::from_bits(3)]);
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.added, [WirePattern::from_bits(3)]);
+    /// assert_eq!(diff.removed, [WirePattern::from_bits(1)]);
+    /// ```
+    pub fn diff(&self, other: &Self) -> TableDiff {
+        let (added, removed) = self
+            .symmetric_difference(other)
+            .iter()
+            .partition(|&pattern| other.contains(pattern));
+
+        TableDiff { added, removed }
+    }
+    /// Returns an iterator over the wire patterns in the lookup table, in
+    /// ascending numeric order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(5), WirePattern::from_bits(3)]);
+    /// let patterns = table.iter().collect::<Vec<_>>();
+    /// assert_eq!(patterns, [WirePattern::from_bits(3), WirePattern::from_bits(5)]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            table: self,
+            next: 0,
+        }
+    }
+    /// Writes the lookup table as an MLU file, one line (terminated by a
+    /// newline) per pattern in the table. Same as the
+    /// [`Display`](fmt::Display) output, but without building the whole
+    /// string in memory.
+    ///
+    /// The writer is buffered internally.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(2)]);
+    /// let mut buffer = Vec::new();
+    /// table.write_to(&mut buffer)?;
+    /// assert_eq!(buffer, b"0x0002 1 .X.............., 1 bits, 1 clusters\n");
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn write_to<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(writer);
+        for WirePattern(pattern) in self {
+            writeln!(
+                writer,
+                "{}",
+                Row {
+                    pattern,
+                    enabled: true
+                }
+            )?;
+        }
+
+        writer.flush()
+    }
+    /// Writes a full dump of the lookup table: all 65536 patterns in
+    /// ascending order, each with a `1` (in the table) or `0` (not in the
+    /// table) flag. This is the format of the files produced by the detector
+    /// software.
+    ///
+    /// The writer is buffered internally.
+    pub fn write_full_dump<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(writer);
+        for pattern in 0..=u16::MAX {
+            let enabled = self.contains(WirePattern(pattern));
+            writeln!(writer, "{}", Row { pattern, enabled })?;
+        }
+
+        writer.flush()
+    }
+    /// Returns a fingerprint of the lookup table: the 64-bit FNV-1a hash of
+    /// [`LookupTable::to_bytes`].
+    ///
+    /// Unlike [`Hash`], the fingerprint is stable across runs, platforms, and
+    /// versions of this crate. Two tables with the same fingerprint are equal
+    /// with very high probability.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// assert_eq!(LookupTable::new().fingerprint(), 0xb9d103fd6854a325);
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        fnv1a(FNV_OFFSET_BASIS, &self.to_bytes())
+    }
+    /// Returns the raw bitset of the lookup table. Pattern `n` is bit `n % 8`
+    /// (least significant bit first) of byte `n / 8`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(9)]);
+    /// let bytes = table.to_bytes();
+    /// assert_eq!(bytes[1], 0b10);
+    /// assert_eq!(LookupTable::from_bytes(&bytes)?, table);
+    /// # Ok::<(), trg::mlu::BinaryError>(())
+    /// ```
+    pub fn to_bytes(&self) -> [u8; TABLE_BYTES] {
+        let mut bytes = [0; TABLE_BYTES];
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(self.inner) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        bytes
+    }
+    /// Creates a lookup table from a raw bitset in the format of
+    /// [`LookupTable::to_bytes`]. Returns an error if `bytes` is not exactly
+    /// [`TABLE_BYTES`] long.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryError> {
+        if bytes.len() != TABLE_BYTES {
+            return Err(BinaryError::Length(bytes.len()));
+        }
+
+        let mut table = Self::new();
+        for (word, chunk) in table.inner.iter_mut().zip(bytes.chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        Ok(table)
+    }
+    /// Writes the lookup table in binary format: a 4-byte header (`MLU`
+    /// followed by the format version) and the raw bitset of
+    /// [`LookupTable::to_bytes`].
+    ///
+    /// This is much faster to read back than the text MLU file format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(9)]);
+    /// let mut buffer = Vec::new();
+    /// table.write_binary(&mut buffer)?;
+    /// assert_eq!(LookupTable::read_binary(buffer.as_slice())?, table);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(&BINARY_MAGIC)?;
+        writer.write_all(&self.to_bytes())
+    }
+    /// Reads a lookup table written by [`LookupTable::write_binary`].
+    pub fn read_binary<R: Read>(mut reader: R) -> Result<Self, BinaryError> {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header).map_err(BinaryError::Io)?;
+        if header != BINARY_MAGIC {
+            return Err(BinaryError::Header(header));
+        }
+
+        let mut bytes = vec![0; TABLE_BYTES];
+        reader.read_exact(&mut bytes).map_err(BinaryError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+// Index of the first pattern in the table at or after `start`.
+fn next_pattern(table: &LookupTable, start: usize) -> Option<usize> {
+    if start >= TABLE_SIZE {
+        return None;
+    }
+    // Ignore the patterns before `start` in its word.
+    let first = table.inner[start / 64] & (u64::MAX << (start % 64));
+    std::iter::once(first)
+        .chain(table.inner[start / 64 + 1..].iter().copied())
+        .zip(start / 64..)
+        .find(|&(word, _)| word != 0)
+        .map(|(word, n)| n * 64 + word.trailing_zeros() as usize)
+}
+
+/// An iterator over the wire patterns of a [`LookupTable`], in ascending
+/// numeric order.
+///
+/// This `struct` is created by [`LookupTable::iter`].
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    table: &'a LookupTable,
+    next: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = WirePattern;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = next_pattern(self.table, self.next)?;
+        self.next = n + 1;
+        Some(WirePattern(u16::try_from(n).unwrap()))
+    }
+}
+
+/// An owning iterator over the wire patterns of a [`LookupTable`], in
+/// ascending numeric order.
+///
+/// This `struct` is created by the `into_iter` method of [`LookupTable`].
+#[derive(Clone, Debug)]
+pub struct IntoIter {
+    table: Box<LookupTable>,
+    next: usize,
+}
+
+impl Iterator for IntoIter {
+    type Item = WirePattern;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = next_pattern(&self.table, self.next)?;
+        self.next = n + 1;
+        Some(WirePattern(u16::try_from(n).unwrap()))
+    }
+}
+
+impl<'a> IntoIterator for &'a LookupTable {
+    type Item = WirePattern;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for LookupTable {
+    type Item = WirePattern;
+    type IntoIter = IntoIter;
+
+    /// Creates an owning iterator over the wire patterns of the lookup table,
+    /// in ascending numeric order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(0b11)]);
+    /// for pattern in table {
+    ///     assert_eq!(pattern.count_ones(), 2);
+    /// }
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            table: Box::new(self),
+            next: 0,
+        }
+    }
+}
+
+impl BitOr for &LookupTable {
+    type Output = LookupTable;
+
+    /// Returns the union of `self` and `rhs` (see [`LookupTable::union`]).
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
+
+impl BitAnd for &LookupTable {
+    type Output = LookupTable;
+
+    /// Returns the intersection of `self` and `rhs` (see
+    /// [`LookupTable::intersection`]).
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.intersection(rhs)
+    }
+}
+
+impl Sub for &LookupTable {
+    type Output = LookupTable;
+
+    /// Returns the difference of `self` and `rhs` (see
+    /// [`LookupTable::difference`]).
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.difference(rhs)
+    }
+}
+
+impl BitXor for &LookupTable {
+    type Output = LookupTable;
+
+    /// Returns the symmetric difference of `self` and `rhs` (see
+    /// [`LookupTable::symmetric_difference`]).
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        self.symmetric_difference(rhs)
+    }
+}
+
+impl Not for &LookupTable {
+    type Output = LookupTable;
+
+    /// Returns the complement of `self` (see [`LookupTable::complement`]).
+    fn not(self) -> Self::Output {
+        self.complement()
+    }
+}
+
+impl Index<WirePattern> for LookupTable {
+    type Output = bool;
+
+    /// Returns whether a wire pattern is in the lookup table. Same as
+    /// [`LookupTable::contains`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(1)]);
+    /// assert!(table[WirePattern::from_bits(1)]);
+    /// assert!(!table[WirePattern::from_bits(2)]);
+    /// ```
+    fn index(&self, wire_pattern: WirePattern) -> &bool {
+        if self.contains(wire_pattern) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl Default for LookupTable {
+    /// Creates a new empty lookup table.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extend<WirePattern> for LookupTable {
+    /// Adds all the wire patterns of an iterator to the lookup table.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let mut table = LookupTable::from([WirePattern::from_bits(0)]);
+    ///
+    /// table.extend([WirePattern::from_bits(0), WirePattern::from_bits(1)]);
+    /// assert_eq!(table.len(), 2);
+    /// ```
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = WirePattern>,
+    {
+        for wire_pattern in iter {
+            self.insert(wire_pattern);
+        }
+    }
+}
+
+impl<'a> Extend<&'a WirePattern> for LookupTable {
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = &'a WirePattern>,
+    {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+impl FromIterator<WirePattern> for LookupTable {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = WirePattern>,
+    {
+        let mut lookup_table = Self::new();
+        lookup_table.extend(iter);
+
+        lookup_table
+    }
+}
+
+impl<'a> FromIterator<&'a WirePattern> for LookupTable {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = &'a WirePattern>,
+    {
+        iter.into_iter().copied().collect()
+    }
+}
+
+impl From<&[WirePattern]> for LookupTable {
+    /// Converts a `&[WirePattern]` into a `LookupTable`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let patterns = vec![WirePattern::from_bits(0), WirePattern::from_bits(1)];
+    /// let table = LookupTable::from(patterns.as_slice());
+    /// assert_eq!(table, patterns.iter().collect());
+    /// ```
+    fn from(slice: &[WirePattern]) -> Self {
+        Self::from_iter(slice)
+    }
+}
+
+impl<const N: usize> From<[WirePattern; N]> for LookupTable {
+    /// Converts a `[WirePattern; N]` into a `LookupTable`.
+    ///
+    /// If the array contains any equal values, all but one will be dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table1 = LookupTable::from([WirePattern::from_bits(0)]);
+    /// let table2: LookupTable = [WirePattern::from_bits(0)].into();
+    /// assert_eq!(table1, table2);
+    /// ```
+    fn from(arr: [WirePattern; N]) -> Self {
+        Self::from_iter(arr)
+    }
+}
+
+// The description of a pattern in an MLU file, e.g.
+// `X..............X, 2 bits, 1 clusters`.
+fn description(pattern: WirePattern) -> String {
+    format!(
+        "{pattern}, {} bits, {} clusters",
+        pattern.count_ones(),
+        pattern.cluster_count()
+    )
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LookupTable {
+    /// Serializes the table as the sorted list of its patterns (see
+    /// [`LookupTable::iter`]), each as a hex string.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LookupTable {
+    /// Deserializes the table from a list of patterns. Duplicates are allowed.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = LookupTable;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a list of wire patterns")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut table = LookupTable::new();
+                while let Some(pattern) = seq.next_element()? {
+                    table.insert(pattern);
+                }
+                Ok(table)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+// A row of an MLU file.
+struct Row {
+    pattern: u16,
+    enabled: bool,
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:04x} {} {}",
+            self.pattern,
+            u8::from(self.enabled),
+            description(WirePattern(self.pattern))
+        )
+    }
+}
+
+/// Summary statistics of a lookup table (see [`LookupTable::stats`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TableStats {
+    /// Number of wire patterns in the table.
+    pub count: usize,
+    /// Number of wire patterns with `i` boards high (see
+    /// [`WirePattern::count_ones`]).
+    pub bits_histogram: [usize; 17],
+    /// Number of wire patterns with `i` clusters (see
+    /// [`WirePattern::cluster_count`]).
+    pub clusters_histogram: [usize; 9],
+    /// Fingerprint of the table (see [`LookupTable::fingerprint`]).
+    pub fingerprint: u64,
+}
+
+impl fmt::Display for TableStats {
+    /// Formats the statistics in a single line. Empty histogram bins are
+    /// omitted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let stats = LookupTable::min_wires(15).stats();
+    /// let expected = format!(
+    ///     "17 patterns enabled; bit-multiplicity histogram: 15: 16, 16: 1; \
+    ///      cluster-count histogram: 1: 17; fingerprint: {:#018x}",
+    ///     stats.fingerprint
+    /// );
+    /// assert_eq!(stats.to_string(), expected);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn histogram(bins: &[usize]) -> String {
+            bins.iter()
+                .enumerate()
+                .filter(|(_, &count)| count > 0)
+                .map(|(i, count)| format!("{i}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        write!(
+            f,
+            "{} patterns enabled; bit-multiplicity histogram: {}; cluster-count histogram: {}; \
+             fingerprint: {:#018x}",
+            self.count,
+            histogram(&self.bits_histogram),
+            histogram(&self.clusters_histogram),
+            self.fingerprint
+        )
+    }
+}
+
+/// Difference between two lookup tables (see [`LookupTable::diff`]).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    /// Wire patterns only in the new table, in ascending numeric order.
+    pub added: Vec<WirePattern>,
+    /// Wire patterns only in the old table, in ascending numeric order.
+    pub removed: Vec<WirePattern>,
+}
+
+impl TableDiff {
+    /// Returns `true` if both tables are equal.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for TableDiff {
+    /// Formats the difference as the MLU file lines of all the changed
+    /// patterns (in ascending numeric order), prefixed with `+` if the
+    /// pattern was added or `-` if it was removed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut added = self.added.iter().peekable();
+        let mut removed = self.removed.iter().peekable();
+        let mut first = true;
+        loop {
+            let (sign, WirePattern(pattern)) = match (added.peek(), removed.peek()) {
+                (Some(a), Some(r)) if a.0 < r.0 => ('+', *added.next().unwrap()),
+                (_, Some(_)) => ('-', *removed.next().unwrap()),
+                (Some(_), None) => ('+', *added.next().unwrap()),
+                (None, None) => break,
+            };
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            write!(
+                f,
+                "{sign}{}",
+                Row {
+                    pattern,
+                    enabled: true
+                }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for LookupTable {
+    /// Formats the lookup table as an MLU file (the format parsed by
+    /// [`LookupTable::from_str`](std::str::FromStr::from_str)): one line per
+    /// wire pattern in ascending numeric order (the order of
+    /// [`LookupTable::iter`]), without a trailing newline.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(3), WirePattern::from_bits(1)]);
+    /// assert_eq!(
+    ///     table.to_string(),
+    ///     "0x0001 1 X..............., 1 bits, 1 clusters\n\
+    ///      0x0003 1 XX.............., 2 bits, 1 clusters"
+    /// );
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, WirePattern(pattern)) in self.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{}",
+                Row {
+                    pattern,
+                    enabled: true
+                }
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// There is no point in adding any `context` to these errors unless I change
+// this to a "tokenize, then parse" approach (allowing for better semantic
+// errors with spans). But given the use case, I don't think it's worth it. A
+// simple "this line is wrong" is enough.
+//
+// Returns the pattern of an enabled row, and `None` for a disabled row.
+fn parse_line(input: &mut &str) -> winnow::Result<Option<u16>> {
+    let n: u16 = preceded((space0, alt(("0x", "0X"))), hex_uint).parse_next(input)?;
+    let enabled =
+        delimited(space1, alt(('0'.value(false), '1'.value(true))), space1).parse_next(input)?;
+
+    let pattern = WirePattern(n);
+    let _ = (
+        pattern.to_string().as_str(),
+        (",", space0),
+        dec_uint::<_, u32, _>.verify(|&bits| bits == pattern.count_ones()),
+        (space1, "bits", ",", space0),
+        dec_uint::<_, u32, _>.verify(|&clusters| clusters == pattern.cluster_count()),
+        (space1, "clusters", space0),
+    )
+        .parse_next(input)?;
+
+    Ok(enabled.then_some(n))
+}
+
+// A line exactly as written by `Display`.
+fn parse_line_strict(input: &mut &str) -> winnow::Result<u16> {
+    let n: u16 = delimited("0x", hex_uint, " 1 ").parse_next(input)?;
+    let _ = description(WirePattern(n)).as_str().parse_next(input)?;
+
+    Ok(n)
+}
+
+// Parse an MLU file line by line. Invalid lines are collected (and skipped)
+// instead of stopping at the first one.
+fn parse_table(input: &str, strict: bool) -> (LookupTable, Vec<LineError>) {
+    let mut table = LookupTable::new();
+    let mut errors = Vec::new();
+
+    let lines: Box<dyn Iterator<Item = &str>> = if strict {
+        // A single trailing newline is allowed, but not empty lines.
+        match input.strip_suffix('\n').unwrap_or(input) {
+            "" => Box::new(std::iter::empty()),
+            input => Box::new(input.split('\n')),
+        }
+    } else {
+        Box::new(input.lines())
+    };
+    for (index, line) in lines.enumerate() {
+        if !strict && line.trim().is_empty() {
+            continue;
+        }
+
+        let result = if strict {
+            parse_line_strict.map(Some).parse(line)
+        } else {
+            parse_line.parse(line)
+        };
+        match result {
+            Ok(Some(n)) => {
+                table.insert(WirePattern(n));
+            }
+            Ok(None) => {}
+            Err(error) => errors.push(LineError {
+                line: index + 1,
+                contents: line.to_string(),
+                span: char_span(line, error.offset()),
+            }),
+        }
+    }
+
+    (table, errors)
+}
+
+// Byte range of the character of `line` that contains the byte at `offset`
+// (empty at the end of the line).
+fn char_span(line: &str, offset: usize) -> std::ops::Range<usize> {
+    let offset = offset.min(line.len());
+    let start = (0..=offset)
+        .rev()
+        .find(|&i| line.is_char_boundary(i))
+        .unwrap_or(0);
+    let len = line[start..].chars().next().map_or(0, char::len_utf8);
+
+    start..start + len
+}
+
+/// An invalid line found when parsing a [`LookupTable`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineError {
+    line: usize,
+    contents: String,
+    span: std::ops::Range<usize>,
+}
+
+impl LineError {
+    /// Returns the line number (starting at 1).
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    /// Returns the contents of the line.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+    /// Returns the byte range of the line where parsing failed. It always
+    /// starts and ends at character boundaries of [`LineError::contents`].
+    pub fn span(&self) -> std::ops::Range<usize> {
+        self.span.clone()
+    }
+    fn snippet(&self) -> annotate_snippets::Snippet<'_> {
+        annotate_snippets::Snippet::source(&self.contents)
+            .line_start(self.line)
+            .annotation(annotate_snippets::Level::Error.span(self.span.clone()))
+    }
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = annotate_snippets::Level::Error
+            .title("invalid line starting here")
+            .snippet(self.snippet());
+        let renderer = annotate_snippets::Renderer::plain();
+        let rendered = renderer.render(message);
+        rendered.fmt(f)
+    }
+}
+
+impl std::error::Error for LineError {}
+
+/// The error type returned when parsing a [`LookupTable`] fails. Only the
+/// first invalid line is reported (see [`LookupTable::parse_all`] to get all
+/// of them).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError(LineError);
+
+impl ParseError {
+    /// Returns the first invalid line.
+    pub fn line_error(&self) -> &LineError {
+        &self.0
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// All the invalid lines found when parsing a [`LookupTable`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseErrors(Vec<LineError>);
+
+impl ParseErrors {
+    /// Returns the invalid lines, in order. There is always at least one.
+    pub fn errors(&self) -> &[LineError] {
+        &self.0
+    }
+}
+
+impl fmt::Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let title = format!("{} invalid lines", self.0.len());
+        let message = annotate_snippets::Level::Error
+            .title(&title)
+            .snippets(self.0.iter().map(LineError::snippet));
+        let renderer = annotate_snippets::Renderer::plain();
+        let rendered = renderer.render(message);
+        rendered.fmt(f)
+    }
+}
+
+impl std::error::Error for ParseErrors {}
+
+/// The error type returned when reading a binary [`LookupTable`] fails.
+#[derive(Debug)]
+pub enum BinaryError {
+    /// The reader failed (e.g. the input is truncated).
+    Io(std::io::Error),
+    /// The header is not a supported binary lookup table header.
+    Header([u8; 4]),
+    /// The raw bitset does not have [`TABLE_BYTES`] bytes.
+    Length(usize),
+}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => write!(f, "failed to read the binary lookup table"),
+            Self::Header(header) => write!(f, "invalid binary lookup table header `{header:02x?}`"),
+            Self::Length(len) => write!(f, "expected {TABLE_BYTES} bytes, found {len}"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Header(_) | Self::Length(_) => None,
+        }
+    }
+}
+
+impl std::str::FromStr for LookupTable {
+    type Err = ParseError;
+
+    /// Parse a [`LookupTable`] from a string. The string should have the same
+    /// format as processed by the real detector.
+    ///
+    /// Every line is a row `0x<pattern> <flag> <description>`. Only rows with
+    /// a `1` flag are added to the table; rows with a `0` flag (as in a full
+    /// dump of the detector MLU) are skipped. Uppercase hex digits and any
+    /// amount of spaces between fields and at the end of lines are allowed.
+    /// See [`LookupTable::from_str_strict`] to only accept the exact format of
+    /// [`Display`](fmt::Display).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use trg::mlu::LookupTable;
+    /// # use std::str::FromStr;
+    /// let string = std::fs::read_to_string("mlu_file.txt")?;
+    /// let table = LookupTable::from_str(&string)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match parse_table(input, false) {
+            (table, errors) if errors.is_empty() => Ok(table),
+            (_, errors) => Err(ParseError(errors.into_iter().next().unwrap())),
+        }
+    }
+}
+
+impl LookupTable {
+    /// Parse a [`LookupTable`] from a string with exactly the format of
+    /// [`Display`](fmt::Display): only enabled rows, with lowercase hex and
+    /// single spaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from([WirePattern::from_bits(1)]);
+    /// assert_eq!(LookupTable::from_str_strict(&table.to_string())?, table);
+    /// assert!(LookupTable::from_str_strict("0x0001  1 X..............., 1 bits, 1 clusters").is_err());
+    /// # Ok::<(), trg::mlu::ParseError>(())
+    /// ```
+    pub fn from_str_strict(input: &str) -> Result<Self, ParseError> {
+        match parse_table(input, true) {
+            (table, errors) if errors.is_empty() => Ok(table),
+            (_, errors) => Err(ParseError(errors.into_iter().next().unwrap())),
+        }
+    }
+    /// Parse a [`LookupTable`] in the same format as
+    /// [`LookupTable::from_str`](std::str::FromStr::from_str), reporting all
+    /// the invalid lines instead of only the first one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let errors = LookupTable::parse_all("0x0001 1 oops\n0x0002 1 oops").unwrap_err();
+    /// assert_eq!(errors.errors().len(), 2);
+    /// ```
+    pub fn parse_all(input: &str) -> Result<Self, ParseErrors> {
+        match parse_table(input, false) {
+            (table, errors) if errors.is_empty() => Ok(table),
+            (_, errors) => Err(ParseErrors(errors)),
+        }
+    }
+    /// Parse a [`LookupTable`] in the same format as
+    /// [`LookupTable::from_str`](std::str::FromStr::from_str), skipping the
+    /// invalid lines. Returns the table with all the valid lines, and the
+    /// invalid lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let input = "0x0001 1 oops\n0x0002 1 .X.............., 1 bits, 1 clusters";
+    /// let (table, errors) = LookupTable::parse_lossy(input);
+    /// assert_eq!(table, LookupTable::from([WirePattern::from_bits(2)]));
+    /// assert_eq!(errors[0].line(), 1);
+    /// ```
+    pub fn parse_lossy(input: &str) -> (Self, Vec<LineError>) {
+        parse_table(input, false)
+    }
+    /// Parse a [`LookupTable`] from a plain list of wire patterns separated by
+    /// commas and/or whitespace (including newlines). Values with a `0x`
+    /// prefix are hexadecimal, and values without a prefix are decimal.
+    ///
+    /// The error points to the first invalid value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from_hex_list("0x8e61, 0x00ff\n255")?;
+    /// assert_eq!(table.len(), 2);
+    /// assert!(table.contains(WirePattern::from_bits(0x8e61)));
+    /// # Ok::<(), trg::mlu::ParseError>(())
+    /// ```
+    pub fn from_hex_list(input: &str) -> Result<Self, ParseError> {
+        let mut table = Self::new();
+        for (index, line) in input.lines().enumerate() {
+            for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                if token.is_empty() {
+                    continue;
+                }
+
+                let value = match token.strip_prefix("0x").or(token.strip_prefix("0X")) {
+                    Some(hex) => u16::from_str_radix(hex, 16),
+                    None => token.parse(),
+                };
+                let Ok(n) = value else {
+                    // `token` is a subslice of `line`.
+                    let start = token.as_ptr() as usize - line.as_ptr() as usize;
+                    return Err(ParseError(LineError {
+                        line: index + 1,
+                        contents: line.to_string(),
+                        span: start..start + token.len(),
+                    }));
+                };
+                table.insert(WirePattern(n));
+            }
+        }
+
+        Ok(table)
+    }
+    /// Parse a [`LookupTable`] from a JSON array of wire patterns. Each
+    /// pattern is either an integer or a hex string (e.g. `"0x8e61"`).
+    ///
+    /// The error points to where the JSON parser failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::mlu::LookupTable;
+    ///
+    /// let table = LookupTable::from_json_array(r#"[36449, "0x00ff"]"#)?;
+    /// assert_eq!(table.len(), 2);
+    /// # Ok::<(), trg::mlu::ParseError>(())
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_json_array(input: &str) -> Result<Self, ParseError> {
+        serde_json::from_str(input).map_err(|error| {
+            let line = error.line().max(1);
+            let contents = input.lines().nth(line - 1).unwrap_or_default();
+            // The column (starting at 1, in bytes) of the last byte read.
+            let column = error.column().clamp(1, contents.len().max(1));
+            ParseError(LineError {
+                line,
+                contents: contents.to_string(),
+                span: char_span(contents, column - 1),
+            })
+        })
+    }
+}
+
+/// The condition that the cumulative [`WirePattern`] of a prompt window has to
+/// satisfy to produce a [`TrgSignal`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TriggerCondition {
+    /// The pattern is in the lookup table.
+    LookupTable(Box<LookupTable>),
+    /// At least this many boards are high (see [`WirePattern::count_ones`]).
+    MinWires(u8),
+    /// At least this many clusters of adjacent boards are high (see
+    /// [`WirePattern::cluster_count`]).
+    MinClusters(u8),
+    /// The pattern is in the lookup table of at least one item of the menu.
+    Menu(TriggerMenu),
+    /// Every wire event is a trigger on its own, without accumulation: the
+    /// [`Mlu`] produces a [`TrgSignal`] for each event, one prompt window
+    /// after it, without waiting for a later event.
+    Passthrough,
+}
+
+impl TriggerCondition {
+    /// Returns `true` if the given wire pattern satisfies the condition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::TriggerCondition;
+    ///
+    /// let pattern = WirePattern::from_bits(0b0000000000000101);
+    /// assert!(TriggerCondition::MinClusters(2).is_satisfied(pattern));
+    /// assert!(!TriggerCondition::MinWires(3).is_satisfied(pattern));
+    /// ```
+    pub fn is_satisfied(&self, wire_pattern: WirePattern) -> bool {
+        self.evaluate(wire_pattern).is_some()
+    }
+    // Returns the bitmask of matched menu items (always 0 for all other
+    // conditions) if the condition is satisfied.
+    fn evaluate(&self, wire_pattern: WirePattern) -> Option<u32> {
+        let satisfied = match self {
+            Self::LookupTable(table) => table.contains(wire_pattern),
+            Self::MinWires(n) => wire_pattern.count_ones() >= u32::from(*n),
+            Self::MinClusters(n) => wire_pattern.cluster_count() >= u32::from(*n),
+            Self::Passthrough => true,
+            Self::Menu(menu) => {
+                let matched = menu.matches(wire_pattern);
+                return (matched != 0).then_some(matched);
+            }
+        };
+        satisfied.then_some(0)
+    }
+}
+
+impl From<LookupTable> for TriggerCondition {
+    fn from(table: LookupTable) -> Self {
+        Self::LookupTable(Box::new(table))
+    }
+}
+
+impl From<Box<LookupTable>> for TriggerCondition {
+    fn from(table: Box<LookupTable>) -> Self {
+        Self::LookupTable(table)
+    }
+}
+
+impl From<TriggerMenu> for TriggerCondition {
+    fn from(menu: TriggerMenu) -> Self {
+        Self::Menu(menu)
+    }
+}
+
+/// List of labeled [`LookupTable`]s (trigger types) evaluated in parallel.
+///
+/// A prompt window produces a [`TrgSignal`] if its cumulative pattern is in any
+/// of the tables, and the signal is tagged with all the items that matched.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TriggerMenu {
+    items: Vec<(String, Box<LookupTable>)>,
+}
+
+impl TriggerMenu {
+    /// Maximum number of items in a menu (the width of
+    /// [`TrgSignal::matched`]).
+    pub const MAX_ITEMS: usize = 32;
+
+    /// Creates a new empty trigger menu.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Appends an item to the menu. The index of the item is its bit in
+    /// [`TrgSignal::matched`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the menu already has [`TriggerMenu::MAX_ITEMS`] items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::WirePattern;
+    /// use trg::mlu::{LookupTable, TriggerMenu};
+    ///
+    /// let mut menu = TriggerMenu::new();
+    /// menu.push("single", LookupTable::from([WirePattern::from_bits(0b1)]));
+    /// menu.push("double", LookupTable::from([WirePattern::from_bits(0b11)]));
+    ///
+    /// assert_eq!(menu.label(1), Some("double"));
+    /// assert_eq!(menu.matches(WirePattern::from_bits(0b11)), 0b10);
+    /// ```
+    pub fn push(&mut self, label: impl Into<String>, table: impl Into<Box<LookupTable>>) {
+        assert!(
+            self.items.len() < Self::MAX_ITEMS,
+            "trigger menu has more than {} items",
+            Self::MAX_ITEMS
+        );
+        self.items.push((label.into(), table.into()));
+    }
+    /// Returns the number of items in the menu.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    /// Returns `true` if the menu has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+    /// Returns the label of the item at the given index.
+    pub fn label(&self, index: usize) -> Option<&str> {
+        self.items.get(index).map(|(label, _)| label.as_str())
+    }
+    /// Returns the bitmask of all items whose table contains the given wire
+    /// pattern.
+    pub fn matches(&self, wire_pattern: WirePattern) -> u32 {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, table))| table.contains(wire_pattern))
+            .fold(0, |matched, (index, _)| matched | (1 << index))
+    }
+    /// Returns an iterator over the labels of the items in a bitmask (e.g.
+    /// [`TrgSignal::matched`]).
+    pub fn labels(&self, matched: u32) -> impl Iterator<Item = &str> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| matched & (1 << index) != 0)
+            .map(|(_, (label, _))| label.as_str())
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, LookupTable)> for TriggerMenu {
+    fn from_iter<I: IntoIterator<Item = (S, LookupTable)>>(iter: I) -> Self {
+        let mut menu = Self::new();
+        for (label, table) in iter {
+            menu.push(label, table);
+        }
+        menu
+    }
+}
+
+/// Grouping (ganging) of the input boards into the 16 bits of the pattern seen
+/// by the MLU.
+///
+/// Each output bit is the OR of the input bits selected by its mask. Groups
+/// can overlap e.g. to study alternative ganging schemes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireMapping {
+    masks: [u16; 16],
+}
+
+impl WireMapping {
+    /// Create a new [`WireMapping`]. The `n`th mask selects the input bits
+    /// that are OR-ed into the `n`th output bit (where bit `n` is `1 << n`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use trg::gen::WirePattern;
+    /// use trg::mlu::WireMapping;
+    ///
+    /// // Each output bit is the OR of two adjacent input bits.
+    /// let mapping = WireMapping::new(std::array::from_fn(|n| 0b11u16.rotate_left(n as u32)));
+    /// assert_eq!(
+    ///     mapping.apply(WirePattern::from_bits(0b1000)),
+    ///     WirePattern::from_bits(0b1100)
+    /// );
+    /// ```
+    pub fn new(masks: [u16; 16]) -> Self {
+        Self { masks }
+    }
+    /// Each output bit is the same as the corresponding input bit.
+    pub fn identity() -> Self {
+        Self::new(std::array::from_fn(|n| 1 << n))
+    }
+    /// Returns the output pattern of the given input pattern.
+    pub fn apply(&self, wire_pattern: WirePattern) -> WirePattern {
+        let bits = self
+            .masks
+            .iter()
+            .enumerate()
+            .filter(|(_, mask)| wire_pattern.0 & **mask != 0)
+            .fold(0, |bits, (n, _)| bits | (1 << n));
+
+        WirePattern(bits)
+    }
+}
+
+impl From<[u16; 16]> for WireMapping {
+    fn from(masks: [u16; 16]) -> Self {
+        Self::new(masks)
+    }
+}
+
+/// Whether an event exactly at the end of a time window is inside it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// The window is `[start, end)`.
+    #[default]
+    Exclusive,
+    /// The window is `[start, end]`.
+    Inclusive,
+}
+
+impl Boundary {
+    // Returns `true` if `time` is before the end of a window (i.e. inside it,
+    // given that it is not before its start).
+    fn is_before<T: PartialOrd>(self, time: &T, end: &T) -> bool {
+        match self {
+            Self::Exclusive => time < end,
+            Self::Inclusive => time <= end,
+        }
+    }
+}
+
+/// Comparison used at the end of the prompt window and the wait gate of the
+/// [`Mlu`].
+///
+/// Both boundaries are exclusive by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BoundaryPolicy {
+    /// An event exactly at the end of the prompt window is accumulated if
+    /// inclusive; otherwise it closes the window.
+    pub prompt_window: Boundary,
+    /// An event exactly at the end of the wait gate extends it if inclusive;
+    /// otherwise it opens a new prompt window.
+    pub wait_gate: Boundary,
+}
+
+/// Output of the MLU for a prompt window that satisfies the
+/// [`TriggerCondition`].
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrgSignal<T> {
+    /// Time of the signal: the end of the prompt window, plus the latency of
+    /// the MLU (see [`Mlu::with_latency`]).
+    pub time: T,
+    /// Time of the first wire event in the prompt window.
+    pub window_start: T,
+    /// End of the prompt window. All the wire events accumulated in the
+    /// window are in `[window_start, window_stop)` (or `[window_start,
+    /// window_stop]`, see [`BoundaryPolicy`]).
+    pub window_stop: T,
+    /// Bitmask of the [`TriggerMenu`] items that matched the cumulative
+    /// pattern. Always 0 unless the trigger condition is a
+    /// [`TriggerCondition::Menu`].
+    pub matched: u32,
+    /// Product of the [`weight`](WireEvent::weight)s of all the wire events
+    /// accumulated in the prompt window. Events are assumed independent, so
+    /// this is the weight of the combination that produced the signal.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default = "crate::gen::unit_weight",
+            skip_serializing_if = "crate::gen::is_unit_weight"
+        )
+    )]
+    pub weight: f64,
+}
+
+// The outcome of a prompt window that satisfies the trigger condition.
+#[derive(Clone, Copy, Debug)]
+pub(super) enum MluDecision<T> {
+    Accept(TrgSignal<T>),
+    // The pattern is also in the veto table.
+    Veto(TrgSignal<T>),
+}
+
+impl<T> MluDecision<T> {
+    pub(super) fn time(&self) -> &T {
+        match self {
+            Self::Accept(signal) | Self::Veto(signal) => &signal.time,
+        }
+    }
+    fn accepted(self) -> Option<TrgSignal<T>> {
+        match self {
+            Self::Accept(signal) => Some(signal),
+            Self::Veto(_) => None,
+        }
+    }
+}
+
+/// Counters of the [`Mlu`], comparable to the scalers of the hardware.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MluStats {
+    /// Number of prompt windows opened.
+    pub windows_opened: u64,
+    /// Number of prompt windows decided (i.e. lookups performed).
+    pub decisions: u64,
+    /// Number of decisions that produced a [`TrgSignal`] (i.e. that satisfied
+    /// the trigger condition and were not vetoed).
+    pub accepted: u64,
+    /// Number of wire events ignored during the wait gate (each one extends
+    /// it).
+    pub wait_gate_extensions: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MluState<T> {
+    Idle,
+    // Accumulating wire patterns during the prompt window.
+    Accumulate {
+        // Time of the event that opened the prompt window.
+        start_time: T,
+        // Time when accumulation will stop and a TRG decision will be made.
+        stop_time: T,
+        cumulative: WirePattern,
+        // Product of the weights of the accumulated events.
+        weight: f64,
+    },
+    Wait {
+        stop_time: T,
+    },
+}
+
+/// Memory Lookup Unit.
+///
+/// The MLU turns a stream of [`WireEvent`]s into [`TrgSignal`]s. It is a state
+/// machine with three states:
+///
+/// - Idle: nothing has happened yet. The first event opens a prompt window.
+/// - Accumulate: a prompt window is open. The wire patterns of all events
+///   before the end of the window (first event time + `prompt_window`) are
+///   combined with a bitwise OR. The first event at or after the end of the
+///   window closes it, and a [`TrgSignal`] (timestamped at the end of the
+///   window) is produced if the combined pattern satisfies the
+///   [`TriggerCondition`] (e.g. it is in the [`LookupTable`]), unless it is
+///   also in the optional veto table (see [`Mlu::with_veto_table`]).
+///   If that event is also before the end of the window + `wait_gate`, the
+///   MLU goes into the wait state; otherwise it opens a new prompt window.
+/// - Wait: events are ignored until there is a gap of at least `wait_gate`
+///   without events. Each ignored event extends the wait gate. The first event
+///   after the wait gate opens a new prompt window.
+///
+/// Events exactly at the end of the prompt window or the wait gate are outside
+/// of them by default (see [`Mlu::with_boundary_policy`]).
+///
+/// Note that a decision is only made when a later event arrives (or when the
+/// MLU is explicitly flushed with [`Mlu::flush`]), except with a
+/// [`TriggerCondition::Passthrough`] where the MLU never leaves the idle state.
+///
+/// # Examples
+///
+/// ```
+/// # use trg::gen::{Positive, Source, WireEvent, WirePattern};
+/// use trg::mlu::{LookupTable, Mlu};
+///
+/// let table = LookupTable::from([WirePattern::from_bits(0b11)]);
+/// let mut mlu = Mlu::new(Positive::new(10).unwrap(), Positive::new(5).unwrap(), table);
+///
+/// let event = |time, bits| WireEvent {
+///     source: Source::Noise,
+///     wire_pattern: WirePattern::from_bits(bits),
+///     time,
+///     id: None,
+///     weight: 1.0,
+/// };
+/// assert!(mlu.process(&event(0, 0b01)).is_none());
+/// assert!(mlu.process(&event(5, 0b10)).is_none());
+/// // The prompt window [0, 10) is closed by the next event.
+/// assert_eq!(mlu.process(&event(30, 0b01)).unwrap().time, 10);
+/// // An open prompt window can be resolved at the end of a stream.
+/// assert!(mlu.flush().is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mlu<T> {
+    state: MluState<T>,
+    prompt_window: Positive<T>,
+    wait_gate: Positive<T>,
+    condition: TriggerCondition,
+    veto_table: Option<Box<LookupTable>>,
+    // `None` is the same as the identity mapping.
+    mapping: Option<WireMapping>,
+    boundary_policy: BoundaryPolicy,
+    // Delay between the end of a prompt window and its `TrgSignal`.
+    latency: Option<Positive<T>>,
+    stats: MluStats,
+}
+
+impl<T> Mlu<T> {
+    /// Create a new MLU in the idle state. A [`LookupTable`] (either by value
+    /// or already boxed) can be used directly as the trigger condition.
+    pub fn new(
+        prompt_window: Positive<T>,
+        wait_gate: Positive<T>,
+        condition: impl Into<TriggerCondition>,
+    ) -> Self {
+        Self {
+            state: MluState::Idle,
+            prompt_window,
+            wait_gate,
+            condition: condition.into(),
+            veto_table: None,
+            mapping: None,
+            boundary_policy: BoundaryPolicy::default(),
+            latency: None,
+            stats: MluStats::default(),
+        }
+    }
+    /// Suppress the decision of all prompt windows with a cumulative pattern
+    /// in the given veto table, even if they satisfy the trigger condition.
+    pub fn with_veto_table(mut self, table: impl Into<Box<LookupTable>>) -> Self {
+        self.veto_table = Some(table.into());
+        self
+    }
+    /// Map the pattern of all wire events before they are accumulated in a
+    /// prompt window.
+    pub fn with_wire_mapping(mut self, mapping: WireMapping) -> Self {
+        self.mapping = Some(mapping);
+        self
+    }
+    /// Set the comparison used at the end of the prompt window and the wait
+    /// gate.
+    pub fn with_boundary_policy(mut self, boundary_policy: BoundaryPolicy) -> Self {
+        self.boundary_policy = boundary_policy;
+        self
+    }
+    /// Delay the [`TrgSignal`] of each decision by a fixed time after the end
+    /// of its prompt window. The prompt window and wait gate are not affected.
+    pub fn with_latency(mut self, latency: Positive<T>) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+    /// Returns the counters of everything the MLU has done so far.
+    pub fn stats(&self) -> MluStats {
+        self.stats
+    }
+    // Replace the trigger condition used for all subsequent decisions.
+    pub(super) fn set_condition(&mut self, condition: TriggerCondition) {
+        self.condition = condition;
+    }
+}
+
+impl<T> Mlu<T>
+where
+    T: Add<Output = T> + PartialOrd + Clone,
+{
+    fn decide(
+        &mut self,
+        start_time: T,
+        stop_time: T,
+        cumulative: WirePattern,
+        weight: f64,
+    ) -> Option<MluDecision<T>> {
+        self.stats.decisions += 1;
+        let matched = self.condition.evaluate(cumulative)?;
+        let time = match &self.latency {
+            Some(latency) => stop_time.clone() + latency.inner().clone(),
+            None => stop_time.clone(),
+        };
+        let signal = TrgSignal {
+            time,
+            window_start: start_time,
+            window_stop: stop_time,
+            matched,
+            weight,
+        };
+        if self
+            .veto_table
+            .as_ref()
+            .is_some_and(|table| table.contains(cumulative))
+        {
+            Some(MluDecision::Veto(signal))
+        } else {
+            self.stats.accepted += 1;
+            Some(MluDecision::Accept(signal))
+        }
+    }
+    // Make the decision of an open prompt window (using the current trigger
+    // condition).
+    pub(super) fn close_window(&mut self) -> Option<MluDecision<T>> {
+        let MluState::Accumulate {
+            start_time,
+            stop_time,
+            cumulative,
+            weight,
+        } = &self.state
+        else {
+            return None;
+        };
+        let (start_time, stop_time, cumulative, weight) =
+            (start_time.clone(), stop_time.clone(), *cumulative, *weight);
+        // Any event before `stop_time + wait_gate` extends the wait gate, and
+        // any later event opens a new prompt window.
+        self.state = MluState::Wait {
+            stop_time: stop_time.clone() + self.wait_gate.inner().clone(),
+        };
+        self.decide(start_time, stop_time, cumulative, weight)
+    }
+    /// Make the decision of an open prompt window that closed before `time`
+    /// (using the current trigger condition). This is equivalent to what
+    /// [`Mlu::process`] would do on the next event, and it is needed before
+    /// changing the trigger condition.
+    pub(super) fn close_window_before(&mut self, time: &T) -> Option<MluDecision<T>> {
+        match &self.state {
+            MluState::Accumulate { stop_time, .. } if stop_time < time => self.close_window(),
+            _ => None,
+        }
+    }
+    /// Make the decision of a prompt window that is still open e.g. at the end
+    /// of a stream of events. This is what [`Mlu::process`] would do if the
+    /// next event arrived after the end of the prompt window.
+    pub fn flush(&mut self) -> Option<TrgSignal<T>> {
+        self.close_window().and_then(MluDecision::accepted)
+    }
+    /// Process the next [`WireEvent`] (events are expected in increasing order
+    /// of time). Returns the [`TrgSignal`] of a prompt window closed by this
+    /// event, if any.
+    pub fn process(&mut self, event: &WireEvent<T>) -> Option<TrgSignal<T>> {
+        self.step(event).and_then(MluDecision::accepted)
+    }
+    pub(super) fn step(&mut self, event: &WireEvent<T>) -> Option<MluDecision<T>> {
+        let wire_pattern = match &self.mapping {
+            Some(mapping) => mapping.apply(event.wire_pattern),
+            None => event.wire_pattern,
+        };
+        if matches!(self.condition, TriggerCondition::Passthrough) {
+            // Each event is its own prompt window, decided right away.
+            self.stats.windows_opened += 1;
+            return self.decide(
+                event.time.clone(),
+                event.time.clone() + self.prompt_window.inner().clone(),
+                wire_pattern,
+                event.weight,
+            );
+        }
+        match std::mem::replace(&mut self.state, MluState::Idle) {
+            MluState::Accumulate {
+                start_time,
+                stop_time,
+                cumulative,
+                weight,
+            } => {
+                if self
+                    .boundary_policy
+                    .prompt_window
+                    .is_before(&event.time, &stop_time)
+                {
                     self.state = MluState::Accumulate {
+                        start_time,
                         stop_time,
-                        cumulative: cumulative | event.wire_pattern,
+                        cumulative: cumulative | wire_pattern,
+                        weight: weight * event.weight,
                     };
                     None
-                } else if event.time < stop_time.clone() + self.wait_gate.inner().clone() {
+                } else if self.boundary_policy.wait_gate.is_before(
+                    &event.time,
+                    &(stop_time.clone() + self.wait_gate.inner().clone()),
+                ) {
+                    self.stats.wait_gate_extensions += 1;
                     self.state = MluState::Wait {
                         stop_time: event.time.clone() + self.wait_gate.inner().clone(),
                     };
-                    match self.table.contains(cumulative) {
-                        true => Some(TrgSignal { time: stop_time }),
-                        false => None,
-                    }
+                    self.decide(start_time, stop_time, cumulative, weight)
                 } else {
+                    self.stats.windows_opened += 1;
                     self.state = MluState::Accumulate {
+                        start_time: event.time.clone(),
                         stop_time: event.time.clone() + self.prompt_window.inner().clone(),
-                        cumulative: event.wire_pattern,
+                        cumulative: wire_pattern,
+                        weight: event.weight,
                     };
-                    match self.table.contains(cumulative) {
-                        true => Some(TrgSignal { time: stop_time }),
-                        false => None,
-                    }
+                    self.decide(start_time, stop_time, cumulative, weight)
                 }
             }
             MluState::Wait { stop_time } => {
-                if event.time < stop_time {
+                if self
+                    .boundary_policy
+                    .wait_gate
+                    .is_before(&event.time, &stop_time)
+                {
+                    self.stats.wait_gate_extensions += 1;
                     self.state = MluState::Wait {
                         stop_time: event.time.clone() + self.wait_gate.inner().clone(),
                     };
                     None
                 } else {
+                    self.stats.windows_opened += 1;
                     self.state = MluState::Accumulate {
+                        start_time: event.time.clone(),
                         stop_time: event.time.clone() + self.prompt_window.inner().clone(),
-                        cumulative: event.wire_pattern,
+                        cumulative: wire_pattern,
+                        weight: event.weight,
                     };
                     None
                 }
             }
             MluState::Idle => {
+                self.stats.windows_opened += 1;
                 self.state = MluState::Accumulate {
+                    start_time: event.time.clone(),
                     stop_time: event.time.clone() + self.prompt_window.inner().clone(),
-                    cumulative: event.wire_pattern,
+                    cumulative: wire_pattern,
+                    weight: event.weight,
                 };
                 None
             }
         }
     }
-}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn lookup_table_new() {
+        let table = LookupTable::new();
+
+        for n in 0..=u16::MAX {
+            let pattern = WirePattern::from_bits(n);
+            assert!(!table.contains(pattern));
+        }
+    }
+
+    #[test]
+    fn lookup_table_insert() {
+        let mut table = LookupTable::new();
+
+        assert!(!table.contains(WirePattern::from_bits(0)));
+        assert!(table.insert(WirePattern::from_bits(0)));
+        assert!(table.contains(WirePattern::from_bits(0)));
+        assert!(!table.insert(WirePattern::from_bits(0)));
+        assert!(table.contains(WirePattern::from_bits(0)));
+    }
+
+    #[test]
+    fn lookup_table_remove() {
+        let mut table = LookupTable::new();
+
+        table.insert(WirePattern::from_bits(0));
+        assert!(table.contains(WirePattern::from_bits(0)));
+        assert!(table.remove(WirePattern::from_bits(0)));
+        assert!(!table.contains(WirePattern::from_bits(0)));
+        assert!(!table.remove(WirePattern::from_bits(0)));
+    }
+
+    #[test]
+    fn lookup_table_from_iter() {
+        let ps = [
+            WirePattern::from_bits(1),
+            WirePattern::from_bits(2),
+            WirePattern::from_bits(2),
+            WirePattern::from_bits(3),
+        ];
+
+        let table: LookupTable = ps.iter().copied().collect();
+
+        for p in &ps {
+            assert!(table.contains(*p));
+        }
+    }
+
+    #[test]
+    fn lookup_table_from_array() {
+        let table = LookupTable::from([
+            WirePattern::from_bits(0),
+            WirePattern::from_bits(1),
+            WirePattern::from_bits(2),
+        ]);
+        let unordered_table = LookupTable::from([
+            WirePattern::from_bits(2),
+            WirePattern::from_bits(1),
+            WirePattern::from_bits(0),
+            WirePattern::from_bits(2),
+        ]);
+        assert_eq!(table, unordered_table);
+    }
+
+    #[test]
+    fn lookup_table_to_string() {
+        let mut table = LookupTable::new();
+        assert_eq!(table.to_string(), "");
+
+        table.insert(WirePattern::from_bits(u16::MAX));
+        assert_eq!(
+            table.to_string(),
+            "0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters"
+        );
+
+        table.insert(WirePattern::from_bits(0));
+        assert_eq!(
+            table.to_string(),
+            "0x0000 1 ................, 0 bits, 0 clusters
+0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters"
+        );
+
+        table.insert(WirePattern::from_bits(36449));
+        assert_eq!(
+            table.to_string(),
+            "0x0000 1 ................, 0 bits, 0 clusters
+0x8e61 1 X....XX..XXX...X, 7 bits, 3 clusters
+0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters"
+        );
+    }
+
+    #[test]
+    fn lookup_table_from_str() {
+        let mut string = String::new();
+        let mut table = LookupTable::new();
+        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+
+        string.push_str("0x0000 1 ................, 0 bits, 0 clusters");
+        table.insert(WirePattern::from_bits(0));
+        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+
+        string.push_str("\n0x0000 1 ................, 0 bits, 0 clusters\n");
+        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+
+        string.push_str("0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters");
+        table.insert(WirePattern::from_bits(u16::MAX));
+        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+
+        assert_eq!(table, LookupTable::from_str(&table.to_string()).unwrap());
+    }
+
+    // Excerpt of a full dump of the detector MLU (every pattern, with its
+    // enabled flag).
+    const FULL_DUMP: &str = "0x0000 0 ................, 0 bits, 0 clusters
+0x0001 0 X..............., 1 bits, 1 clusters
+0x0002 1 .X.............., 1 bits, 1 clusters
+0x0003 0 XX.............., 2 bits, 1 clusters
+0x0004 0 ..X............., 1 bits, 1 clusters
+0x0005 1 X.X............., 2 bits, 2 clusters
+";
+
+    #[test]
+    fn lookup_table_from_str_full_dump() {
+        let expected = table(&[0x0002, 0x0005]);
+        assert_eq!(LookupTable::from_str(FULL_DUMP).unwrap(), expected);
+        assert!(LookupTable::from_str_strict(FULL_DUMP).is_err());
+
+        let variations = "  0x0002   1   .X..............,   1 bits,1  clusters  
+0X0005 1 X.X............., 2 bits, 2 clusters\t
+0x00Ff 0 XXXXXXXX........, 8 bits, 1 clusters
+
+";
+        assert_eq!(LookupTable::from_str(variations).unwrap(), expected);
+        assert!(LookupTable::from_str_strict(variations).is_err());
+
+        for line in [
+            "0x0002 2 .X.............., 1 bits, 1 clusters",
+            "0x0002 1 .X.............., 2 bits, 1 clusters",
+            "0x0002 1 .X.............., 1 bits, 0 clusters",
+            "0x0002 1 X..............., 1 bits, 1 clusters",
+            "0x00021 .X.............., 1 bits, 1 clusters",
+        ] {
+            assert!(LookupTable::from_str(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn lookup_table_from_str_strict() {
+        let table = table(&[0, 0x8e61, 0xffff]);
+        assert_eq!(
+            LookupTable::from_str_strict(&table.to_string()).unwrap(),
+            table
+        );
+        assert_eq!(
+            LookupTable::from_str_strict(&format!("{table}\n")).unwrap(),
+            table
+        );
+        assert!(LookupTable::from_str_strict(&format!("{table}\n\n")).is_err());
+        assert!(LookupTable::from_str_strict(&table.to_string().to_uppercase()).is_err());
+    }
+
+    #[test]
+    fn lookup_table_parse_errors() {
+        let input = "0x0002 1 .X.............., 1 bits, 1 clusters
+0x0003 1 XX.............., 1 bits, 1 clusters
+0x0005 1 X.X............., 2 bits, 2 clusters
+0x0006 7 .XX.............., 2 bits, 1 clusters
+";
+        let (lossy, errors) = LookupTable::parse_lossy(input);
+        assert_eq!(lossy, table(&[0x0002, 0x0005]));
+        assert_eq!(
+            errors.iter().map(LineError::line).collect::<Vec<_>>(),
+            [2, 4]
+        );
+        assert_eq!(
+            errors[1].contents(),
+            "0x0006 7 .XX.............., 2 bits, 1 clusters"
+        );
+        assert_eq!(errors[1].span(), 7..8);
+
+        let error = LookupTable::from_str(input).unwrap_err();
+        assert_eq!(*error.line_error(), errors[0]);
+        assert!(error.to_string().contains("2 | 0x0003"));
+
+        let all = LookupTable::parse_all(input).unwrap_err();
+        assert_eq!(all.errors(), errors);
+        let rendered = all.to_string();
+        assert!(rendered.contains("2 invalid lines"));
+        assert!(rendered.contains("2 | 0x0003"));
+        assert!(rendered.contains("4 | 0x0006"));
+
+        assert_eq!(
+            LookupTable::parse_all(input.lines().next().unwrap()),
+            Ok(table(&[2]))
+        );
+    }
+
+    #[test]
+    fn lookup_table_parse_errors_non_ascii() {
+        // `é` is 2 bytes long.
+        let (_, errors) =
+            LookupTable::parse_lossy("0x0002 1 é.X.............., 1 bits, 1 clusters");
+        assert_eq!(errors[0].span(), 9..11);
+        assert_eq!(&errors[0].contents()[errors[0].span()], "é");
+
+        let (_, errors) = LookupTable::parse_lossy("0x0002 é .X.............., 1 bits, 1 clusters");
+        assert_eq!(errors[0].span(), 7..9);
+        assert_eq!(&errors[0].contents()[errors[0].span()], "é");
+
+        assert_eq!(char_span("aé", 1), 1..3);
+        assert_eq!(char_span("aé", 2), 1..3);
+        assert_eq!(char_span("aé", 3), 3..3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn lookup_table_from_json_array_non_ascii() {
+        let error = LookupTable::from_json_array("[1, é]").unwrap_err();
+        let error = error.line_error();
+        assert_eq!(&error.contents()[error.span()], "é");
+    }
+
+    #[test]
+    fn lookup_table_write_to() {
+        let table = table(&[0, 0x8e61, 0xffff]);
+        let mut buffer = Vec::new();
+        table.write_to(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text, format!("{table}\n"));
+        assert_eq!(LookupTable::from_str_strict(&text).unwrap(), table);
+
+        let mut buffer = Vec::new();
+        LookupTable::new().write_to(&mut buffer).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn lookup_table_write_full_dump() {
+        let table = table(&[0x0002, 0x0005]);
+        let mut buffer = Vec::new();
+        table.write_full_dump(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text.lines().count(), 65536);
+        assert!(text.starts_with(FULL_DUMP));
+        assert!(text.ends_with("0xffff 0 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters\n"));
+        assert_eq!(LookupTable::from_str(&text).unwrap(), table);
+
+        let all = LookupTable::min_wires(0);
+        let mut buffer = Vec::new();
+        all.write_full_dump(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text, format!("{all}\n"));
+    }
+
+    #[test]
+    fn lookup_table_stats() {
+        let stats = LookupTable::new().stats();
+        assert_eq!(stats.count, 0);
+        assert_eq!(
+            stats.to_string(),
+            "0 patterns enabled; bit-multiplicity histogram: ; cluster-count histogram: ; \
+             fingerprint: 0xb9d103fd6854a325"
+        );
+
+        let stats = LookupTable::min_wires(0).stats();
+        assert_eq!(stats.count, 65536);
+        // Binomial coefficients.
+        assert_eq!(stats.bits_histogram[..3], [1, 16, 120]);
+        assert_eq!(stats.bits_histogram[8], 12870);
+        assert_eq!(stats.bits_histogram.iter().sum::<usize>(), 65536);
+        assert_eq!(stats.clusters_histogram[..2], [1, 16 * 15 + 1]);
+        assert_eq!(stats.clusters_histogram[8], 2);
+        assert_eq!(stats.clusters_histogram.iter().sum::<usize>(), 65536);
+
+        // Wrap-around clusters.
+        let stats = table(&[0x8001, 0xc003, 0x8002, 0x4001, 0x7ffe]).stats();
+        assert_eq!(stats.count, 5);
+        assert_eq!(stats.bits_histogram[..5], [0, 0, 3, 0, 1]);
+        assert_eq!(stats.bits_histogram[14], 1);
+        assert_eq!(stats.clusters_histogram[..3], [0, 3, 2]);
+        assert_eq!(
+            stats.to_string(),
+            format!(
+                "5 patterns enabled; bit-multiplicity histogram: 2: 3, 4: 1, 14: 1; \
+                 cluster-count histogram: 1: 3, 2: 2; fingerprint: {:#018x}",
+                stats.fingerprint
+            )
+        );
+    }
+
+    #[test]
+    fn lookup_table_retain() {
+        let mut retained = table(&[0, 1, 63, 64, 0x0101, 0xffff]);
+        let mut calls = Vec::new();
+        retained.retain(|pattern| {
+            calls.push(pattern.0);
+            pattern.cluster_count() == 1
+        });
+        assert_eq!(calls, [0, 1, 63, 64, 0x0101, 0xffff]);
+        assert_eq!(retained, table(&[1, 63, 64, 0xffff]));
+
+        retained.retain(|_| true);
+        assert_eq!(retained.len(), 4);
+        retained.retain(|_| false);
+        assert!(retained.is_empty());
+    }
+
+    #[test]
+    fn lookup_table_extend() {
+        let patterns = [1, 2, 0xffff].map(WirePattern::from_bits);
+        let mut extended = table(&[0, 1]);
+        extended.extend(patterns);
+        assert_eq!(extended, table(&[0, 1, 2, 0xffff]));
+        extended.extend(&[WirePattern::from_bits(3)]);
+        assert_eq!(extended.len(), 5);
+
+        assert_eq!(
+            LookupTable::from(&patterns[..]),
+            LookupTable::from(patterns)
+        );
+        assert_eq!(
+            patterns.iter().collect::<LookupTable>(),
+            LookupTable::from(patterns)
+        );
+    }
+
+    #[test]
+    fn lookup_table_close_under_rotation() {
+        // Orbits of 1, 2, 4, and 16 patterns.
+        let mut closed = table(&[0, 0x5555, 0x1111, 0x0003]);
+        assert!(!closed.is_rotation_invariant());
+        closed.close_under_rotation();
+        assert!(closed.is_rotation_invariant());
+        assert_eq!(closed.len(), 1 + 2 + 4 + 16);
+        assert!(closed.contains(WirePattern::from_bits(0xaaaa)));
+        assert!(closed.contains(WirePattern::from_bits(0x8888)));
+
+        let before = closed;
+        closed.close_under_rotation();
+        assert_eq!(closed, before);
+
+        assert!(LookupTable::new().is_rotation_invariant());
+        assert!(LookupTable::min_clusters(2).is_rotation_invariant());
+        assert!(!table(&[0x5555]).is_rotation_invariant());
+        assert!(table(&[0x5555, 0xaaaa]).is_rotation_invariant());
+    }
+
+    #[test]
+    fn lookup_table_random() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        for density in [0.0, 0.01, 0.5, 0.9, 1.0] {
+            let random = LookupTable::random(density, &mut StdRng::seed_from_u64(7));
+            // 5 standard deviations of a binomial distribution.
+            let sigma = (TABLE_SIZE as f64 * density * (1.0 - density)).sqrt();
+            let expected = TABLE_SIZE as f64 * density;
+            assert!(
+                (random.len() as f64 - expected).abs() <= 5.0 * sigma,
+                "{density}: {}",
+                random.len()
+            );
+            assert_eq!(
+                random,
+                LookupTable::random(density, &mut StdRng::seed_from_u64(7))
+            );
+        }
+        assert_ne!(
+            LookupTable::random(0.5, &mut StdRng::seed_from_u64(1)),
+            LookupTable::random(0.5, &mut StdRng::seed_from_u64(2))
+        );
+    }
+
+    #[test]
+    fn lookup_table_random_with_min_bits() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let random = LookupTable::random_with_min_bits(0.5, 4, &mut rng);
+        assert!(random.iter().all(|pattern| pattern.count_ones() >= 4));
+        // Patterns with at least 4 boards high.
+        let eligible = 65536 - (1 + 16 + 120 + 560);
+        let sigma = (eligible as f64 * 0.25).sqrt();
+        assert!((random.len() as f64 - eligible as f64 * 0.5).abs() <= 5.0 * sigma);
+
+        let all = LookupTable::random_with_min_bits(1.0, 4, &mut rng);
+        assert_eq!(all, LookupTable::min_wires(4));
+        assert_eq!(all.len(), eligible);
+    }
+
+    #[test]
+    #[should_panic(expected = "density 1.5 is not in [0, 1]")]
+    fn lookup_table_random_invalid_density() {
+        LookupTable::random(1.5, &mut rand::rng());
+    }
+
+    #[test]
+    fn lookup_table_fingerprint() {
+        // Pinned values: changing them breaks comparisons with fingerprints
+        // stored elsewhere.
+        assert_eq!(LookupTable::new().fingerprint(), 0xb9d103fd6854a325);
+        assert_eq!(table(&[0x0002]).fingerprint(), 0xac394db80fd2a321);
+        assert_eq!(LookupTable::min_wires(0).fingerprint(), 0x9c50825ef0adc325);
+        assert_eq!(
+            LookupTable::min_clusters(2).fingerprint(),
+            0xf14b81e6fdb68253
+        );
+
+        let hash = |table: &LookupTable| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::hash::Hash::hash(table, &mut hasher);
+            std::hash::Hasher::finish(&hasher)
+        };
+        let a = table(&[1, 0x8000]);
+        let b = [0x8000, 1]
+            .map(WirePattern::from_bits)
+            .into_iter()
+            .collect();
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), table(&[1]).fingerprint());
+        assert_eq!(
+            std::collections::HashSet::from([a, b, table(&[1])]).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn lookup_table_efficiency() {
+        let table = LookupTable::min_clusters(2);
+        let patterns = [0b101, 0b11, 0b1001, 0, 0b1, 0b10101].map(WirePattern::from_bits);
+        let efficiency = table.efficiency(patterns);
+        assert_eq!(efficiency, Efficiency::new(3, 6));
+        assert_eq!(efficiency.value(), Some(0.5));
+
+        let empty = table.efficiency([]);
+        assert_eq!((empty.passed(), empty.total(), empty.value()), (0, 0, None));
+        assert_eq!(empty.interval(0.9), (0.0, 1.0));
+    }
+
+    #[test]
+    fn subsets_and_supersets() {
+        assert_eq!(
+            subsets(0b1010).collect::<Vec<_>>(),
+            [0, 0b10, 0b1000, 0b1010]
+        );
+        assert_eq!(subsets(0).collect::<Vec<_>>(), [0]);
+        assert_eq!(subsets(u16::MAX).count(), 65536);
+        assert!(subsets(u16::MAX).eq(0..=u16::MAX));
+
+        assert_eq!(
+            supersets(!0b1010).collect::<Vec<_>>(),
+            [!0b1010, !0b1000, !0b10, !0]
+        );
+        assert_eq!(supersets(u16::MAX).collect::<Vec<_>>(), [u16::MAX]);
+        assert!(supersets(0).eq(0..=u16::MAX));
+
+        for mask in [0x8001, 0x0ff0, 0x5555] {
+            assert!(subsets(mask).all(|sub| sub & !mask == 0));
+            assert!(supersets(mask).all(|sup| sup & mask == mask));
+            let n = 1 << mask.count_ones();
+            assert_eq!(subsets(mask).count(), n);
+            assert_eq!(supersets(mask).count(), 65536 / n);
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::str::FromStr;
+    #[test]
+    fn lookup_table_subsets_of() {
+        let lookup = table(&[0b0011, 0b0110, 0b1111, 0x8000]);
+        let subsets = |bits| {
+            lookup
+                .subsets_of(WirePattern::from_bits(bits))
+                .map(|pattern| pattern.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(subsets(0b0111), [0b0011, 0b0110]);
+        assert_eq!(subsets(0b1111), [0b0011, 0b0110, 0b1111]);
+        assert!(subsets(0b0101).is_empty());
+        assert_eq!(subsets(u16::MAX), [0b0011, 0b0110, 0b1111, 0x8000]);
+        assert!(subsets(0).is_empty());
+        assert!(lookup.contains_subset_of(WirePattern::from_bits(0x8001)));
+        assert!(!lookup.contains_subset_of(WirePattern::from_bits(0)));
+
+        // The empty pattern is a subset of every pattern.
+        let empty = table(&[0]);
+        assert!(empty.contains_subset_of(WirePattern::from_bits(0)));
+        assert!(empty.contains_subset_of(WirePattern::from_bits(0x1234)));
+        assert!(!LookupTable::new().contains_subset_of(WirePattern::from_bits(u16::MAX)));
+    }
 
     #[test]
-    fn lookup_table_new() {
-        let table = LookupTable::new();
+    fn lookup_table_supersets_of() {
+        let lookup = table(&[0b0011, 0b0110, 0b1111, 0x8000]);
+        let supersets = |bits| {
+            lookup
+                .supersets_of(WirePattern::from_bits(bits))
+                .map(|pattern| pattern.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(supersets(0b0010), [0b0011, 0b0110, 0b1111]);
+        assert_eq!(supersets(0b0011), [0b0011, 0b1111]);
+        assert_eq!(supersets(0b1001), [0b1111]);
+        assert_eq!(supersets(0), [0b0011, 0b0110, 0b1111, 0x8000]);
+        assert!(supersets(u16::MAX).is_empty());
+        assert!(!lookup.contains_superset_of(WirePattern::from_bits(0x8001)));
 
-        for n in 0..=u16::MAX {
-            let pattern = WirePattern::from_bits(n);
-            assert!(!table.contains(pattern));
+        // The full pattern is a superset of every pattern.
+        let full = table(&[u16::MAX]);
+        assert!(full.contains_superset_of(WirePattern::from_bits(u16::MAX)));
+        assert!(full.contains_superset_of(WirePattern::from_bits(0)));
+        assert!(!LookupTable::new().contains_superset_of(WirePattern::from_bits(0)));
+    }
+
+    #[test]
+    fn lookup_table_diff() {
+        let old = table(&[0, 1, 63, 64, 0xffff]);
+        let new = table(&[1, 2, 64, 0x8000, 0xffff]);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, [2, 0x8000].map(WirePattern::from_bits));
+        assert_eq!(diff.removed, [0, 63].map(WirePattern::from_bits));
+        assert_eq!(
+            diff.to_string(),
+            "-0x0000 1 ................, 0 bits, 0 clusters
++0x0002 1 .X.............., 1 bits, 1 clusters
+-0x003f 1 XXXXXX.........., 6 bits, 1 clusters
++0x8000 1 ...............X, 1 bits, 1 clusters"
+        );
+
+        let reverse = new.diff(&old);
+        assert_eq!((reverse.added, reverse.removed), (diff.removed, diff.added));
+
+        let same = old.diff(&old);
+        assert!(same.is_empty());
+        assert_eq!(same.to_string(), "");
+        assert_eq!(LookupTable::new().diff(&old).added.len(), 5);
+    }
+
+    #[test]
+    fn lookup_table_from_hex_list() {
+        let input = "0x8e61, 0x00FF,0X0001\n  255 , 1\n\n65535\t0x0000,,\n";
+        assert_eq!(
+            LookupTable::from_hex_list(input).unwrap(),
+            table(&[0x8e61, 0x00ff, 1, 0xffff, 0])
+        );
+        assert!(LookupTable::from_hex_list("").unwrap().is_empty());
+
+        for (input, line, span) in [
+            ("0x0001, 65536", 1, 8..13),
+            ("0x0001\n0x10000", 2, 0..7),
+            ("1, 2,\n 3, 8e61, 4", 2, 4..8),
+            ("0x, 1", 1, 0..2),
+            ("-1", 1, 0..2),
+        ] {
+            let error = LookupTable::from_hex_list(input).unwrap_err();
+            let error = error.line_error();
+            assert_eq!((error.line(), error.span()), (line, span), "{input}");
+            assert_eq!(error.contents(), input.lines().nth(line - 1).unwrap());
         }
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    fn lookup_table_insert() {
-        let mut table = LookupTable::new();
+    fn lookup_table_from_json_array() {
+        assert_eq!(
+            LookupTable::from_json_array("[36449, \"0x00ff\",\n 36449]").unwrap(),
+            table(&[0x8e61, 0x00ff])
+        );
 
-        assert!(!table.contains(WirePattern::from_bits(0)));
-        assert!(table.insert(WirePattern::from_bits(0)));
-        assert!(table.contains(WirePattern::from_bits(0)));
-        assert!(!table.insert(WirePattern::from_bits(0)));
-        assert!(table.contains(WirePattern::from_bits(0)));
+        let error = LookupTable::from_json_array("[1,\n 2, \"0xzz\"]").unwrap_err();
+        let error = error.line_error();
+        assert_eq!((error.line(), error.contents()), (2, r#" 2, "0xzz"]"#));
+        // The end of the invalid string.
+        assert_eq!(error.span(), 9..10);
+
+        let error = LookupTable::from_json_array("[1, 65536]").unwrap_err();
+        assert_eq!(error.line_error().line(), 1);
+        assert!(LookupTable::from_json_array("{}").is_err());
     }
 
     #[test]
-    fn lookup_table_remove() {
+    fn lookup_table_iter() {
+        assert_eq!(LookupTable::new().iter().next(), None);
+
+        let patterns = [0xffff, 0, 0x8000, 7].map(WirePattern::from_bits);
+        let table = LookupTable::from(patterns);
+        let expected = [0, 7, 0x8000, 0xffff].map(WirePattern::from_bits);
+        assert_eq!(table.iter().collect::<Vec<_>>(), expected);
+        assert_eq!((&table).into_iter().collect::<Vec<_>>(), expected);
+        assert_eq!(table.into_iter().collect::<Vec<_>>(), expected);
+        assert_eq!(table.iter().collect::<LookupTable>(), table);
+    }
+
+    #[test]
+    fn lookup_table_toggle_and_index() {
+        let mut toggled = table(&[1]);
+        for bits in [0, 1, 63, 64, 0xffff] {
+            let pattern = WirePattern::from_bits(bits);
+            let before = toggled[pattern];
+            assert_eq!(toggled.toggle(pattern), !before);
+            assert_eq!(toggled[pattern], !before);
+            assert_eq!(toggled.contains(pattern), !before);
+        }
+        assert_eq!(toggled, table(&[0, 63, 64, 0xffff]));
+        assert!(!toggled[WirePattern::from_bits(65)]);
+    }
+
+    #[test]
+    fn lookup_table_ascending_order() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let random = LookupTable::random(0.01, &mut StdRng::seed_from_u64(3));
+        let patterns = random.iter().map(|pattern| pattern.0).collect::<Vec<_>>();
+        assert!(patterns.is_sorted_by(|a, b| a < b));
+        assert!(random
+            .into_iter()
+            .map(|pattern| pattern.0)
+            .eq(patterns.iter().copied()));
+
+        let lines = random
+            .to_string()
+            .lines()
+            .map(|line| u16::from_str_radix(&line[2..6], 16).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, patterns);
+    }
+
+    #[test]
+    fn lookup_table_packed() {
+        // One bit per pattern.
+        assert_eq!(std::mem::size_of::<LookupTable>(), 8192);
+
+        let mut packed = table(&[63, 64]);
+        assert_eq!(packed, table(&[64, 63]));
+        assert_ne!(packed, table(&[63]));
+        packed.insert(WirePattern::from_bits(65));
+        packed.remove(WirePattern::from_bits(65));
+        assert_eq!(packed, table(&[63, 64]));
+        assert_eq!(
+            packed.iter().collect::<Vec<_>>(),
+            [63, 64].map(WirePattern::from_bits)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_lookup_table() {
+        let original = table(&[0xffff, 0, 0x0102]);
+        let json = serde_json::to_string(&original).unwrap();
+        assert_eq!(json, r#"["0x0000","0x0102","0xffff"]"#);
+        let parsed: LookupTable = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, original);
+
+        let parsed: LookupTable = serde_json::from_str(r#"[258, "0x0102", 0]"#).unwrap();
+        assert_eq!(parsed, table(&[0, 0x0102]));
+        assert_eq!(serde_json::to_string(&LookupTable::new()).unwrap(), "[]");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_trg_signal() {
+        let signal = TrgSignal {
+            time: 12,
+            window_start: 0,
+            window_stop: 10,
+            matched: 0b10,
+            weight: 1.0,
+        };
+        let json = serde_json::to_string(&signal).unwrap();
+        assert_eq!(
+            json,
+            r#"{"time":12,"window_start":0,"window_stop":10,"matched":2}"#
+        );
+        let parsed: TrgSignal<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            (
+                parsed.time,
+                parsed.window_start,
+                parsed.window_stop,
+                parsed.matched
+            ),
+            (12, 0, 10, 0b10)
+        );
+        assert_eq!(parsed.weight, 1.0);
+
+        let signal = TrgSignal {
+            weight: 0.5,
+            ..signal
+        };
+        let json = serde_json::to_string(&signal).unwrap();
+        assert!(json.ends_with(r#""weight":0.5}"#));
+        let parsed: TrgSignal<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.weight, 0.5);
+    }
+
+    #[test]
+    fn lookup_table_binary() {
+        let text = "0x0000 1 ................, 0 bits, 0 clusters
+0x0041 1 X.....X........., 2 bits, 2 clusters
+0x8001 1 X..............X, 2 bits, 1 clusters
+0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters";
+        let original = text.parse::<LookupTable>().unwrap();
+
+        let bytes = original.to_bytes();
+        assert_eq!((bytes[0], bytes[8], bytes[4096]), (0b1, 0b10, 0b10));
+        assert_eq!(bytes[TABLE_BYTES - 1], 0x80);
+        let from_bytes = LookupTable::from_bytes(&bytes).unwrap();
+        assert_eq!(from_bytes, original);
+        assert_eq!(from_bytes.to_string(), text);
+
+        let mut buffer = Vec::new();
+        original.write_binary(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], b"MLU\x01");
+        assert_eq!(buffer.len(), 4 + TABLE_BYTES);
+        let read = LookupTable::read_binary(buffer.as_slice()).unwrap();
+        assert_eq!(read.to_string(), text);
+
+        assert!(matches!(
+            LookupTable::from_bytes(&bytes[1..]),
+            Err(BinaryError::Length(8191))
+        ));
+        assert!(matches!(
+            LookupTable::read_binary(&buffer[..100]),
+            Err(BinaryError::Io(_))
+        ));
+        buffer[3] = 2;
+        assert!(matches!(
+            LookupTable::read_binary(buffer.as_slice()),
+            Err(BinaryError::Header(header)) if header == *b"MLU\x02"
+        ));
+    }
+
+    #[test]
+    fn lookup_table_from_predicate() {
+        let mut calls = Vec::new();
+        let odd = LookupTable::from_predicate(|pattern| {
+            calls.push(pattern.0);
+            pattern.0 % 2 == 1
+        });
+        assert!(calls.iter().copied().eq(0..=u16::MAX));
+        assert_eq!(odd.len(), 32768);
+        assert!(odd.contains(WirePattern::from_bits(0xffff)));
+        assert!(!odd.contains(WirePattern::from_bits(0xfffe)));
+    }
+
+    #[test]
+    fn lookup_table_min_wires() {
+        // All patterns but the empty one and the 16 single board patterns.
+        assert_eq!(LookupTable::min_wires(2).len(), 65519);
+        assert_eq!(LookupTable::min_wires(0).len(), 65536);
+        assert_eq!(LookupTable::min_wires(15).len(), 17);
+        assert!(LookupTable::min_wires(17).is_empty());
+        assert!(LookupTable::min_wires(3).is_subset(&LookupTable::min_wires(2)));
+    }
+
+    #[test]
+    fn lookup_table_min_clusters() {
+        // A single cluster on the ring of 16 boards is either all boards high,
+        // or one of 15 lengths starting at one of 16 boards.
+        assert_eq!(LookupTable::min_clusters(1).len(), 65535);
+        assert_eq!(LookupTable::min_clusters(2).len(), 65535 - (16 * 15 + 1));
+        // Alternating boards, in the two possible phases.
+        assert_eq!(
+            LookupTable::min_clusters(8),
+            table(&[0b0101010101010101, 0b1010101010101010])
+        );
+        assert!(LookupTable::min_clusters(9).is_empty());
+        assert!(LookupTable::min_clusters(2).is_subset(&LookupTable::min_wires(2)));
+    }
+
+    #[test]
+    fn lookup_table_len() {
         let mut table = LookupTable::new();
+        assert_eq!(
+            (table.len(), table.is_empty(), table.density()),
+            (0, true, 0.0)
+        );
 
+        table.insert(WirePattern::from_bits(0xffff));
+        table.insert(WirePattern::from_bits(0xffff));
         table.insert(WirePattern::from_bits(0));
-        assert!(table.contains(WirePattern::from_bits(0)));
-        assert!(table.remove(WirePattern::from_bits(0)));
-        assert!(!table.contains(WirePattern::from_bits(0)));
-        assert!(!table.remove(WirePattern::from_bits(0)));
+        assert_eq!(table.len(), 2);
+        assert!(!table.is_empty());
+        table.remove(WirePattern::from_bits(0));
+        assert_eq!(table.len(), 1);
+
+        table.clear();
+        assert!(table.is_empty());
+        assert_eq!(table, LookupTable::new());
+
+        let full = (0..=u16::MAX)
+            .map(WirePattern::from_bits)
+            .collect::<LookupTable>();
+        assert_eq!((full.len(), full.density()), (65536, 1.0));
+    }
+
+    fn table(patterns: &[u16]) -> LookupTable {
+        patterns
+            .iter()
+            .map(|&p| WirePattern::from_bits(p))
+            .collect()
+    }
+
+    // Patterns at both ends of the table, and around the boundaries of 64-bit
+    // words (in case the table is bit-packed).
+    fn set_operands() -> (LookupTable, LookupTable) {
+        (
+            table(&[0, 1, 63, 0x7fff, 0xffff]),
+            table(&[1, 2, 64, 0x8000, 0xffff]),
+        )
     }
 
     #[test]
-    fn lookup_table_from_iter() {
-        let ps = [
-            WirePattern::from_bits(1),
-            WirePattern::from_bits(2),
-            WirePattern::from_bits(2),
-            WirePattern::from_bits(3),
-        ];
+    fn lookup_table_set_operations() {
+        let (a, b) = set_operands();
 
-        let table: LookupTable = ps.iter().copied().collect();
+        assert_eq!(&a | &b, table(&[0, 1, 2, 63, 64, 0x7fff, 0x8000, 0xffff]));
+        assert_eq!(&a & &b, table(&[1, 0xffff]));
+        assert_eq!(&a - &b, table(&[0, 63, 0x7fff]));
+        assert_eq!(&b - &a, table(&[2, 64, 0x8000]));
+        assert_eq!(&a ^ &b, table(&[0, 2, 63, 64, 0x7fff, 0x8000]));
+    }
 
-        for p in &ps {
-            assert!(table.contains(*p));
+    #[test]
+    fn lookup_table_set_methods() {
+        let (a, b) = set_operands();
+
+        assert_eq!(a.union(&b), &a | &b);
+        assert_eq!(a.intersection(&b), &a & &b);
+        assert_eq!(a.difference(&b), &a - &b);
+        assert_eq!(a.symmetric_difference(&b), &a ^ &b);
+    }
+
+    #[test]
+    fn lookup_table_complement() {
+        let (a, b) = set_operands();
+
+        let not_a = !&a;
+        assert_eq!(not_a.len(), 65531);
+        assert!(!not_a.contains(WirePattern::from_bits(0xffff)));
+        assert!(not_a.contains(WirePattern::from_bits(0xfffe)));
+        assert_eq!(!&not_a, a);
+        assert!((&a & &not_a).is_empty());
+        assert_eq!((&a | &not_a).len(), 65536);
+        // De Morgan.
+        assert_eq!(!&(&a | &b), &not_a & &!&b);
+    }
+
+    #[test]
+    fn lookup_table_subset() {
+        let (a, b) = set_operands();
+
+        assert!(LookupTable::new().is_subset(&a));
+        assert!(a.is_subset(&a));
+        assert!((&a & &b).is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert!(a.is_superset(&(&a - &b)));
+        assert!(!a.is_superset(&b));
+    }
+
+    #[test]
+    fn trigger_condition() {
+        // "X....XX..XXX...X" (3 clusters, 7 bits).
+        let pattern = WirePattern::from_bits(36449);
+
+        assert!(TriggerCondition::MinClusters(3).is_satisfied(pattern));
+        assert!(!TriggerCondition::MinClusters(4).is_satisfied(pattern));
+        assert!(TriggerCondition::MinWires(7).is_satisfied(pattern));
+        assert!(!TriggerCondition::MinWires(8).is_satisfied(pattern));
+        assert!(TriggerCondition::from(LookupTable::from([pattern])).is_satisfied(pattern));
+        assert!(!TriggerCondition::from(LookupTable::new()).is_satisfied(pattern));
+    }
+
+    #[test]
+    fn mlu_min_clusters() {
+        let mut mlu = Mlu::new(
+            Positive::new(10).unwrap(),
+            Positive::new(5).unwrap(),
+            TriggerCondition::MinClusters(2),
+        );
+
+        assert!(mlu.process(&event(0, 0b0001)).is_none());
+        assert!(mlu.process(&event(5, 0b0010)).is_none());
+        // "XX.." is a single cluster.
+        assert!(mlu.process(&event(20, 0b0100)).is_none());
+        assert!(mlu.process(&event(25, 0b0001)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 30);
+    }
+
+    #[test]
+    fn trigger_menu() {
+        let menu: TriggerMenu = [
+            ("low", LookupTable::from([WirePattern::from_bits(0b01)])),
+            (
+                "any",
+                LookupTable::from([WirePattern::from_bits(0b01), WirePattern::from_bits(0b10)]),
+            ),
+            ("high", LookupTable::from([WirePattern::from_bits(0b10)])),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(menu.len(), 3);
+        assert_eq!(menu.label(2), Some("high"));
+        assert_eq!(menu.label(3), None);
+        assert_eq!(menu.matches(WirePattern::from_bits(0b01)), 0b011);
+        assert_eq!(menu.matches(WirePattern::from_bits(0b10)), 0b110);
+        assert_eq!(menu.matches(WirePattern::from_bits(0b11)), 0);
+        assert_eq!(menu.labels(0b101).collect::<Vec<_>>(), vec!["low", "high"]);
+
+        let condition = TriggerCondition::from(menu);
+        assert!(condition.is_satisfied(WirePattern::from_bits(0b10)));
+        assert!(!condition.is_satisfied(WirePattern::from_bits(0b11)));
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 32 items")]
+    fn trigger_menu_too_many_items() {
+        let mut menu = TriggerMenu::new();
+        for i in 0..=TriggerMenu::MAX_ITEMS {
+            menu.push(i.to_string(), LookupTable::new());
         }
     }
 
     #[test]
-    fn lookup_table_from_array() {
-        let table = LookupTable::from([
-            WirePattern::from_bits(0),
-            WirePattern::from_bits(1),
-            WirePattern::from_bits(2),
-        ]);
-        let unordered_table = LookupTable::from([
-            WirePattern::from_bits(2),
-            WirePattern::from_bits(1),
-            WirePattern::from_bits(0),
-            WirePattern::from_bits(2),
-        ]);
-        assert_eq!(table, unordered_table);
+    fn mlu_trigger_menu() {
+        let mut menu = TriggerMenu::new();
+        menu.push("single", LookupTable::from([WirePattern::from_bits(0b01)]));
+        menu.push("double", LookupTable::from([WirePattern::from_bits(0b11)]));
+        let mut tagged = Mlu::new(Positive::new(10).unwrap(), Positive::new(5).unwrap(), menu);
+
+        assert!(tagged.process(&event(0, 0b01)).is_none());
+        let signal = tagged.process(&event(20, 0b01)).unwrap();
+        assert_eq!((signal.time, signal.matched), (10, 0b01));
+        assert!(tagged.process(&event(25, 0b10)).is_none());
+        let signal = tagged.flush().unwrap();
+        assert_eq!((signal.time, signal.matched), (30, 0b10));
+        // Other conditions never tag the signal.
+        let mut mlu = mlu();
+        assert!(mlu.process(&event(0, 0b11)).is_none());
+        assert_eq!(mlu.flush().unwrap().matched, 0);
+    }
+
+    fn boundary_signals(prompt_window: Boundary, wait_gate: Boundary) -> Vec<Option<i32>> {
+        let table = LookupTable::from([WirePattern::from_bits(0b01), WirePattern::from_bits(0b11)]);
+        let mut mlu = Mlu::new(Positive::new(10).unwrap(), Positive::new(5).unwrap(), table)
+            .with_boundary_policy(BoundaryPolicy {
+                prompt_window,
+                wait_gate,
+            });
+        // Exactly at the end of the prompt window, and then exactly at the end
+        // of the wait gate (if the previous event closed the window).
+        let mut signals = [event(0, 0b01), event(10, 0b10), event(15, 0b01)]
+            .iter()
+            .map(|event| mlu.process(event).map(|s| s.time))
+            .collect::<Vec<_>>();
+        signals.push(mlu.flush().map(|s| s.time));
+        signals
     }
 
     #[test]
-    fn lookup_table_to_string() {
-        let mut table = LookupTable::new();
-        assert_eq!(table.to_string(), "");
+    fn mlu_boundary_policy() {
+        use Boundary::*;
 
-        table.insert(WirePattern::from_bits(u16::MAX));
         assert_eq!(
-            table.to_string(),
-            "0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters"
+            boundary_signals(Exclusive, Exclusive),
+            vec![None, Some(10), None, Some(25)]
+        );
+        assert_eq!(
+            boundary_signals(Inclusive, Exclusive),
+            vec![None, None, Some(10), Some(25)]
+        );
+        assert_eq!(
+            boundary_signals(Exclusive, Inclusive),
+            vec![None, Some(10), None, None]
+        );
+        assert_eq!(
+            boundary_signals(Inclusive, Inclusive),
+            vec![None, None, Some(10), None]
         );
+    }
 
-        table.insert(WirePattern::from_bits(0));
+    #[test]
+    fn mlu_latency() {
+        let mut mlu = mlu().with_latency(Positive::new(3).unwrap());
+
+        assert!(mlu.process(&event(0, 0b11)).is_none());
+        // The wait gate still starts at the end of the prompt window.
+        assert_eq!(mlu.process(&event(14, 0b01)).unwrap().time, 13);
+        assert!(mlu.process(&event(18, 0b11)).is_none());
+        assert!(mlu.process(&event(23, 0b11)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 36);
+    }
+
+    #[test]
+    fn trg_signal_window() {
+        let mut mlu = mlu().with_latency(Positive::new(3).unwrap());
+
+        assert!(mlu.process(&event(2, 0b01)).is_none());
+        assert!(mlu.process(&event(7, 0b10)).is_none());
+        let signal = mlu.process(&event(30, 0b11)).unwrap();
         assert_eq!(
-            table.to_string(),
-            "0x0000 1 ................, 0 bits, 0 clusters
-0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters"
+            (signal.window_start, signal.window_stop, signal.time),
+            (2, 12, 15)
+        );
+        let signal = mlu.flush().unwrap();
+        assert_eq!(
+            (signal.window_start, signal.window_stop, signal.time),
+            (30, 40, 43)
         );
+    }
 
-        table.insert(WirePattern::from_bits(36449));
+    #[test]
+    fn mlu_stats() {
+        let mut mlu = mlu();
+
+        assert!(mlu.process(&event(0, 0b11)).is_none());
+        assert!(mlu.process(&event(12, 0b01)).is_some());
+        assert!(mlu.process(&event(16, 0b01)).is_none());
+        assert!(mlu.process(&event(30, 0b01)).is_none());
+        assert!(mlu.process(&event(45, 0b10)).is_none());
+        assert!(mlu.flush().is_none());
         assert_eq!(
-            table.to_string(),
-            "0x0000 1 ................, 0 bits, 0 clusters
-0x8e61 1 X....XX..XXX...X, 7 bits, 3 clusters
-0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters"
+            mlu.stats(),
+            MluStats {
+                windows_opened: 3,
+                decisions: 3,
+                accepted: 1,
+                wait_gate_extensions: 2,
+            }
         );
     }
 
     #[test]
-    fn lookup_table_from_str() {
-        let mut string = String::new();
-        let mut table = LookupTable::new();
-        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+    fn mlu_passthrough() {
+        let mut mlu = Mlu::new(
+            Positive::new(10).unwrap(),
+            Positive::new(5).unwrap(),
+            TriggerCondition::Passthrough,
+        );
 
-        string.push_str("0x0000 1 ................, 0 bits, 0 clusters");
-        table.insert(WirePattern::from_bits(0));
-        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+        let signal = mlu.process(&event(0, 0b01)).unwrap();
+        assert_eq!((signal.window_start, signal.time), (0, 10));
+        // No accumulation nor wait gate.
+        assert_eq!(mlu.process(&event(1, 0b10)).unwrap().time, 11);
+        assert_eq!(mlu.process(&event(2, 0b00)).unwrap().time, 12);
+        assert!(mlu.flush().is_none());
+        assert_eq!(mlu.stats().windows_opened, 3);
+    }
 
-        string.push_str("\n0x0000 1 ................, 0 bits, 0 clusters\n");
-        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+    #[test]
+    fn mlu_weight() {
+        let weighted = |time, bits, weight| WireEvent {
+            weight,
+            ..event(time, bits)
+        };
+        let events = [
+            weighted(0, 0b01, 0.5),
+            weighted(3, 0b10, 4.0),
+            weighted(9, 0b01, 3.0),
+            // Wait gate; not part of any window.
+            weighted(12, 0b01, 7.0),
+            weighted(30, 0b11, 0.25),
+            weighted(50, 0b01, 2.0),
+        ];
+        let mut mlu = mlu();
+        let mut reference = reference::Mlu::new(
+            Positive::new(10).unwrap(),
+            Positive::new(5).unwrap(),
+            LookupTable::from([WirePattern::from_bits(0b11)]),
+        );
+        let mut weights = Vec::new();
+        for event in &events {
+            let signal = mlu.process(event);
+            assert_eq!(
+                signal.map(|s| s.weight),
+                reference.process(event).map(|s| s.weight)
+            );
+            weights.extend(signal.map(|s| s.weight));
+        }
+        // The product of the weights of the events in each window.
+        assert_eq!(weights, [6.0, 0.25]);
+        assert!(mlu.flush().is_none());
+    }
 
-        string.push_str("0xffff 1 XXXXXXXXXXXXXXXX, 16 bits, 1 clusters");
-        table.insert(WirePattern::from_bits(u16::MAX));
-        assert_eq!(table, LookupTable::from_str(&string).unwrap());
+    #[test]
+    fn mlu_veto_table() {
+        let mut vetoed = mlu().with_veto_table(LookupTable::from([WirePattern::from_bits(0b11)]));
 
-        assert_eq!(table, LookupTable::from_str(&table.to_string()).unwrap());
+        // In both the accept and the veto tables.
+        assert!(vetoed.process(&event(0, 0b11)).is_none());
+        assert!(matches!(
+            vetoed.step(&event(20, 0b01)),
+            Some(MluDecision::Veto(TrgSignal {
+                time: 10,
+                window_start: 0,
+                window_stop: 10,
+                matched: 0,
+                ..
+            }))
+        ));
+        // Only in the veto table.
+        assert!(vetoed.step(&event(40, 0b11)).is_none());
+        // Only in the accept table.
+        let mut mlu = mlu().with_veto_table(LookupTable::from([WirePattern::from_bits(0b01)]));
+        assert!(mlu.process(&event(0, 0b11)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 10);
+    }
+
+    #[test]
+    fn wire_mapping() {
+        for n in [0, 1, 0b1000, 0b1010_0000_0000_0001, u16::MAX] {
+            let pattern = WirePattern::from_bits(n);
+            assert_eq!(WireMapping::identity().apply(pattern), pattern);
+        }
+
+        // Output bit `n` is the OR of input bits `n` and `n + 1`.
+        let overlapping = WireMapping::from(std::array::from_fn(|n| 0b11u16.rotate_left(n as u32)));
+        assert_eq!(
+            overlapping.apply(WirePattern::from_bits(0b1000)),
+            WirePattern::from_bits(0b1100)
+        );
+        assert_eq!(
+            overlapping.apply(WirePattern::from_bits(1)),
+            WirePattern::from_bits(0b1000_0000_0000_0001)
+        );
+        assert_eq!(
+            WireMapping::new([0; 16]).apply(WirePattern::from_bits(u16::MAX)),
+            WirePattern::from_bits(0)
+        );
+    }
+
+    #[test]
+    fn mlu_wire_mapping() {
+        let mut mlu = mlu().with_wire_mapping(WireMapping::new(std::array::from_fn(|n| {
+            0b11u16.rotate_left(n as u32)
+        })));
+
+        // Input bit 1 lights output bits 0 and 1.
+        assert!(mlu.process(&event(0, 0b10)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 10);
+    }
+
+    fn mlu() -> Mlu<i32> {
+        let mut table = Box::new(LookupTable::new());
+        table.insert(WirePattern::from_bits(0b11));
+        Mlu::new(Positive::new(10).unwrap(), Positive::new(5).unwrap(), table)
+    }
+
+    fn event(time: i32, bits: u16) -> WireEvent<i32> {
+        WireEvent {
+            source: crate::gen::Source::Noise,
+            wire_pattern: WirePattern::from_bits(bits),
+            time,
+            id: None,
+            weight: 1.0,
+        }
+    }
+
+    #[test]
+    fn mlu_process() {
+        let mut mlu = mlu();
+
+        assert!(mlu.process(&event(0, 0b01)).is_none());
+        assert!(mlu.process(&event(9, 0b10)).is_none());
+        // Closes the prompt window and starts waiting.
+        assert_eq!(mlu.process(&event(12, 0b01)).unwrap().time, 10);
+        // Each event during the wait gate extends it.
+        assert!(mlu.process(&event(16, 0b11)).is_none());
+        assert!(mlu.process(&event(20, 0b11)).is_none());
+        // Opens a new prompt window.
+        assert!(mlu.process(&event(25, 0b01)).is_none());
+        // Patterns not in the lookup table don't produce a signal.
+        assert!(mlu.process(&event(50, 0b11)).is_none());
+        assert_eq!(mlu.process(&event(100, 0b01)).unwrap().time, 60);
+    }
+
+    #[test]
+    fn mlu_flush() {
+        let mut mlu = mlu();
+        assert!(mlu.flush().is_none());
+
+        assert!(mlu.process(&event(0, 0b11)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 10);
+        assert!(mlu.flush().is_none());
+        // The MLU is waiting after a flush.
+        assert!(mlu.process(&event(14, 0b11)).is_none());
+        assert!(mlu.flush().is_none());
+        assert!(mlu.process(&event(20, 0b11)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 30);
     }
 }
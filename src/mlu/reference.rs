@@ -0,0 +1,162 @@
+use crate::gen::{Positive, WireEvent, WirePattern};
+use crate::mlu::{LookupTable, TrgSignal};
+use std::ops::Add;
+
+/// Naive implementation of the [`Mlu`](super::Mlu) with a lookup table as the
+/// trigger condition.
+///
+/// All wire events are stored and the prompt windows are recomputed from
+/// scratch after every event. This is slow, but the rules are written down
+/// directly (without a state machine) which makes it useful to cross-check the
+/// real implementation.
+#[derive(Clone, Debug)]
+pub struct Mlu<T> {
+    prompt_window: Positive<T>,
+    wait_gate: Positive<T>,
+    table: Box<LookupTable>,
+    events: Vec<WireEvent<T>>,
+    // Number of prompt windows that have already been decided.
+    decided: usize,
+}
+
+struct Window<T> {
+    start_time: T,
+    stop_time: T,
+    cumulative: WirePattern,
+    weight: f64,
+    // A later event arrived after the end of the window.
+    closed: bool,
+}
+
+impl<T> Mlu<T> {
+    /// Create a new MLU without any events.
+    pub fn new(
+        prompt_window: Positive<T>,
+        wait_gate: Positive<T>,
+        table: impl Into<Box<LookupTable>>,
+    ) -> Self {
+        Self {
+            prompt_window,
+            wait_gate,
+            table: table.into(),
+            events: Vec::new(),
+            decided: 0,
+        }
+    }
+}
+
+impl<T> Mlu<T>
+where
+    T: Add<Output = T> + PartialOrd + Clone,
+{
+    // All prompt windows opened by the events seen so far.
+    fn windows(&self) -> Vec<Window<T>> {
+        let mut windows: Vec<Window<T>> = Vec::new();
+        for (i, event) in self.events.iter().enumerate() {
+            // An event opens a new window if it is at least one wait gate
+            // after both the end of the previous window and the previous event.
+            let opens = windows.last().is_none_or(|window| {
+                let previous = &self.events[i - 1].time;
+                let wait_start = if *previous > window.stop_time {
+                    previous.clone()
+                } else {
+                    window.stop_time.clone()
+                };
+                event.time >= wait_start + self.wait_gate.inner().clone()
+            });
+
+            if opens {
+                if let Some(window) = windows.last_mut() {
+                    window.closed = true;
+                }
+                windows.push(Window {
+                    start_time: event.time.clone(),
+                    stop_time: event.time.clone() + self.prompt_window.inner().clone(),
+                    cumulative: event.wire_pattern,
+                    weight: event.weight,
+                    closed: false,
+                });
+            } else if let Some(window) = windows.last_mut() {
+                if event.time < window.stop_time {
+                    window.cumulative = window.cumulative | event.wire_pattern;
+                    window.weight *= event.weight;
+                } else {
+                    window.closed = true;
+                }
+            }
+        }
+
+        windows
+    }
+
+    fn signal(&self, window: &Window<T>) -> Option<TrgSignal<T>> {
+        self.table.contains(window.cumulative).then(|| TrgSignal {
+            time: window.stop_time.clone(),
+            window_start: window.start_time.clone(),
+            window_stop: window.stop_time.clone(),
+            matched: 0,
+            weight: window.weight,
+        })
+    }
+    /// Process the next [`WireEvent`] (events are expected in increasing order
+    /// of time). Returns the [`TrgSignal`] of a prompt window closed by this
+    /// event, if any.
+    pub fn process(&mut self, event: &WireEvent<T>) -> Option<TrgSignal<T>> {
+        self.events.push(event.clone());
+
+        let windows = self.windows();
+        // Only the last window can still be open.
+        let closed = windows.iter().filter(|window| window.closed).count();
+        let signal = windows[self.decided..closed]
+            .iter()
+            .find_map(|window| self.signal(window));
+        self.decided = closed;
+
+        signal
+    }
+    /// Make the decision of the prompt window that is still open at the end of
+    /// the stream of events.
+    ///
+    /// Unlike [`Mlu::flush`](super::Mlu::flush), this consumes the MLU; the
+    /// reference is only defined for a flush at the end of the stream.
+    pub fn flush(self) -> Option<TrgSignal<T>> {
+        self.windows()
+            .pop()
+            .filter(|window| !window.closed)
+            .and_then(|window| self.signal(&window))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::Source;
+
+    fn event(time: i32, bits: u16) -> WireEvent<i32> {
+        WireEvent {
+            source: Source::Noise,
+            wire_pattern: WirePattern::from_bits(bits),
+            time,
+            id: None,
+            weight: 1.0,
+        }
+    }
+
+    #[test]
+    fn reference_mlu() {
+        let mut mlu = Mlu::new(
+            Positive::new(10).unwrap(),
+            Positive::new(5).unwrap(),
+            LookupTable::from([WirePattern::from_bits(0b11)]),
+        );
+
+        assert!(mlu.process(&event(0, 0b01)).is_none());
+        assert!(mlu.process(&event(9, 0b10)).is_none());
+        // Extends the wait gate until 19.
+        assert_eq!(mlu.process(&event(14, 0b01)).unwrap().time, 10);
+        assert!(mlu.process(&event(18, 0b11)).is_none());
+        // Opens a new window at 23.
+        assert!(mlu.process(&event(23, 0b11)).is_none());
+        assert_eq!(mlu.flush().unwrap().time, 33);
+    }
+}
@@ -0,0 +1,645 @@
+use crate::gen::{WireEvent, WirePattern};
+use crate::mlu::TrgSignal;
+use crate::validate::{OrderingChecker, OrderingViolation};
+use crate::Observer;
+use bon::bon;
+use std::marker::PhantomData;
+
+/// Number of signals seen at each stage of the trigger system (analogous to
+/// the scalers of the real TRG box).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScalerCounts {
+    /// Number of wire events.
+    pub wire_events: u64,
+    /// Number of signals into the TRG box.
+    pub trg_in: u64,
+    /// Number of signals suppressed by the drift veto.
+    pub drift_veto: u64,
+    /// Number of signals suppressed by the scaledown.
+    pub scaledown: u64,
+    /// Number of signals suppressed by the dead time.
+    pub dead_time: u64,
+    /// Number of triggers sent to the DAQ.
+    pub trg_out: u64,
+}
+
+/// Sum of the weights of the signals seen at each stage of the trigger system
+/// (see [`WireEvent::weight`] and [`TrgSignal::weight`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WeightedScalerCounts {
+    /// Weighted number of wire events.
+    pub wire_events: f64,
+    /// Weighted number of signals into the TRG box.
+    pub trg_in: f64,
+    /// Weighted number of signals suppressed by the drift veto.
+    pub drift_veto: f64,
+    /// Weighted number of signals suppressed by the scaledown.
+    pub scaledown: f64,
+    /// Weighted number of signals suppressed by the dead time.
+    pub dead_time: f64,
+    /// Weighted number of triggers sent to the DAQ.
+    pub trg_out: f64,
+}
+
+/// An [`Observer`] that counts the signals at each stage of the trigger
+/// system.
+#[derive(Clone, Copy, Debug)]
+pub struct Scalers<T> {
+    counts: ScalerCounts,
+    weighted: WeightedScalerCounts,
+    _time: PhantomData<fn(&T)>,
+}
+
+// Deriving `Default` would only work for `T: Default`.
+impl<T> Default for Scalers<T> {
+    fn default() -> Self {
+        Self {
+            counts: ScalerCounts::default(),
+            weighted: WeightedScalerCounts::default(),
+            _time: PhantomData,
+        }
+    }
+}
+
+impl<T> Scalers<T> {
+    /// Current value of all counters.
+    pub fn counts(&self) -> ScalerCounts {
+        self.counts
+    }
+    /// Current value of all counters, with each signal counted by its weight.
+    pub fn weighted_counts(&self) -> WeightedScalerCounts {
+        self.weighted
+    }
+}
+
+impl<T> Observer for Scalers<T> {
+    type Time = T;
+
+    fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+        self.counts.wire_events += 1;
+        self.weighted.wire_events += event.weight;
+    }
+    fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.counts.trg_in += 1;
+        self.weighted.trg_in += signal.weight;
+    }
+    fn on_trg_drift_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.counts.drift_veto += 1;
+        self.weighted.drift_veto += signal.weight;
+    }
+    fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.counts.scaledown += 1;
+        self.weighted.scaledown += signal.weight;
+    }
+    fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.counts.dead_time += 1;
+        self.weighted.dead_time += signal.weight;
+    }
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, _: u64) {
+        self.counts.trg_out += 1;
+        self.weighted.trg_out += signal.weight;
+    }
+}
+
+/// An [`Observer`] that verifies that all callbacks happen in increasing
+/// order of time.
+///
+/// Only the first violation is recorded.
+#[derive(Clone, Debug)]
+pub struct OrderingAuditor<T> {
+    checker: OrderingChecker<T>,
+    violation: Option<OrderingViolation<T>>,
+}
+
+// Deriving `Default` would only work for `T: Default`.
+impl<T> Default for OrderingAuditor<T> {
+    fn default() -> Self {
+        Self {
+            checker: OrderingChecker::default(),
+            violation: None,
+        }
+    }
+}
+
+impl<T: PartialOrd + Clone> OrderingAuditor<T> {
+    /// Returns the first ordering violation, if any. The `index` of the
+    /// violation counts all callbacks, not only wire events.
+    pub fn violation(&self) -> Option<&OrderingViolation<T>> {
+        self.violation.as_ref()
+    }
+
+    fn check(&mut self, time: &T) {
+        if self.violation.is_none() {
+            self.violation = self.checker.check(time).err();
+        }
+    }
+}
+
+impl<T: PartialOrd + Clone> Observer for OrderingAuditor<T> {
+    type Time = T;
+
+    fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+        self.check(&event.time);
+    }
+    fn on_wire_event_masked(&mut self, event: &WireEvent<Self::Time>, _: WirePattern) {
+        self.check(&event.time);
+    }
+    fn on_mlu_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.check(&signal.time);
+    }
+    fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.check(&signal.time);
+    }
+    fn on_trg_drift_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.check(&signal.time);
+    }
+    fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.check(&signal.time);
+    }
+    fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.check(&signal.time);
+    }
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, _: u64) {
+        self.check(&signal.time);
+    }
+    fn on_phase_start(&mut self, _: usize, time: &Self::Time) {
+        self.check(time);
+    }
+    fn on_truncated(&mut self, time: &Self::Time) {
+        self.check(time);
+    }
+}
+
+/// How [`WindowCountStats::finish`] treats the last (incomplete) window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PartialWindow {
+    /// The last window is ignored.
+    #[default]
+    Dropped,
+    /// The count of the last window is included as is.
+    Raw,
+    /// The count of the last window is scaled by `window / elapsed` (i.e. it
+    /// is extrapolated to a full window).
+    Scaled,
+}
+
+// Welford's online algorithm.
+#[derive(Clone, Copy, Debug, Default)]
+struct Welford {
+    n: u64,
+    mean: f64,
+    m2: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Welford {
+    fn push(&mut self, value: f64) {
+        self.n += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = Some(self.min.map_or(value, |m| m.min(value)));
+        self.max = Some(self.max.map_or(value, |m| m.max(value)));
+    }
+    // Equivalent to calling `push(0.0)` `k` times, but in constant time.
+    fn push_zeros(&mut self, k: u64) {
+        if k == 0 {
+            return;
+        }
+        let n = self.n + k;
+        let delta = -self.mean;
+        self.mean += delta * k as f64 / n as f64;
+        self.m2 += delta * delta * (self.n as f64) * (k as f64) / n as f64;
+        self.n = n;
+        self.min = Some(self.min.map_or(0.0, |m| m.min(0.0)));
+        self.max = Some(self.max.map_or(0.0, |m| m.max(0.0)));
+    }
+}
+
+/// An [`Observer`] that accumulates statistics of the number of triggers sent
+/// to the DAQ in consecutive, fixed-length time windows.
+///
+/// No trigger times are stored; the mean and variance are accumulated online.
+/// Windows are `[origin + k * window, origin + (k + 1) * window)` for
+/// non-negative integers `k`. Only complete windows are included until
+/// [`WindowCountStats::finish`] is called.
+pub struct WindowCountStats<T> {
+    window: f64,
+    origin: f64,
+    to_f64: Box<dyn Fn(&T) -> f64>,
+    partial_window: PartialWindow,
+    stats: Welford,
+    // Index and count of the current (open) window.
+    current: u64,
+    count: u64,
+}
+
+#[bon]
+impl<T> WindowCountStats<T> {
+    #[builder]
+    pub fn new(
+        /// Length of each window. Same units as the output of `to_f64`.
+        window: f64,
+        /// Start of the first window. Same units as the output of `to_f64`.
+        #[builder(default)]
+        origin: f64,
+        /// Conversion from the simulation time to a number.
+        #[builder(with = |f: impl Fn(&T) -> f64 + 'static| Box::new(f) as Box<dyn Fn(&T) -> f64>)]
+        to_f64: Box<dyn Fn(&T) -> f64>,
+        /// Treatment of the last, incomplete window when calling
+        /// [`WindowCountStats::finish`].
+        #[builder(default)]
+        partial_window: PartialWindow,
+    ) -> Self {
+        Self {
+            window,
+            origin,
+            to_f64,
+            partial_window,
+            stats: Welford::default(),
+            current: 0,
+            count: 0,
+        }
+    }
+}
+
+impl<T> WindowCountStats<T> {
+    fn window_index(&self, time: f64) -> u64 {
+        ((time - self.origin) / self.window).floor().max(0.0) as u64
+    }
+    // Close all windows before `index`.
+    fn advance_to(&mut self, index: u64) {
+        if index > self.current {
+            self.stats.push(self.count as f64);
+            self.stats.push_zeros(index - self.current - 1);
+            self.current = index;
+            self.count = 0;
+        }
+    }
+    /// Close all windows up to `end_time` (typically the end of the run).
+    /// The last, incomplete window is handled according to the configured
+    /// [`PartialWindow`]. No further triggers should be observed afterwards.
+    pub fn finish(&mut self, end_time: &T) {
+        let end_time = (self.to_f64)(end_time);
+        self.advance_to(self.window_index(end_time));
+
+        let elapsed = end_time - (self.origin + self.current as f64 * self.window);
+        if elapsed > 0.0 {
+            match self.partial_window {
+                PartialWindow::Dropped => {}
+                PartialWindow::Raw => self.stats.push(self.count as f64),
+                PartialWindow::Scaled => self.stats.push(self.count as f64 * self.window / elapsed),
+            }
+        }
+        // Nothing else can be added to the current window.
+        self.current += 1;
+        self.count = 0;
+    }
+    /// Mean number of triggers per window. Returns `None` if there are no
+    /// complete windows.
+    pub fn mean(&self) -> Option<f64> {
+        (self.stats.n > 0).then_some(self.stats.mean)
+    }
+    /// Sample variance of the number of triggers per window. Returns `None` if
+    /// there are fewer than two complete windows.
+    pub fn variance(&self) -> Option<f64> {
+        (self.stats.n > 1).then(|| self.stats.m2 / (self.stats.n - 1) as f64)
+    }
+    /// Largest number of triggers in a window.
+    pub fn max_window(&self) -> Option<f64> {
+        self.stats.max
+    }
+    /// Smallest number of triggers in a window.
+    pub fn min_window(&self) -> Option<f64> {
+        self.stats.min
+    }
+    /// Number of complete windows.
+    pub fn n_windows(&self) -> u64 {
+        self.stats.n
+    }
+}
+
+impl<T> Observer for WindowCountStats<T> {
+    type Time = T;
+
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, _: u64) {
+        let index = self.window_index((self.to_f64)(&signal.time));
+        self.advance_to(index);
+        self.count += 1;
+    }
+}
+
+/// Canonical, platform independent byte encoding of a time value.
+pub trait TimeEncode {
+    /// Appends the encoding of `self` to `bytes`.
+    fn encode(&self, bytes: &mut Vec<u8>);
+}
+
+impl TimeEncode for i32 {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl TimeEncode for i64 {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl TimeEncode for f64 {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        // `0.0 == -0.0` and all `NaN`s are equivalent for our purposes.
+        let value = if *self == 0.0 {
+            0.0
+        } else if self.is_nan() {
+            f64::NAN
+        } else {
+            *self
+        };
+        bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+}
+
+#[cfg(feature = "uom")]
+impl TimeEncode for uom::si::f64::Time {
+    fn encode(&self, bytes: &mut Vec<u8>) {
+        // Value in the base unit (seconds).
+        self.value.encode(bytes);
+    }
+}
+
+// 64-bit FNV-1a. Unlike `std::hash`, it is guaranteed to be stable across
+// platforms and Rust versions.
+pub(crate) const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// Feeds `bytes` to the FNV-1a hash with the given `state`.
+pub(crate) fn fnv1a(state: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(state, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// An [`Observer`] that computes a deterministic digest of all callbacks (and
+/// their times) in the order in which they happen.
+///
+/// Two runs have the same fingerprint if they produce the same sequence of
+/// callbacks. This is useful for regression tests of the trigger logic.
+#[derive(Clone, Debug)]
+pub struct RunFingerprint<T> {
+    state: u64,
+    buffer: Vec<u8>,
+    _time: PhantomData<fn(&T)>,
+}
+
+// Deriving `Default` would only work for `T: Default`.
+impl<T> Default for RunFingerprint<T> {
+    fn default() -> Self {
+        Self {
+            state: FNV_OFFSET_BASIS,
+            buffer: Vec::new(),
+            _time: PhantomData,
+        }
+    }
+}
+
+impl<T: TimeEncode> RunFingerprint<T> {
+    /// Returns the digest of all callbacks observed so far.
+    pub fn digest(&self) -> u64 {
+        self.state
+    }
+
+    fn update(&mut self, kind: u8, time: &T) {
+        self.buffer.clear();
+        self.buffer.push(kind);
+        time.encode(&mut self.buffer);
+        self.state = fnv1a(self.state, &self.buffer);
+    }
+}
+
+impl<T: TimeEncode> Observer for RunFingerprint<T> {
+    type Time = T;
+
+    fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+        self.update(0, &event.time);
+    }
+    fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.update(1, &signal.time);
+    }
+    fn on_trg_drift_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.update(2, &signal.time);
+    }
+    fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.update(3, &signal.time);
+    }
+    fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.update(4, &signal.time);
+    }
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, _: u64) {
+        self.update(5, &signal.time);
+    }
+    fn on_phase_start(&mut self, _: usize, time: &Self::Time) {
+        self.update(6, time);
+    }
+    fn on_mlu_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.update(7, &signal.time);
+    }
+    fn on_wire_event_masked(&mut self, event: &WireEvent<Self::Time>, _: WirePattern) {
+        self.update(8, &event.time);
+    }
+    fn on_truncated(&mut self, time: &Self::Time) {
+        self.update(9, time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::{Positive, ScriptedGenerator, SecondaryGenerator, Source, WirePattern};
+    use crate::mlu::LookupTable;
+    use crate::World;
+
+    fn stats(times: &[f64], end_time: f64, partial_window: PartialWindow) -> WindowCountStats<f64> {
+        let mut stats = WindowCountStats::builder()
+            .window(1.0)
+            .to_f64(|t: &f64| *t)
+            .partial_window(partial_window)
+            .build();
+        for (number, &time) in (0..).zip(times) {
+            let signal = TrgSignal {
+                time,
+                window_start: time,
+                window_stop: time,
+                matched: 0,
+                weight: 1.0,
+            };
+            stats.on_trg_out(&signal, number);
+        }
+        stats.finish(&end_time);
+
+        stats
+    }
+
+    #[test]
+    fn scalers_weighted() {
+        let events = [(0, 0b01, 2.0), (5, 0b10, 3.0), (100, 0b11, 0.25)]
+            .map(|(time, bits, weight)| WireEvent {
+                source: Source::Noise,
+                wire_pattern: WirePattern::from_bits(bits),
+                time,
+                id: None,
+                weight,
+            })
+            .to_vec();
+        let scalers = World::builder()
+            .add_generator(ScriptedGenerator::new(events))
+            .prompt_window(Positive::new(10).unwrap())
+            .wait_gate(Positive::new(10).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(0b11)]))
+            .drift_veto(Positive::new(1).unwrap())
+            .scaledown(0)
+            .dead_time(Positive::new(1).unwrap())
+            .observer(Scalers::default())
+            .build()
+            .run();
+
+        assert_eq!(scalers.counts().wire_events, 3);
+        assert_eq!(scalers.counts().trg_out, 2);
+        assert_eq!(
+            scalers.weighted_counts(),
+            WeightedScalerCounts {
+                wire_events: 5.25,
+                trg_in: 6.25,
+                trg_out: 6.25,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn window_count_stats_periodic() {
+        let times = (0..40).map(|i| f64::from(i) * 0.25).collect::<Vec<_>>();
+        let stats = stats(&times, 10.0, PartialWindow::Dropped);
+
+        assert_eq!(stats.n_windows(), 10);
+        assert_eq!(stats.mean(), Some(4.0));
+        assert_eq!(stats.variance(), Some(0.0));
+        assert_eq!(stats.max_window(), Some(4.0));
+        assert_eq!(stats.min_window(), Some(4.0));
+    }
+
+    #[test]
+    fn window_count_stats_bursty() {
+        // Counts per window are [3, 0, 1, 0, 0].
+        let stats = stats(&[0.1, 0.2, 0.3, 2.5], 5.0, PartialWindow::Dropped);
+
+        assert_eq!(stats.n_windows(), 5);
+        assert!((stats.mean().unwrap() - 0.8).abs() < 1e-12);
+        assert!((stats.variance().unwrap() - 1.7).abs() < 1e-12);
+        assert_eq!(stats.max_window(), Some(3.0));
+        assert_eq!(stats.min_window(), Some(0.0));
+    }
+
+    #[test]
+    fn window_count_stats_partial_window() {
+        let times = [0.5, 1.5, 2.2];
+
+        let dropped = stats(&times, 2.5, PartialWindow::Dropped);
+        assert_eq!(dropped.n_windows(), 2);
+        assert_eq!(dropped.mean(), Some(1.0));
+
+        let raw = stats(&times, 2.5, PartialWindow::Raw);
+        assert_eq!(raw.n_windows(), 3);
+        assert_eq!(raw.mean(), Some(1.0));
+
+        let scaled = stats(&times, 2.5, PartialWindow::Scaled);
+        assert_eq!(scaled.n_windows(), 3);
+        assert_eq!(scaled.max_window(), Some(2.0));
+    }
+
+    fn fingerprint<T>(
+        inter_arrival_time: Vec<T>,
+        window: T,
+        dead_time: T,
+        veto_table: Option<LookupTable>,
+    ) -> u64
+    where
+        T: num_traits::Zero + std::ops::Add<Output = T> + PartialOrd + Clone,
+        T: std::fmt::Debug + TimeEncode + 'static,
+    {
+        let patterns = [1, 2, 1, 3, 1, 1, 2, 1, 1, 1].map(WirePattern::from_bits);
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(T::zero())
+            .inter_arrival_time(
+                inter_arrival_time
+                    .into_iter()
+                    .map(|t| Positive::new(t).unwrap()),
+            )
+            .wire_pattern(patterns)
+            .build();
+
+        World::builder()
+            .add_generator(noise)
+            .prompt_window(Positive::new(window.clone()).unwrap())
+            .wait_gate(Positive::new(window.clone()).unwrap())
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .maybe_veto_table(veto_table)
+            .drift_veto(Positive::new(window).unwrap())
+            .scaledown(1)
+            .dead_time(Positive::new(dead_time).unwrap())
+            .observer(RunFingerprint::default())
+            .build()
+            .run()
+            .digest()
+    }
+
+    const DELTAS: [i64; 10] = [5, 1, 7, 9, 3, 12, 2, 8, 8, 30];
+
+    #[test]
+    fn run_fingerprint_golden() {
+        assert_eq!(
+            fingerprint(DELTAS.to_vec(), 2, 18, None),
+            0x29ac05f4563af8ea
+        );
+        assert_eq!(
+            fingerprint(DELTAS.map(|t| t as f64 * 0.5).to_vec(), 1.0, 9.0, None),
+            0x98e5ea4ca6dcbfbf
+        );
+        // All MLU signals are vetoed.
+        let veto_table = LookupTable::from([WirePattern::from_bits(1)]);
+        assert_eq!(
+            fingerprint(DELTAS.to_vec(), 2, 18, Some(veto_table)),
+            0x686d176527a7988b
+        );
+    }
+
+    #[test]
+    fn run_fingerprint_sensitivity() {
+        let golden = fingerprint(DELTAS.to_vec(), 2, 18, None);
+
+        assert_eq!(fingerprint(DELTAS.to_vec(), 2, 18, None), golden);
+        assert_ne!(fingerprint(DELTAS.to_vec(), 2, 17, None), golden);
+        assert_ne!(fingerprint(DELTAS.to_vec(), 3, 18, None), golden);
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn run_fingerprint_uom() {
+        use uom::si::f64::Time;
+        use uom::si::time::second;
+
+        let seconds = |t: f64| Time::new::<second>(t);
+        assert_eq!(
+            fingerprint(
+                DELTAS.map(|t| seconds(t as f64 * 0.5)).to_vec(),
+                seconds(1.0),
+                seconds(9.0),
+                None
+            ),
+            fingerprint(DELTAS.map(|t| t as f64 * 0.5).to_vec(), 1.0, 9.0, None)
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::gen::Positive;
+use crate::mlu::{LookupTable, ParseError};
+use crate::{world_builder, WorldBuilder};
+use serde::Deserialize;
+use std::fmt;
+use std::io::Read;
+use std::path::PathBuf;
+use uom::si::f64::Time;
+use uom::si::time::nanosecond;
+
+// Periods of the clocks used to express the ODB settings.
+const MLU_CLOCK_NS: f64 = 8.0;
+const TRG_CLOCK_NS: f64 = 16.0;
+
+/// Trigger settings as exported from the MIDAS ODB (JSON).
+///
+/// Durations are integer numbers of clock cycles: the MLU settings are in 8 ns
+/// cycles, and the TRG box settings are in 16 ns cycles.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct TrgOdbConfig {
+    /// Prompt window in 8 ns cycles.
+    pub prompt_window: u32,
+    /// Wait gate in 8 ns cycles.
+    pub wait_gate: u32,
+    /// Drift veto in 16 ns cycles.
+    pub drift_veto: u32,
+    /// Dead time in 16 ns cycles.
+    pub dead_time: u32,
+    /// Scaledown of the TRG box.
+    pub scaledown: u32,
+    /// Path of the MLU lookup table file. A relative path is relative to the
+    /// current working directory.
+    pub mlu_file: PathBuf,
+}
+
+/// The error type returned when loading a [`TrgOdbConfig`] fails.
+#[derive(Debug)]
+pub enum OdbError {
+    /// The JSON dump is not a valid configuration.
+    Json(serde_json::Error),
+    /// The MLU file could not be read.
+    Io(std::io::Error),
+    /// The MLU file is not a valid lookup table.
+    LookupTable(ParseError),
+    /// A duration that has to be positive is zero clock cycles.
+    ZeroDuration(&'static str),
+}
+
+impl fmt::Display for OdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(_) => write!(f, "invalid ODB configuration"),
+            Self::Io(_) => write!(f, "failed to read the MLU file"),
+            Self::LookupTable(_) => write!(f, "invalid MLU file"),
+            Self::ZeroDuration(name) => write!(f, "`{name}` is zero clock cycles"),
+        }
+    }
+}
+
+impl std::error::Error for OdbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::LookupTable(error) => Some(error),
+            Self::ZeroDuration(_) => None,
+        }
+    }
+}
+
+fn clock_cycles(
+    name: &'static str,
+    cycles: u32,
+    period_ns: f64,
+) -> Result<Positive<Time>, OdbError> {
+    Positive::new(Time::new::<nanosecond>(f64::from(cycles) * period_ns))
+        .ok_or(OdbError::ZeroDuration(name))
+}
+
+/// A [`WorldBuilder`] after [`TrgOdbConfig::apply`].
+pub type OdbApplied<S> = world_builder::SetDeadTime<
+    world_builder::SetScaledown<
+        world_builder::SetDriftVeto<
+            world_builder::SetCondition<
+                world_builder::SetWaitGate<world_builder::SetPromptWindow<S>>,
+            >,
+        >,
+    >,
+>;
+
+impl TrgOdbConfig {
+    /// Deserialize the configuration from a JSON reader (e.g. a file).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::odb::TrgOdbConfig;
+    ///
+    /// let json = r#"{
+    ///     "prompt_window": 64,
+    ///     "wait_gate": 128,
+    ///     "drift_veto": 300,
+    ///     "dead_time": 211864,
+    ///     "scaledown": 0,
+    ///     "mlu_file": "mlu_file.txt"
+    /// }"#;
+    /// let config = TrgOdbConfig::from_reader(json.as_bytes())?;
+    /// assert_eq!(config.prompt_window, 64);
+    /// # Ok::<(), trg::odb::OdbError>(())
+    /// ```
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, OdbError> {
+        serde_json::from_reader(reader).map_err(OdbError::Json)
+    }
+    /// Returns the prompt window of the MLU.
+    pub fn prompt_window(&self) -> Result<Positive<Time>, OdbError> {
+        clock_cycles("prompt_window", self.prompt_window, MLU_CLOCK_NS)
+    }
+    /// Returns the wait gate of the MLU.
+    pub fn wait_gate(&self) -> Result<Positive<Time>, OdbError> {
+        clock_cycles("wait_gate", self.wait_gate, MLU_CLOCK_NS)
+    }
+    /// Returns the drift veto of the TRG box.
+    pub fn drift_veto(&self) -> Result<Positive<Time>, OdbError> {
+        clock_cycles("drift_veto", self.drift_veto, TRG_CLOCK_NS)
+    }
+    /// Returns the dead time of the TRG box.
+    pub fn dead_time(&self) -> Result<Positive<Time>, OdbError> {
+        clock_cycles("dead_time", self.dead_time, TRG_CLOCK_NS)
+    }
+    /// Read and parse the MLU file.
+    pub fn lookup_table(&self) -> Result<LookupTable, OdbError> {
+        std::fs::read_to_string(&self.mlu_file)
+            .map_err(OdbError::Io)?
+            .parse()
+            .map_err(OdbError::LookupTable)
+    }
+    /// Set all the trigger settings of a [`World`](crate::World): prompt
+    /// window, wait gate, lookup table, drift veto, scaledown, and dead time.
+    pub fn apply<'a, O, S>(
+        &self,
+        builder: WorldBuilder<'a, Time, O, S>,
+    ) -> Result<WorldBuilder<'a, Time, O, OdbApplied<S>>, OdbError>
+    where
+        S: world_builder::State,
+        S::PromptWindow: world_builder::IsUnset,
+        S::WaitGate: world_builder::IsUnset,
+        S::Condition: world_builder::IsUnset,
+        S::DriftVeto: world_builder::IsUnset,
+        S::Scaledown: world_builder::IsUnset,
+        S::DeadTime: world_builder::IsUnset,
+    {
+        Ok(builder
+            .prompt_window(self.prompt_window()?)
+            .wait_gate(self.wait_gate()?)
+            .lookup_table(self.lookup_table()?)
+            .drift_veto(self.drift_veto()?)
+            .scaledown(self.scaledown)
+            .dead_time(self.dead_time()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> TrgOdbConfig {
+        TrgOdbConfig::from_reader(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn odb_clock_conversion() {
+        let config = config(
+            r#"{
+                "prompt_window": 64,
+                "wait_gate": 128,
+                "drift_veto": 300,
+                "dead_time": 211864,
+                "scaledown": 2,
+                "mlu_file": "mlu_file.txt",
+                "other_setting": true
+            }"#,
+        );
+
+        let assert_ns = |time: Result<Positive<Time>, OdbError>, ns: f64| {
+            let time = time.unwrap().inner().get::<nanosecond>();
+            assert!((time - ns).abs() < 1e-6, "{time} ns != {ns} ns");
+        };
+        assert_ns(config.prompt_window(), 512.0);
+        assert_ns(config.wait_gate(), 1024.0);
+        assert_ns(config.drift_veto(), 4800.0);
+        assert_ns(config.dead_time(), 3389824.0);
+        assert_eq!(config.scaledown, 2);
+    }
+
+    #[test]
+    fn odb_errors() {
+        let config = config(
+            r#"{
+                "prompt_window": 0,
+                "wait_gate": 1,
+                "drift_veto": 1,
+                "dead_time": 1,
+                "scaledown": 0,
+                "mlu_file": "does/not/exist.txt"
+            }"#,
+        );
+        assert!(matches!(
+            config.prompt_window(),
+            Err(OdbError::ZeroDuration("prompt_window"))
+        ));
+        assert!(matches!(config.lookup_table(), Err(OdbError::Io(_))));
+        assert!(matches!(
+            TrgOdbConfig::from_reader(r#"{"prompt_window": 1}"#.as_bytes()),
+            Err(OdbError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn odb_apply() {
+        use crate::gen::{SecondaryGenerator, Source, WirePattern};
+        use crate::observers::Scalers;
+        use crate::World;
+
+        let mlu_file = std::env::temp_dir().join("trg_odb_apply_mlu.txt");
+        std::fs::write(
+            &mlu_file,
+            LookupTable::from([WirePattern::from_bits(2)]).to_string(),
+        )
+        .unwrap();
+        let config = TrgOdbConfig {
+            prompt_window: 1,
+            wait_gate: 1,
+            drift_veto: 1,
+            dead_time: 1,
+            scaledown: 0,
+            mlu_file,
+        };
+
+        let noise = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(Time::new::<nanosecond>(0.0))
+            .inter_arrival_time(vec![
+                Positive::new(Time::new::<nanosecond>(100.0)).unwrap();
+                4
+            ])
+            .wire_pattern([1, 2, 2, 3].map(WirePattern::from_bits))
+            .build();
+        let counts = config
+            .apply(World::builder().add_generator(noise))
+            .unwrap()
+            .observer(Scalers::default())
+            .build()
+            .run()
+            .counts();
+        assert_eq!((counts.trg_in, counts.trg_out), (2, 2));
+    }
+}
@@ -0,0 +1,772 @@
+#[cfg(feature = "uom")]
+use crate::gen::Positive;
+use rand::Rng;
+use rand_distr::Distribution;
+#[cfg(feature = "uom")]
+use uom::si::f64::{Frequency, Time};
+
+/// Dead-time corrections of trigger rates.
+pub mod deadtime;
+
+/// Number of events that passed a selection out of a sample e.g. the wire
+/// patterns accepted by a lookup table (see
+/// [`LookupTable::efficiency`](crate::mlu::LookupTable::efficiency)).
+///
+/// The Bayesian methods ([`Efficiency::mean`] and [`Efficiency::sample`]) use
+/// the posterior `Beta(passed + 1, total - passed + 1)` of a uniform prior.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Efficiency {
+    // Never greater than `total`.
+    passed: u64,
+    total: u64,
+}
+
+impl Efficiency {
+    /// Create a new efficiency of `passed` out of `total` events.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `passed` is greater than `total`.
+    pub fn new(passed: u64, total: u64) -> Self {
+        assert!(
+            passed <= total,
+            "passed events {passed} are more than the total {total}"
+        );
+        Self { passed, total }
+    }
+    /// Returns the number of events that passed.
+    pub fn passed(&self) -> u64 {
+        self.passed
+    }
+    /// Returns the number of events in the sample.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+    /// Returns the point estimate `passed / total`, or `None` if the sample is
+    /// empty.
+    pub fn value(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.passed as f64 / self.total as f64)
+    }
+    /// Returns the posterior mean `(passed + 1) / (total + 2)`. This is `0.5`
+    /// if the sample is empty.
+    pub fn mean(&self) -> f64 {
+        (self.passed + 1) as f64 / (self.total + 2) as f64
+    }
+    /// Returns the Clopper–Pearson (exact binomial) confidence interval of the
+    /// efficiency, with the given confidence level (e.g. `0.95`). The interval
+    /// is `(0.0, 1.0)` if the sample is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not in `(0, 1)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::statistics::Efficiency;
+    ///
+    /// let efficiency = Efficiency::new(0, 10);
+    /// let (low, high) = efficiency.interval(0.95);
+    /// assert_eq!(low, 0.0);
+    /// // 1 - 0.025^(1/10)
+    /// assert!((high - 0.3084971).abs() < 1e-6);
+    /// ```
+    pub fn interval(&self, confidence: f64) -> (f64, f64) {
+        let alpha = tail_probability(confidence);
+        let (k, n) = (self.passed, self.total);
+
+        // The lower bound is the `p` with `P(X >= k) = alpha`, and the upper
+        // bound is the `p` with `P(X <= k) = alpha`, where `X ~ Bin(n, p)`.
+        // Both probabilities are monotonic in `p`.
+        let low = if k == 0 {
+            0.0
+        } else {
+            bisect(0.0, 1.0, |p| 1.0 - binomial_cdf(k - 1, n, p) < alpha)
+        };
+        let high = if k == n {
+            1.0
+        } else {
+            bisect(0.0, 1.0, |p| binomial_cdf(k, n, p) > alpha)
+        };
+
+        (low, high)
+    }
+    /// Returns the Jeffreys confidence interval of the efficiency, with the
+    /// given confidence level (e.g. `0.95`). This is the equal-tailed interval
+    /// of the posterior `Beta(passed + 1/2, total - passed + 1/2)`, except that
+    /// the lower (upper) bound is `0.0` (`1.0`) if no (all) events passed.
+    /// It is narrower than [`Efficiency::interval`], with a coverage that is
+    /// closer to the confidence level on average.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not in `(0, 1)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::statistics::Efficiency;
+    ///
+    /// let efficiency = Efficiency::new(3, 10);
+    /// let (low, high) = efficiency.jeffreys_interval(0.95);
+    /// assert!((low - 0.0926946).abs() < 1e-6);
+    /// assert!((high - 0.6058183).abs() < 1e-6);
+    /// ```
+    pub fn jeffreys_interval(&self, confidence: f64) -> (f64, f64) {
+        let alpha = tail_probability(confidence);
+        let (k, n) = (self.passed, self.total);
+        let (a, b) = (k as f64 + 0.5, (n - k) as f64 + 0.5);
+
+        let low = if k == 0 {
+            0.0
+        } else {
+            bisect(0.0, 1.0, |p| beta_cdf(p, a, b) < alpha)
+        };
+        let high = if k == n {
+            1.0
+        } else {
+            bisect(0.0, 1.0, |p| beta_cdf(p, a, b) < 1.0 - alpha)
+        };
+
+        (low, high)
+    }
+    /// Returns a random efficiency drawn from the posterior, e.g. to propagate
+    /// its uncertainty to the results of a simulation.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        rand_distr::Beta::new(
+            (self.passed + 1) as f64,
+            (self.total - self.passed + 1) as f64,
+        )
+        .expect("the parameters are positive and finite")
+        .sample(rng)
+    }
+}
+
+/// Number of events counted during a livetime, to estimate the rate of a
+/// Poisson process e.g. of the cosmic background.
+///
+/// The Bayesian methods ([`RateEstimate::mean`] and [`RateEstimate::sample`])
+/// use the posterior `Gamma(counts + 1)` (in units of `1 / livetime`) of a
+/// uniform prior.
+#[cfg(feature = "uom")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateEstimate {
+    /// Number of events counted.
+    pub counts: u64,
+    /// Time during which events were counted.
+    pub livetime: Positive<Time>,
+}
+
+#[cfg(feature = "uom")]
+impl RateEstimate {
+    /// Create a new estimate of `counts` events in `livetime`.
+    pub fn new(counts: u64, livetime: Positive<Time>) -> Self {
+        Self { counts, livetime }
+    }
+    fn seconds(&self) -> f64 {
+        self.livetime.inner().get::<uom::si::time::second>()
+    }
+    fn rate(&self, counts: f64) -> Frequency {
+        Frequency::new::<uom::si::frequency::hertz>(counts / self.seconds())
+    }
+    /// Returns the point estimate `counts / livetime`.
+    pub fn value(&self) -> Frequency {
+        self.rate(self.counts as f64)
+    }
+    /// Returns the posterior mean `(counts + 1) / livetime`.
+    pub fn mean(&self) -> Frequency {
+        self.rate((self.counts + 1) as f64)
+    }
+    /// Returns the exact (Garwood) confidence interval of the rate, with the
+    /// given confidence level (e.g. `0.95`). The lower bound is zero if no
+    /// events were counted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not in `(0, 1)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use trg::gen::Positive;
+    /// use trg::statistics::RateEstimate;
+    /// use uom::si::f64::Time;
+    /// use uom::si::{frequency::hertz, time::second};
+    ///
+    /// let livetime = Positive::new(Time::new::<second>(2.0)).unwrap();
+    /// let (low, high) = RateEstimate::new(3, livetime).interval(0.95);
+    /// assert!((low.get::<hertz>() - 0.6186721 / 2.0).abs() < 1e-6);
+    /// assert!((high.get::<hertz>() - 8.7672731 / 2.0).abs() < 1e-6);
+    /// ```
+    pub fn interval(&self, confidence: f64) -> (Frequency, Frequency) {
+        let alpha = tail_probability(confidence);
+        let k = self.counts as f64;
+
+        // `P(X >= k) = alpha` and `P(X <= k) = alpha` for `X ~ Poisson(mu)`
+        // are the Gamma quantiles below.
+        let low = if self.counts == 0 {
+            0.0
+        } else {
+            gamma_quantile(alpha, k)
+        };
+        let high = gamma_quantile(1.0 - alpha, k + 1.0);
+
+        (self.rate(low), self.rate(high))
+    }
+    /// Returns the Jeffreys confidence interval of the rate, with the given
+    /// confidence level (e.g. `0.95`). This is the equal-tailed interval of
+    /// the posterior `Gamma(counts + 1/2)` (in units of `1 / livetime`),
+    /// except that the lower bound is zero if no events were counted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not in `(0, 1)`.
+    pub fn jeffreys_interval(&self, confidence: f64) -> (Frequency, Frequency) {
+        let alpha = tail_probability(confidence);
+        let a = self.counts as f64 + 0.5;
+
+        let low = if self.counts == 0 {
+            0.0
+        } else {
+            gamma_quantile(alpha, a)
+        };
+        let high = gamma_quantile(1.0 - alpha, a);
+
+        (self.rate(low), self.rate(high))
+    }
+    /// Returns a random rate drawn from the posterior, e.g. to propagate its
+    /// uncertainty to the results of a simulation.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Frequency {
+        let hertz = rand_distr::Gamma::new((self.counts + 1) as f64, 1.0 / self.seconds())
+            .expect("the parameters are positive and finite")
+            .sample(rng);
+        Frequency::new::<uom::si::frequency::hertz>(hertz)
+    }
+}
+
+/// Keep each item (e.g. an arrival time) independently with probability
+/// `probability` e.g. to model a detector efficiency or a prescale. Thinning a
+/// Poisson process gives a Poisson process with `probability` times the rate.
+///
+/// This makes a single random draw per item, so it keeps exactly the items
+/// labeled as survivors by [`thin_labeled`] with the same `rng`.
+///
+/// # Panics
+///
+/// Panics if `probability` is not in `[0, 1]`.
+///
+/// # Examples
+///
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use trg::statistics::thin;
+///
+/// let kept = thin(0..1000, 0.25, StdRng::seed_from_u64(0)).count();
+/// assert!((200..300).contains(&kept));
+/// ```
+pub fn thin<I: IntoIterator, R: Rng>(iter: I, probability: f64, rng: R) -> Thin<I::IntoIter, R> {
+    Thin(thin_labeled(iter, probability, rng))
+}
+
+/// Label each item (e.g. an arrival time) as a survivor independently with
+/// probability `probability`, keeping all of them. See [`thin`].
+///
+/// # Panics
+///
+/// Panics if `probability` is not in `[0, 1]`.
+///
+/// # Examples
+///
+/// Wire patterns that pass the MLU a fraction of the time:
+///
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// use std::iter::repeat;
+/// use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+/// use trg::statistics::thin_labeled;
+///
+/// let patterns = thin_labeled(repeat(()), 0.3, StdRng::seed_from_u64(0)).map(|(_, pass)| {
+///     WirePattern::from_bits(if pass { 2 } else { 1 })
+/// });
+/// let gen = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0)
+///     .max_events(100)
+///     .inter_arrival_time(repeat(Positive::new(10).unwrap()))
+///     .wire_pattern(patterns)
+///     .build();
+/// let pass = gen.filter(|event| event.wire_pattern.bits() == 2).count();
+/// assert!((10..50).contains(&pass));
+/// ```
+pub fn thin_labeled<I: IntoIterator, R: Rng>(
+    iter: I,
+    probability: f64,
+    rng: R,
+) -> ThinLabeled<I::IntoIter, R> {
+    ThinLabeled {
+        inner: iter.into_iter(),
+        survives: rand::distr::Bernoulli::new(probability)
+            .unwrap_or_else(|_| panic!("probability {probability} is not in [0, 1]")),
+        rng,
+    }
+}
+
+/// Iterator returned by [`thin_labeled`].
+#[derive(Clone, Debug)]
+pub struct ThinLabeled<I, R> {
+    inner: I,
+    survives: rand::distr::Bernoulli,
+    rng: R,
+}
+
+impl<I: Iterator, R: Rng> Iterator for ThinLabeled<I, R> {
+    type Item = (I::Item, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        Some((item, self.survives.sample(&mut self.rng)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Iterator returned by [`thin`].
+#[derive(Clone, Debug)]
+pub struct Thin<I, R>(ThinLabeled<I, R>);
+
+impl<I: Iterator, R: Rng> Iterator for Thin<I, R> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.find_map(|(item, survives)| survives.then_some(item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.0.size_hint().1)
+    }
+}
+
+// Probability in each tail of an equal-tailed interval.
+fn tail_probability(confidence: f64) -> f64 {
+    assert!(
+        confidence > 0.0 && confidence < 1.0,
+        "confidence {confidence} is not in (0, 1)"
+    );
+    (1.0 - confidence) / 2.0
+}
+
+// Largest `x` in `(low, high)` such that `below(x)` is true, assuming `below`
+// is true up to some point and false after it.
+fn bisect(mut low: f64, mut high: f64, below: impl Fn(f64) -> bool) -> f64 {
+    // Enough iterations to exhaust the precision of an `f64`.
+    for _ in 0..64 {
+        let mid = (low + high) / 2.0;
+        if below(mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+// `P(X <= k)` for `X ~ Bin(n, p)` with `0 < p < 1`. The terms are computed in
+// log space to avoid underflow for large `n`.
+fn binomial_cdf(k: u64, n: u64, p: f64) -> f64 {
+    let log_odds = (p / (1.0 - p)).ln();
+    // `ln P(X = i)` for `i` in `0..=k`.
+    let mut log_pmfs = vec![n as f64 * (1.0 - p).ln()];
+    for i in 0..k.min(n) {
+        let ratio = (n - i) as f64 / (i + 1) as f64;
+        log_pmfs.push(log_pmfs[log_pmfs.len() - 1] + ratio.ln() + log_odds);
+    }
+
+    let max = log_pmfs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let sum = log_pmfs
+        .iter()
+        .map(|log_pmf| (log_pmf - max).exp())
+        .sum::<f64>();
+
+    (max.exp() * sum).min(1.0)
+}
+
+// Relative tolerance and maximum number of terms of the series and continued
+// fractions below. The number of terms grows with the square root of the
+// parameters, so this is enough for any realistic number of events.
+const EPSILON: f64 = 1e-15;
+const MAX_TERMS: usize = 100_000;
+// Replaces zeros in the modified Lentz algorithm.
+const TINY: f64 = 1e-300;
+
+// `ln Γ(x)` for `x > 0` (Lanczos approximation with `g = 7`).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula.
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let sum = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| {
+            sum + c / (x + (i + 1) as f64)
+        });
+    let t = x + 7.5;
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+fn nonzero(x: f64) -> f64 {
+    if x.abs() < TINY {
+        TINY
+    } else {
+        x
+    }
+}
+
+// Step of the modified Lentz algorithm for a continued fraction: updates `c`
+// and `d` with the next partial numerator `a` and denominator `b`, and returns
+// the factor by which the value changes.
+fn lentz_step(c: &mut f64, d: &mut f64, a: f64, b: f64) -> f64 {
+    *d = 1.0 / nonzero(b + a * *d);
+    *c = nonzero(b + a / *c);
+    *c * *d
+}
+
+// Regularized incomplete beta function `I_x(a, b)` i.e. the CDF of a
+// `Beta(a, b)` distribution.
+fn beta_cdf(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    // The continued fraction converges quickly only below the mean.
+    if x < (a + 1.0) / (a + b + 2.0) {
+        ln_front.exp() * beta_fraction(x, a, b) / a
+    } else {
+        1.0 - ln_front.exp() * beta_fraction(1.0 - x, b, a) / b
+    }
+}
+
+// Continued fraction of `I_x(a, b)`, in the form of Numerical Recipes.
+fn beta_fraction(x: f64, a: f64, b: f64) -> f64 {
+    let mut c = 1.0;
+    let mut d = 1.0 / nonzero(1.0 - (a + b) * x / (a + 1.0));
+    let mut value = d;
+    for m in 1..MAX_TERMS {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        value *= lentz_step(&mut c, &mut d, even, 1.0);
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        let delta = lentz_step(&mut c, &mut d, odd, 1.0);
+        value *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    value
+}
+
+// Regularized lower incomplete gamma function `P(a, x)` i.e. the CDF of a
+// `Gamma(a, 1)` distribution.
+#[cfg(feature = "uom")]
+fn gamma_cdf(x: f64, a: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let ln_front = a * x.ln() - x - ln_gamma(a);
+    // The series converges quickly below the mean, and the continued fraction
+    // (of the upper function) above it.
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..MAX_TERMS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        ln_front.exp() * sum
+    } else {
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / nonzero(x + 1.0 - a);
+        let mut value = d;
+        for n in 1..MAX_TERMS {
+            let n = n as f64;
+            let delta = lentz_step(&mut c, &mut d, -n * (n - a), x + 2.0 * n + 1.0 - a);
+            value *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        1.0 - ln_front.exp() * value
+    }
+}
+
+// The `x` with `P(a, x) = probability`.
+#[cfg(feature = "uom")]
+fn gamma_quantile(probability: f64, a: f64) -> f64 {
+    let mut high = a + 1.0;
+    while gamma_cdf(high, a) < probability {
+        high *= 2.0;
+    }
+
+    bisect(0.0, high, |x| gamma_cdf(x, a) < probability)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn close((a, b): (f64, f64), (c, d): (f64, f64)) {
+        assert!((a - c).abs() < 1e-6 && (b - d).abs() < 1e-6, "{a} {b}");
+    }
+
+    #[test]
+    fn special_functions() {
+        // `Γ(n) = (n - 1)!` and `Γ(1/2) = sqrt(pi)`.
+        assert!(ln_gamma(1.0).abs() < 1e-14);
+        assert!((ln_gamma(10.0) - 362_880f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-14);
+        assert!((ln_gamma(0.1) - 2.252_712_651_734_206).abs() < 1e-12);
+
+        // `I_x(1, 1) = x`, `I_x(a, 1) = x^a`, and `I_x(a, a) = 1/2` at `x = 1/2`.
+        assert!((beta_cdf(0.3, 1.0, 1.0) - 0.3).abs() < 1e-14);
+        assert!((beta_cdf(0.3, 2.5, 1.0) - 0.3f64.powf(2.5)).abs() < 1e-14);
+        // Large parameters lose precision in the cancellation of `ln Γ`.
+        assert!((beta_cdf(0.5, 50_000.5, 50_000.5) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn efficiency_new() {
+        let efficiency = Efficiency::new(3, 6);
+        assert_eq!(efficiency.value(), Some(0.5));
+        assert_eq!(efficiency.mean(), 0.5);
+        assert_eq!(Efficiency::new(0, 0).mean(), 0.5);
+        assert_eq!(Efficiency::new(0, 8).mean(), 0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "passed events 3 are more than the total 2")]
+    fn efficiency_new_invalid() {
+        Efficiency::new(3, 2);
+    }
+
+    #[test]
+    fn efficiency_interval() {
+        let interval =
+            |passed, total, confidence| Efficiency::new(passed, total).interval(confidence);
+
+        // Closed forms at the edges: `(alpha / 2)^(1 / n)`.
+        close(interval(0, 10, 0.95), (0.0, 1.0 - 0.025f64.powf(0.1)));
+        close(interval(10, 10, 0.95), (0.025f64.powf(0.1), 1.0));
+        close(interval(1, 1, 0.8), (0.1, 1.0));
+        close(interval(0, 1, 0.8), (0.0, 0.9));
+        // n = 2, k = 1: `1 - (1 - p)^2 = alpha / 2` and `1 - p^2 = alpha / 2`.
+        close(interval(1, 2, 0.5), (1.0 - 0.75f64.sqrt(), 0.75f64.sqrt()));
+        // Reference values (e.g. `scipy.stats.binomtest(3, 10).proportion_ci()`).
+        close(interval(3, 10, 0.95), (0.0667395, 0.6524529));
+        // Large samples don't underflow, and approach the normal interval.
+        let (low, high) = interval(50_000, 100_000, 0.95);
+        assert!((low - (0.5 - 0.0031)).abs() < 1e-4 && (high - (0.5 + 0.0031)).abs() < 1e-4);
+        assert_eq!(interval(0, 0, 0.9), (0.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "confidence 1 is not in (0, 1)")]
+    fn efficiency_interval_invalid_confidence() {
+        Efficiency::new(1, 2).interval(1.0);
+    }
+
+    #[test]
+    fn efficiency_jeffreys_interval() {
+        let interval = |passed, total, confidence| {
+            Efficiency::new(passed, total).jeffreys_interval(confidence)
+        };
+
+        // Reference values (e.g. `scipy.stats.beta(3.5, 7.5).ppf([0.025, 0.975])`).
+        close(interval(3, 10, 0.95), (0.0926946, 0.6058183));
+        close(interval(0, 10, 0.95), (0.0, 0.2171963));
+        close(interval(10, 10, 0.95), (0.7828037, 1.0));
+        close(interval(1, 2, 0.5), (0.2980136, 0.7019864));
+        // Narrower than Clopper–Pearson.
+        let (low, high) = interval(3, 10, 0.95);
+        let (cp_low, cp_high) = Efficiency::new(3, 10).interval(0.95);
+        assert!(cp_low < low && high < cp_high);
+        // Large samples approach the normal interval.
+        let (low, high) = interval(50_000, 100_000, 0.95);
+        assert!((low - (0.5 - 0.0031)).abs() < 1e-4 && (high - (0.5 + 0.0031)).abs() < 1e-4);
+        assert_eq!(interval(0, 0, 0.9), (0.0, 1.0));
+    }
+
+    #[test]
+    fn efficiency_sample() {
+        let efficiency = Efficiency::new(30, 100);
+        let mut rng = StdRng::seed_from_u64(0);
+        let n = 100_000;
+        let samples: Vec<_> = (0..n).map(|_| efficiency.sample(&mut rng)).collect();
+        assert!(samples.iter().all(|p| (0.0..=1.0).contains(p)));
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        // The posterior standard deviation is about 0.045.
+        assert!((mean - efficiency.mean()).abs() < 5.0 * 0.045 / (n as f64).sqrt());
+
+        // The same as the previously inlined posterior.
+        let expected = rand_distr::Beta::new(31.0, 71.0)
+            .unwrap()
+            .sample(&mut StdRng::seed_from_u64(1));
+        assert_eq!(efficiency.sample(&mut StdRng::seed_from_u64(1)), expected);
+    }
+
+    #[test]
+    fn thin_survival_fraction() {
+        let n = 100_000;
+        for probability in [0.0, 0.01, 0.3, 0.5, 1.0] {
+            let kept = thin(0..n, probability, StdRng::seed_from_u64(0)).count() as f64;
+            let sigma = (n as f64 * probability * (1.0 - probability)).sqrt();
+            assert!(
+                (kept - n as f64 * probability).abs() <= 5.0 * sigma,
+                "{kept}"
+            );
+        }
+    }
+
+    #[test]
+    fn thin_deterministic() {
+        let thinned =
+            |seed| -> Vec<_> { thin(0..1000, 0.5, StdRng::seed_from_u64(seed)).collect() };
+        assert_eq!(thinned(0), thinned(0));
+        assert_ne!(thinned(0), thinned(1));
+        assert!(thinned(0).windows(2).all(|w| w[0] < w[1]));
+
+        let labeled: Vec<_> = thin_labeled(0..1000, 0.5, StdRng::seed_from_u64(0)).collect();
+        assert_eq!(labeled.len(), 1000);
+        assert!(labeled.iter().enumerate().all(|(i, (item, _))| i == *item));
+        let survivors: Vec<_> = labeled
+            .into_iter()
+            .filter_map(|(item, survives)| survives.then_some(item))
+            .collect();
+        assert_eq!(survivors, thinned(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "probability 1.5 is not in [0, 1]")]
+    fn thin_invalid_probability() {
+        thin(0..10, 1.5, StdRng::seed_from_u64(0));
+    }
+
+    #[cfg(feature = "uom")]
+    mod rate {
+        use super::*;
+        use uom::si::{frequency::hertz, time::second};
+
+        fn estimate(counts: u64, seconds: f64) -> RateEstimate {
+            RateEstimate::new(counts, Positive::new(Time::new::<second>(seconds)).unwrap())
+        }
+
+        fn in_hertz((low, high): (Frequency, Frequency)) -> (f64, f64) {
+            (low.get::<hertz>(), high.get::<hertz>())
+        }
+
+        #[test]
+        fn incomplete_gamma() {
+            // `P(1, x) = 1 - exp(-x)` on both sides of the mean.
+            for x in [0.1, 1.5, 20.0] {
+                assert!((gamma_cdf(x, 1.0) - (1.0 - f64::exp(-x))).abs() < 1e-14);
+            }
+            // `P(1/2, x) = erf(sqrt(x))`, with `erf(1) = 0.8427007929497149`.
+            assert!((gamma_cdf(1.0, 0.5) - 0.842_700_792_949_714_9).abs() < 1e-14);
+        }
+
+        #[test]
+        fn rate_estimate() {
+            let rate = estimate(3, 2.0);
+            assert_eq!(rate.value().get::<hertz>(), 1.5);
+            assert_eq!(rate.mean().get::<hertz>(), 2.0);
+            assert_eq!(estimate(0, 2.0).value().get::<hertz>(), 0.0);
+        }
+
+        #[test]
+        fn rate_estimate_interval() {
+            // Reference values (e.g. `scipy.stats.chi2.ppf([0.025, 0.975], [6, 8]) / 2`).
+            close(
+                in_hertz(estimate(3, 1.0).interval(0.95)),
+                (0.6186721, 8.7672731),
+            );
+            close(
+                in_hertz(estimate(3, 2.0).interval(0.95)),
+                (0.3093361, 4.3836365),
+            );
+            close(
+                in_hertz(estimate(100, 1.0).interval(0.95)),
+                (81.3639913, 121.6267938),
+            );
+            // Closed form with no counts: `-ln(alpha / 2)`.
+            close(
+                in_hertz(estimate(0, 1.0).interval(0.95)),
+                (0.0, -0.025f64.ln()),
+            );
+        }
+
+        #[test]
+        fn rate_estimate_jeffreys_interval() {
+            // Reference values (e.g. `scipy.stats.gamma(3.5).ppf([0.025, 0.975])`).
+            close(
+                in_hertz(estimate(3, 1.0).jeffreys_interval(0.95)),
+                (0.8449346, 8.0063821),
+            );
+            close(
+                in_hertz(estimate(0, 1.0).jeffreys_interval(0.95)),
+                (0.0, 2.5119431),
+            );
+            // Large counts approach the normal interval.
+            let (low, high) = in_hertz(estimate(1_000_000, 1.0).jeffreys_interval(0.95));
+            assert!((low - (1e6 - 1960.0)).abs() < 5.0 && (high - (1e6 + 1960.0)).abs() < 5.0);
+        }
+
+        #[test]
+        fn rate_estimate_sample() {
+            let rate = estimate(99, 10.0);
+            let mut rng = StdRng::seed_from_u64(0);
+            let n = 100_000;
+            let mean = (0..n)
+                .map(|_| rate.sample(&mut rng).get::<hertz>())
+                .sum::<f64>()
+                / n as f64;
+            // The posterior standard deviation is 1 Hz.
+            assert!((mean - rate.mean().get::<hertz>()).abs() < 5.0 / (n as f64).sqrt());
+
+            // The same as the previously inlined posterior.
+            let expected = rand_distr::Gamma::new(100.0, 0.1)
+                .unwrap()
+                .sample(&mut StdRng::seed_from_u64(1));
+            assert_eq!(
+                rate.sample(&mut StdRng::seed_from_u64(1)).get::<hertz>(),
+                expected
+            );
+        }
+    }
+}
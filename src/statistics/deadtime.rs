@@ -0,0 +1,300 @@
+use crate::gen::Positive;
+
+/// A rate that can be corrected for a dead time of type `Self::Time` e.g.
+/// `f64` rates in Hz with `f64` times in seconds, or `uom` quantities.
+pub trait DeadTimeRate: Sized {
+    type Time;
+
+    /// Returns the expected number of events in `time` at this rate.
+    fn events_in(&self, time: &Self::Time) -> f64;
+    /// Returns the rate with an expected number of `events` in `time`.
+    fn from_events_in(events: f64, time: &Self::Time) -> Self;
+}
+
+impl DeadTimeRate for f64 {
+    type Time = f64;
+
+    fn events_in(&self, time: &f64) -> f64 {
+        self * time
+    }
+    fn from_events_in(events: f64, time: &f64) -> Self {
+        events / time
+    }
+}
+
+#[cfg(feature = "uom")]
+impl DeadTimeRate for uom::si::f64::Frequency {
+    type Time = uom::si::f64::Time;
+
+    fn events_in(&self, time: &Self::Time) -> f64 {
+        (*self * *time).get::<uom::si::ratio::ratio>()
+    }
+    fn from_events_in(events: f64, time: &Self::Time) -> Self {
+        Self::new::<uom::si::frequency::hertz>(events / time.get::<uom::si::time::second>())
+    }
+}
+
+/// Returns the rate recorded by a non-paralyzable system (i.e. events during
+/// the dead time are lost, but do not extend it) at a true rate of
+/// `true_rate`. This is `true_rate / (1 + true_rate * dead_time)`.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::Positive;
+/// use trg::statistics::deadtime::observed_rate;
+///
+/// // 10 kHz with a dead time of 100 us.
+/// let observed = observed_rate(10_000.0, Positive::new(1e-4).unwrap());
+/// assert!((observed - 5_000.0).abs() < 1e-9);
+/// ```
+pub fn observed_rate<R: DeadTimeRate>(true_rate: R, dead_time: Positive<R::Time>) -> R {
+    let dead_time = dead_time.inner();
+    let events = true_rate.events_in(dead_time);
+    R::from_events_in(events / (1.0 + events), dead_time)
+}
+
+/// Returns the true rate that a non-paralyzable system records as
+/// `observed` (the inverse of [`observed_rate`]). This is
+/// `observed / (1 - observed * dead_time)`, or `None` if `observed` is not
+/// below `1 / dead_time`.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::Positive;
+/// use trg::statistics::deadtime::true_rate;
+///
+/// let dead_time = Positive::new(1e-4).unwrap();
+/// let rate = true_rate(5_000.0, dead_time).unwrap();
+/// assert!((rate - 10_000.0).abs() < 1e-9);
+/// // More than one event per dead time.
+/// assert!(true_rate(20_000.0, dead_time).is_none());
+/// ```
+pub fn true_rate<R: DeadTimeRate>(observed: R, dead_time: Positive<R::Time>) -> Option<R> {
+    let dead_time = dead_time.inner();
+    let events = observed.events_in(dead_time);
+    (events < 1.0).then(|| R::from_events_in(events / (1.0 - events), dead_time))
+}
+
+/// Returns the rate recorded by a paralyzable system (i.e. every event, even
+/// if lost, restarts the dead time) at a true rate of `true_rate`. This is
+/// `true_rate * exp(-true_rate * dead_time)`.
+pub fn paralyzable_observed_rate<R: DeadTimeRate>(true_rate: R, dead_time: Positive<R::Time>) -> R {
+    let dead_time = dead_time.inner();
+    let events = true_rate.events_in(dead_time);
+    R::from_events_in(events * (-events).exp(), dead_time)
+}
+
+/// Returns the true rate that a paralyzable system records as `observed`
+/// (the inverse of [`paralyzable_observed_rate`]), or `None` if `observed`
+/// is above the maximum recorded rate `1 / (e * dead_time)`.
+///
+/// Any other observed rate is recorded at two true rates, one below and one
+/// above `1 / dead_time`; this returns the lower one. It is found
+/// numerically, with a relative precision of about `1e-8` close to the
+/// maximum and close to machine precision elsewhere.
+///
+/// # Examples
+///
+/// ```
+/// use trg::gen::Positive;
+/// use trg::statistics::deadtime::{paralyzable_observed_rate, paralyzable_true_rate};
+///
+/// let dead_time = Positive::new(1e-4).unwrap();
+/// let observed = paralyzable_observed_rate(5_000.0, dead_time);
+/// let rate = paralyzable_true_rate(observed, dead_time).unwrap();
+/// assert!((rate - 5_000.0).abs() < 1e-9);
+/// // Too high for any true rate.
+/// assert!(paralyzable_true_rate(4_000.0, dead_time).is_none());
+/// ```
+pub fn paralyzable_true_rate<R: DeadTimeRate>(
+    observed: R,
+    dead_time: Positive<R::Time>,
+) -> Option<R> {
+    let dead_time = dead_time.inner();
+    let events = paralyzable_events(observed.events_in(dead_time))?;
+    Some(R::from_events_in(events, dead_time))
+}
+
+// The lower solution `x` of `x * exp(-x) = observed` (i.e. `-W0(-observed)`
+// with the Lambert W function), with both sides in events per dead time.
+fn paralyzable_events(observed: f64) -> Option<f64> {
+    // A few ulps above the maximum are rounding errors e.g. of the observed
+    // rate at a true rate of `1 / dead_time`.
+    if observed > (-1.0f64).exp() * (1.0 + 4.0 * f64::EPSILON) {
+        return None;
+    }
+    // `x * exp(-x)` is increasing and concave below 1, so Newton's method
+    // from 0 approaches the solution from below. The convergence is only
+    // linear close to the maximum, where the derivative vanishes.
+    let mut x: f64 = 0.0;
+    for _ in 0..100 {
+        let f = x * (-x).exp() - observed;
+        let derivative = (1.0 - x) * (-x).exp();
+        let next = (x - f / derivative).min(1.0);
+        // Also stops at `NaN` e.g. a zero derivative.
+        if next > x {
+            x = next;
+        } else {
+            break;
+        }
+    }
+
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::{PoissonGenerator, PoissonTimes, Source, WirePattern};
+    use crate::mlu::LookupTable;
+    use crate::observers::Scalers;
+    use crate::World;
+    use rand::distr::Distribution;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const DEAD_TIME: f64 = 1e-6;
+
+    fn dead_time() -> Positive<f64> {
+        Positive::new(DEAD_TIME).unwrap()
+    }
+
+    // From 1 Hz to 10 MHz i.e. from 1e-6 to 10 events per dead time.
+    fn rates() -> impl Iterator<Item = f64> {
+        (0..=70).map(|i| 10f64.powf(f64::from(i) / 10.0))
+    }
+
+    fn assert_close(a: f64, b: f64, relative: f64) {
+        assert!((a - b).abs() <= relative * b, "{a} {b}");
+    }
+
+    #[test]
+    fn non_paralyzable_round_trip() {
+        for rate in rates() {
+            let observed = observed_rate(rate, dead_time());
+            assert!(observed < rate && observed < 1.0 / DEAD_TIME);
+            assert_close(true_rate(observed, dead_time()).unwrap(), rate, 1e-12);
+        }
+        assert_eq!(true_rate(0.0, dead_time()), Some(0.0));
+        assert_eq!(true_rate(1.0 / DEAD_TIME, dead_time()), None);
+        assert_eq!(true_rate(2.0 / DEAD_TIME, dead_time()), None);
+    }
+
+    #[test]
+    fn paralyzable_round_trip() {
+        let max = 1.0 / (std::f64::consts::E * DEAD_TIME);
+        for rate in rates() {
+            let observed = paralyzable_observed_rate(rate, dead_time());
+            assert!(observed < rate && observed <= max);
+            let recovered = paralyzable_true_rate(observed, dead_time()).unwrap();
+            if rate * DEAD_TIME < 0.9 {
+                assert_close(recovered, rate, 1e-12);
+            } else {
+                // The lower of the two true rates.
+                assert!(recovered <= 1.0 / DEAD_TIME);
+                assert_close(
+                    paralyzable_observed_rate(recovered, dead_time()),
+                    observed,
+                    1e-12,
+                );
+            }
+        }
+        assert_eq!(paralyzable_true_rate(0.0, dead_time()), Some(0.0));
+        // At the maximum, the derivative vanishes.
+        assert_close(
+            paralyzable_true_rate(max, dead_time()).unwrap(),
+            1.0 / DEAD_TIME,
+            1e-7,
+        );
+        assert_eq!(paralyzable_true_rate(max * 1.001, dead_time()), None);
+    }
+
+    #[test]
+    fn paralyzable_more_dead_than_non_paralyzable() {
+        for rate in rates() {
+            assert!(
+                paralyzable_observed_rate(rate, dead_time()) <= observed_rate(rate, dead_time())
+            );
+        }
+    }
+
+    #[cfg(feature = "uom")]
+    #[test]
+    fn uom_rates() {
+        use uom::si::f64::{Frequency, Time};
+        use uom::si::{frequency::kilohertz, time::microsecond};
+
+        let dead_time = Positive::new(Time::new::<microsecond>(1.0)).unwrap();
+        for rate in rates() {
+            let frequency = Frequency::new::<kilohertz>(rate / 1000.0);
+            assert_close(
+                observed_rate(frequency, dead_time).get::<kilohertz>(),
+                observed_rate(rate, self::dead_time()) / 1000.0,
+                1e-12,
+            );
+            assert_close(
+                paralyzable_observed_rate(frequency, dead_time).get::<kilohertz>(),
+                paralyzable_observed_rate(rate, self::dead_time()) / 1000.0,
+                1e-12,
+            );
+        }
+    }
+
+    #[test]
+    fn non_paralyzable_world() {
+        let (rate, dead_time, duration) = (10_000.0, 1e-4, 10.0);
+        let tiny = Positive::new(1e-9).unwrap();
+        let noise = PoissonGenerator::builder()
+            .source(Source::Noise)
+            .origin(0.0)
+            .duration(Positive::new(duration).unwrap())
+            .rate(Positive::new(rate).unwrap())
+            .rng(StdRng::seed_from_u64(0))
+            .wire_pattern(rand::distr::StandardUniform.map(|_: u8| WirePattern::from_bits(1)))
+            .build();
+        // The TRG box dead time is non-paralyzable; everything else is
+        // negligible.
+        let counts = World::builder()
+            .add_generator(noise)
+            .prompt_window(tiny)
+            .wait_gate(tiny)
+            .lookup_table(LookupTable::from([WirePattern::from_bits(1)]))
+            .drift_veto(tiny)
+            .scaledown(0)
+            .dead_time(Positive::new(dead_time).unwrap())
+            .observer(Scalers::default())
+            .build()
+            .run()
+            .counts();
+
+        let dead_time = Positive::new(dead_time).unwrap();
+        let expected = observed_rate(rate, dead_time) * duration;
+        let trg_out = counts.trg_out as f64;
+        // 5 sigma of a Poisson count (the spread is actually smaller).
+        assert!((trg_out - expected).abs() < 5.0 * expected.sqrt());
+        let recovered = true_rate(trg_out / duration, dead_time).unwrap();
+        assert_close(recovered, counts.wire_events as f64 / duration, 0.02);
+    }
+
+    #[test]
+    fn paralyzable_brute_force() {
+        // An event is recorded only if there was no other event within the
+        // dead time before it.
+        let (rate, dead_time, events) = (5_000.0, 1e-4, 100_000);
+        let recorded = PoissonTimes::<f64, _, _>::seeded(Positive::new(rate).unwrap(), 0)
+            .take(events)
+            .filter(|interval| *interval.inner() > dead_time)
+            .count() as f64;
+
+        // `exp(-1/2)` of the events are recorded.
+        let dead_time = Positive::new(dead_time).unwrap();
+        let p = paralyzable_observed_rate(rate, dead_time) / rate;
+        let expected = p * events as f64;
+        assert!((recorded - expected).abs() < 5.0 * (expected * (1.0 - p)).sqrt());
+        let duration = events as f64 / rate;
+        let recovered = paralyzable_true_rate(recorded / duration, dead_time).unwrap();
+        assert_close(recovered, rate, 0.02);
+    }
+}
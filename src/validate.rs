@@ -0,0 +1,152 @@
+use crate::gen::WireEvent;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The error type returned when a stream of [`WireEvent`]s is not sorted in
+/// time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrderingViolation<T> {
+    /// Position (0-based) of the offending event in the stream.
+    pub index: u64,
+    /// Time of the event preceding the offending event.
+    pub previous: T,
+    /// Time of the offending event.
+    pub current: T,
+}
+
+impl<T: fmt::Debug> fmt::Display for OrderingViolation<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "wire event #{} at time {:?} is earlier than the previous event at time {:?}",
+            self.index, self.current, self.previous
+        )
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for OrderingViolation<T> {}
+
+// Streaming (i.e. constant memory) check of the time ordering of events.
+#[derive(Clone, Debug)]
+pub(crate) struct OrderingChecker<T> {
+    previous: Option<T>,
+    count: u64,
+}
+
+impl<T> Default for OrderingChecker<T> {
+    fn default() -> Self {
+        Self {
+            previous: None,
+            count: 0,
+        }
+    }
+}
+
+impl<T: PartialOrd + Clone> OrderingChecker<T> {
+    pub(crate) fn check(&mut self, time: &T) -> Result<(), OrderingViolation<T>> {
+        if let Some(previous) = &self.previous {
+            // Incomparable times (e.g. `NaN`) are also reported.
+            if !matches!(
+                previous.partial_cmp(time),
+                Some(Ordering::Less | Ordering::Equal)
+            ) {
+                return Err(OrderingViolation {
+                    index: self.count,
+                    previous: previous.clone(),
+                    current: time.clone(),
+                });
+            }
+        }
+        self.previous = Some(time.clone());
+        self.count += 1;
+
+        Ok(())
+    }
+}
+
+/// Verify that a stream of [`WireEvent`]s is sorted in (non-decreasing) time.
+/// Returns the number of events in the stream.
+///
+/// This is useful to vet a custom source of events before a long simulation.
+/// The check uses constant memory, but it will never return if the stream is
+/// infinite and sorted.
+///
+/// # Examples
+///
+/// ```
+/// # use trg::gen::{Positive, SecondaryGenerator, Source, WirePattern};
+/// use trg::validate::check_ordering;
+/// use std::iter::repeat;
+///
+/// let gen = SecondaryGenerator::builder()
+///     .source(Source::Noise)
+///     .origin(0)
+///     .inter_arrival_time(repeat(Positive::new(1).unwrap()).take(5))
+///     .wire_pattern(repeat(WirePattern::from_bits(0)))
+///     .build();
+///
+/// assert_eq!(check_ordering(gen)?, 5);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn check_ordering<I, T>(iter: I) -> Result<u64, OrderingViolation<T>>
+where
+    I: IntoIterator<Item = WireEvent<T>>,
+    T: PartialOrd + Clone,
+{
+    let mut checker = OrderingChecker::default();
+    for event in iter {
+        checker.check(&event.time)?;
+    }
+
+    Ok(checker.count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::{Source, WirePattern};
+
+    fn events(times: &[f64]) -> Vec<WireEvent<f64>> {
+        times
+            .iter()
+            .map(|&time| WireEvent {
+                source: Source::Noise,
+                wire_pattern: WirePattern::from_bits(0),
+                time,
+                id: None,
+                weight: 1.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_ordering_sorted() {
+        assert_eq!(check_ordering(events(&[])), Ok(0));
+        assert_eq!(check_ordering(events(&[1.0, 2.0, 2.0, 3.0])), Ok(4));
+    }
+
+    #[test]
+    fn check_ordering_unsorted() {
+        assert_eq!(
+            check_ordering(events(&[1.0, 3.0, 2.0, 4.0])),
+            Err(OrderingViolation {
+                index: 2,
+                previous: 3.0,
+                current: 2.0,
+            })
+        );
+
+        let err = check_ordering(events(&[1.0, f64::NAN])).unwrap_err();
+        assert_eq!(err.index, 1);
+        assert!(err.current.is_nan());
+    }
+
+    #[test]
+    fn ordering_violation_display() {
+        let err = check_ordering(events(&[1.0, 3.0, 2.0])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "wire event #2 at time 2.0 is earlier than the previous event at time 3.0"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Search for trigger configurations that break the invariants of the trigger
+//! system.
+
+use proptest::prelude::*;
+use trg::gen::{Positive, SecondaryGenerator, Source, WireEvent, WirePattern};
+use trg::invariants::{check, RunStats};
+use trg::mlu::{LookupTable, TrgSignal};
+use trg::observers::{OrderingAuditor, ScalerCounts, Scalers};
+use trg::{Observer, World};
+
+#[derive(Default)]
+struct FuzzObserver {
+    scalers: Scalers<i64>,
+    auditor: OrderingAuditor<i64>,
+    snapshots: Vec<ScalerCounts>,
+    trg_out: Vec<i64>,
+    last_event: Option<i64>,
+}
+
+impl Observer for FuzzObserver {
+    type Time = i64;
+
+    fn on_wire_event(&mut self, event: &WireEvent<Self::Time>) {
+        // All previous TRG signals have been fully processed at this point.
+        self.snapshots.push(self.scalers.counts());
+        self.scalers.on_wire_event(event);
+        self.auditor.on_wire_event(event);
+        self.last_event = Some(event.time);
+    }
+    fn on_trg_in(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.scalers.on_trg_in(signal);
+        self.auditor.on_trg_in(signal);
+    }
+    fn on_trg_drift_veto(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.scalers.on_trg_drift_veto(signal);
+        self.auditor.on_trg_drift_veto(signal);
+    }
+    fn on_trg_scaledown(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.scalers.on_trg_scaledown(signal);
+        self.auditor.on_trg_scaledown(signal);
+    }
+    fn on_trg_dead_time(&mut self, signal: &TrgSignal<Self::Time>) {
+        self.scalers.on_trg_dead_time(signal);
+        self.auditor.on_trg_dead_time(signal);
+    }
+    fn on_trg_out(&mut self, signal: &TrgSignal<Self::Time>, number: u64) {
+        assert_eq!(number, self.scalers.counts().trg_out);
+        self.scalers.on_trg_out(signal, number);
+        self.auditor.on_trg_out(signal, number);
+        self.trg_out.push(signal.time);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+    prompt_window: i64,
+    wait_gate: i64,
+    table: Vec<u16>,
+    table_switches: Vec<(i64, Vec<u16>)>,
+    drift_veto: i64,
+    scaledown: u32,
+    dead_time: i64,
+}
+
+// Restricting patterns to a few boards makes lookup table hits likely.
+fn pattern() -> impl Strategy<Value = u16> {
+    0u16..16
+}
+
+fn config() -> impl Strategy<Value = Config> {
+    (
+        1i64..50,
+        1i64..50,
+        prop::collection::vec(pattern(), 0..8),
+        prop::collection::vec((0i64..2000, prop::collection::vec(pattern(), 0..8)), 0..3),
+        1i64..100,
+        0u32..4,
+        1i64..200,
+    )
+        .prop_map(
+            |(
+                prompt_window,
+                wait_gate,
+                table,
+                mut table_switches,
+                drift_veto,
+                scaledown,
+                dead_time,
+            )| {
+                table_switches.sort_by_key(|(time, _)| *time);
+                Config {
+                    prompt_window,
+                    wait_gate,
+                    table,
+                    table_switches,
+                    drift_veto,
+                    scaledown,
+                    dead_time,
+                }
+            },
+        )
+}
+
+// (inter-arrival time, wire pattern) of each event in a stream.
+fn stream() -> impl Strategy<Value = Vec<(i64, u16)>> {
+    prop::collection::vec((1i64..40, pattern()), 0..200)
+}
+
+fn table(patterns: &[u16]) -> LookupTable {
+    patterns
+        .iter()
+        .map(|&p| WirePattern::from_bits(p))
+        .collect()
+}
+
+fn run(config: &Config, streams: &[Vec<(i64, u16)>]) -> FuzzObserver {
+    let mut builder = World::builder();
+    for stream in streams {
+        let gen = SecondaryGenerator::builder()
+            .source(Source::Noise)
+            .origin(0)
+            .inter_arrival_time(
+                stream
+                    .iter()
+                    .map(|(dt, _)| Positive::new(*dt).unwrap())
+                    .collect::<Vec<_>>(),
+            )
+            .wire_pattern(
+                stream
+                    .iter()
+                    .map(|(_, p)| WirePattern::from_bits(*p))
+                    .collect::<Vec<_>>(),
+            )
+            .build();
+        builder = builder.add_generator(gen);
+    }
+
+    builder
+        .prompt_window(Positive::new(config.prompt_window).unwrap())
+        .wait_gate(Positive::new(config.wait_gate).unwrap())
+        .lookup_table(table(&config.table))
+        .table_switches(
+            config
+                .table_switches
+                .iter()
+                .map(|(time, patterns)| (*time, table(patterns)))
+                .collect(),
+        )
+        .drift_veto(Positive::new(config.drift_veto).unwrap())
+        .scaledown(config.scaledown)
+        .dead_time(Positive::new(config.dead_time).unwrap())
+        .observer(FuzzObserver::default())
+        .check_ordering(true)
+        .build()
+        .run()
+}
+
+fn run_stats(observer: &FuzzObserver, dead_time_per_trigger: i64) -> RunStats {
+    // A prompt window still open at the end of the stream can produce a
+    // trigger after the last wire event.
+    let span = observer
+        .last_event
+        .into_iter()
+        .chain(observer.trg_out.last().copied())
+        .max()
+        .unwrap_or(0);
+    // Busy windows never overlap because a trigger is only sent to the DAQ
+    // after the previous busy window has ended.
+    let dead_time = observer
+        .trg_out
+        .iter()
+        .map(|t| dead_time_per_trigger.min(span - t))
+        .sum::<i64>();
+    // The gaps before, between, and after the busy windows. Computed on their
+    // own (not as `span - dead_time`), so overlapping busy windows make the
+    // live time plus the dead time exceed the span.
+    let mut live_time = 0;
+    let mut live_since = 0;
+    for &t in &observer.trg_out {
+        live_time += (t - live_since).max(0);
+        live_since = t + dead_time_per_trigger;
+    }
+    live_time += (span - live_since).max(0);
+
+    RunStats {
+        span: span as f64,
+        live_time: live_time as f64,
+        dead_time: dead_time as f64,
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn config_fuzz(config in config(), streams in prop::collection::vec(stream(), 1..4)) {
+        let observer = run(&config, &streams);
+
+        prop_assert!(
+            observer.auditor.violation().is_none(),
+            "{}",
+            observer.auditor.violation().unwrap()
+        );
+
+        let mut snapshots = observer.snapshots.clone();
+        snapshots.push(observer.scalers.counts());
+        let run_stats = run_stats(&observer, config.dead_time);
+        if let Err(violation) = check(&snapshots, &run_stats) {
+            prop_assert!(false, "{violation}");
+        }
+
+        let events = streams.iter().map(Vec::len).sum::<usize>();
+        prop_assert_eq!(observer.scalers.counts().wire_events, events as u64);
+    }
+}
@@ -0,0 +1,71 @@
+//! Replay recorded wire events from CSV under a simulated trigger.
+#![cfg(feature = "csv")]
+
+use trg::gen::csv::{CsvErrorKind, CsvEventGenerator};
+use trg::gen::{Positive, WirePattern};
+use trg::mlu::LookupTable;
+use trg::observers::Scalers;
+use trg::World;
+
+const RECORDED: &str = "\
+time_ns,pattern_hex,source
+0,0x0001,Noise
+2.5,0x0002,Noise
+100,0x0001,PrimaryCosmic
+200,0x0003,PrimaryCosmic
+201,0x0004,SecondaryCosmic
+";
+
+fn run(csv: &str, table: LookupTable) -> (u64, u64) {
+    let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+    let counts = World::builder()
+        .add_generator(&mut gen)
+        .prompt_window(Positive::new(10.0).unwrap())
+        .wait_gate(Positive::new(10.0).unwrap())
+        .lookup_table(table)
+        .drift_veto(Positive::new(1.0).unwrap())
+        .scaledown(0)
+        .dead_time(Positive::new(1.0).unwrap())
+        .observer(Scalers::default())
+        .build()
+        .run()
+        .counts();
+    if let Some(error) = gen.error() {
+        panic!("{error}");
+    }
+
+    (counts.trg_in, counts.trg_out)
+}
+
+#[test]
+fn csv_replay_modified_tables() {
+    let table = |bits: &[u16]| bits.iter().copied().map(WirePattern::from_bits).collect();
+    // Prompt windows: {0x0003}, {0x0001}, and {0x0007}.
+    assert_eq!(run(RECORDED, table(&[0x0003])), (1, 1));
+    assert_eq!(run(RECORDED, table(&[0x0001, 0x0007])), (2, 2));
+    assert_eq!(run(RECORDED, LookupTable::min_wires(1)), (3, 3));
+}
+
+#[test]
+fn csv_replay_invalid_row() {
+    let csv = format!("{RECORDED}150,0x0001,Noise\n");
+    let mut gen = CsvEventGenerator::new(csv.as_bytes(), |ns| ns);
+    let events = World::builder()
+        .add_generator(&mut gen)
+        .prompt_window(Positive::new(10.0).unwrap())
+        .wait_gate(Positive::new(10.0).unwrap())
+        .lookup_table(LookupTable::min_wires(1))
+        .drift_veto(Positive::new(1.0).unwrap())
+        .scaledown(0)
+        .dead_time(Positive::new(1.0).unwrap())
+        .observer(Scalers::default())
+        .build()
+        .run()
+        .counts()
+        .wire_events;
+
+    assert_eq!(events, 5);
+    let error = gen.error().unwrap();
+    assert_eq!(error.row(), 7);
+    assert!(matches!(error.kind(), CsvErrorKind::Unordered));
+}
@@ -0,0 +1,71 @@
+//! `#[cfg]` consistency of the serde impls, with and without `uom`. Every
+//! feature combination is built by `cargo xtask feature-matrix`.
+#![cfg(feature = "serde")]
+
+use trg::gen::{
+    AfterpulseLibrary, Positive, ScriptedGenerator, Source, WireEvent, WirePattern, Zero,
+};
+use trg::mlu::LookupTable;
+use uom::si::f64::Time;
+use uom::si::time::nanosecond;
+
+// Inter-arrival times in ticks of 16 ns.
+const AFTERPULSES: &str = "[[1, 1, 7], [], [2]]";
+
+#[test]
+fn serde_wire_events() {
+    let laser = Source::custom("feature-matrix-laser");
+    let events: Vec<WireEvent<f64>> =
+        ScriptedGenerator::from_times(laser, [1.0, 2.5], WirePattern::from_bits(0x8001)).collect();
+    let json = serde_json::to_string(&events).unwrap();
+    assert!(json.starts_with(r#"[{"source":"feature-matrix-laser","wire_pattern":"0x8001""#));
+
+    let replayed: Vec<WireEvent<f64>> = serde_json::from_str(&json).unwrap();
+    let fields = |events: &[WireEvent<f64>]| {
+        events
+            .iter()
+            .map(|e| (e.source, e.wire_pattern, e.time))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(fields(&replayed), fields(&events));
+}
+
+#[test]
+fn serde_lookup_table() {
+    let table = LookupTable::from([WirePattern::from_bits(1), WirePattern::from_bits(0x8001)]);
+    let json = serde_json::to_string(&table).unwrap();
+    assert_eq!(json, r#"["0x0001","0x8001"]"#);
+    assert_eq!(serde_json::from_str::<LookupTable>(&json).unwrap(), table);
+}
+
+// The trains of `AFTERPULSES`, with times in nanoseconds converted by
+// `to_time`.
+fn expected<T: Zero + PartialOrd>(to_time: impl Fn(f64) -> T) -> Vec<Vec<Positive<T>>> {
+    [vec![16.0, 16.0, 112.0], vec![], vec![32.0]]
+        .into_iter()
+        .map(|train| {
+            train
+                .into_iter()
+                .map(|ns| Positive::new(to_time(ns)).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn serde_afterpulse_library() {
+    let library = AfterpulseLibrary::from_reader(AFTERPULSES.as_bytes(), |ns| ns).unwrap();
+    assert_eq!(library.trains(), expected(|ns| ns));
+    // Without the `uom` feature, quantities are still available through the
+    // generic reader.
+    let library =
+        AfterpulseLibrary::from_reader(AFTERPULSES.as_bytes(), Time::new::<nanosecond>).unwrap();
+    assert_eq!(library.trains(), expected(Time::new::<nanosecond>));
+}
+
+#[cfg(feature = "uom")]
+#[test]
+fn serde_afterpulse_library_with_uom() {
+    let library = AfterpulseLibrary::from_reader_with_uom(AFTERPULSES.as_bytes()).unwrap();
+    assert_eq!(library.trains(), expected(Time::new::<nanosecond>));
+}
@@ -0,0 +1,50 @@
+//! Cross-check the MLU state machine against the naive reference
+//! implementation.
+
+use proptest::prelude::*;
+use trg::gen::{Positive, Source, WireEvent, WirePattern};
+use trg::mlu::{reference, LookupTable, Mlu, TrgSignal};
+
+// Small times make events landing exactly on the window boundaries likely.
+fn events() -> impl Strategy<Value = Vec<WireEvent<i64>>> {
+    prop::collection::vec((0i64..12, 0u16..4), 0..100).prop_map(|deltas| {
+        deltas
+            .into_iter()
+            .scan(0, |time, (dt, bits)| {
+                *time += dt;
+                Some(WireEvent {
+                    source: Source::Noise,
+                    wire_pattern: WirePattern::from_bits(bits),
+                    time: *time,
+                    id: None,
+                    weight: 1.0,
+                })
+            })
+            .collect()
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1024))]
+
+    #[test]
+    fn mlu_reference(
+        prompt_window in 1i64..10,
+        wait_gate in 1i64..10,
+        table in prop::collection::vec(0u16..4, 0..4),
+        events in events(),
+    ) {
+        let table: LookupTable = table.into_iter().map(WirePattern::from_bits).collect();
+        let prompt_window = Positive::new(prompt_window).unwrap();
+        let wait_gate = Positive::new(wait_gate).unwrap();
+        let mut mlu = Mlu::new(prompt_window, wait_gate, table);
+        let mut naive = reference::Mlu::new(prompt_window, wait_gate, table);
+
+        let window = |s: TrgSignal<i64>| (s.time, s.window_start, s.window_stop);
+        for event in &events {
+            let expected = naive.process(event).map(window);
+            prop_assert_eq!(mlu.process(event).map(window), expected, "{:?}", event);
+        }
+        prop_assert_eq!(mlu.flush().map(window), naive.flush().map(window));
+    }
+}
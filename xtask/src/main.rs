@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::{env, fs};
+
+/// Features of the `trg` crate that are checked in every possible
+/// combination. Keep this list in sync with the `[features]` table of the
+/// `trg` manifest.
+const FEATURES: &[&str] = &["csv", "odb", "serde", "uom"];
+
+fn usage() -> ExitCode {
+    eprintln!("Usage: cargo xtask <TASK>");
+    eprintln!();
+    eprintln!("Tasks:");
+    eprintln!("  feature-matrix [--force]  `cargo check` every feature combination");
+    ExitCode::FAILURE
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(String::as_str) {
+        Some("feature-matrix") => {
+            let force = args[1..].iter().any(|a| a == "--force");
+            feature_matrix(force)
+        }
+        _ => usage(),
+    }
+}
+
+fn project_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// All subsets of `FEATURES`. The empty set is checked with
+/// `--no-default-features`.
+fn power_set() -> Vec<Vec<&'static str>> {
+    (0..1usize << FEATURES.len())
+        .map(|mask| {
+            FEATURES
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, f)| *f)
+                .collect()
+        })
+        .collect()
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, the hash of the same bytes never
+/// changes (e.g. with the Rust version), so the cache stays valid.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// All the files in `dir` (recursively), relative to `root`.
+fn files(root: &Path, dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            files(root, &path, found);
+        } else {
+            found.push(path.strip_prefix(root).unwrap().to_path_buf());
+        }
+    }
+}
+
+// Fingerprint of everything that can change the outcome of `cargo check` on
+// any target. Combinations that passed with the same fingerprint are skipped.
+fn fingerprint(root: &Path) -> u64 {
+    let mut paths = ["Cargo.toml", "Cargo.lock", "build.rs"]
+        .into_iter()
+        .map(PathBuf::from)
+        .filter(|path| root.join(path).is_file())
+        .collect::<Vec<_>>();
+    for dir in ["src", "tests", "examples", "benches", "xtask"] {
+        files(root, &root.join(dir), &mut paths);
+    }
+    // The order of `read_dir` is platform dependent.
+    paths.sort();
+
+    let mut hasher = Fnv1a::new();
+    for path in paths {
+        let Ok(contents) = fs::read(root.join(&path)) else {
+            continue;
+        };
+        // Lengths keep the boundaries between paths and contents unambiguous.
+        let path = path.to_string_lossy().replace('\\', "/");
+        for bytes in [path.as_bytes(), &contents] {
+            hasher.write(&(bytes.len() as u64).to_le_bytes());
+            hasher.write(bytes);
+        }
+    }
+
+    hasher.0
+}
+
+fn feature_matrix(force: bool) -> ExitCode {
+    let root = project_root();
+    let cache_path = root.join("target").join("xtask").join("feature-matrix");
+    let fingerprint = fingerprint(&root);
+
+    let mut passed = if force {
+        HashSet::new()
+    } else {
+        fs::read_to_string(&cache_path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .filter(|(hash, _)| *hash == format!("{fingerprint:016x}"))
+            .map(|(_, features)| features.to_string())
+            .collect::<HashSet<_>>()
+    };
+
+    let mut failed = Vec::new();
+    for features in power_set() {
+        let features = features.join(",");
+        let label = if features.is_empty() {
+            "--no-default-features"
+        } else {
+            &features
+        };
+        if passed.contains(&features) {
+            println!("skipped (unchanged): {label}");
+            continue;
+        }
+
+        let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+            .current_dir(&root)
+            .args(["check", "--quiet", "--package", "trg", "--all-targets"])
+            .args(["--no-default-features", "--features", &features])
+            .status();
+        match status {
+            Ok(status) if status.success() => {
+                println!("ok: {label}");
+                passed.insert(features);
+            }
+            _ => {
+                println!("FAILED: {label}");
+                failed.push(label.to_string());
+            }
+        }
+    }
+
+    let cache = passed
+        .iter()
+        .map(|features| format!("{fingerprint:016x} {features}\n"))
+        .collect::<String>();
+    let _ = fs::create_dir_all(cache_path.parent().unwrap());
+    let _ = fs::write(&cache_path, cache);
+
+    if failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("Failing feature combinations:");
+        for label in failed {
+            eprintln!("  {label}");
+        }
+        ExitCode::FAILURE
+    }
+}